@@ -79,12 +79,12 @@ use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use constants::{
-    colors::*, DrawStyle, DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, DEFAULT_ZOOM_LEVEL, GRID_DRAW_STYLE,
-    INTRO_DURATION, INTRO_PAUSE_DURATION,
+    colors::*, DrawStyle, CURRENT_PLAYER_ID, DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, DEFAULT_ZOOM_LEVEL,
+    GRID_DRAW_STYLE, INTRO_DURATION, INTRO_PAUSE_DURATION,
 };
 use input::{MouseAction, ScrollEvent};
 use ui::{
-    context::{EmitEvent, Event, Handled, Handler, UIContext},
+    context::{EmitEvent, Event, Handled, Handler, KeyCodeOrChar, UIContext},
     Chatbox, ChatboxPublishHandle, EventType, GameArea, GameAreaState, TextField,
 };
 use uilayout::{StaticNodeIds, UILayout};
@@ -137,6 +137,23 @@ impl ColorSettings {
         }
     }
 
+    /// Dying cells fade from the owner's alive color to the dead color as they pass through the
+    /// `dying_stages` stages.
+    fn get_dying_color(&self, opt_player_id: Option<usize>, stage: usize, dying_stages: usize) -> Color {
+        let alive = self
+            .cell_colors
+            .get(&CellState::Alive(opt_player_id))
+            .unwrap_or(&self.cell_colors[&CellState::Alive(None)]);
+        let dead = self.cell_colors[&CellState::Dead];
+        let fade = stage as f32 / (dying_stages + 1) as f32;
+        Color::new(
+            alive.r + (dead.r - alive.r) * fade,
+            alive.g + (dead.g - alive.g) * fade,
+            alive.b + (dead.b - alive.b) * fade,
+            alive.a + (dead.a - alive.a) * fade,
+        )
+    }
+
     fn get_random_color(&self) -> Color {
         let mut colors = vec![1.0, 2.0, 3.0];
         let mut rng = rand::thread_rng();
@@ -172,6 +189,30 @@ fn get_text_entered_handler(
     )
 }
 
+// Asks the server to clear the player's writable region when the game area clears it locally.
+fn get_clear_area_handler(net_worker: Arc<Mutex<Option<network::ConwaysteNetWorker>>>) -> Handler {
+    Box::new(
+        move |obj: &mut dyn EmitEvent, _uictx: &mut UIContext, evt: &Event| -> Result<Handled, Box<dyn Error>> {
+            let game_area = obj.downcast_ref::<GameArea>().unwrap(); // unwrap OK because this is a GameArea handler
+            if !game_area.has_keyboard_focus || evt.key != Some(KeyCodeOrChar::KeyCode(KeyCode::C)) {
+                return Ok(Handled::NotHandled);
+            }
+
+            if let Some(region) = game_area.uni.writable_region(CURRENT_PLAYER_ID) {
+                if let Some(ref mut netwayste) = *(net_worker.lock().unwrap()) {
+                    netwayste.try_send(NetwaysteEvent::ClearArea(
+                        region.left() as i32,
+                        region.top() as i32,
+                        region.width() as u32,
+                        region.height() as u32,
+                    ));
+                }
+            }
+            Ok(Handled::Handled)
+        },
+    )
+}
+
 // Then we implement the `ggez::game::GameState` trait on it, which
 // requires callbacks for creating the game state, updating it each
 // frame, and drawing it.
@@ -293,6 +334,18 @@ impl MainState {
             let tf = w.downcast_mut::<TextField>().unwrap();
             tf.on(EventType::TextEntered, text_entered_handler).unwrap(); // unwrap OK because not in handler
         }
+        {
+            let game_area_id = static_node_ids.game_area_id.clone();
+            let w = ui_layout
+                .get_screen_layering_mut(Screen::Run)
+                .unwrap()
+                .get_widget_mut(&game_area_id)
+                .unwrap();
+            let game_area = w.downcast_mut::<GameArea>().unwrap();
+            game_area
+                .on(EventType::KeyPress, get_clear_area_handler(net_worker.clone()))
+                .unwrap(); // unwrap OK because not in handler
+        }
 
         let mut s = MainState {
             screen_stack: vec![Screen::Intro],
@@ -868,23 +921,31 @@ impl MainState {
             Some(0)
         };
 
-        // TODO: call each_non_dead with visible region (add method to viewport)
-        universe.each_non_dead_full(visibility, &mut |col, row, state| {
-            let color = if self.uni_draw_params.player_id >= 0 {
-                self.color_settings.get_color(Some(state))
-            } else {
-                self.color_settings.get_random_color()
-            };
-
-            if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
-                let p = graphics::DrawParam::new()
-                    .dest(Point2 { x: rect.x, y: rect.y })
-                    .scale(Vector2 { x: rect.w, y: rect.h })
-                    .color(color);
-
-                main_spritebatch.add(p);
+        let dying_stages = universe.rule().dying_stages();
+
+        if let Some(region) = viewport.visible_region() {
+            for (col, row, state) in universe.cells_in(region, visibility) {
+                let color = if self.uni_draw_params.player_id >= 0 {
+                    match state {
+                        CellState::Dying(opt_player_id, stage) => {
+                            self.color_settings.get_dying_color(opt_player_id, stage, dying_stages)
+                        }
+                        _ => self.color_settings.get_color(Some(state)),
+                    }
+                } else {
+                    self.color_settings.get_random_color()
+                };
+
+                if let Some(rect) = viewport.window_coords_from_game(viewport::Cell::new(col, row)) {
+                    let p = graphics::DrawParam::new()
+                        .dest(Point2 { x: rect.x, y: rect.y })
+                        .scale(Vector2 { x: rect.w, y: rect.h })
+                        .color(color);
+
+                    main_spritebatch.add(p);
+                }
             }
-        });
+        }
 
         let mut insert_mode = None;
         GameArea::widget_from_screen_and_id(&self.ui_layout, Screen::Run, &self.static_node_ids.game_area_id).map(
@@ -1112,8 +1173,8 @@ impl MainState {
                 NetwaysteEvent::RoomList(list) => {
                     println!("RoomList: {:?}", list);
                 }
-                NetwaysteEvent::UniverseUpdate => {
-                    println!("Universe update");
+                NetwaysteEvent::UniverseUpdate(diff) => {
+                    println!("Universe update: generation {} -> {}", diff.gen0, diff.gen1);
                 }
                 NetwaysteEvent::ChatMessages(msgs) => {
                     for m in msgs {
@@ -1126,6 +1187,34 @@ impl MainState {
                 NetwaysteEvent::LeftRoom => {
                     println!("Left Room");
                 }
+                NetwaysteEvent::Match(room_name, expire_secs) => {
+                    println!("Matched in room {}; join within {} seconds to play", room_name, expire_secs);
+                }
+                NetwaysteEvent::GameNotification(msg) => {
+                    println!("Game notification: {}", msg);
+                }
+                NetwaysteEvent::GameStart(options) => {
+                    println!("Game started: {:?}", options);
+                }
+                NetwaysteEvent::GameFinish(outcome) => {
+                    match outcome.winner {
+                        Some(winner) => println!("Game finished; {} won", winner),
+                        None => println!("Game finished without a winner"),
+                    }
+                }
+                NetwaysteEvent::PlayerRoster(players) => {
+                    println!("Players in room: {:?}", players);
+                }
+                NetwaysteEvent::PlayerJoin(player) => {
+                    println!("{} joined the room", player.name);
+                }
+                NetwaysteEvent::PlayerLeave(name) => {
+                    println!("{} left the room", name);
+                }
+                NetwaysteEvent::PlayerChange(player, old_name) => match old_name {
+                    Some(old_name) => println!("{} is now known as {}", old_name, player.name),
+                    None => println!("Player changed: {:?}", player),
+                },
                 NetwaysteEvent::BadRequest(error) => {
                     println!("Server responded with Bad Request: {:?}", error);
                 }
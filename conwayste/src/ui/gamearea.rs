@@ -323,6 +323,16 @@ impl GameArea {
                         settings.gameplay.zoom = cell_size;
                     });
                 }
+                KeyCode::C => {
+                    // clear our writable region, and let the client's handler ask the server to
+                    // clear it too
+                    if let Some(region) = game_area.uni.writable_region(CURRENT_PLAYER_ID) {
+                        if let Err(e) = game_area.uni.clear_region(region, Some(CURRENT_PLAYER_ID)) {
+                            info!("Could not clear region: {}", e);
+                        }
+                    }
+                    return Ok(NotHandled);
+                }
                 KeyCode::D => {
                     // TODO: do something with this debug code
                     let visibility = None; // can also do Some(player_id)
@@ -381,9 +391,12 @@ impl GameArea {
                         let insert_col = cell.col as isize - (width / 2) as isize;
                         let insert_row = cell.row as isize - (height / 2) as isize;
                         let dst_region = Region::new(insert_col, insert_row, width, height);
-                        game_area
+                        if let Err(e) = game_area
                             .uni
-                            .copy_from_bit_grid(grid, dst_region, Some(CURRENT_PLAYER_ID));
+                            .copy_from_bit_grid(grid, dst_region, Some(CURRENT_PLAYER_ID))
+                        {
+                            info!("Could not insert pattern: {}", e);
+                        }
 
                         event_handled = Handled;
                     } else {
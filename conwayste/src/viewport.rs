@@ -21,6 +21,8 @@ extern crate ggez;
 use ggez::graphics::Rect;
 use ggez::mint::Point2;
 
+use conway::universe::Region;
+
 use crate::constants::{
     DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH, MAX_CELL_SIZE, MIN_CELL_SIZE, PIXELS_SCROLLED_PER_FRAME,
 };
@@ -331,6 +333,31 @@ impl GridView {
         ui::intersection(rect, self.rect)
     }
 
+    /// Returns the region of the universe that is at least partly on screen, or `None` if none of
+    /// it is.
+    pub fn visible_region(&self) -> Option<Region> {
+        // unlike game_coords_from_window_unchecked, round towards negative infinity, so that cells
+        // just left of or above the grid aren't mistaken for column or row 0
+        let to_game = |x: f32, origin: f32| ((x - origin) / self.cell_size).floor() as isize;
+        let left = to_game(self.rect.x, self.grid_origin.x);
+        let top = to_game(self.rect.y, self.grid_origin.y);
+        // bottom-right pixel
+        let right = to_game(self.rect.x + self.rect.w - 1.0, self.grid_origin.x);
+        let bottom = to_game(self.rect.y + self.rect.h - 1.0, self.grid_origin.y);
+        let (left, top) = (left.max(0), top.max(0));
+        let right = right.min(self.columns as isize - 1);
+        let bottom = bottom.min(self.rows as isize - 1);
+        if left > right || top > bottom {
+            return None;
+        }
+        Some(Region::new(
+            left,
+            top,
+            (right - left + 1) as usize,
+            (bottom - top + 1) as usize,
+        ))
+    }
+
     /// The column and row supplied lies is `None` outside of the grid.
     /// Otherwise we'll translate a row/column pair into its representative rectangle.
     pub fn window_coords_from_game(&self, cell: Cell) -> Option<Rect> {
@@ -436,4 +463,20 @@ mod test {
         assert_eq!(gv.window_coords_from_game(outside1), None);
         assert_eq!(gv.window_coords_from_game(outside2), None);
     }
+
+    #[test]
+    fn test_gridview_visible_region() {
+        let mut gv = gen_default_gridview();
+        assert_eq!(gv.visible_region(), Some(Region::new(0, 0, 120, 80)));
+
+        gv.set_origin(Point2 { x: -500.0, y: -300.0 });
+        assert_eq!(gv.visible_region(), Some(Region::new(50, 30, 120, 80)));
+
+        // clipped to the universe
+        gv.set_origin(Point2 { x: -2000.0, y: -1000.0 });
+        assert_eq!(gv.visible_region(), Some(Region::new(200, 100, 56, 28)));
+
+        gv.set_origin(Point2 { x: 1200.0, y: 0.0 });
+        assert_eq!(gv.visible_region(), None);
+    }
 }
@@ -0,0 +1,272 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+/*
+ * C API of libconway, exported by the shared library of the conway-ffi crate:
+ *
+ *     cargo build -p conway-ffi
+ *
+ * Every function except the *_free functions and conway_last_error returns CONWAY_OK or one of
+ * the CONWAY_ERR_* codes, and passes results back through pointer arguments, which may be NULL
+ * where noted. Unless noted, pointer arguments must be NULL or valid; NULL is reported as
+ * CONWAY_ERR_INVALID_ARGUMENT. A universe must not be used by two threads at once.
+ *
+ * Strings are NUL-terminated UTF-8. Strings returned by the library must be freed with
+ * conway_string_free.
+ */
+
+#ifndef CONWAY_H
+#define CONWAY_H
+
+/* Generated from src/lib.rs by cbindgen. Do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Status codes. Codes 1 to 7 correspond to the variants of ConwayError.
+ */
+#define CONWAY_OK 0
+
+#define CONWAY_ERR_INVALID_DATA 1
+
+#define CONWAY_ERR_ACCESS_DENIED 2
+
+#define CONWAY_ERR_INSUFFICIENT_ENERGY 3
+
+#define CONWAY_ERR_OUT_OF_BOUNDS 4
+
+#define CONWAY_ERR_RLE_SYNTAX 5
+
+#define CONWAY_ERR_GENERATION_MISMATCH 6
+
+#define CONWAY_ERR_HISTORY_EXHAUSTED 7
+
+/**
+ * A NULL pointer, a string that isn't UTF-8, or an out-of-range value was passed in.
+ */
+#define CONWAY_ERR_INVALID_ARGUMENT 8
+
+/**
+ * The library panicked. The universe may be inconsistent, and should be freed.
+ */
+#define CONWAY_ERR_PANIC 9
+
+/**
+ * Player ID meaning no player: unowned cells, unchecked edits, or a fog-less view.
+ */
+#define CONWAY_NO_PLAYER -1
+
+/**
+ * Kinds of cell.
+ */
+#define CONWAY_CELL_DEAD 0
+
+#define CONWAY_CELL_ALIVE 1
+
+#define CONWAY_CELL_WALL 2
+
+#define CONWAY_CELL_FOG 3
+
+#define CONWAY_CELL_DYING 4
+
+/**
+ * A universe, created by conway_universe_new.
+ */
+typedef struct ConwayUniverse ConwayUniverse;
+
+/**
+ * C version of `Region`. The width and height must be positive.
+ */
+typedef struct ConwayRegion {
+  ptrdiff_t left;
+  ptrdiff_t top;
+  size_t width;
+  size_t height;
+} ConwayRegion;
+
+/**
+ * Parameters for conway_universe_new, like those of BigBang.
+ */
+typedef struct ConwayParams {
+  /**
+   * Must be a positive multiple of 64
+   */
+  size_t width;
+  size_t height;
+  /**
+   * Nonzero if every cell is known
+   */
+  int is_server;
+  /**
+   * Generations kept; 0 for the default
+   */
+  size_t history;
+  /**
+   * 0 for the default
+   */
+  size_t fog_radius;
+  /**
+   * E.g. "B36/S23"; NULL for Conway's Game of Life
+   */
+  const char *rule;
+  /**
+   * Number of elements in player_writable
+   */
+  size_t num_players;
+  /**
+   * Writable region of each player
+   */
+  const struct ConwayRegion *player_writable;
+} ConwayParams;
+
+/**
+ * C version of `CellState`.
+ */
+typedef struct ConwayCell {
+  /**
+   * One of the CONWAY_CELL_* constants
+   */
+  uint8_t kind;
+  /**
+   * Player ID, or CONWAY_NO_PLAYER (alive and dying cells only)
+   */
+  int32_t owner;
+  /**
+   * 1-based dying stage (dying cells only)
+   */
+  uint32_t stage;
+} ConwayCell;
+
+/**
+ * Called by conway_cells_in for each non-dead cell.
+ */
+typedef void (*ConwayCellCallback)(size_t col, size_t row, struct ConwayCell cell, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a universe, to be freed with conway_universe_free.
+ */
+int conway_universe_new(const struct ConwayParams *params, struct ConwayUniverse **out_uni);
+
+/**
+ * Frees a universe. Does nothing if uni is NULL.
+ */
+void conway_universe_free(struct ConwayUniverse *uni);
+
+/**
+ * Returns the size of the universe in cells. Either out pointer may be NULL.
+ */
+int conway_universe_size(const struct ConwayUniverse *uni, size_t *out_width, size_t *out_height);
+
+/**
+ * Returns the latest generation.
+ */
+int conway_latest_gen(const struct ConwayUniverse *uni, size_t *out_generation);
+
+/**
+ * Toggles a cell between dead and alive, as player_id, or unchecked and unowned if player_id is
+ * CONWAY_NO_PLAYER. Returns the new state of the cell in out_cell, which may be NULL.
+ */
+int conway_toggle(struct ConwayUniverse *uni,
+                  size_t col,
+                  size_t row,
+                  int32_t player_id,
+                  struct ConwayCell *out_cell);
+
+/**
+ * Sets a cell, as player_id, or unchecked if player_id is CONWAY_NO_PLAYER. As player_id, the
+ * cell is silently left alone if the player may not change it. Fog can't be set.
+ */
+int conway_set(struct ConwayUniverse *uni,
+               size_t col,
+               size_t row,
+               struct ConwayCell cell,
+               int32_t player_id);
+
+/**
+ * Computes the next generation, returning its number in out_generation, which may be NULL.
+ */
+int conway_next(struct ConwayUniverse *uni, size_t *out_generation);
+
+/**
+ * Returns an RLE pattern that turns generation gen0 into generation gen1, as seen by visibility
+ * (a player ID or CONWAY_NO_PLAYER). If gen0 is 0, it is the whole of generation gen1.
+ */
+int conway_diff(const struct ConwayUniverse *uni,
+                size_t gen0,
+                size_t gen1,
+                int32_t visibility,
+                char **out_rle);
+
+/**
+ * Applies a pattern from conway_diff, as seen by visibility. Returns the new latest generation in
+ * out_generation, which may be NULL, or 0 if the diff was valid but not applied because the
+ * universe doesn't have generation gen0 or already has generation gen1.
+ */
+int conway_apply(struct ConwayUniverse *uni,
+                 size_t gen0,
+                 size_t gen1,
+                 const char *rle,
+                 int32_t visibility,
+                 size_t *out_generation);
+
+/**
+ * Parses the contents of an RLE file, and copies its live cells into the universe with their
+ * top-left corner at (left, top), as player_id, or unchecked and unowned if player_id is
+ * CONWAY_NO_PLAYER. The pattern must fit within the universe.
+ */
+int conway_load_rle(struct ConwayUniverse *uni,
+                    const char *rle_file,
+                    ptrdiff_t left,
+                    ptrdiff_t top,
+                    int32_t player_id);
+
+/**
+ * Returns the latest generation as an RLE pattern, as seen by visibility.
+ */
+int conway_to_rle(const struct ConwayUniverse *uni, int32_t visibility, char **out_rle);
+
+/**
+ * Calls callback for each non-dead cell of the latest generation within region, as seen by
+ * visibility, in row-major order. The region wraps around the edges of the universe.
+ */
+int conway_cells_in(const struct ConwayUniverse *uni,
+                    struct ConwayRegion region,
+                    int32_t visibility,
+                    ConwayCellCallback callback,
+                    void *user_data);
+
+/**
+ * Frees a string returned by the library. Does nothing if s is NULL.
+ */
+void conway_string_free(char *s);
+
+/**
+ * Returns a description of the last error on this thread, or NULL if there hasn't been one. It
+ * is valid until the next error on this thread.
+ */
+const char *conway_last_error(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CONWAY_H */
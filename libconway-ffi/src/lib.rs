@@ -0,0 +1,551 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! A C API for driving a `Universe` from other languages. The shared library is built as
+//! `libconway_ffi.so` (or the platform's equivalent), and the C declarations in
+//! `include/conway.h` are generated from this file with cbindgen. The header documents each
+//! function and its safety requirements.
+//!
+//! Every function returns a status code -- `CONWAY_OK`, or one of the `CONWAY_ERR_*` codes -- and
+//! passes results back through pointer arguments. Each `ConwayError` variant has its own code.
+//! Panics are caught rather than unwinding into C, and reported as `CONWAY_ERR_PANIC`; a universe
+//! may be left inconsistent by a panic, so it should be freed. `conway_last_error` describes the
+//! most recent error on the calling thread.
+
+// The safety requirements of each function are documented in include/conway.h.
+#![allow(clippy::missing_safety_doc)]
+
+// cbindgen only exports items from this crate, so nothing here can be re-exported from conway.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use conway::error::ConwayError;
+use conway::grids::CharGrid;
+use conway::rle::{Pattern, PatternFile};
+use conway::rule::Rule;
+use conway::universe::{BigBang, CellState, GenStateDiff, PlayerBuilder, Region, Universe};
+
+/// Status codes. Codes 1 to 7 correspond to the variants of ConwayError.
+pub const CONWAY_OK: c_int = 0;
+pub const CONWAY_ERR_INVALID_DATA: c_int = 1;
+pub const CONWAY_ERR_ACCESS_DENIED: c_int = 2;
+pub const CONWAY_ERR_INSUFFICIENT_ENERGY: c_int = 3;
+pub const CONWAY_ERR_OUT_OF_BOUNDS: c_int = 4;
+pub const CONWAY_ERR_RLE_SYNTAX: c_int = 5;
+pub const CONWAY_ERR_GENERATION_MISMATCH: c_int = 6;
+pub const CONWAY_ERR_HISTORY_EXHAUSTED: c_int = 7;
+/// A NULL pointer, a string that isn't UTF-8, or an out-of-range value was passed in.
+pub const CONWAY_ERR_INVALID_ARGUMENT: c_int = 8;
+/// The library panicked. The universe may be inconsistent, and should be freed.
+pub const CONWAY_ERR_PANIC: c_int = 9;
+
+/// Player ID meaning no player: unowned cells, unchecked edits, or a fog-less view.
+pub const CONWAY_NO_PLAYER: i32 = -1;
+
+/// Kinds of cell.
+pub const CONWAY_CELL_DEAD: u8 = 0;
+pub const CONWAY_CELL_ALIVE: u8 = 1;
+pub const CONWAY_CELL_WALL: u8 = 2;
+pub const CONWAY_CELL_FOG: u8 = 3;
+pub const CONWAY_CELL_DYING: u8 = 4;
+
+/// A universe, created by conway_universe_new.
+pub struct ConwayUniverse(Universe);
+
+/// C version of `Region`. The width and height must be positive.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ConwayRegion {
+    pub left:   isize,
+    pub top:    isize,
+    pub width:  usize,
+    pub height: usize,
+}
+
+/// C version of `CellState`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConwayCell {
+    /// One of the CONWAY_CELL_* constants
+    pub kind:  u8,
+    /// Player ID, or CONWAY_NO_PLAYER (alive and dying cells only)
+    pub owner: i32,
+    /// 1-based dying stage (dying cells only)
+    pub stage: u32,
+}
+
+/// Parameters for conway_universe_new, like those of BigBang.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ConwayParams {
+    /// Must be a positive multiple of 64
+    pub width:           usize,
+    pub height:          usize,
+    /// Nonzero if every cell is known
+    pub is_server:       c_int,
+    /// Generations kept; 0 for the default
+    pub history:         usize,
+    /// 0 for the default
+    pub fog_radius:      usize,
+    /// E.g. "B36/S23"; NULL for Conway's Game of Life
+    pub rule:            *const c_char,
+    /// Number of elements in player_writable
+    pub num_players:     usize,
+    /// Writable region of each player
+    pub player_writable: *const ConwayRegion,
+}
+
+/// Called by conway_cells_in for each non-dead cell.
+pub type ConwayCellCallback = Option<extern "C" fn(col: usize, row: usize, cell: ConwayCell, user_data: *mut c_void)>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+struct FfiError {
+    code:    c_int,
+    message: String,
+}
+
+impl From<ConwayError> for FfiError {
+    fn from(e: ConwayError) -> Self {
+        use ConwayError::*;
+        let code = match e {
+            InvalidData { .. } => CONWAY_ERR_INVALID_DATA,
+            AccessDenied { .. } => CONWAY_ERR_ACCESS_DENIED,
+            InsufficientEnergy { .. } => CONWAY_ERR_INSUFFICIENT_ENERGY,
+            OutOfBounds { .. } => CONWAY_ERR_OUT_OF_BOUNDS,
+            RleSyntax { .. } => CONWAY_ERR_RLE_SYNTAX,
+            GenerationMismatch { .. } => CONWAY_ERR_GENERATION_MISMATCH,
+            HistoryExhausted { .. } => CONWAY_ERR_HISTORY_EXHAUSTED,
+        };
+        FfiError {
+            code,
+            message: e.to_string(),
+        }
+    }
+}
+
+type FfiResult<T> = Result<T, FfiError>;
+
+fn invalid_argument<T>(message: String) -> FfiResult<T> {
+    Err(FfiError {
+        code: CONWAY_ERR_INVALID_ARGUMENT,
+        message,
+    })
+}
+
+/// Runs `f`, catching any panic, and returns its status code. The message of an error is saved
+/// for `conway_last_error`.
+fn call<F: FnOnce() -> FfiResult<()>>(f: F) -> c_int {
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_owned()
+        };
+        Err(FfiError {
+            code:    CONWAY_ERR_PANIC,
+            message: format!("panic: {}", message),
+        })
+    });
+    match result {
+        Ok(()) => CONWAY_OK,
+        Err(e) => {
+            // the message can't contain a NUL, except perhaps in a panic message
+            let message = CString::new(e.message.replace('\0', " ")).unwrap();
+            LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+            e.code
+        }
+    }
+}
+
+unsafe fn deref<'a, T>(ptr: *const T, name: &str) -> FfiResult<&'a T> {
+    match ptr.as_ref() {
+        Some(r) => Ok(r),
+        None => invalid_argument(format!("{} is NULL", name)),
+    }
+}
+
+unsafe fn deref_mut<'a, T>(ptr: *mut T, name: &str) -> FfiResult<&'a mut T> {
+    match ptr.as_mut() {
+        Some(r) => Ok(r),
+        None => invalid_argument(format!("{} is NULL", name)),
+    }
+}
+
+unsafe fn to_str<'a>(ptr: *const c_char, name: &str) -> FfiResult<&'a str> {
+    if ptr.is_null() {
+        return invalid_argument(format!("{} is NULL", name));
+    }
+    match CStr::from_ptr(ptr).to_str() {
+        Ok(s) => Ok(s),
+        Err(_) => invalid_argument(format!("{} is not UTF-8", name)),
+    }
+}
+
+/// Writes `value` to `out`, unless `out` is NULL.
+unsafe fn write_opt<T>(out: *mut T, value: T) {
+    if let Some(out) = out.as_mut() {
+        *out = value;
+    }
+}
+
+fn to_c_string(s: String) -> FfiResult<*mut c_char> {
+    match CString::new(s) {
+        Ok(s) => Ok(s.into_raw()),
+        Err(_) => invalid_argument("string contains a NUL".to_owned()),
+    }
+}
+
+/// Converts a C player ID to an optional player ID, checking that the player exists.
+fn player(uni: &Universe, player_id: i32) -> FfiResult<Option<usize>> {
+    match player_id {
+        CONWAY_NO_PLAYER => Ok(None),
+        id if id >= 0 && (id as usize) < uni.num_players() => Ok(Some(id as usize)),
+        id => invalid_argument(format!("there is no player {}", id)),
+    }
+}
+
+fn check_bounds(uni: &Universe, col: usize, row: usize) -> FfiResult<()> {
+    if col >= uni.width() || row >= uni.height() {
+        return Err(ConwayError::OutOfBounds {
+            col,
+            row,
+            width: uni.width(),
+            height: uni.height(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+impl ConwayRegion {
+    /// Converts to a `Region`, which can't be empty.
+    fn to_region(self, name: &str) -> FfiResult<Region> {
+        if self.width == 0 || self.height == 0 {
+            return invalid_argument(format!("{} is {}x{}, but must not be empty", name, self.width, self.height));
+        }
+        Ok(Region::new(self.left, self.top, self.width, self.height))
+    }
+}
+
+impl From<CellState> for ConwayCell {
+    fn from(state: CellState) -> Self {
+        let owner = |opt_player_id: Option<usize>| opt_player_id.map_or(CONWAY_NO_PLAYER, |id| id as i32);
+        let (kind, owner, stage) = match state {
+            CellState::Dead => (CONWAY_CELL_DEAD, CONWAY_NO_PLAYER, 0),
+            CellState::Alive(opt_player_id) => (CONWAY_CELL_ALIVE, owner(opt_player_id), 0),
+            CellState::Wall => (CONWAY_CELL_WALL, CONWAY_NO_PLAYER, 0),
+            CellState::Fog => (CONWAY_CELL_FOG, CONWAY_NO_PLAYER, 0),
+            CellState::Dying(opt_player_id, stage) => (CONWAY_CELL_DYING, owner(opt_player_id), stage as u32),
+        };
+        ConwayCell { kind, owner, stage }
+    }
+}
+
+impl ConwayCell {
+    /// Converts to a `CellState` that can be written to `uni`. Fog can't be written.
+    fn to_cell_state(self, uni: &Universe) -> FfiResult<CellState> {
+        let owner = player(uni, self.owner)?;
+        match self.kind {
+            CONWAY_CELL_DEAD => Ok(CellState::Dead),
+            CONWAY_CELL_ALIVE => Ok(CellState::Alive(owner)),
+            CONWAY_CELL_WALL => Ok(CellState::Wall),
+            CONWAY_CELL_DYING if self.stage >= 1 && self.stage as usize <= uni.rule().dying_stages() => {
+                Ok(CellState::Dying(owner, self.stage as usize))
+            }
+            CONWAY_CELL_DYING => invalid_argument(format!("the rule has no dying stage {}", self.stage)),
+            kind => invalid_argument(format!("cannot write a cell of kind {}", kind)),
+        }
+    }
+}
+
+/// Creates a universe, to be freed with conway_universe_free.
+#[no_mangle]
+pub unsafe extern "C" fn conway_universe_new(params: *const ConwayParams, out_uni: *mut *mut ConwayUniverse) -> c_int {
+    call(|| {
+        let params = deref(params, "params")?;
+        let out_uni = deref_mut(out_uni, "out_uni")?;
+        let mut bigbang = BigBang::new()
+            .width(params.width)
+            .height(params.height)
+            .server_mode(params.is_server != 0);
+        if params.history > 0 {
+            bigbang = bigbang.history(params.history);
+        }
+        if params.fog_radius > 0 {
+            bigbang = bigbang.fog_radius(params.fog_radius);
+        }
+        if !params.rule.is_null() {
+            bigbang = bigbang.rule(to_str(params.rule, "params->rule")?.parse::<Rule>()?);
+        }
+        if params.num_players > 0 {
+            let writable = deref(params.player_writable, "params->player_writable")?;
+            for region in std::slice::from_raw_parts(writable, params.num_players) {
+                let region = region.to_region("params->player_writable")?;
+                bigbang = bigbang.add_player(PlayerBuilder::new(region));
+            }
+        }
+        *out_uni = Box::into_raw(Box::new(ConwayUniverse(bigbang.birth()?)));
+        Ok(())
+    })
+}
+
+/// Frees a universe. Does nothing if uni is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn conway_universe_free(uni: *mut ConwayUniverse) {
+    if !uni.is_null() {
+        call(|| {
+            drop(Box::from_raw(uni));
+            Ok(())
+        });
+    }
+}
+
+/// Returns the size of the universe in cells. Either out pointer may be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn conway_universe_size(
+    uni: *const ConwayUniverse,
+    out_width: *mut usize,
+    out_height: *mut usize,
+) -> c_int {
+    call(|| {
+        let uni = &deref(uni, "uni")?.0;
+        write_opt(out_width, uni.width());
+        write_opt(out_height, uni.height());
+        Ok(())
+    })
+}
+
+/// Returns the latest generation.
+#[no_mangle]
+pub unsafe extern "C" fn conway_latest_gen(uni: *const ConwayUniverse, out_generation: *mut usize) -> c_int {
+    call(|| {
+        let uni = &deref(uni, "uni")?.0;
+        *deref_mut(out_generation, "out_generation")? = uni.latest_gen();
+        Ok(())
+    })
+}
+
+/// Toggles a cell between dead and alive, as player_id, or unchecked and unowned if player_id is
+/// CONWAY_NO_PLAYER. Returns the new state of the cell in out_cell, which may be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn conway_toggle(
+    uni: *mut ConwayUniverse,
+    col: usize,
+    row: usize,
+    player_id: i32,
+    out_cell: *mut ConwayCell,
+) -> c_int {
+    call(|| {
+        let uni = &mut deref_mut(uni, "uni")?.0;
+        let state = match player(uni, player_id)? {
+            Some(player_id) => uni.toggle(col, row, player_id)?,
+            None => {
+                check_bounds(uni, col, row)?;
+                uni.toggle_unchecked(col, row, None)
+            }
+        };
+        write_opt(out_cell, state.into());
+        Ok(())
+    })
+}
+
+/// Sets a cell, as player_id, or unchecked if player_id is CONWAY_NO_PLAYER. As player_id, the
+/// cell is silently left alone if the player may not change it. Fog can't be set.
+#[no_mangle]
+pub unsafe extern "C" fn conway_set(
+    uni: *mut ConwayUniverse,
+    col: usize,
+    row: usize,
+    cell: ConwayCell,
+    player_id: i32,
+) -> c_int {
+    call(|| {
+        let uni = &mut deref_mut(uni, "uni")?.0;
+        check_bounds(uni, col, row)?;
+        let state = cell.to_cell_state(uni)?;
+        match player(uni, player_id)? {
+            Some(player_id) => {
+                if let CellState::Alive(Some(owner)) | CellState::Dying(Some(owner), _) = state {
+                    if owner != player_id {
+                        return invalid_argument(format!("player {} cannot set a cell of player {}", player_id, owner));
+                    }
+                }
+                uni.set(col, row, state, player_id);
+            }
+            None => uni.set_unchecked(col, row, state),
+        }
+        Ok(())
+    })
+}
+
+/// Computes the next generation, returning its number in out_generation, which may be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn conway_next(uni: *mut ConwayUniverse, out_generation: *mut usize) -> c_int {
+    call(|| {
+        let uni = &mut deref_mut(uni, "uni")?.0;
+        let generation = uni.next();
+        write_opt(out_generation, generation);
+        Ok(())
+    })
+}
+
+/// Returns an RLE pattern that turns generation gen0 into generation gen1, as seen by visibility
+/// (a player ID or CONWAY_NO_PLAYER). If gen0 is 0, it is the whole of generation gen1.
+#[no_mangle]
+pub unsafe extern "C" fn conway_diff(
+    uni: *const ConwayUniverse,
+    gen0: usize,
+    gen1: usize,
+    visibility: i32,
+    out_rle: *mut *mut c_char,
+) -> c_int {
+    call(|| {
+        let uni = &deref(uni, "uni")?.0;
+        let out_rle = deref_mut(out_rle, "out_rle")?;
+        if gen0 >= gen1 {
+            return invalid_argument(format!("expected gen0 < gen1, but {} >= {}", gen0, gen1));
+        }
+        let diff = uni.diff(gen0, gen1, player(uni, visibility)?)?;
+        *out_rle = to_c_string(diff.pattern.0)?;
+        Ok(())
+    })
+}
+
+/// Applies a pattern from conway_diff, as seen by visibility. Returns the new latest generation in
+/// out_generation, which may be NULL, or 0 if the diff was valid but not applied because the
+/// universe doesn't have generation gen0 or already has generation gen1.
+#[no_mangle]
+pub unsafe extern "C" fn conway_apply(
+    uni: *mut ConwayUniverse,
+    gen0: usize,
+    gen1: usize,
+    rle: *const c_char,
+    visibility: i32,
+    out_generation: *mut usize,
+) -> c_int {
+    call(|| {
+        let uni = &mut deref_mut(uni, "uni")?.0;
+        if gen0 >= gen1 {
+            return invalid_argument(format!("expected gen0 < gen1, but {} >= {}", gen0, gen1));
+        }
+        let diff = GenStateDiff {
+            gen0,
+            gen1,
+            pattern: Pattern(to_str(rle, "rle")?.to_owned()),
+        };
+        let opt_generation = uni.apply(&diff, player(uni, visibility)?)?;
+        write_opt(out_generation, opt_generation.unwrap_or(0));
+        Ok(())
+    })
+}
+
+/// Parses the contents of an RLE file, and copies its live cells into the universe with their
+/// top-left corner at (left, top), as player_id, or unchecked and unowned if player_id is
+/// CONWAY_NO_PLAYER. The pattern must fit within the universe.
+#[no_mangle]
+pub unsafe extern "C" fn conway_load_rle(
+    uni: *mut ConwayUniverse,
+    rle_file: *const c_char,
+    left: isize,
+    top: isize,
+    player_id: i32,
+) -> c_int {
+    call(|| {
+        let uni = &mut deref_mut(uni, "uni")?.0;
+        let pattern_file = to_str(rle_file, "rle_file")?.parse::<PatternFile>()?;
+        if pattern_file.width() == 0 || pattern_file.height() == 0 {
+            return Ok(());
+        }
+        let grid = pattern_file.to_new_bit_grid()?;
+        let region = Region::new(left, top, pattern_file.width(), pattern_file.height());
+        if region.intersection(uni.region()) != Some(region) {
+            return Err(ConwayError::OutOfBounds {
+                col:    (left + region.width() as isize - 1).max(0) as usize,
+                row:    (top + region.height() as isize - 1).max(0) as usize,
+                width:  uni.width(),
+                height: uni.height(),
+            }
+            .into());
+        }
+        uni.copy_from_bit_grid(&grid, region, player(uni, player_id)?)?;
+        Ok(())
+    })
+}
+
+/// Returns the latest generation as an RLE pattern, as seen by visibility.
+#[no_mangle]
+pub unsafe extern "C" fn conway_to_rle(uni: *const ConwayUniverse, visibility: i32, out_rle: *mut *mut c_char) -> c_int {
+    call(|| {
+        let uni = &deref(uni, "uni")?.0;
+        let out_rle = deref_mut(out_rle, "out_rle")?;
+        *out_rle = to_c_string(uni.to_pattern(player(uni, visibility)?).0)?;
+        Ok(())
+    })
+}
+
+/// Calls callback for each non-dead cell of the latest generation within region, as seen by
+/// visibility, in row-major order. The region wraps around the edges of the universe.
+#[no_mangle]
+pub unsafe extern "C" fn conway_cells_in(
+    uni: *const ConwayUniverse,
+    region: ConwayRegion,
+    visibility: i32,
+    callback: ConwayCellCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    call(|| {
+        let uni = &deref(uni, "uni")?.0;
+        let callback = match callback {
+            Some(callback) => callback,
+            None => return invalid_argument("callback is NULL".to_owned()),
+        };
+        let region = region.to_region("region")?;
+        for (col, row, state) in uni.cells_in(region, player(uni, visibility)?) {
+            callback(col, row, state.into(), user_data);
+        }
+        Ok(())
+    })
+}
+
+/// Frees a string returned by the library. Does nothing if s is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn conway_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Returns a description of the last error on this thread, or NULL if there hasn't been one. It
+/// is valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn conway_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
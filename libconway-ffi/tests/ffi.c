@@ -0,0 +1,167 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+/* Exercises the C API. Built and run by tests/ffi.rs; exits with a nonzero status on failure. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "conway.h"
+
+#define CHECK(cond)                                                                   \
+    do {                                                                              \
+        if (!(cond)) {                                                                \
+            const char *err = conway_last_error();                                    \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n", __FILE__,  \
+                    __LINE__, #cond, err ? err : "none");                             \
+            exit(1);                                                                  \
+        }                                                                             \
+    } while (0)
+
+struct census {
+    size_t alive;
+    size_t fog;
+    size_t owned_by_0;
+};
+
+static void count_cell(size_t col, size_t row, ConwayCell cell, void *user_data) {
+    struct census *census = user_data;
+    (void)col;
+    (void)row;
+    if (cell.kind == CONWAY_CELL_ALIVE) {
+        census->alive++;
+        if (cell.owner == 0) {
+            census->owned_by_0++;
+        }
+    } else if (cell.kind == CONWAY_CELL_FOG) {
+        census->fog++;
+    }
+}
+
+static struct census take_census(const ConwayUniverse *uni, int32_t visibility) {
+    struct census census = {0, 0, 0};
+    ConwayRegion everything = {0, 0, 128, 64};
+    CHECK(conway_cells_in(uni, everything, visibility, count_cell, &census) == CONWAY_OK);
+    return census;
+}
+
+static ConwayUniverse *new_universe(int is_server) {
+    ConwayRegion writable[2] = {{0, 0, 32, 32}, {64, 32, 32, 32}};
+    ConwayParams params = {128, 64, is_server, 16, 0, NULL, 2, writable};
+    ConwayUniverse *uni = NULL;
+    CHECK(conway_universe_new(&params, &uni) == CONWAY_OK);
+    CHECK(uni != NULL);
+    return uni;
+}
+
+static void test_edits_and_next(void) {
+    ConwayUniverse *uni = new_universe(1);
+    size_t width = 0, height = 0, gen = 0;
+    CHECK(conway_universe_size(uni, &width, &height) == CONWAY_OK);
+    CHECK(width == 128 && height == 64);
+    CHECK(conway_latest_gen(uni, &gen) == CONWAY_OK && gen == 1);
+
+    /* a blinker, owned by player 0 */
+    ConwayCell cell;
+    CHECK(conway_toggle(uni, 10, 10, 0, &cell) == CONWAY_OK);
+    CHECK(cell.kind == CONWAY_CELL_ALIVE && cell.owner == 0);
+    CHECK(conway_toggle(uni, 11, 10, 0, NULL) == CONWAY_OK);
+    ConwayCell alive0 = {CONWAY_CELL_ALIVE, 0, 0};
+    CHECK(conway_set(uni, 12, 10, alive0, 0) == CONWAY_OK);
+    CHECK(take_census(uni, CONWAY_NO_PLAYER).owned_by_0 == 3);
+
+    /* player 0 can't write outside their region */
+    CHECK(conway_toggle(uni, 70, 40, 0, NULL) == CONWAY_ERR_ACCESS_DENIED);
+    CHECK(conway_last_error() != NULL);
+    CHECK(conway_toggle(uni, 500, 10, 0, NULL) == CONWAY_ERR_OUT_OF_BOUNDS);
+    CHECK(conway_toggle(uni, 10, 10, 7, NULL) == CONWAY_ERR_INVALID_ARGUMENT);
+    ConwayCell fog = {CONWAY_CELL_FOG, CONWAY_NO_PLAYER, 0};
+    CHECK(conway_set(uni, 1, 1, fog, CONWAY_NO_PLAYER) == CONWAY_ERR_INVALID_ARGUMENT);
+    ConwayCell alive1 = {CONWAY_CELL_ALIVE, 1, 0};
+    CHECK(conway_set(uni, 1, 1, alive1, 0) == CONWAY_ERR_INVALID_ARGUMENT);
+
+    CHECK(conway_next(uni, &gen) == CONWAY_OK && gen == 2);
+    char *rle = NULL;
+    CHECK(conway_to_rle(uni, CONWAY_NO_PLAYER, &rle) == CONWAY_OK);
+    CHECK(strcmp(rle, "9$11bA$11bA$11bA!") == 0);
+    conway_string_free(rle);
+
+    /* player 1 can't see the blinker */
+    struct census census = take_census(uni, 1);
+    CHECK(census.alive == 0 && census.fog > 0);
+
+    /* empty regions */
+    ConwayRegion empty = {0, 0, 0, 64};
+    CHECK(conway_cells_in(uni, empty, CONWAY_NO_PLAYER, count_cell, &census) == CONWAY_ERR_INVALID_ARGUMENT);
+    ConwayRegion writable[1] = {{0, 0, 32, 0}};
+    ConwayParams params = {128, 64, 1, 0, 0, NULL, 1, writable};
+    ConwayUniverse *empty_uni = NULL;
+    CHECK(conway_universe_new(&params, &empty_uni) == CONWAY_ERR_INVALID_ARGUMENT);
+    CHECK(empty_uni == NULL);
+
+    CHECK(conway_universe_new(NULL, &uni) == CONWAY_ERR_INVALID_ARGUMENT);
+    conway_universe_free(uni);
+    conway_universe_free(NULL);
+}
+
+static void test_diff_and_apply(void) {
+    ConwayUniverse *server = new_universe(1);
+    ConwayUniverse *client = new_universe(0);
+    const char *glider = "#N Glider\nx = 3, y = 3\nbo$2bo$3o!\n";
+    CHECK(conway_load_rle(server, glider, 70, 40, 1) == CONWAY_OK);
+    for (int i = 0; i < 4; i++) {
+        CHECK(conway_next(server, NULL) == CONWAY_OK);
+    }
+
+    char *rle = NULL;
+    size_t gen = 0;
+    CHECK(conway_diff(server, 0, 5, 1, &rle) == CONWAY_OK);
+    CHECK(conway_apply(client, 0, 5, rle, 1, &gen) == CONWAY_OK && gen == 5);
+    conway_string_free(rle);
+    /* already applied */
+    CHECK(conway_apply(client, 0, 5, "!", 1, &gen) == CONWAY_OK && gen == 0);
+
+    CHECK(conway_next(server, NULL) == CONWAY_OK);
+    CHECK(conway_diff(server, 5, 6, 1, &rle) == CONWAY_OK);
+    CHECK(conway_apply(client, 5, 6, rle, 1, &gen) == CONWAY_OK && gen == 6);
+    conway_string_free(rle);
+
+    char *server_rle = NULL, *client_rle = NULL;
+    CHECK(conway_to_rle(server, 1, &server_rle) == CONWAY_OK);
+    CHECK(conway_to_rle(client, 1, &client_rle) == CONWAY_OK);
+    CHECK(strcmp(server_rle, client_rle) == 0);
+    conway_string_free(server_rle);
+    conway_string_free(client_rle);
+    CHECK(take_census(client, 1).alive == 5);
+
+    CHECK(conway_diff(server, 6, 99, 1, &rle) == CONWAY_ERR_GENERATION_MISMATCH);
+    CHECK(conway_diff(server, 6, 5, 1, &rle) == CONWAY_ERR_INVALID_ARGUMENT);
+    CHECK(conway_apply(client, 6, 7, "3o", 1, NULL) == CONWAY_ERR_RLE_SYNTAX);
+    CHECK(strstr(conway_last_error(), "RleSyntax") != NULL);
+    CHECK(conway_load_rle(server, "x = 3, y = 1\n3o!", 126, 0, CONWAY_NO_PLAYER) == CONWAY_ERR_OUT_OF_BOUNDS);
+
+    conway_universe_free(server);
+    conway_universe_free(client);
+}
+
+int main(void) {
+    test_edits_and_next();
+    test_diff_and_apply();
+    printf("ok\n");
+    return 0;
+}
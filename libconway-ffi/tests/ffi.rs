@@ -0,0 +1,93 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Tests of the C API, run with `cargo test -p conway-ffi`. These check that include/conway.h is
+//! up to date, and compile and run tests/ffi.c against the shared library, so they need a C
+//! compiler (`cc`, or `$CC`).
+
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// The directory holding the shared library: the `deps` directory holding this test executable.
+fn library_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let config = cbindgen::Config::from_file(manifest_dir().join("cbindgen.toml")).unwrap();
+    let mut generated = vec![];
+    cbindgen::generate_with_config(manifest_dir(), config)
+        .unwrap()
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+
+    let path = manifest_dir().join("include/conway.h");
+    if env::var_os("CONWAY_FFI_UPDATE_HEADER").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    let header = fs::read_to_string(&path).unwrap();
+    assert!(
+        header == generated,
+        "include/conway.h is out of date; run `CONWAY_FFI_UPDATE_HEADER=1 cargo test -p conway-ffi` to regenerate it"
+    );
+}
+
+#[test]
+fn c_test_program_passes() {
+    let lib_dir = library_dir();
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let exe = out_dir.join("conway_ffi_test");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror"])
+        .arg("-I")
+        .arg(manifest_dir().join("include"))
+        .arg(manifest_dir().join("tests/ffi.c"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lconway_ffi")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .unwrap_or_else(|e| panic!("cannot run C compiler {:?}: {}", compiler, e));
+    assert!(status.success(), "compiling tests/ffi.c failed");
+
+    // cargo's LD_LIBRARY_PATH would otherwise take precedence over the rpath
+    let mut library_path = env::split_paths(&env::var_os("LD_LIBRARY_PATH").unwrap_or_default()).collect::<Vec<_>>();
+    library_path.insert(0, lib_dir);
+    let output = Command::new(&exe)
+        .env("LD_LIBRARY_PATH", env::join_paths(library_path).unwrap())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "tests/ffi.c failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -1,8 +1,7 @@
 extern crate conway;
-extern crate rand;
 
+use conway::soup::Soup;
 use conway::universe::*;
-use rand::Rng;
 use std::{thread, time};
 
 fn main() {
@@ -22,19 +21,11 @@ fn main() {
     let mut uni = bigbang.unwrap();
     let step_time = time::Duration::from_millis(30);
 
-    let mut rng = rand::thread_rng();
     loop {
         println!("\x1b[H\x1b[2J{}", uni);
         println!("Gen: {}", uni.latest_gen());
-        let mut rand_word: u64 = rng.gen::<u8>() as u64;
-        for col in 60..64 {
-            for row in 15..17 {
-                if rand_word & 1 == 1 {
-                    let _ = uni.toggle(col, row, 0);
-                }
-                rand_word >>= 1;
-            }
-        }
+        let soup = Soup::new(Region::new(60, 15, 4, 2), 0.5, uni.latest_gen() as u64);
+        uni.random_soup(&soup).unwrap();
         uni.next();
         thread::sleep(step_time);
     }
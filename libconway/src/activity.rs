@@ -0,0 +1,211 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+use std::collections::VecDeque;
+
+use crate::grids::{BitGrid, CharGrid};
+use crate::universe::Region;
+
+/// A count for each cell of a region, returned by `Universe::ages` and `Universe::activity`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellCounts {
+    width:  usize,
+    height: usize,
+    counts: Vec<u32>, // row-major
+}
+
+impl CellCounts {
+    pub(crate) fn new(width: usize, height: usize, counts: Vec<u32>) -> CellCounts {
+        assert_eq!(counts.len(), width * height);
+        CellCounts { width, height, counts }
+    }
+
+    /// Width in cells.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in cells.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the count for the cell at (`col`, `row`), relative to the top-left corner of the
+    /// region.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `col` or `row` are out of range.
+    pub fn get(&self, col: usize, row: usize) -> u32 {
+        assert!(col < self.width && row < self.height);
+        self.counts[row * self.width + col]
+    }
+
+    /// Returns the largest count, or 0 if every count is 0.
+    pub fn max(&self) -> u32 {
+        self.counts.iter().cloned().max().unwrap_or(0)
+    }
+
+    /// Scales the counts to shades of gray in row-major order, from 0 (black) for a count of 0 to
+    /// 255 (white) for a count of `max`, or of the largest count if `max` is `None`. Counts above
+    /// `max` are white.
+    pub fn to_grayscale(&self, max: Option<u32>) -> Vec<u8> {
+        let max = u64::from(max.unwrap_or_else(|| self.max()));
+        self.counts
+            .iter()
+            .map(|&count| (u64::from(count).min(max) * 255).checked_div(max).unwrap_or(0) as u8)
+            .collect()
+    }
+
+    /// Like `to_grayscale`, but as the contents of a binary PGM image file, which most image
+    /// viewers can open.
+    pub fn to_pgm(&self, max: Option<u32>) -> Vec<u8> {
+        let mut pgm = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        pgm.extend(self.to_grayscale(max));
+        pgm
+    }
+}
+
+/// Per-cell ages and activity counts of a `Universe`, updated each time its latest generation
+/// advances. See `Universe::set_activity_window`.
+#[derive(Debug, Clone)]
+pub(crate) struct ActivityTracker {
+    width:  usize,
+    height: usize,
+    window: usize,
+    ages:   Vec<u32>,          // generations alive, for cells alive as of the last update (row-major)
+    counts: Vec<u32>,          // births plus deaths within the last `window` updates (row-major)
+    recent: VecDeque<BitGrid>, // cells born or died, for each of the last `window` updates
+}
+
+impl ActivityTracker {
+    /// Creates a tracker for a universe whose cells are currently `cells`.
+    pub(crate) fn new(cells: &BitGrid, window: usize) -> ActivityTracker {
+        let (width, height) = (cells.width_in_words() * 64, cells.height());
+        let mut tracker = ActivityTracker {
+            width,
+            height,
+            window,
+            ages: vec![0; width * height],
+            counts: vec![0; width * height],
+            recent: VecDeque::with_capacity(window + 1),
+        };
+        tracker.update(None, cells);
+        tracker
+    }
+
+    /// Records that the live cells changed from `opt_old`, or from an unknown state if `None`, to
+    /// `new`. Cells alive in an unknown state count as newborn, but not as activity.
+    pub(crate) fn update(&mut self, opt_old: Option<&BitGrid>, new: &BitGrid) {
+        let mut changed = BitGrid::new(new.width_in_words(), new.height());
+        for row in 0..self.height {
+            for word_col in 0..new.width_in_words() {
+                let new_word = new[row][word_col];
+                let old_word = opt_old.map_or(0, |old| old[row][word_col]);
+                let first = row * self.width + word_col * 64;
+                let ages = &mut self.ages[first..first + 64];
+                if new_word == 0 {
+                    ages.iter_mut().for_each(|age| *age = 0);
+                } else {
+                    for (i, age) in ages.iter_mut().enumerate() {
+                        let mask = 1 << (63 - i);
+                        *age = if new_word & mask == 0 {
+                            0
+                        } else if old_word & mask == 0 {
+                            1
+                        } else {
+                            // cells placed since the last update have no age yet
+                            (*age).max(1).saturating_add(1)
+                        };
+                    }
+                }
+                if opt_old.is_some() {
+                    changed[row][word_col] = old_word ^ new_word;
+                }
+            }
+        }
+        ActivityTracker::add_counts(&mut self.counts, &changed, self.width, true);
+        self.recent.push_back(changed);
+        while self.recent.len() > self.window {
+            let expired = self.recent.pop_front().unwrap();
+            ActivityTracker::add_counts(&mut self.counts, &expired, self.width, false);
+        }
+    }
+
+    /// Adds 1 to, or subtracts 1 from, the count of each cell whose bit is set in `grid`.
+    fn add_counts(counts: &mut [u32], grid: &BitGrid, width: usize, add: bool) {
+        grid.each_set(|col, row| {
+            let count = &mut counts[row * width + col];
+            *count = if add { *count + 1 } else { *count - 1 };
+        });
+    }
+
+    /// Returns the age of the cell at (`col`, `row`), given whether it is alive now.
+    pub(crate) fn age(&self, col: usize, row: usize, alive: bool) -> u32 {
+        if alive {
+            self.ages[row * self.width + col].max(1)
+        } else {
+            0
+        }
+    }
+
+    /// Returns the number of births and deaths of the cell at (`col`, `row`) within the window.
+    pub(crate) fn count(&self, col: usize, row: usize) -> u32 {
+        self.counts[row * self.width + col]
+    }
+
+    /// Keeps only the part within `region`, as for `Universe::crop`, with counts of 0 outside
+    /// of the old universe.
+    pub(crate) fn crop(&mut self, region: Region) {
+        let (width, height) = (region.width(), region.height());
+        let crop_counts = |old: &[u32]| -> Vec<u32> {
+            let mut new = vec![0; width * height];
+            for row in 0..height {
+                let old_row = region.top() + row as isize;
+                if old_row < 0 || old_row >= self.height as isize {
+                    continue;
+                }
+                for col in 0..width {
+                    let old_col = region.left() + col as isize;
+                    if old_col >= 0 && old_col < self.width as isize {
+                        new[row * width + col] = old[old_row as usize * self.width + old_col as usize];
+                    }
+                }
+            }
+            new
+        };
+        let ages = crop_counts(&self.ages);
+        let counts = crop_counts(&self.counts);
+        let mut recent = VecDeque::with_capacity(self.window + 1);
+        for changed in self.recent.iter() {
+            let mut cropped = BitGrid::new(width / 64, height);
+            changed.each_set(|col, row| {
+                if region.contains(col as isize, row as isize) {
+                    let col = (col as isize - region.left()) as usize;
+                    let row = (row as isize - region.top()) as usize;
+                    cropped[row][col / 64] |= 1 << (63 - col % 64);
+                }
+            });
+            recent.push_back(cropped);
+        }
+        self.width = width;
+        self.height = height;
+        self.ages = ages;
+        self.counts = counts;
+        self.recent = recent;
+    }
+}
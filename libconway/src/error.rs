@@ -15,9 +15,34 @@
  *  You should have received a copy of the GNU General Public License
  *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
 
+use std::num::ParseIntError;
+
 custom_error! {pub ConwayError
     InvalidData {reason: String} = "ConwayError->InvalidData->{reason}",
-    AccessDenied{reason: String} = "ConwayError->AccessDenied->{reason}"
+    AccessDenied{reason: String} = "ConwayError->AccessDenied->{reason}",
+    InsufficientEnergy{player_id: usize, cost: u32, energy: u32} =
+        "ConwayError->InsufficientEnergy->player {player_id} needs {cost} energy but has {energy}",
+    OutOfBounds{col: usize, row: usize, width: usize, height: usize} =
+        "ConwayError->OutOfBounds->col={col}, row={row} is outside of {width}x{height} cells",
+    RleSyntax{line: usize, column: usize, snippet: String, source: RleError} =
+        "ConwayError->RleSyntax->line {line}, column {column}, at {snippet:?}: {source}",
+    GenerationMismatch{gen0: usize, gen1: usize, reason: String} =
+        "ConwayError->GenerationMismatch->generations {gen0} to {gen1}: {reason}",
+    HistoryExhausted{generation: usize, oldest: usize} =
+        "ConwayError->HistoryExhausted->generation {generation} is not in the history, which starts at {oldest}"
+}
+
+// The cause of a `ConwayError::RleSyntax` error, which gives its location.
+custom_error! {pub RleError
+    UnexpectedChar{ch: char} = "unexpected character {ch:?}",
+    NumberTooLarge{max: usize} = "number is larger than {max}",
+    NumberBeforeEnd = "number right before `!`",
+    MissingEnd = "pattern does not end with `!`",
+    MisplacedComment = "comment line after a non-comment line",
+    MissingHeader = "missing header line",
+    MissingPattern = "missing pattern lines",
+    InvalidHeader{reason: String} = "invalid header line: {reason}",
+    InvalidNumber{source: ParseIntError} = "invalid number: {source}"
 }
 
 pub type ConwayResult<T> = ::std::result::Result<T, ConwayError>;
@@ -25,31 +50,90 @@ pub type ConwayResult<T> = ::std::result::Result<T, ConwayError>;
 impl PartialEq for ConwayError {
     fn eq(&self, other: &ConwayError) -> bool {
         use ConwayError::*;
-        match *self {
-            InvalidData {
-                reason: ref self_reason,
-            } => {
-                if let InvalidData {
-                    reason: ref other_reason,
-                } = *other
-                {
-                    self_reason == other_reason
-                } else {
-                    false
-                }
-            }
-            AccessDenied {
-                reason: ref self_reason,
-            } => {
-                if let AccessDenied {
-                    reason: ref other_reason,
-                } = *other
-                {
-                    self_reason == other_reason
-                } else {
-                    false
-                }
-            }
+        match (self, other) {
+            (InvalidData { reason: a }, InvalidData { reason: b }) => a == b,
+            (AccessDenied { reason: a }, AccessDenied { reason: b }) => a == b,
+            (
+                InsufficientEnergy {
+                    player_id: a_player_id,
+                    cost: a_cost,
+                    energy: a_energy,
+                },
+                InsufficientEnergy {
+                    player_id: b_player_id,
+                    cost: b_cost,
+                    energy: b_energy,
+                },
+            ) => (a_player_id, a_cost, a_energy) == (b_player_id, b_cost, b_energy),
+            (
+                OutOfBounds {
+                    col: a_col,
+                    row: a_row,
+                    width: a_width,
+                    height: a_height,
+                },
+                OutOfBounds {
+                    col: b_col,
+                    row: b_row,
+                    width: b_width,
+                    height: b_height,
+                },
+            ) => (a_col, a_row, a_width, a_height) == (b_col, b_row, b_width, b_height),
+            (
+                RleSyntax {
+                    line: a_line,
+                    column: a_column,
+                    snippet: a_snippet,
+                    source: a_source,
+                },
+                RleSyntax {
+                    line: b_line,
+                    column: b_column,
+                    snippet: b_snippet,
+                    source: b_source,
+                },
+            ) => (a_line, a_column, a_snippet, a_source) == (b_line, b_column, b_snippet, b_source),
+            (
+                GenerationMismatch {
+                    gen0: a_gen0,
+                    gen1: a_gen1,
+                    reason: a_reason,
+                },
+                GenerationMismatch {
+                    gen0: b_gen0,
+                    gen1: b_gen1,
+                    reason: b_reason,
+                },
+            ) => (a_gen0, a_gen1, a_reason) == (b_gen0, b_gen1, b_reason),
+            (
+                HistoryExhausted {
+                    generation: a_generation,
+                    oldest: a_oldest,
+                },
+                HistoryExhausted {
+                    generation: b_generation,
+                    oldest: b_oldest,
+                },
+            ) => (a_generation, a_oldest) == (b_generation, b_oldest),
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for RleError {
+    fn eq(&self, other: &RleError) -> bool {
+        use RleError::*;
+        match (self, other) {
+            (UnexpectedChar { ch: a }, UnexpectedChar { ch: b }) => a == b,
+            (NumberTooLarge { max: a }, NumberTooLarge { max: b }) => a == b,
+            (InvalidHeader { reason: a }, InvalidHeader { reason: b }) => a == b,
+            (InvalidNumber { source: a }, InvalidNumber { source: b }) => a == b,
+            (NumberBeforeEnd, NumberBeforeEnd)
+            | (MissingEnd, MissingEnd)
+            | (MisplacedComment, MisplacedComment)
+            | (MissingHeader, MissingHeader)
+            | (MissingPattern, MissingPattern) => true,
+            _ => false,
         }
     }
 }
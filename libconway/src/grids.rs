@@ -15,8 +15,9 @@
  *  You should have received a copy of the GNU General Public License
  *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
 
-use crate::rle::Pattern;
+use crate::rle::{self, Pattern};
 use crate::universe::Region;
+use serde::{Deserialize, Serialize};
 use std::cmp;
 use std::error::Error;
 use std::ops::{Index, IndexMut};
@@ -35,6 +36,69 @@ pub enum Rotation {
     CCW, // counter-clockwise
 }
 
+/// One of the eight ways to rotate and/or mirror a pattern. In scenario files, these are written in
+/// snake case, such as `rotate_cw` or `flip_horizontal`.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Identity,
+    #[serde(rename = "rotate_cw")]
+    RotateCW, // a quarter turn clockwise
+    Rotate180,
+    #[serde(rename = "rotate_ccw")]
+    RotateCCW, // a quarter turn counter-clockwise
+    FlipHorizontal,   // mirror left to right
+    FlipVertical,     // mirror top to bottom
+    FlipDiagonal,     // mirror across the diagonal from top-left to bottom-right
+    FlipAntidiagonal, // mirror across the diagonal from top-right to bottom-left
+}
+
+impl Transform {
+    /// All eight transforms, starting with `Identity`.
+    pub const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::RotateCW,
+        Transform::Rotate180,
+        Transform::RotateCCW,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::FlipDiagonal,
+        Transform::FlipAntidiagonal,
+    ];
+
+    /// Returns whether the transform swaps width and height.
+    pub fn swaps_axes(self) -> bool {
+        use Transform::*;
+        matches!(self, RotateCW | RotateCCW | FlipDiagonal | FlipAntidiagonal)
+    }
+
+    /// Returns the width and height of a `width` by `height` pattern after transforming it.
+    pub fn size(self, width: usize, height: usize) -> (usize, usize) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Returns where the cell at (`col`, `row`) in a `width` by `height` pattern ends up after
+    /// transforming the pattern, keeping its top-left corner in place.
+    pub fn apply(self, col: usize, row: usize, width: usize, height: usize) -> (usize, usize) {
+        use Transform::*;
+        let (right, bottom) = (width - 1, height - 1);
+        match self {
+            Identity => (col, row),
+            RotateCW => (bottom - row, col),
+            Rotate180 => (right - col, bottom - row),
+            RotateCCW => (row, right - col),
+            FlipHorizontal => (right - col, row),
+            FlipVertical => (col, bottom - row),
+            FlipDiagonal => (row, col),
+            FlipAntidiagonal => (bottom - row, right - col),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct BitGrid(pub Vec<Vec<u64>>);
 
@@ -211,6 +275,14 @@ impl BitGrid {
         }
     }
 
+    /// Returns the number of bits that are set (1).
+    pub fn count_ones(&self) -> usize {
+        self.0
+            .iter()
+            .map(|row| row.iter().map(|word| word.count_ones() as usize).sum::<usize>())
+            .sum()
+    }
+
     /// Calls callback on each bit that is set (1). Callback receives (col, row).
     pub fn each_set<F: FnMut(usize, usize)>(&self, mut callback: F) {
         for row in 0..self.height() {
@@ -305,6 +377,18 @@ pub trait CharGrid {
     /// * `visibility` is invalid. That is, it equals `Some(player_id)`, but there is no such `player_id`.
     fn write_at_position(&mut self, col: usize, row: usize, ch: char, visibility: Option<usize>);
 
+    /// Write `len` copies of char `ch` to the cells starting at (`col`, `row`) and going right.
+    /// The default implementation calls `write_at_position` for each cell.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as `write_at_position`.
+    fn write_run(&mut self, col: usize, row: usize, ch: char, len: usize, visibility: Option<usize>) {
+        for i in 0..len {
+            self.write_at_position(col + i, row, ch, visibility);
+        }
+    }
+
     /// Is `ch` a valid character?
     fn is_valid(ch: char) -> bool;
 
@@ -319,11 +403,9 @@ pub trait CharGrid {
     fn to_pattern(&self, visibility: Option<usize>) -> Pattern {
         fn push(result: &mut String, output_col: &mut usize, rle_len: usize, ch: char) {
             let what_to_add = if rle_len == 1 {
-                let mut s = String::with_capacity(1);
-                s.push(ch);
-                s
+                rle::cell_token(ch)
             } else {
-                format!("{}{}", rle_len, ch)
+                format!("{}{}", rle_len, rle::cell_token(ch))
             };
             if *output_col + what_to_add.len() > 70 {
                 result.push_str("\r\n");
@@ -21,14 +21,20 @@ extern crate env_logger;
 #[macro_use]
 extern crate custom_error;
 
+pub mod activity;
 pub mod error;
 pub mod grids;
+pub mod reference;
 pub mod rle;
+pub mod rule;
+pub mod scenario;
+pub mod soup;
 pub mod universe;
 
-pub use error::{ConwayError, ConwayResult};
+pub use error::{ConwayError, ConwayResult, RleError};
 
-pub use grids::Rotation;
+pub use grids::{Rotation, Transform};
+pub use rule::{Neighborhood, Rule};
 
 #[cfg(test)]
 pub mod tests;
@@ -15,66 +15,459 @@
  *  You should have received a copy of the GNU General Public License
  *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
 
+//! Headless command-line tool for running, converting, inspecting and comparing patterns and
+//! scenarios. Run it without a subcommand to watch the demo scenario.
+
+extern crate clap;
 extern crate conway;
+#[macro_use]
+extern crate serde_json;
 
-use conway::universe::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
 use std::{thread, time};
 
-fn main() {
-    let mut uni = Universe::new(
-        128,
-        32,
-        true,
-        16,
-        2,
-        vec![Region::new(40, 6, 16, 8), Region::new(60, 16, 8, 8)],
-        16,
-    )
-    .unwrap();
-    let step_time = time::Duration::from_millis(150);
-
-    // pi heptomino
-    uni.toggle(62, 17, 1).unwrap();
-    uni.toggle(63, 17, 1).unwrap();
-    uni.toggle(61, 18, 1).unwrap();
-    uni.toggle(62, 18, 1).unwrap();
-    uni.toggle(62, 19, 1).unwrap();
-
-    // Spaceship in reverse direction
-    uni.toggle(48, 6, 0).unwrap();
-    uni.toggle(47, 7, 0).unwrap();
-    uni.toggle(47, 8, 0).unwrap();
-    uni.toggle(52, 8, 0).unwrap();
-    uni.toggle(47, 9, 0).unwrap();
-    uni.toggle(48, 9, 0).unwrap();
-    uni.toggle(49, 9, 0).unwrap();
-    uni.toggle(50, 9, 0).unwrap();
-    uni.toggle(51, 9, 0).unwrap();
-
-    uni.set_unchecked(74, 13, CellState::Wall);
-    uni.set_unchecked(75, 13, CellState::Wall);
-    uni.set_unchecked(76, 13, CellState::Wall);
-    uni.set_unchecked(77, 13, CellState::Wall);
-    uni.set_unchecked(78, 13, CellState::Wall);
-    uni.set_unchecked(78, 14, CellState::Wall);
-    uni.set_unchecked(78, 15, CellState::Wall);
-    uni.set_unchecked(78, 16, CellState::Wall);
-    uni.set_unchecked(78, 17, CellState::Wall);
-    uni.set_unchecked(78, 18, CellState::Wall);
-    uni.set_unchecked(78, 19, CellState::Wall);
-    uni.set_unchecked(78, 20, CellState::Wall);
-    uni.set_unchecked(78, 21, CellState::Wall);
-    uni.set_unchecked(78, 22, CellState::Wall);
-    uni.set_unchecked(78, 23, CellState::Wall);
-    uni.set_unchecked(78, 24, CellState::Wall);
-    uni.set_unchecked(77, 24, CellState::Wall);
-    uni.set_unchecked(76, 24, CellState::Wall);
-    uni.set_unchecked(75, 24, CellState::Wall);
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use conway::grids::CharGrid;
+use conway::rle::{HeaderLine, Pattern, PatternFile};
+use conway::scenario::{PlacedPattern, Scenario, Topology};
+use conway::universe::{BigBang, CellState, Region, Universe};
+use conway::{Rule, Transform};
+
+const DEFAULT_MARGIN: usize = 16;
+const DEFAULT_DELAY_MS: u64 = 150;
+
+/// Exit status for errors. `diff` exits with 1 if the files differ.
+const EXIT_ERROR: i32 = 2;
+
+type CliResult<T> = Result<T, String>;
+
+/// A loaded input file.
+enum Input {
+    Pattern(PatternFile),
+    Scenario(Scenario),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Rle,
+    Plaintext,
+}
+
+impl Format {
+    /// Returns the format named by `name`, or else the format for the extension of `path`, or
+    /// else RLE.
+    fn choose(name: Option<&str>, path: Option<&str>) -> CliResult<Format> {
+        match name {
+            Some("rle") => Ok(Format::Rle),
+            Some("cells") => Ok(Format::Plaintext),
+            Some(other) => Err(format!("unknown format {:?}; expected rle or cells", other)),
+            None => match path.and_then(|path| Path::new(path).extension()) {
+                Some(ext) if ext == "cells" => Ok(Format::Plaintext),
+                _ => Ok(Format::Rle),
+            },
+        }
+    }
+}
+
+/// Loads a scenario (.toml), a Life plaintext pattern (.cells), or an RLE pattern (anything else).
+fn load(path: &str) -> CliResult<Input> {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+    if extension == Some("toml") {
+        return Scenario::from_file(path)
+            .map(Input::Scenario)
+            .map_err(|e| format!("{}: {}", path, e));
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let file = if extension == Some("cells") {
+        PatternFile::from_plaintext(&text)
+    } else {
+        PatternFile::from_str(&text)
+    };
+    file.map(Input::Pattern).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Builds a universe from `input`, returning it along with the region holding the pattern, or
+/// the whole universe for a scenario. The region is `None` for an empty pattern, 0x0 cells with
+/// none of them set. A pattern is placed `margin` cells from the top-left corner of a universe big
+/// enough to leave at least that many dead cells around it.
+fn birth(input: &Input, margin: usize, rule_override: Option<&Rule>) -> CliResult<(Universe, Option<Region>)> {
+    let (scenario, opt_region) = match *input {
+        Input::Scenario(ref scenario) => {
+            let mut scenario = scenario.clone();
+            if let Some(rule) = rule_override {
+                scenario.rule = Some(rule.clone());
+            }
+            (scenario, None)
+        }
+        Input::Pattern(ref file) => {
+            let rule = match rule_override {
+                Some(rule) => rule.clone(),
+                None => file.header_line.to_rule().map_err(|e| e.to_string())?,
+            };
+            // the size in the header line, or of the pattern itself if that is bigger
+            let (width, height) = file.pattern.calc_size().map_err(|e| e.to_string())?;
+            let (pattern_width, pattern_height) = (width.max(file.width()), height.max(file.height()));
+            let min_size = 2 * rule.neighborhood().range() + 1;
+            let width = (pattern_width + 2 * margin).max(min_size);
+            let height = (pattern_height + 2 * margin).max(min_size);
+            let width = width.div_ceil(64) * 64;
+            // owned cells are written as the owner's char, even in dying cell tokens
+            let num_players = file
+                .pattern
+                .0
+                .chars()
+                .filter_map(|ch| match CellState::from_char(ch) {
+                    Some(CellState::Alive(Some(player_id))) => Some(player_id + 1),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0);
+            let region = Region::new(margin as isize, margin as isize, pattern_width, pattern_height);
+            let scenario = Scenario {
+                width,
+                height,
+                topology: Topology::Torus,
+                // pattern files have no fog, and the smallest fog circle fits in any universe
+                fog_radius: Some(1),
+                rule: Some(rule),
+                player_writable: vec![Region::new(0, 0, width, height); num_players],
+                patterns: vec![PlacedPattern {
+                    pattern:   file.pattern.clone(),
+                    width:     pattern_width,
+                    height:    pattern_height,
+                    left:      margin as isize,
+                    top:       margin as isize,
+                    owner:     None,
+                    transform: Transform::Identity,
+                }],
+            };
+            (scenario, Some(region))
+        }
+    };
+    let uni = BigBang::from_scenario(&scenario).birth().map_err(|e| e.to_string())?;
+    // the pattern takes up at least one cell, unless the header says it takes up none
+    let is_empty = matches!(*input, Input::Pattern(ref file) if file.width() == 0 && file.height() == 0);
+    if is_empty && bounding_box(&uni).is_none() {
+        return Ok((uni, None));
+    }
+    let region = opt_region.unwrap_or_else(|| uni.region());
+    Ok((uni, Some(region)))
+}
+
+/// Returns the pattern of the cells in `region` of the universe, or an empty pattern, 0x0 cells,
+/// if there is no region.
+fn to_pattern_file(uni: &Universe, opt_region: Option<Region>) -> PatternFile {
+    match opt_region {
+        Some(region) => uni.to_pattern_file(region, None),
+        None => PatternFile {
+            comment_lines: vec![],
+            header_line:   HeaderLine {
+                x:    0,
+                y:    0,
+                rule: Some(uni.rule().to_string()),
+            },
+            pattern:       Pattern("!".to_owned()),
+        },
+    }
+}
+
+/// Returns the smallest region holding every non-dead cell, ignoring wrapping, or `None` if
+/// every cell is dead.
+fn bounding_box(uni: &Universe) -> Option<Region> {
+    let mut cells = uni.cells_in(uni.region(), None);
+    let (col, row, _) = cells.next()?;
+    let (mut left, mut top, mut right, mut bottom) = (col, row, col, row);
+    for (col, row, _) in cells {
+        left = left.min(col);
+        right = right.max(col);
+        top = top.min(row);
+        bottom = bottom.max(row);
+    }
+    Some(Region::new(
+        left as isize,
+        top as isize,
+        right - left + 1,
+        bottom - top + 1,
+    ))
+}
+
+fn write_output(text: &str, output: Option<&str>) -> CliResult<()> {
+    match output {
+        Some(path) => fs::write(path, text).map_err(|e| format!("cannot write {}: {}", path, e)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn format_pattern(file: &PatternFile, format: Format) -> CliResult<String> {
+    match format {
+        Format::Rle => Ok(file.to_string()),
+        Format::Plaintext => file.to_plaintext().map_err(|e| e.to_string()),
+    }
+}
+
+fn parse_number<T: FromStr>(matches: &ArgMatches, name: &str) -> CliResult<Option<T>> {
+    matches
+        .value_of(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("--{} must be a non-negative integer, not {:?}", name, value))
+        })
+        .transpose()
+}
 
+fn parse_rule(matches: &ArgMatches) -> CliResult<Option<Rule>> {
+    matches
+        .value_of("rule")
+        .map(|rule| Rule::from_str(rule).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// Prints the universe every `delay` until `generations` have passed, or forever.
+fn animate(uni: &mut Universe, generations: Option<usize>, delay: time::Duration) {
+    let mut remaining = generations;
     loop {
         println!("\x1b[H\x1b[2J{}", uni);
         println!("Gen: {}", uni.latest_gen());
+        match remaining {
+            Some(0) => return,
+            Some(ref mut n) => *n -= 1,
+            None => {}
+        }
+        uni.next();
+        thread::sleep(delay);
+    }
+}
+
+fn run(matches: &ArgMatches) -> CliResult<()> {
+    let input = load(matches.value_of("INPUT").unwrap())?;
+    let generations: Option<usize> = parse_number(matches, "generations")?;
+    let margin = parse_number(matches, "margin")?.unwrap_or(DEFAULT_MARGIN);
+    let (mut uni, _) = birth(&input, margin, parse_rule(matches)?.as_ref())?;
+
+    if matches.is_present("animate") {
+        let delay = parse_number(matches, "delay")?.unwrap_or(DEFAULT_DELAY_MS);
+        animate(&mut uni, generations, time::Duration::from_millis(delay));
+        return Ok(());
+    }
+
+    for _ in 0..generations.unwrap_or(0) {
+        uni.next();
+    }
+    let output = matches.value_of("output");
+    let format = Format::choose(matches.value_of("format"), output)?;
+    let file = to_pattern_file(&uni, bounding_box(&uni));
+    write_output(&format_pattern(&file, format)?, output)
+}
+
+fn convert(matches: &ArgMatches) -> CliResult<()> {
+    let output = matches.value_of("output");
+    let format = Format::choose(matches.value_of("format"), output)?;
+    let (uni, opt_region) = birth(&load(matches.value_of("INPUT").unwrap())?, 0, None)?;
+    write_output(&format_pattern(&to_pattern_file(&uni, opt_region), format)?, output)
+}
+
+fn census(matches: &ArgMatches) -> CliResult<()> {
+    let input = load(matches.value_of("INPUT").unwrap())?;
+    let generations = parse_number(matches, "generations")?.unwrap_or(0);
+    let margin = parse_number(matches, "margin")?.unwrap_or(DEFAULT_MARGIN);
+    let (mut uni, _) = birth(&input, margin, parse_rule(matches)?.as_ref())?;
+    for _ in 0..generations {
         uni.next();
-        thread::sleep(step_time);
+    }
+
+    let (mut population, mut walls, mut dying, mut unowned) = (0, 0, 0, 0);
+    let mut players = vec![0; uni.num_players()];
+    for (_, _, state) in uni.cells_in(uni.region(), None) {
+        match state {
+            CellState::Alive(opt_player_id) => {
+                population += 1;
+                match opt_player_id {
+                    Some(player_id) => players[player_id] += 1,
+                    None => unowned += 1,
+                }
+            }
+            CellState::Dying(..) => dying += 1,
+            CellState::Wall => walls += 1,
+            CellState::Dead | CellState::Fog => {}
+        }
+    }
+    let bounding_box = bounding_box(&uni).map(|region| {
+        json!({
+            "left": region.left(),
+            "top": region.top(),
+            "width": region.width(),
+            "height": region.height(),
+        })
+    });
+    let census = json!({
+        "generations": generations,
+        "width": uni.width(),
+        "height": uni.height(),
+        "rule": uni.rule().to_string(),
+        "population": population,
+        "unowned": unowned,
+        "players": players,
+        "dying": dying,
+        "walls": walls,
+        "bounding_box": bounding_box,
+    });
+    println!("{}", serde_json::to_string_pretty(&census).unwrap());
+    Ok(())
+}
+
+/// Returns the size of the pattern or scenario in the file at `path`, and its non-dead cells,
+/// keyed by (`row`, `col`) so that they are in row-major order.
+fn load_cells(path: &str) -> CliResult<((usize, usize), BTreeMap<(usize, usize), CellState>)> {
+    let (uni, opt_region) = birth(&load(path)?, 0, None)?;
+    let region = match opt_region {
+        Some(region) => region,
+        None => return Ok(((0, 0), BTreeMap::new())),
+    };
+    let cells = uni
+        .cells_in(region, None)
+        .map(|(col, row, state)| ((row, col), state))
+        .collect();
+    Ok(((region.width(), region.height()), cells))
+}
+
+/// Returns whether the files differ, after printing their differences.
+fn diff(matches: &ArgMatches) -> CliResult<bool> {
+    let (size_a, cells_a) = load_cells(matches.value_of("A").unwrap())?;
+    let (size_b, cells_b) = load_cells(matches.value_of("B").unwrap())?;
+
+    let mut differ = false;
+    if size_a != size_b {
+        println!("size: {}x{} -> {}x{}", size_a.0, size_a.1, size_b.0, size_b.1);
+        differ = true;
+    }
+    let positions: BTreeSet<&(usize, usize)> = cells_a.keys().chain(cells_b.keys()).collect();
+    for &(row, col) in positions {
+        let state_a = cells_a.get(&(row, col)).copied().unwrap_or(CellState::Dead);
+        let state_b = cells_b.get(&(row, col)).copied().unwrap_or(CellState::Dead);
+        if state_a != state_b {
+            println!("({}, {}): {:?} -> {:?}", col, row, state_a, state_b);
+            differ = true;
+        }
+    }
+    Ok(differ)
+}
+
+fn demo() {
+    let scenario: Scenario = include_str!("../scenarios/demo.toml").parse().unwrap();
+    let mut uni = BigBang::from_scenario(&scenario).birth().unwrap();
+    animate(&mut uni, None, time::Duration::from_millis(DEFAULT_DELAY_MS));
+}
+
+fn main() {
+    let input_arg = Arg::with_name("INPUT")
+        .help("pattern (.rle or .cells) or scenario (.toml) file")
+        .required(true)
+        .index(1);
+    let generations_arg = Arg::with_name("generations")
+        .short("g")
+        .long("generations")
+        .help("number of generations to run [default 0]")
+        .takes_value(true);
+    let margin_help = format!(
+        "dead cells to leave around a pattern, which wraps around the edges of the universe \
+         [default {}]",
+        DEFAULT_MARGIN
+    );
+    let margin_arg = Arg::with_name("margin")
+        .long("margin")
+        .help(&margin_help)
+        .takes_value(true);
+    let rule_arg = Arg::with_name("rule")
+        .long("rule")
+        .help("rule to run, such as B36/S23, instead of the input's rule")
+        .takes_value(true);
+    let output_arg = Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .help("file to write to [default stdout]")
+        .takes_value(true);
+    let format_arg = Arg::with_name("format")
+        .short("f")
+        .long("format")
+        .help("output format, rle or cells [default from output file extension, else rle]")
+        .takes_value(true);
+
+    let matches = App::new("conway")
+        .about("runs, converts and inspects Conwayste patterns and scenarios; shows a demo if no subcommand is given")
+        .setting(AppSettings::VersionlessSubcommands)
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("runs a pattern or scenario, and writes the live part of the result as a pattern")
+                .arg(input_arg.clone())
+                .arg(generations_arg.clone())
+                .arg(margin_arg.clone())
+                .arg(rule_arg.clone())
+                .arg(output_arg.clone())
+                .arg(format_arg.clone())
+                .arg(
+                    Arg::with_name("animate")
+                        .long("animate")
+                        .help("print each generation to the terminal instead, forever if -g is not given"),
+                )
+                .arg(
+                    Arg::with_name("delay")
+                        .long("delay")
+                        .help(&format!(
+                            "milliseconds between generations with --animate [default {}]",
+                            DEFAULT_DELAY_MS
+                        ))
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("converts a pattern or scenario to a pattern in another format")
+                .arg(input_arg.clone())
+                .arg(output_arg)
+                .arg(format_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("census")
+                .alias("stats")
+                .about("prints cell counts of a pattern or scenario as JSON")
+                .arg(input_arg)
+                .arg(generations_arg)
+                .arg(margin_arg)
+                .arg(rule_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("lists the cells that differ between two files; exits with 1 if there are any")
+                .arg(Arg::with_name("A").required(true).index(1))
+                .arg(Arg::with_name("B").required(true).index(2)),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("run", Some(sub_matches)) => run(sub_matches),
+        ("convert", Some(sub_matches)) => convert(sub_matches),
+        ("census", Some(sub_matches)) => census(sub_matches),
+        ("diff", Some(sub_matches)) => diff(sub_matches).map(|differ| {
+            if differ {
+                process::exit(1);
+            }
+        }),
+        _ => {
+            demo();
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("conway: error: {}", e);
+        process::exit(EXIT_ERROR);
     }
 }
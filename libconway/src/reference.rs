@@ -0,0 +1,405 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! A naive, cell-by-cell implementation of the game, used to verify the bit-parallel engine in
+//! `Universe`. It is far too slow for anything else.
+
+use crate::grids::CharGrid;
+use crate::rle::Pattern;
+use crate::rule::Rule;
+use crate::universe::{BigBang, CellState, PlayerBuilder, Region, Universe};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::str::FromStr;
+use std::{cmp, fmt};
+
+/// Rules picked from by `random_universe`.
+const RANDOM_RULES: [&str; 8] = [
+    "B3/S23",
+    "B36/S23",
+    "B3/S23V",
+    "B2/S34H",
+    "B2/S/C3",
+    "B2/S345/C4",
+    "B3/S23/C6",
+    "R2,C0,M1,S3..5,B3..4,NM",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    known:       bool,
+    wall:        bool,
+    alive:       bool,
+    owner:       Option<usize>, // owner of the live or dying cell
+    dying_stage: usize,         // 0 if not dying
+    fog:         Vec<bool>,     // indexed by player_id
+}
+
+/// Reference implementation of the game semantics of `Universe::next`: rules and rule zones,
+/// dying stages, walls, known cells, cell ownership, and fog.
+pub struct ReferenceUniverse {
+    width:      usize,
+    height:     usize,
+    generation: usize,
+    fog_radius: usize,
+    rules:      Vec<(Rule, Vec<(isize, isize)>)>, // distinct rules, and their neighbor offsets
+    rule_index: Vec<Vec<usize>>,                  // index into `rules` for each cell
+    cells:      Vec<Vec<Cell>>,
+}
+
+impl ReferenceUniverse {
+    /// Creates a reference universe in the same state as the latest generation of `uni`, as
+    /// observed through `CharGrid::get_run`.
+    pub fn from_universe(uni: &Universe) -> ReferenceUniverse {
+        let (width, height) = (uni.width(), uni.height());
+        let num_players = uni.num_players();
+        let mut rules: Vec<(Rule, Vec<(isize, isize)>)> = vec![];
+        let mut rule_index = Vec::with_capacity(height);
+        let mut cells = Vec::with_capacity(height);
+        for row in 0..height {
+            let mut rule_index_row = Vec::with_capacity(width);
+            let mut cells_row = Vec::with_capacity(width);
+            for col in 0..width {
+                let rule = uni.rule_at(col, row);
+                let i = match rules.iter().position(|(r, _)| r == rule) {
+                    Some(i) => i,
+                    None => {
+                        rules.push((rule.clone(), rule.neighborhood().offsets()));
+                        rules.len() - 1
+                    }
+                };
+                rule_index_row.push(i);
+
+                let ch = uni.get_run(col, row, None).1;
+                let mut cell = Cell {
+                    known:       ch != '?',
+                    wall:        false,
+                    alive:       false,
+                    owner:       None,
+                    dying_stage: 0,
+                    fog:         (0..num_players)
+                        .map(|player_id| ch == '?' || uni.get_run(col, row, Some(player_id)).1 == '?')
+                        .collect(),
+                };
+                match CellState::from_char(ch) {
+                    Some(CellState::Wall) => cell.wall = true,
+                    Some(CellState::Alive(opt_player_id)) => {
+                        cell.alive = true;
+                        cell.owner = opt_player_id;
+                    }
+                    Some(CellState::Dying(opt_player_id, stage)) => {
+                        cell.dying_stage = stage;
+                        cell.owner = opt_player_id;
+                    }
+                    _ => {}
+                }
+                cells_row.push(cell);
+            }
+            rule_index.push(rule_index_row);
+            cells.push(cells_row);
+        }
+        ReferenceUniverse {
+            width,
+            height,
+            generation: uni.latest_gen(),
+            fog_radius: uni.fog_radius(),
+            rules,
+            rule_index,
+            cells,
+        }
+    }
+
+    /// Get the latest generation number (1-based).
+    pub fn latest_gen(&self) -> usize {
+        self.generation
+    }
+
+    /// Returns the cell at `(col + dx, row + dy)`, wrapping around the edges.
+    fn neighbor(&self, col: usize, row: usize, dx: isize, dy: isize) -> &Cell {
+        let col = (col as isize + dx).rem_euclid(self.width as isize) as usize;
+        let row = (row as isize + dy).rem_euclid(self.height as isize) as usize;
+        &self.cells[row][col]
+    }
+
+    /// Compute the next generation. Returns the new latest generation number.
+    #[allow(clippy::should_implement_trait)] // named like `Universe::next`, which it mirrors
+    pub fn next(&mut self) -> usize {
+        let num_players = self.cells[0][0].fog.len();
+        let mut next_cells = self.cells.clone();
+        for (row, next_row) in next_cells.iter_mut().enumerate() {
+            for (col, next) in next_row.iter_mut().enumerate() {
+                let (rule, offsets) = &self.rules[self.rule_index[row][col]];
+                let cell = &self.cells[row][col];
+
+                // a cell stays known only if it and all of its neighbors are known
+                next.known = cell.known && offsets.iter().all(|&(dx, dy)| self.neighbor(col, row, dx, dy).known);
+
+                let mut count = offsets
+                    .iter()
+                    .filter(|&&(dx, dy)| self.neighbor(col, row, dx, dy).alive)
+                    .count();
+                if rule.middle() && cell.alive {
+                    count += 1;
+                }
+                next.alive = if cell.alive {
+                    rule.survival().contains(&count)
+                } else {
+                    rule.birth().contains(&count)
+                };
+                next.alive &= next.known && !cell.wall && cell.dying_stage == 0;
+
+                // dying cells keep their owner until they are dead
+                next.dying_stage = 0;
+                if next.known {
+                    if cell.dying_stage > 0 && cell.dying_stage < rule.dying_stages() {
+                        next.dying_stage = cell.dying_stage + 1;
+                    } else if cell.alive && !next.alive && rule.dying_stages() > 0 {
+                        next.dying_stage = 1;
+                    }
+                }
+
+                // a live cell belongs to a player if it or one of its neighbors did, unless it
+                // would belong to several players
+                next.owner = None;
+                if next.alive {
+                    let mut claims = (0..num_players).filter(|&player_id| {
+                        cell.alive && cell.owner == Some(player_id)
+                            || offsets.iter().any(|&(dx, dy)| {
+                                let neighbor = self.neighbor(col, row, dx, dy);
+                                neighbor.alive && neighbor.owner == Some(player_id)
+                            })
+                    });
+                    if let (Some(player_id), None) = (claims.next(), claims.next()) {
+                        next.owner = Some(player_id);
+                    }
+                } else if next.dying_stage > 0 {
+                    next.owner = cell.owner;
+                }
+            }
+        }
+
+        // clear fog around cells that players acquired in this generation
+        let radius = self.fog_radius as isize;
+        for (row, cells_row) in self.cells.iter().enumerate() {
+            for (col, cell) in cells_row.iter().enumerate() {
+                let player_id = match next_cells[row][col] {
+                    Cell {
+                        alive: true,
+                        owner: Some(player_id),
+                        ..
+                    } if !(cell.alive && cell.owner == Some(player_id)) => player_id,
+                    _ => continue,
+                };
+                for dy in -(radius - 1)..radius {
+                    for dx in -(radius - 1)..radius {
+                        if dx * dx + dy * dy < radius * radius {
+                            let fog_col = (col as isize + dx).rem_euclid(self.width as isize) as usize;
+                            let fog_row = (row as isize + dy).rem_euclid(self.height as isize) as usize;
+                            next_cells[fog_row][fog_col].fog[player_id] = false;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.cells = next_cells;
+        self.generation += 1;
+        self.generation
+    }
+
+    /// Returns the character for the cell at (`col`, `row`), as `CharGrid::get_run` would.
+    fn cell_char(&self, col: usize, row: usize, visibility: Option<usize>) -> char {
+        let cell = &self.cells[row][col];
+        if !cell.known || visibility.is_some_and(|player_id| cell.fog[player_id]) {
+            CellState::Fog.to_char()
+        } else if cell.alive {
+            CellState::Alive(cell.owner).to_char()
+        } else if cell.dying_stage > 0 {
+            CellState::Dying(cell.owner, cell.dying_stage).to_char()
+        } else if cell.wall {
+            CellState::Wall.to_char()
+        } else {
+            CellState::Dead.to_char()
+        }
+    }
+}
+
+impl CharGrid for ReferenceUniverse {
+    fn is_valid(ch: char) -> bool {
+        CellState::from_char(ch).is_some()
+    }
+
+    fn write_at_position(&mut self, _col: usize, _row: usize, _ch: char, _visibility: Option<usize>) {
+        unimplemented!("ReferenceUniverse can only be created from a Universe");
+    }
+
+    /// Return width in cells.
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Return height in cells.
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get_run(&self, col: usize, row: usize, visibility: Option<usize>) -> (usize, char) {
+        let ch = self.cell_char(col, row, visibility);
+        let run = (col..self.width)
+            .take_while(|&c| self.cell_char(c, row, visibility) == ch)
+            .count();
+        (run, ch)
+    }
+}
+
+/// The first cell at which a `Universe` and a `ReferenceUniverse` disagree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub generation: usize,
+    pub visibility: Option<usize>,
+    pub col:        usize,
+    pub row:        usize,
+    pub expected:   Pattern, // as computed by the reference implementation
+    pub actual:     Pattern, // as computed by `Universe`
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Divergence at generation {}, visibility {:?}, col {}, row {}",
+            self.generation, self.visibility, self.col, self.row
+        )?;
+        writeln!(f, "expected: {}", self.expected.0)?;
+        write!(f, "actual:   {}", self.actual.0)
+    }
+}
+
+/// Compares the latest generation of `uni` and `reference` from the point of view of the server
+/// and of every player. Returns the first differing cell, if any, in row-major order.
+pub fn first_divergence(uni: &Universe, reference: &ReferenceUniverse) -> Option<Divergence> {
+    let visibilities = Some(None).into_iter().chain((0..uni.num_players()).map(Some));
+    for visibility in visibilities {
+        let actual = uni.to_pattern(visibility);
+        let expected = reference.to_pattern(visibility);
+        if actual == expected {
+            continue;
+        }
+        for row in 0..uni.height() {
+            for col in 0..uni.width() {
+                if uni.get_run(col, row, visibility).1 != reference.cell_char(col, row, visibility) {
+                    return Some(Divergence {
+                        generation: uni.latest_gen(),
+                        visibility,
+                        col,
+                        row,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Runs `uni` and a `ReferenceUniverse` created from it for `generations` generations, comparing
+/// them after each one.
+///
+/// # Errors
+///
+/// Returns the first `Divergence` found.
+pub fn run_differential(uni: &mut Universe, generations: usize) -> Result<(), Divergence> {
+    let mut reference = ReferenceUniverse::from_universe(uni);
+    if let Some(divergence) = first_divergence(uni, &reference) {
+        return Err(divergence);
+    }
+    for _ in 0..generations {
+        uni.next();
+        reference.next();
+        if let Some(divergence) = first_divergence(uni, &reference) {
+            return Err(divergence);
+        }
+    }
+    Ok(())
+}
+
+/// Creates a small random universe for `run_differential`, with random rules and rule zones,
+/// players, walls, live and dying cells, and fog. Half of the universes returned are clients
+/// which only know what one of the players can see.
+pub fn random_universe<R: Rng>(rng: &mut R) -> Universe {
+    let width = 64 * rng.gen_range(1..=2);
+    let height = rng.gen_range(8..=48);
+    let random_region = |rng: &mut R| {
+        let (left, top) = (rng.gen_range(0..width), rng.gen_range(0..height));
+        Region::new(
+            left as isize,
+            top as isize,
+            rng.gen_range(1..=width - left),
+            rng.gen_range(1..=height - top),
+        )
+    };
+    let random_rule = |rng: &mut R| Rule::from_str(RANDOM_RULES.choose(rng).unwrap()).unwrap();
+
+    let mut bigbang = BigBang::new()
+        .width(width)
+        .height(height)
+        // `BigBang::birth` rejects fog circles (`2 * fog_radius - 1` across) that don't fit
+        .fog_radius(rng.gen_range(1..=cmp::min(8, height.div_ceil(2))))
+        .rule(random_rule(rng));
+    if rng.gen_bool(0.5) {
+        bigbang = bigbang.rule_zone(random_region(rng), random_rule(rng));
+    }
+    let num_players = rng.gen_range(0..=3);
+    for _ in 0..num_players {
+        bigbang = bigbang.add_player(PlayerBuilder::new(random_region(rng)));
+    }
+    let mut uni = bigbang.birth().unwrap();
+
+    let density = rng.gen_range(0.1..0.6);
+    for row in 0..height {
+        for col in 0..width {
+            let opt_player_id = if num_players > 0 && rng.gen_bool(0.5) {
+                Some(rng.gen_range(0..num_players))
+            } else {
+                None
+            };
+            let dying_stages = uni.rule_at(col, row).dying_stages();
+            let state = if rng.gen_bool(0.03) {
+                CellState::Wall
+            } else if dying_stages > 0 && rng.gen_bool(0.1) {
+                CellState::Dying(opt_player_id, rng.gen_range(1..=dying_stages))
+            } else if rng.gen_bool(density) {
+                CellState::Alive(opt_player_id)
+            } else {
+                continue;
+            };
+            uni.set_unchecked(col, row, state);
+        }
+    }
+
+    if num_players == 0 || rng.gen_bool(0.5) {
+        return uni;
+    }
+
+    // a client only knows what the server sends it
+    let player_id = rng.gen_range(0..num_players);
+    let diff = uni.diff(0, 1, Some(player_id)).unwrap();
+    let mut client = bigbang.server_mode(false).birth().unwrap();
+    client.apply(&diff, Some(player_id)).unwrap();
+    client
+}
@@ -18,10 +18,18 @@
 const MAX_NUMBER: usize = 50000;
 pub const NO_OP_CHAR: char = '"';
 
-use crate::error::{ConwayError, ConwayResult};
-use crate::grids::{BitGrid, CharGrid};
+/// Dying cells (Generations rules only) are written as two-char tokens: one of these prefixes,
+/// indexed by dying stage minus one, followed by the owner's char (`o` if unowned, else `A`-`V`).
+/// For example, `qB` is a cell at dying stage 2 owned by player 1.
+const DYING_STAGE_PREFIXES: [char; MAX_STATES - 2] = ['p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y'];
+
+use crate::error::{ConwayError, ConwayResult, RleError};
+use crate::grids::{BitGrid, BitOperation, CharGrid};
+use crate::rule::{Rule, MAX_STATES};
+use crate::universe::CellState;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 
 /// This contains just the RLE pattern string. For example: "4bobo$7b3o!"
@@ -63,24 +71,124 @@ impl PatternFile {
     pub fn to_grid<G: CharGrid>(&self, grid: &mut G, visibility: Option<usize>) -> ConwayResult<()> {
         self.pattern.to_grid(grid, visibility)
     }
+
+    /// Generate a PatternFile from the contents of a Life plaintext (.cells) file, in which lines
+    /// starting with `!` are comments, and each other line is a row of `.` (dead) and `O` (alive)
+    /// cells. `*` is also accepted for alive cells. The comments are dropped, and there is no rule.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if a row contains any other character.
+    pub fn from_plaintext(file_contents: &str) -> ConwayResult<PatternFile> {
+        let rows: Vec<(usize, &str)> = (1..)
+            .zip(file_contents.lines())
+            .filter(|&(_, line)| !line.starts_with('!'))
+            .map(|(line_num, line)| (line_num, line.trim_end()))
+            .collect();
+        let width = rows.iter().map(|&(_, row)| row.chars().count()).max().unwrap_or(0);
+        // a BitGrid can't be empty, but an empty pattern doesn't use it
+        let mut grid = BitGrid::new(width.div_ceil(64).max(1), rows.len().max(1));
+        for (row, &(line_num, line)) in rows.iter().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                match ch {
+                    '.' => {}
+                    'O' | '*' => grid.modify_bits_in_word(row, col / 64, 1 << (63 - col % 64), BitOperation::Set),
+                    _ => {
+                        return Err(ConwayError::InvalidData {
+                            reason: format!(
+                                "Unexpected character {:?} at line {}, column {} of plaintext pattern",
+                                ch,
+                                line_num,
+                                col + 1
+                            ),
+                        })
+                    }
+                }
+            }
+        }
+        let pattern = if width == 0 {
+            Pattern("!".to_owned())
+        } else {
+            grid.to_pattern(None)
+        };
+        Ok(PatternFile {
+            comment_lines: vec![],
+            header_line: HeaderLine {
+                x:    width,
+                y:    rows.len(),
+                rule: None,
+            },
+            pattern,
+        })
+    }
+
+    /// Returns the contents of a Life plaintext (.cells) file for this pattern, without comments.
+    /// Plaintext only has dead and alive cells, so owners are dropped. The size is that given by
+    /// the header line, or of the pattern if it is bigger. A 0x0 pattern with no live cells has no
+    /// rows.
+    ///
+    /// # Errors
+    ///
+    /// * It is a `ConwayError::RleSyntax` error if the pattern is malformed.
+    /// * It is a `ConwayError::InvalidData` error if the pattern has walls or dying cells, which
+    ///   plaintext can't represent.
+    pub fn to_plaintext(&self) -> ConwayResult<String> {
+        let (width, height) = self.pattern.calc_size()?;
+        let mut plaintext = Plaintext {
+            rows:            vec![vec!['.'; width.max(self.width())]; height.max(self.height())],
+            unrepresentable: None,
+        };
+        self.pattern.to_grid(&mut plaintext, None)?;
+        if let Some((col, row, state)) = plaintext.unrepresentable {
+            return Err(ConwayError::InvalidData {
+                reason: format!("Cell ({}, {}) is {:?}, which plaintext can't represent", col, row, state),
+            });
+        }
+        let mut text = String::new();
+        // the pattern takes up at least one cell, unless the header says it takes up none
+        let is_empty = self.width() == 0 && self.height() == 0;
+        if is_empty && plaintext.rows.iter().flatten().all(|&ch| ch == '.') {
+            return Ok(text);
+        }
+        for row in plaintext.rows {
+            text.extend(row);
+            text.push('\n');
+        }
+        Ok(text)
+    }
+}
+
+impl fmt::Display for PatternFile {
+    /// Writes the contents of an RLE file: the comment lines, the header line, and the pattern.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.comment_lines.iter() {
+            writeln!(f, "{}", line)?;
+        }
+        writeln!(f, "{}", self.header_line)?;
+        writeln!(f, "{}", self.pattern.0.replace("\r\n", "\n"))
+    }
 }
 
 impl FromStr for PatternFile {
     type Err = ConwayError;
 
     /// Generate a PatternFile from the contents of an RLE file.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::RleSyntax` error if the file is malformed, with the line and column
+    /// in the file where the problem was found.
     fn from_str(file_contents: &str) -> Result<Self, Self::Err> {
-        use ConwayError::*;
         let mut comment_lines: Vec<String> = vec![];
         let mut comments_ended = false;
         let mut opt_header_line: Option<HeaderLine> = None;
-        let mut pattern_lines: Vec<&str> = vec![];
-        for line in file_contents.lines() {
+        let mut pattern_lines: Vec<(usize, &str)> = vec![]; // (line number, line)
+        let mut num_lines = 0;
+        for (line_num, line) in (1..).zip(file_contents.lines()) {
+            num_lines = line_num;
             if line.starts_with("#") {
                 if comments_ended {
-                    return Err(InvalidData {
-                        reason: "Found a comment line after a non-comment line".to_owned(),
-                    });
+                    return Err(syntax_error(line, 0, line_num, RleError::MisplacedComment));
                 }
                 comment_lines.push(line.to_owned());
                 continue;
@@ -89,35 +197,45 @@ impl FromStr for PatternFile {
             }
             if opt_header_line.is_none() {
                 // this line should be a header line
-                opt_header_line = Some(HeaderLine::from_str(line)?);
+                let header_line =
+                    HeaderLine::parse(line).map_err(|(offset, e)| syntax_error(line, offset, line_num, e))?;
+                opt_header_line = Some(header_line);
                 continue;
             }
             match line.find('!') {
                 Some(idx) => {
-                    pattern_lines.push(&line[0..=idx]);
+                    pattern_lines.push((line_num, &line[0..=idx]));
                     break; // we don't care about anything after the '!'
                 }
-                None => pattern_lines.push(line),
+                None => pattern_lines.push((line_num, line)),
             };
         }
-        if opt_header_line.is_none() {
-            return Err(InvalidData {
-                reason: "missing header line".to_owned(),
-            });
-        }
+        let header_line = opt_header_line.ok_or_else(|| syntax_error("", 0, num_lines + 1, RleError::MissingHeader))?;
         if pattern_lines.is_empty() {
-            return Err(InvalidData {
-                reason: "missing pattern lines".to_owned(),
-            });
+            return Err(syntax_error("", 0, num_lines + 1, RleError::MissingPattern));
         }
         let mut pattern = "".to_owned();
-        for line in pattern_lines {
+        for &(_, line) in pattern_lines.iter() {
             pattern.push_str(line);
         }
+        let pattern = Pattern(pattern);
+
+        // Check the pattern now, so that the error can point at the line in the file
+        if let Err((offset, e)) = pattern.parse_into(&mut PatternSize::default(), None) {
+            let mut line_start = 0;
+            for &(line_num, line) in pattern_lines.iter() {
+                if offset <= line_start + line.len() {
+                    return Err(syntax_error(line, offset - line_start, line_num, e));
+                }
+                line_start += line.len();
+            }
+            unreachable!("error offset {} is past the end of the pattern", offset);
+        }
+
         Ok(PatternFile {
             comment_lines,
-            header_line: opt_header_line.unwrap(),
-            pattern: Pattern(pattern),
+            header_line,
+            pattern,
         })
     }
 }
@@ -125,49 +243,135 @@ impl FromStr for PatternFile {
 impl FromStr for HeaderLine {
     type Err = ConwayError;
 
+    /// # Errors
+    ///
+    /// It is a `ConwayError::RleSyntax` error if the line is malformed, as if it were line 1.
     fn from_str(line: &str) -> Result<Self, Self::Err> {
-        use ConwayError::*;
+        HeaderLine::parse(line).map_err(|(offset, e)| syntax_error(line, offset, 1, e))
+    }
+}
+
+impl fmt::Display for HeaderLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "x = {}, y = {}", self.x, self.y)?;
+        if let Some(ref rule) = self.rule {
+            write!(f, ", rule = {}", rule)?;
+        }
+        Ok(())
+    }
+}
+
+impl HeaderLine {
+    /// Parses a header line, returning the byte offset of the problem on error.
+    fn parse(line: &str) -> Result<HeaderLine, (usize, RleError)> {
+        use RleError::*;
         let mut map = BTreeMap::new();
-        for term in line.split(",") {
+        let mut rule = None;
+        let terms = line.split(",").collect::<Vec<&str>>();
+        let mut term_offset = 0;
+        for (i, term) in terms.iter().enumerate() {
+            let offset = term_offset + term.len() - term.trim_start().len();
+            term_offset += term.len() + 1;
             let parts = term.split("=").map(|part| part.trim()).collect::<Vec<&str>>();
+            if parts.len() == 2 && parts[0] == "rule" {
+                // Larger than Life rules contain commas, so the rest of the line is part of the rule
+                let mut rule_terms = vec![parts[1]];
+                rule_terms.extend(terms[i + 1..].iter().map(|term| term.trim()));
+                rule = Some(rule_terms.join(","));
+                break;
+            }
             if parts.len() != 2 {
-                return Err(InvalidData {
-                    reason: format!("unexpected term in header line: {:?}", term),
-                });
+                return Err((
+                    offset,
+                    InvalidHeader {
+                        reason: format!("unexpected term {:?}", term),
+                    },
+                ));
             }
-            map.insert(parts[0], parts[1]);
-        }
-        if !map.contains_key("x") || !map.contains_key("y") {
-            return Err(InvalidData {
-                reason: format!("header line missing `x` and/or `y`: {:?}", line),
-            });
+            map.insert(parts[0], (offset, parts[1]));
         }
-        let x = usize::from_str(map.get("x").unwrap()).map_err(|e| InvalidData {
-            reason: format!("Error while parsing x: {}", e),
-        })?;
-        let y = usize::from_str(map.get("y").unwrap()).map_err(|e| InvalidData {
-            reason: format!("Error while parsing y: {}", e),
-        })?;
-        let rule = map.get("rule").map(|s: &&str| (*s).to_owned());
+        let parse_dimension = |name: &str| -> Result<usize, (usize, RleError)> {
+            match map.get(name) {
+                Some(&(offset, value)) => usize::from_str(value).map_err(|source| (offset, InvalidNumber { source })),
+                None => Err((
+                    0,
+                    InvalidHeader {
+                        reason: format!("missing `{}`", name),
+                    },
+                )),
+            }
+        };
+        let x = parse_dimension("x")?;
+        let y = parse_dimension("y")?;
         Ok(HeaderLine { x, y, rule })
     }
+
+    /// Parses the `rule` of this header line. A missing rule means Conway's Game of Life.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if the rule is not recognized.
+    pub fn to_rule(&self) -> ConwayResult<Rule> {
+        match self.rule {
+            Some(ref rule) => Rule::from_str(rule),
+            None => Ok(Rule::conway()),
+        }
+    }
+}
+
+/// Returns the RLE token for a cell char. This is the char itself, except for dying cells.
+pub(crate) fn cell_token(ch: char) -> String {
+    match CellState::from_char(ch) {
+        Some(CellState::Dying(opt_player_id, stage)) => {
+            let mut token = String::with_capacity(2);
+            token.push(DYING_STAGE_PREFIXES[stage - 1]);
+            token.push(CellState::Alive(opt_player_id).to_char());
+            token
+        }
+        _ => ch.to_string(),
+    }
+}
+
+/// Returns the char for the dying cell token made of `prefix` and `owner_ch`, if valid.
+fn dying_char(prefix: char, owner_ch: char) -> Option<char> {
+    let stage = DYING_STAGE_PREFIXES.iter().position(|&p| p == prefix)? + 1;
+    match CellState::from_char(owner_ch) {
+        Some(CellState::Alive(opt_player_id)) => Some(CellState::Dying(opt_player_id, stage).to_char()),
+        _ => None,
+    }
 }
 
-fn digits_to_number(digits: &Vec<char>) -> ConwayResult<usize> {
-    use ConwayError::*;
+fn digits_to_number(digits: &Vec<char>) -> Result<usize, RleError> {
     let mut result = 0;
     for ch in digits {
         let d = ch.to_digit(10).unwrap();
         result = result * 10 + d as usize;
         if result > MAX_NUMBER {
-            return Err(InvalidData {
-                reason: format!("Could not parse digits {:?} because larger than {}", digits, MAX_NUMBER),
-            });
+            return Err(RleError::NumberTooLarge { max: MAX_NUMBER });
         }
     }
     Ok(result)
 }
 
+/// Returns a `ConwayError::RleSyntax` error for a problem at byte `offset` of `text`, where
+/// `text` starts at line `first_line` (1-based). The snippet is the text around `offset`, on the
+/// same line.
+fn syntax_error(text: &str, offset: usize, first_line: usize, source: RleError) -> ConwayError {
+    const SNIPPET_CONTEXT: usize = 8; // chars on either side
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[offset..].find(['\r', '\n']).map_or(text.len(), |i| offset + i);
+    let before: Vec<char> = text[line_start..offset].chars().collect();
+    let snippet_start = before.len().saturating_sub(SNIPPET_CONTEXT);
+    let mut snippet: String = before[snippet_start..].iter().collect();
+    snippet.extend(text[offset..line_end].chars().take(SNIPPET_CONTEXT));
+    ConwayError::RleSyntax {
+        line: first_line + text[..offset].matches('\n').count(),
+        column: before.len() + 1,
+        snippet,
+        source,
+    }
+}
+
 /// Dummy implementation of the `CharGrid` trait that is only used to get the size of a pattern
 /// using `Pattern::to_grid`.
 #[derive(Default)]
@@ -187,6 +391,14 @@ impl CharGrid for PatternSize {
         }
     }
 
+    /// Only the last cell of the run can widen the pattern, so this takes the same time however
+    /// long the run is.
+    fn write_run(&mut self, col: usize, row: usize, ch: char, len: usize, visibility: Option<usize>) {
+        if len > 0 {
+            self.write_at_position(col + len - 1, row, ch, visibility);
+        }
+    }
+
     /// Is `ch` a valid character?
     fn is_valid(_ch: char) -> bool {
         true
@@ -207,6 +419,42 @@ impl CharGrid for PatternSize {
     }
 }
 
+/// Write-only `CharGrid` that renders a pattern as the rows of a Life plaintext file, for
+/// `PatternFile::to_plaintext`.
+struct Plaintext {
+    rows:            Vec<Vec<char>>,
+    unrepresentable: Option<(usize, usize, CellState)>, // the first cell plaintext can't represent
+}
+
+impl CharGrid for Plaintext {
+    fn write_at_position(&mut self, col: usize, row: usize, ch: char, _visibility: Option<usize>) {
+        self.rows[row][col] = match CellState::from_char(ch).unwrap() {
+            CellState::Dead => '.',
+            CellState::Alive(_) => 'O',
+            state => {
+                self.unrepresentable.get_or_insert((col, row, state));
+                return;
+            }
+        };
+    }
+
+    fn is_valid(ch: char) -> bool {
+        CellState::from_char(ch).is_some()
+    }
+
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn get_run(&self, _col: usize, _row: usize, _visibility: Option<usize>) -> (usize, char) {
+        unimplemented!("Plaintext is write-only");
+    }
+}
+
 impl Pattern {
     /// Creates a BitGrid out of this pattern. If there are no parse errors, the result contains
     /// the smallest BitGrid that fits a pattern `width` cells wide and `height` cells high.
@@ -217,7 +465,9 @@ impl Pattern {
         Ok(grid)
     }
 
-    /// Calculates the width and height actually taken up by the pattern.
+    /// Calculates the width and height actually taken up by the pattern. This takes time
+    /// proportional to the length of the pattern, not its area, so it is safe to call on a pattern
+    /// from an untrusted source before deciding whether it is small enough to write to a grid.
     pub fn calc_size(&self) -> ConwayResult<(usize, usize)> {
         let mut ps: PatternSize = Default::default();
         self.to_grid(&mut ps, None)?;
@@ -228,6 +478,11 @@ impl Pattern {
     /// characters in pattern must be valid for the grid, as determined by `::is_valid(ch)`, with
     /// one exception: `NO_OP_CHAR` (`"`). Cells are skipped with runs containing `NO_OP_CHAR`.
     ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::RleSyntax` error if the pattern is malformed, with the line and
+    /// column within the pattern where the problem was found.
+    ///
     /// # Panics
     ///
     /// This function will panic if an attempt is made to write out of bounds.
@@ -237,37 +492,37 @@ impl Pattern {
     /// If there is a parsing error, `grid` may be in a partially written state. If this is a
     /// problem, then back up `grid` before calling this.
     pub fn to_grid<G: CharGrid>(&self, grid: &mut G, visibility: Option<usize>) -> ConwayResult<()> {
-        use ConwayError::*;
+        self.parse_into(grid, visibility)
+            .map_err(|(offset, e)| syntax_error(&self.0, offset, 1, e))
+    }
+
+    /// Like `to_grid`, but returns the byte offset of the problem on error.
+    fn parse_into<G: CharGrid>(&self, grid: &mut G, visibility: Option<usize>) -> Result<(), (usize, RleError)> {
+        use RleError::*;
         let mut col: usize = 0;
         let mut row: usize = 0;
-        let mut char_indices = self.0.char_indices();
-        let mut ch;
-        let mut i = None;
-        let mut complete = false;
+        let mut char_indices = self.0.char_indices().peekable();
         let mut digits: Vec<char> = vec![];
-        loop {
-            match char_indices.next() {
-                Some((_i, _ch)) => {
-                    ch = _ch;
-                    i = Some(_i);
-                }
-                None => break,
-            };
+        let mut digits_offset = 0;
+        while let Some((i, token_ch)) = char_indices.next() {
+            let mut ch = token_ch;
+            // a dying cell token is a stage prefix followed by the owner
+            if let Some(dying_ch) = char_indices.peek().and_then(|&(_, owner_ch)| dying_char(ch, owner_ch)) {
+                char_indices.next();
+                ch = dying_ch;
+            }
             if digits.len() > 0 && ch == '!' {
-                return Err(InvalidData {
-                    reason: format!("Cannot have {} after number at {}", ch, i.unwrap()),
-                });
+                return Err((digits_offset, NumberBeforeEnd));
             }
             match ch {
                 '!' => {
                     // end of input
-                    complete = true;
-                    break;
+                    return Ok(());
                 }
                 '$' => {
                     // new line
                     let number = if digits.len() > 0 {
-                        digits_to_number(&digits)?
+                        digits_to_number(&digits).map_err(|e| (digits_offset, e))?
                     } else {
                         1
                     };
@@ -279,38 +534,29 @@ impl Pattern {
                     // ignore newlines
                 }
                 x if x.is_digit(10) => {
+                    if digits.is_empty() {
+                        digits_offset = i;
+                    }
                     digits.push(ch);
                 }
                 _ if G::is_valid(ch) => {
                     // cell
                     let number = if digits.len() > 0 {
-                        //TODO: wrap errors from digits_to_number rather than just forwarding
-                        digits_to_number(&digits)?
+                        digits_to_number(&digits).map_err(|e| (digits_offset, e))?
                     } else {
                         1
                     };
                     digits.clear();
                     if ch != NO_OP_CHAR {
-                        for _ in 0..number {
-                            grid.write_at_position(col, row, ch, visibility);
-                            col += 1;
-                        }
-                    } else {
-                        col += number;
+                        grid.write_run(col, row, ch, number, visibility);
                     }
+                    col += number;
                 }
                 _ => {
-                    return Err(InvalidData {
-                        reason: format!("Unrecognized character {} at {}", ch, i.unwrap()),
-                    });
+                    return Err((i, UnexpectedChar { ch: token_ch }));
                 }
             }
         }
-        if !complete {
-            return Err(InvalidData {
-                reason: format!("Premature termination at {:?}", i),
-            });
-        }
-        Ok(())
+        Err((self.0.len(), MissingEnd))
     }
 }
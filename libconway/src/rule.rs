@@ -0,0 +1,560 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+use std::cmp;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::error::{ConwayError, ConwayResult};
+use crate::grids::BitGrid;
+
+/// Largest supported neighborhood range. Offsets are applied by shifting 64-bit words, so this
+/// must stay below 64.
+pub const MAX_RANGE: usize = 32;
+
+/// Largest supported number of cell states for Generations rules: dead, alive, and up to 10 dying
+/// states. The RLE encoding of dying cells has one prefix character per dying state (`p` through
+/// `y`).
+pub const MAX_STATES: usize = 12;
+
+/// Number of bit grids needed to hold the dying stage of a cell, for up to `MAX_STATES` states.
+pub(crate) const MAX_DECAY_PLANES: usize = 4;
+
+/// Number of bits in the bit-sliced neighbor counters. Must be able to hold the largest possible
+/// count, `(2 * MAX_RANGE + 1)^2`.
+const COUNTER_BITS: usize = 13;
+
+/// The set of cells that are counted as neighbors of a cell.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Neighborhood {
+    /// Square neighborhood of the given range. `Moore(1)` is the classic 8-cell neighborhood.
+    Moore(usize),
+    /// Diamond-shaped neighborhood of the given range. `VonNeumann(1)` is the 4-cell neighborhood.
+    VonNeumann(usize),
+    /// 6-cell neighborhood emulated on the square grid the same way Golly does it: the Moore
+    /// neighborhood without the north-east and south-west cells.
+    Hexagonal,
+}
+
+impl Neighborhood {
+    /// Returns the maximum distance, along either axis, between a cell and its neighbors.
+    pub fn range(&self) -> usize {
+        match *self {
+            Neighborhood::Moore(range) | Neighborhood::VonNeumann(range) => range,
+            Neighborhood::Hexagonal => 1,
+        }
+    }
+
+    /// Returns `(dx, dy)` offsets of every neighbor, not including the cell itself. Positive `dx`
+    /// is to the east, and positive `dy` is to the south.
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let range = self.range() as isize;
+        let mut offsets = vec![];
+        for dy in -range..=range {
+            for dx in -range..=range {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let is_neighbor = match *self {
+                    Neighborhood::Moore(_) => true,
+                    Neighborhood::VonNeumann(_) => dx.abs() + dy.abs() <= range,
+                    Neighborhood::Hexagonal => (dx, dy) != (1, -1) && (dx, dy) != (-1, 1),
+                };
+                if is_neighbor {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+
+    /// Returns the number of neighbors, not including the cell itself.
+    pub fn size(&self) -> usize {
+        self.offsets().len()
+    }
+}
+
+/// A totalistic cellular automaton rule. The default is Conway's Game of Life, `B3/S23`.
+///
+/// Rules with a range of 1 that don't count the middle cell are written in B/S notation, with an
+/// optional neighborhood suffix (`B3/S23`, `B2/S34H`, `B3/S013V`). Larger than Life rules use
+/// Golly's notation (`R5,C0,M1,S34..58,B34..45,NM`). `Rule` implements `FromStr` and `Display`, and
+/// parsing the output of `to_string()` always gives back the same `Rule`.
+///
+/// Generations rules have more than two states: a live cell that doesn't survive goes through
+/// `states - 2` dying states before it is dead. Dying cells are not counted as neighbors and
+/// cannot be born into. These are written with a `C` term, such as `B2/S/C3` (Brian's Brain).
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Rule {
+    neighborhood: Neighborhood,
+    middle:       bool,       // if true, the cell itself is included in the neighbor count
+    birth:        Vec<usize>, // sorted neighbor counts causing a dead cell to come alive
+    survival:     Vec<usize>, // sorted neighbor counts allowing a live cell to stay alive
+    states:       usize,      // 2 unless this is a Generations rule
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::conway()
+    }
+}
+
+impl Rule {
+    /// Returns Conway's Game of Life, `B3/S23`.
+    pub fn conway() -> Rule {
+        Rule {
+            neighborhood: Neighborhood::Moore(1),
+            middle:       false,
+            birth:        vec![3],
+            survival:     vec![2, 3],
+            states:       2,
+        }
+    }
+
+    /// Creates an outer totalistic rule from the neighbor counts that cause birth and survival.
+    ///
+    /// # Errors
+    ///
+    /// * A count is larger than the number of cells in `neighborhood`.
+    /// * `birth` contains 0. Such rules would fill unknown and empty parts of the universe alike.
+    /// * The neighborhood range is zero or larger than `MAX_RANGE`.
+    /// * The rule can only be written in Larger than Life notation (see `larger_than_life`) but
+    ///   `birth` or `survival` are not contiguous, non-empty ranges.
+    pub fn new(birth: Vec<usize>, survival: Vec<usize>, neighborhood: Neighborhood) -> ConwayResult<Rule> {
+        Rule::with_middle(birth, survival, neighborhood, false)
+    }
+
+    /// Returns a copy of this rule with `states` cell states, making it a Generations rule if
+    /// `states` is greater than 2.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if `states` is less than 2 or greater than
+    /// `MAX_STATES`.
+    pub fn with_states(mut self, states: usize) -> ConwayResult<Rule> {
+        if !(2..=MAX_STATES).contains(&states) {
+            return Err(ConwayError::InvalidData {
+                reason: format!(
+                    "Number of states must be between 2 and {}, but is {}",
+                    MAX_STATES, states
+                ),
+            });
+        }
+        self.states = states;
+        Ok(self)
+    }
+
+    /// Creates a Larger than Life rule. If `middle` is true, the cell itself is included in the
+    /// neighbor count. Only `Moore` and `VonNeumann` neighborhoods are allowed.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Rule::new`, and also if `neighborhood` is `Hexagonal`.
+    pub fn larger_than_life(
+        neighborhood: Neighborhood,
+        middle: bool,
+        birth: RangeInclusive<usize>,
+        survival: RangeInclusive<usize>,
+    ) -> ConwayResult<Rule> {
+        if neighborhood == Neighborhood::Hexagonal {
+            return Err(ConwayError::InvalidData {
+                reason: "Larger than Life rules do not support the hexagonal neighborhood".to_owned(),
+            });
+        }
+        Rule::with_middle(birth.collect(), survival.collect(), neighborhood, middle)
+    }
+
+    fn with_middle(
+        mut birth: Vec<usize>,
+        mut survival: Vec<usize>,
+        neighborhood: Neighborhood,
+        middle: bool,
+    ) -> ConwayResult<Rule> {
+        use ConwayError::*;
+        let range = neighborhood.range();
+        if range == 0 || range > MAX_RANGE {
+            return Err(InvalidData {
+                reason: format!(
+                    "Neighborhood range must be between 1 and {}, but is {}",
+                    MAX_RANGE, range
+                ),
+            });
+        }
+        birth.sort();
+        birth.dedup();
+        survival.sort();
+        survival.dedup();
+        let max_count = neighborhood.size() + if middle { 1 } else { 0 };
+        if let Some(count) = birth.iter().chain(survival.iter()).find(|&&count| count > max_count) {
+            return Err(InvalidData {
+                reason: format!(
+                    "Neighbor count {} is too large for a neighborhood of {} cells",
+                    count, max_count
+                ),
+            });
+        }
+        if birth.first() == Some(&0) {
+            return Err(InvalidData {
+                reason: "Rules with birth on 0 neighbors are not supported".to_owned(),
+            });
+        }
+        let rule = Rule {
+            neighborhood,
+            middle,
+            birth,
+            survival,
+            states: 2,
+        };
+        if !rule.is_outer_totalistic() && (!is_contiguous(&rule.birth) || !is_contiguous(&rule.survival)) {
+            return Err(InvalidData {
+                reason: "Larger than Life rules need contiguous, non-empty birth and survival ranges".to_owned(),
+            });
+        }
+        Ok(rule)
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// Returns whether the cell itself is included in the neighbor count.
+    pub fn middle(&self) -> bool {
+        self.middle
+    }
+
+    /// Neighbor counts causing a dead cell to come alive, in ascending order.
+    pub fn birth(&self) -> &[usize] {
+        &self.birth
+    }
+
+    /// Neighbor counts allowing a live cell to stay alive, in ascending order.
+    pub fn survival(&self) -> &[usize] {
+        &self.survival
+    }
+
+    /// Number of cell states; 2 unless this is a Generations rule.
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    /// Number of dying states a cell goes through before it is dead; 0 unless this is a
+    /// Generations rule.
+    pub fn dying_stages(&self) -> usize {
+        self.states - 2
+    }
+
+    /// Returns true if this is Conway's Game of Life, which has a faster implementation.
+    pub fn is_conway(&self) -> bool {
+        *self == Rule::conway()
+    }
+
+    /// Returns true if this rule can be written in B/S notation.
+    fn is_outer_totalistic(&self) -> bool {
+        self.neighborhood.range() == 1 && !self.middle
+    }
+
+    /// Computes the next generation of the 64 cells in the word at (`row`, `col_idx`) of `cells`.
+    /// `offsets` must be the result of `self.neighborhood().offsets()`; it is passed in so that it
+    /// only needs to be calculated once per generation.
+    pub(crate) fn next_word(&self, cells: &BitGrid, row: usize, col_idx: usize, offsets: &[(isize, isize)]) -> u64 {
+        let center = cells[row][col_idx];
+        let mut counter = [0u64; COUNTER_BITS];
+        for &(dx, dy) in offsets {
+            add_to_counter(&mut counter, offset_word(cells, row, col_idx, dx, dy));
+        }
+        if self.middle {
+            add_to_counter(&mut counter, center);
+        }
+        let births = count_mask(&counter, &self.birth);
+        let survivals = count_mask(&counter, &self.survival);
+        (!center & births) | (center & survivals)
+    }
+
+    /// Advances the dying stages of the 64 cells in a word by one. `stage_bits` holds the
+    /// bit-sliced stages, least significant bit first, where 0 means the cell is not dying. Cells
+    /// past the last dying stage become dead, and `newly_dying` cells, which must currently be 0,
+    /// start at stage 1 unless this rule has no dying stages.
+    pub(crate) fn next_decay(&self, stage_bits: &mut [u64], newly_dying: u64) {
+        let mut carry = stage_bits.iter().fold(0, |dying, plane| dying | plane);
+        for plane in stage_bits.iter_mut() {
+            let next_carry = *plane & carry;
+            *plane ^= carry;
+            carry = next_carry;
+        }
+        let mut dead = 0;
+        for stage in self.dying_stages() + 1..1 << stage_bits.len() {
+            dead |= count_mask(stage_bits, &[stage]);
+        }
+        for plane in stage_bits.iter_mut() {
+            *plane &= !dead;
+        }
+        if self.dying_stages() > 0 {
+            if let Some(first_plane) = stage_bits.first_mut() {
+                *first_plane |= newly_dying;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_outer_totalistic() {
+            let birth: String = self.birth.iter().map(|count| count.to_string()).collect();
+            let survival: String = self.survival.iter().map(|count| count.to_string()).collect();
+            let suffix = match self.neighborhood {
+                Neighborhood::Moore(_) => "",
+                Neighborhood::VonNeumann(_) => "V",
+                Neighborhood::Hexagonal => "H",
+            };
+            if self.states > 2 {
+                write!(f, "B{}/S{}/C{}{}", birth, survival, self.states, suffix)
+            } else {
+                write!(f, "B{}/S{}{}", birth, survival, suffix)
+            }
+        } else {
+            let neighborhood = match self.neighborhood {
+                Neighborhood::VonNeumann(_) => 'N',
+                _ => 'M',
+            };
+            write!(
+                f,
+                "R{},C{},M{},S{}..{},B{}..{},N{}",
+                self.neighborhood.range(),
+                if self.states > 2 { self.states } else { 0 },
+                if self.middle { 1 } else { 0 },
+                self.survival[0],
+                self.survival[self.survival.len() - 1],
+                self.birth[0],
+                self.birth[self.birth.len() - 1],
+                neighborhood
+            )
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ConwayError;
+
+    /// Parses a rule in B/S notation (optionally with a `/C` states term and a `V` or `H` suffix,
+    /// and also accepting the older S/B and S/B/C notations like `23/3` and `345/2/4`) or in Golly's
+    /// Larger than Life notation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with('R') || s.starts_with('r') {
+            parse_larger_than_life(s)
+        } else {
+            parse_outer_totalistic(s)
+        }
+    }
+}
+
+fn parse_outer_totalistic(s: &str) -> ConwayResult<Rule> {
+    use ConwayError::*;
+    let (body, neighborhood) = match s.chars().last() {
+        Some('V') | Some('v') => (&s[..s.len() - 1], Neighborhood::VonNeumann(1)),
+        Some('H') | Some('h') => (&s[..s.len() - 1], Neighborhood::Hexagonal),
+        _ => (s, Neighborhood::Moore(1)),
+    };
+    let mut parts = body.split('/').collect::<Vec<&str>>();
+    let mut states = 2;
+    if parts.len() == 3 {
+        let states_part = parts.pop().unwrap();
+        let digits = states_part.trim_start_matches(['C', 'c']);
+        states = usize::from_str(digits).map_err(|_| InvalidData {
+            reason: format!("unexpected number of states {:?} in rule {:?}", states_part, s),
+        })?;
+    }
+    if parts.len() != 2 {
+        return Err(InvalidData {
+            reason: format!("expected one or two '/' in rule {:?}", s),
+        });
+    }
+    let (mut opt_birth, mut opt_survival) = (None, None);
+    for part in &parts {
+        match part.chars().next() {
+            Some('B') | Some('b') => opt_birth = Some(parse_digits(&part[1..], s)?),
+            Some('S') | Some('s') => opt_survival = Some(parse_digits(&part[1..], s)?),
+            _ => {}
+        }
+    }
+    let (birth, survival) = match (opt_birth, opt_survival) {
+        (Some(birth), Some(survival)) => (birth, survival),
+        // S/B notation without letters, such as "23/3"
+        (None, None) => (parse_digits(parts[1], s)?, parse_digits(parts[0], s)?),
+        _ => {
+            return Err(InvalidData {
+                reason: format!("rule {:?} must have both a B and an S part", s),
+            });
+        }
+    };
+    Rule::new(birth, survival, neighborhood)?.with_states(states)
+}
+
+fn parse_digits(digits: &str, rule: &str) -> ConwayResult<Vec<usize>> {
+    digits
+        .chars()
+        .map(|ch| {
+            ch.to_digit(10)
+                .map(|d| d as usize)
+                .ok_or_else(|| ConwayError::InvalidData {
+                    reason: format!("unexpected character {:?} in rule {:?}", ch, rule),
+                })
+        })
+        .collect()
+}
+
+fn parse_larger_than_life(s: &str) -> ConwayResult<Rule> {
+    use ConwayError::*;
+    let mut opt_range = None;
+    let mut middle = false;
+    let mut opt_birth = None;
+    let mut opt_survival = None;
+    let mut neighborhood_ch = 'M';
+    let mut states = 2;
+    for term in s.split(',').map(|term| term.trim()) {
+        let mut chars = term.chars();
+        let key = chars.next().map(|ch| ch.to_ascii_uppercase());
+        let value = chars.as_str();
+        let bad_term = || InvalidData {
+            reason: format!("unexpected term {:?} in rule {:?}", term, s),
+        };
+        match key {
+            Some('R') => opt_range = Some(usize::from_str(value).map_err(|_| bad_term())?),
+            Some('C') => {
+                // C0 and C1 are the same as C2
+                states = cmp::max(2, usize::from_str(value).map_err(|_| bad_term())?);
+            }
+            Some('M') => {
+                middle = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(bad_term()),
+                }
+            }
+            Some('S') => opt_survival = Some(parse_count_range(value).ok_or_else(bad_term)?),
+            Some('B') => opt_birth = Some(parse_count_range(value).ok_or_else(bad_term)?),
+            Some('N') => {
+                neighborhood_ch = match value {
+                    "M" | "m" => 'M',
+                    "N" | "n" => 'N',
+                    _ => return Err(bad_term()),
+                }
+            }
+            _ => return Err(bad_term()),
+        }
+    }
+    match (opt_range, opt_birth, opt_survival) {
+        (Some(range), Some(birth), Some(survival)) => {
+            let neighborhood = if neighborhood_ch == 'N' {
+                Neighborhood::VonNeumann(range)
+            } else {
+                Neighborhood::Moore(range)
+            };
+            Rule::larger_than_life(neighborhood, middle, birth, survival)?.with_states(states)
+        }
+        _ => Err(InvalidData {
+            reason: format!("rule {:?} must have R, B and S terms", s),
+        }),
+    }
+}
+
+/// Parses `"min..max"` or a single count.
+fn parse_count_range(value: &str) -> Option<RangeInclusive<usize>> {
+    let mut parts = value.splitn(2, "..");
+    let min = usize::from_str(parts.next()?).ok()?;
+    let max = match parts.next() {
+        Some(max) => usize::from_str(max).ok()?,
+        None => min,
+    };
+    if min > max {
+        return None;
+    }
+    Some(min..=max)
+}
+
+fn is_contiguous(counts: &[usize]) -> bool {
+    !counts.is_empty() && counts[counts.len() - 1] - counts[0] + 1 == counts.len()
+}
+
+/// Returns the word at (`row`, `col_idx`) of `grid`, shifted such that each bit holds the cell
+/// that is `dx` columns to the east and `dy` rows to the south of it, wrapping around the edges of
+/// the grid.
+///
+/// # Panics
+///
+/// Panics if `dx` is not between -63 and 63, inclusive.
+#[inline]
+pub(crate) fn offset_word(grid: &BitGrid, row: usize, col_idx: usize, dx: isize, dy: isize) -> u64 {
+    assert!(dx.abs() < 64);
+    let width_in_words = grid.width_in_words();
+    let row = &grid[(row as isize + dy).rem_euclid(grid.0.len() as isize) as usize];
+    let shift = dx.abs();
+    if dx < 0 {
+        let west = row[(col_idx + width_in_words - 1) % width_in_words];
+        (row[col_idx] >> shift) | (west << (64 - shift))
+    } else if dx > 0 {
+        let east = row[(col_idx + 1) % width_in_words];
+        (row[col_idx] << shift) | (east >> (64 - shift))
+    } else {
+        row[col_idx]
+    }
+}
+
+/// A cell is 1 if itself or any of its neighbors specified by `offsets` are 1.
+pub(crate) fn contagious_one(grid: &BitGrid, row: usize, col_idx: usize, offsets: &[(isize, isize)]) -> u64 {
+    offsets.iter().fold(grid[row][col_idx], |word, &(dx, dy)| {
+        word | offset_word(grid, row, col_idx, dx, dy)
+    })
+}
+
+/// A cell is 0 if itself or any of its neighbors specified by `offsets` are 0.
+pub(crate) fn contagious_zero(grid: &BitGrid, row: usize, col_idx: usize, offsets: &[(isize, isize)]) -> u64 {
+    offsets.iter().fold(grid[row][col_idx], |word, &(dx, dy)| {
+        word & offset_word(grid, row, col_idx, dx, dy)
+    })
+}
+
+/// Adds one to each bit-sliced counter whose bit is set in `bits`.
+#[inline]
+fn add_to_counter(counter: &mut [u64; COUNTER_BITS], mut bits: u64) {
+    for counter_bit in counter.iter_mut() {
+        if bits == 0 {
+            break;
+        }
+        let carry = *counter_bit & bits;
+        *counter_bit ^= bits;
+        bits = carry;
+    }
+}
+
+/// Returns a word with a 1 bit wherever the bit-sliced counter equals any of `counts`.
+fn count_mask(counter: &[u64], counts: &[usize]) -> u64 {
+    let mut result = 0;
+    for &count in counts {
+        let mut equal = u64::MAX;
+        for (bit, counter_bit) in counter.iter().enumerate() {
+            equal &= if (count >> bit) & 1 == 1 {
+                *counter_bit
+            } else {
+                !*counter_bit
+            };
+        }
+        result |= equal;
+    }
+    result
+}
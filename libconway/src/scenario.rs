@@ -0,0 +1,321 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Scenarios describe a map -- the size of the universe, the players and the patterns placed in it
+//! -- as a TOML file, so that maps can be shipped as data rather than code. For example:
+//!
+//! ```toml
+//! width = 128
+//! height = 32
+//! topology = "torus"     # optional; the only topology for now
+//! fog_radius = 16        # optional
+//! rule = "B3/S23"        # optional; Conway's Game of Life by default
+//!
+//! [[players]]
+//! writable = { left = 40, top = 6, width = 16, height = 8 }
+//!
+//! [[patterns]]
+//! file = "glider.rle"    # relative to the scenario file
+//! left = 40
+//! top = 6
+//! owner = 0              # optional; makes all live cells belong to player 0
+//! transform = "rotate_cw"  # optional; see `Transform`
+//!
+//! [[patterns]]
+//! rle = "5W$4bW$4bW$4b5W!"  # an inline pattern, as found in an RLE file after the header
+//! left = 74
+//! top = 13
+//! ```
+//!
+//! Load a scenario with `Scenario::from_file` (or `str::parse`), then create the universe with
+//! `BigBang::from_scenario`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::error::{ConwayError, ConwayResult};
+use crate::grids::{CharGrid, Transform};
+use crate::rle::{Pattern, PatternFile, NO_OP_CHAR};
+use crate::rule::Rule;
+use crate::universe::{CellState, Region, Universe};
+
+/// How the edges of the universe connect.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    /// The left and right edges meet, as do the top and bottom edges. This is the only topology
+    /// that `Universe` supports.
+    Torus,
+}
+
+/// A map loaded from a scenario file. See the module documentation for the file format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    pub width:           usize,
+    pub height:          usize,
+    pub topology:        Topology,
+    pub fog_radius:      Option<usize>,
+    pub rule:            Option<Rule>,
+    pub player_writable: Vec<Region>, // indexed by player_id
+    pub patterns:        Vec<PlacedPattern>,
+}
+
+/// A pattern in a scenario, and where to put it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedPattern {
+    pub pattern:   Pattern,
+    pub width:     usize, // before transforming
+    pub height:    usize, // before transforming
+    pub left:      isize, // after transforming
+    pub top:       isize, // after transforming
+    pub owner:     Option<usize>,
+    pub transform: Transform,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioSpec {
+    width:      usize,
+    height:     usize,
+    topology:   Option<Topology>,
+    fog_radius: Option<usize>,
+    rule:       Option<String>,
+    #[serde(default)]
+    players:    Vec<PlayerSpec>,
+    #[serde(default)]
+    patterns:   Vec<PatternSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlayerSpec {
+    writable: RegionSpec,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegionSpec {
+    left:   isize,
+    top:    isize,
+    width:  usize,
+    height: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatternSpec {
+    file:      Option<PathBuf>,
+    rle:       Option<String>,
+    left:      isize,
+    top:       isize,
+    owner:     Option<usize>,
+    transform: Option<Transform>,
+}
+
+impl Scenario {
+    /// Loads a scenario file. Pattern files named in it are relative to the directory containing
+    /// the scenario file.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if the scenario file or any of its pattern files
+    /// can't be read or are invalid, except that a malformed pattern is a `ConwayError::RleSyntax`
+    /// error.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ConwayResult<Scenario> {
+        let path = path.as_ref();
+        let contents = read_file(path)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Scenario::parse(&contents, base_dir)
+    }
+
+    fn parse(contents: &str, base_dir: &Path) -> ConwayResult<Scenario> {
+        use ConwayError::*;
+        let spec: ScenarioSpec = toml::from_str(contents).map_err(|e| InvalidData {
+            reason: format!("Invalid scenario: {}", e),
+        })?;
+        let rule = match spec.rule {
+            Some(rule) => Some(rule.parse::<Rule>()?),
+            None => None,
+        };
+        let mut player_writable = vec![];
+        for player in spec.players {
+            let RegionSpec {
+                left,
+                top,
+                width,
+                height,
+            } = player.writable;
+            if width == 0 || height == 0 {
+                return Err(InvalidData {
+                    reason: format!("Player {} has an empty writable region", player_writable.len()),
+                });
+            }
+            player_writable.push(Region::new(left, top, width, height));
+        }
+        let mut patterns = vec![];
+        for pattern_spec in spec.patterns {
+            patterns.push(PlacedPattern::from_spec(pattern_spec, base_dir)?);
+        }
+        Ok(Scenario {
+            width: spec.width,
+            height: spec.height,
+            topology: spec.topology.unwrap_or(Topology::Torus),
+            fog_radius: spec.fog_radius,
+            rule,
+            player_writable,
+            patterns,
+        })
+    }
+}
+
+impl FromStr for Scenario {
+    type Err = ConwayError;
+
+    /// Parses the contents of a scenario file. Pattern files named in it are relative to the
+    /// current directory.
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        Scenario::parse(contents, Path::new(""))
+    }
+}
+
+impl PlacedPattern {
+    fn from_spec(spec: PatternSpec, base_dir: &Path) -> ConwayResult<PlacedPattern> {
+        use ConwayError::*;
+        let (pattern, width, height) = match (spec.file, spec.rle) {
+            (Some(file), None) => {
+                let pattern_file = read_file(&base_dir.join(file))?.parse::<PatternFile>()?;
+                let (width, height) = (pattern_file.width(), pattern_file.height());
+                let (actual_width, actual_height) = pattern_file.pattern.calc_size()?;
+                if actual_width > width || actual_height > height {
+                    return Err(InvalidData {
+                        reason: format!(
+                            "Pattern is {}x{}, but its header says {}x{}",
+                            actual_width, actual_height, width, height
+                        ),
+                    });
+                }
+                (pattern_file.pattern, width, height)
+            }
+            (None, Some(rle)) => {
+                let pattern = Pattern(rle);
+                let (width, height) = pattern.calc_size()?;
+                (pattern, width, height)
+            }
+            _ => {
+                return Err(InvalidData {
+                    reason: "Each scenario pattern needs exactly one of `file` and `rle`".to_owned(),
+                });
+            }
+        };
+        Ok(PlacedPattern {
+            pattern,
+            width,
+            height,
+            left: spec.left,
+            top: spec.top,
+            owner: spec.owner,
+            transform: spec.transform.unwrap_or(Transform::Identity),
+        })
+    }
+
+    /// Writes the non-dead cells of the pattern to the latest generation of `uni`, wrapping around
+    /// the edges of the universe. If there is an owner, every live or dying cell belongs to it.
+    /// Writable regions and energy budgets are ignored.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::RleSyntax` error if the pattern can't be parsed or contains fog, and a
+    /// `ConwayError::InvalidData` error if it refers to a player that doesn't exist or contains
+    /// dying cells that the universe's rule doesn't have. Nothing is written in either case.
+    pub fn place(&self, uni: &mut Universe) -> ConwayResult<()> {
+        use ConwayError::*;
+        let mut cells = PatternCells(vec![]);
+        self.pattern.to_grid(&mut cells, None)?;
+        for &(_, _, state) in cells.0.iter() {
+            let (opt_player_id, stage) = match state {
+                CellState::Alive(opt_player_id) => (opt_player_id, 0),
+                CellState::Dying(opt_player_id, stage) => (opt_player_id, stage),
+                _ => (None, 0),
+            };
+            if let Some(player_id) = self.owner.or(opt_player_id) {
+                if player_id >= uni.num_players() {
+                    return Err(InvalidData {
+                        reason: format!("Pattern cells belong to player {}, who doesn't exist", player_id),
+                    });
+                }
+            }
+            if stage > uni.rule().dying_stages() {
+                return Err(InvalidData {
+                    reason: format!("Pattern has dying stage {}, which the rule doesn't have", stage),
+                });
+            }
+        }
+
+        let (uni_width, uni_height) = (uni.width() as isize, uni.height() as isize);
+        for (col, row, state) in cells.0 {
+            let state = match (state, self.owner) {
+                (CellState::Alive(_), Some(owner)) => CellState::Alive(Some(owner)),
+                (CellState::Dying(_, stage), Some(owner)) => CellState::Dying(Some(owner), stage),
+                (state, _) => state,
+            };
+            let (col, row) = self.transform.apply(col, row, self.width, self.height);
+            let col = (self.left + col as isize).rem_euclid(uni_width) as usize;
+            let row = (self.top + row as isize).rem_euclid(uni_height) as usize;
+            uni.set_unchecked(col, row, state);
+        }
+        Ok(())
+    }
+}
+
+fn read_file(path: &Path) -> ConwayResult<String> {
+    fs::read_to_string(path).map_err(|e| ConwayError::InvalidData {
+        reason: format!("Cannot read {}: {}", path.display(), e),
+    })
+}
+
+/// Write-only `CharGrid` that collects the non-dead cells of a pattern, so that they can be
+/// transformed before placing them.
+struct PatternCells(Vec<(usize, usize, CellState)>);
+
+impl CharGrid for PatternCells {
+    fn write_at_position(&mut self, col: usize, row: usize, ch: char, _visibility: Option<usize>) {
+        let state = CellState::from_char(ch).unwrap();
+        if state != CellState::Dead {
+            self.0.push((col, row, state));
+        }
+    }
+
+    /// Any cell state other than fog.
+    fn is_valid(ch: char) -> bool {
+        ch == NO_OP_CHAR || CellState::from_char(ch).is_some_and(|state| state != CellState::Fog)
+    }
+
+    fn width(&self) -> usize {
+        unimplemented!("PatternCells has no fixed size");
+    }
+
+    fn height(&self) -> usize {
+        unimplemented!("PatternCells has no fixed size");
+    }
+
+    fn get_run(&self, _col: usize, _row: usize, _visibility: Option<usize>) -> (usize, char) {
+        unimplemented!("PatternCells is write-only");
+    }
+}
@@ -0,0 +1,154 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::error::{ConwayError, ConwayResult};
+use crate::grids::{BitGrid, BitOperation};
+use crate::universe::Region;
+
+/// The symmetry of a random soup.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Symmetry {
+    /// No symmetry.
+    Asymmetric,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn. The region must be square.
+    C4,
+    /// Unchanged by a quarter turn, or by mirroring horizontally, vertically or diagonally. The
+    /// region must be square.
+    D4,
+}
+
+/// A region filled with randomly placed live cells, created with `Soup::new`. The same soup always
+/// produces the same cells, on any platform, so a client and server can generate it separately.
+///
+/// See `Universe::random_soup` and `BigBang::random_soup`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Soup {
+    region:    Region,
+    density:   f64,
+    seed:      u64,
+    player_id: Option<usize>,
+    symmetry:  Symmetry,
+}
+
+impl Soup {
+    /// Creates an unowned soup without symmetry, filling `region`. Each cell is alive with
+    /// probability `density`, which must be between 0.0 and 1.0, and `seed` determines which.
+    pub fn new(region: Region, density: f64, seed: u64) -> Soup {
+        Soup {
+            region,
+            density,
+            seed,
+            player_id: None,
+            symmetry: Symmetry::Asymmetric,
+        }
+    }
+
+    /// The live cells of the soup will belong to `player_id`.
+    pub fn player(mut self, player_id: usize) -> Soup {
+        self.player_id = Some(player_id);
+        self
+    }
+
+    /// Sets the symmetry of the soup.
+    pub fn symmetry(mut self, symmetry: Symmetry) -> Soup {
+        self.symmetry = symmetry;
+        self
+    }
+
+    /// Returns the region filled by the soup.
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Returns the player that the soup's cells belong to, if any.
+    pub fn player_id(&self) -> Option<usize> {
+        self.player_id
+    }
+
+    /// Generates the cells of the soup, as a `BitGrid` the size of its region whose top-left
+    /// cell corresponds to the top-left cell of the region.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if `density` is not between 0.0 and 1.0, or if
+    /// the symmetry requires a square region and the region isn't square.
+    pub fn to_bit_grid(&self) -> ConwayResult<BitGrid> {
+        use ConwayError::*;
+        if !(0.0..=1.0).contains(&self.density) {
+            return Err(InvalidData {
+                reason: format!("Soup density must be between 0.0 and 1.0, not {}", self.density),
+            });
+        }
+        let (width, height) = (self.region.width(), self.region.height());
+        if matches!(self.symmetry, Symmetry::C4 | Symmetry::D4) && width != height {
+            return Err(InvalidData {
+                reason: format!(
+                    "Soup with {:?} symmetry must be square, not {}x{}",
+                    self.symmetry, width, height
+                ),
+            });
+        }
+
+        // Each cell not yet decided gets a random value, which is copied to the rest of its orbit
+        // under the symmetry.
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut decided = vec![false; width * height];
+        let mut grid = BitGrid::new((width - 1) / 64 + 1, height);
+        for row in 0..height {
+            for col in 0..width {
+                if decided[row * width + col] {
+                    continue;
+                }
+                let alive = rng.gen_bool(self.density);
+                for (orbit_col, orbit_row) in self.orbit(col, row) {
+                    decided[orbit_row * width + orbit_col] = true;
+                    if alive {
+                        let mask = 1 << (63 - orbit_col % 64);
+                        grid.modify_bits_in_word(orbit_row, orbit_col / 64, mask, BitOperation::Set);
+                    }
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    /// Returns the cells which must have the same state as (`col`, `row`) under the symmetry.
+    fn orbit(&self, col: usize, row: usize) -> Vec<(usize, usize)> {
+        let (right, bottom) = (self.region.width() - 1, self.region.height() - 1);
+        let half_turn = (right - col, bottom - row);
+        match self.symmetry {
+            Symmetry::Asymmetric => vec![(col, row)],
+            Symmetry::C2 => vec![(col, row), half_turn],
+            Symmetry::C4 => vec![(col, row), (right - row, col), half_turn, (row, right - col)],
+            Symmetry::D4 => vec![
+                (col, row),
+                (right - row, col),
+                half_turn,
+                (row, right - col),
+                (right - col, row),
+                (col, bottom - row),
+                (row, col),
+                (right - row, bottom - col),
+            ],
+        }
+    }
+}
@@ -18,9 +18,12 @@
 mod universe_tests {
     use crate::error::ConwayError::*;
     use crate::grids::CharGrid;
+    use crate::reference::random_universe;
     use crate::rle::Pattern;
     use crate::universe::test_helpers::*;
     use crate::universe::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn new_universe_with_valid_dims() {
@@ -198,6 +201,21 @@ mod universe_tests {
         assert!(uni.is_err());
     }
 
+    #[test]
+    fn birth_fails_with_fog_circle_larger_than_universe() {
+        // a circle of radius 33 is 65 cells across
+        let uni = BigBang::new().width(64).height(128).fog_radius(33).birth();
+        assert!(uni.is_err());
+
+        // radius 17 is 33 cells across
+        let uni = BigBang::new().width(128).height(32).fog_radius(17).birth();
+        assert!(uni.is_err());
+
+        // the largest circles that fit
+        assert!(BigBang::new().width(64).height(128).fog_radius(32).birth().is_ok());
+        assert!(BigBang::new().width(128).height(33).fog_radius(17).birth().is_ok());
+    }
+
     #[test]
     fn each_non_dead_detects_some_cells() {
         let mut uni = generate_test_universe_with_default_params(UniType::Server);
@@ -265,6 +283,84 @@ mod universe_tests {
         });
     }
 
+    #[test]
+    fn cells_in_only_returns_cells_within_region() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(62, 3, CellState::Alive(None));
+        uni.set_unchecked(63, 3, CellState::Wall);
+        uni.set_unchecked(64, 3, CellState::Alive(None));
+        uni.set_unchecked(70, 3, CellState::Alive(None));
+        uni.set_unchecked(64, 4, CellState::Alive(None));
+
+        let cells: Vec<(usize, usize, CellState)> = uni.cells_in(Region::new(63, 3, 7, 1), None).collect();
+        assert_eq!(cells, vec![(63, 3, CellState::Wall), (64, 3, CellState::Alive(None))]);
+    }
+
+    #[test]
+    fn cells_in_wraps_around_the_edges() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(0, 0, CellState::Alive(None));
+        uni.set_unchecked(255, 0, CellState::Alive(None));
+        uni.set_unchecked(0, 127, CellState::Alive(None));
+        uni.set_unchecked(255, 127, CellState::Alive(None));
+        uni.set_unchecked(2, 2, CellState::Alive(None)); // outside the region
+
+        let cells: Vec<(usize, usize, CellState)> = uni.cells_in(Region::new(-1, -1, 2, 2), None).collect();
+        assert_eq!(
+            cells,
+            vec![
+                (255, 127, CellState::Alive(None)),
+                (0, 127, CellState::Alive(None)),
+                (255, 0, CellState::Alive(None)),
+                (0, 0, CellState::Alive(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn cells_in_returns_each_cell_once_for_oversized_regions() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(10, 20, CellState::Alive(None));
+        uni.set_unchecked(200, 100, CellState::Wall);
+
+        let cells: Vec<(usize, usize, CellState)> = uni.cells_in(Region::new(-300, 0, 1000, 1000), None).collect();
+        assert_eq!(
+            cells,
+            vec![(10, 20, CellState::Alive(None)), (200, 100, CellState::Wall)]
+        );
+    }
+
+    #[test]
+    fn cells_in_matches_get_run() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let uni = random_universe(&mut rng);
+            let visibility = if uni.num_players() > 0 { Some(0) } else { None };
+            let (width, height) = (uni.width() as isize, uni.height() as isize);
+            let left = rng.gen_range(-width..width);
+            let top = rng.gen_range(-height..height);
+            let region = Region::new(
+                left,
+                top,
+                rng.gen_range(1..=width as usize),
+                rng.gen_range(1..=height as usize),
+            );
+
+            let mut expected = vec![];
+            for row in region.top()..=region.bottom() {
+                for col in region.left()..=region.right() {
+                    let (col, row) = (col.rem_euclid(width) as usize, row.rem_euclid(height) as usize);
+                    let state = CellState::from_char(uni.get_run(col, row, visibility).1).unwrap();
+                    if state != CellState::Dead {
+                        expected.push((col, row, state));
+                    }
+                }
+            }
+            let cells: Vec<(usize, usize, CellState)> = uni.cells_in(region, visibility).collect();
+            assert_eq!(cells, expected, "region {:?}", region);
+        }
+    }
+
     #[test]
     fn universe_apply_basic2() {
         // we do Server to Server so that the fog doesn't interfere with pattern comparison
@@ -342,23 +438,94 @@ mod universe_tests {
         let mut c_uni = generate_test_universe_with_default_params(UniType::Client);
         assert_eq!(
             c_uni.apply(&diff, None),
-            Err(InvalidData {
+            Err(GenerationMismatch {
+                gen0:   base,
+                gen1:   base + GEN_BUFSIZE + 1,
                 reason: "diff is across too many generations to be applied: 17 >= 16".to_owned(),
             })
         );
     }
 
     #[test]
-    fn universe_diff_crazy_numbers_is_none() {
+    fn universe_apply_pattern_out_of_bounds() {
+        let mut c_uni = generate_test_universe_with_default_params(UniType::Client);
+        let diff = GenStateDiff {
+            gen0:    0,
+            gen1:    1,
+            pattern: Pattern("3$300bo!".to_owned()),
+        };
+        assert_eq!(
+            c_uni.apply(&diff, None),
+            Err(OutOfBounds {
+                col:    300,
+                row:    3,
+                width:  256,
+                height: 128,
+            })
+        );
+        // nothing was applied
+        let diff = GenStateDiff {
+            gen0:    0,
+            gen1:    1,
+            pattern: Pattern("3$30bo!".to_owned()),
+        };
+        assert_eq!(c_uni.apply(&diff, None), Ok(Some(1)));
+    }
+
+    #[test]
+    fn universe_diff_history_exhausted() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        for _ in 0..GEN_BUFSIZE + 2 {
+            uni.next();
+        }
+        assert_eq!(
+            uni.diff(2, 10, None),
+            Err(HistoryExhausted {
+                generation: 2,
+                oldest:     4,
+            })
+        );
+        assert!(uni.diff(4, 10, None).is_ok());
+        assert!(uni.diff(0, 10, None).is_ok());
+    }
+
+    #[test]
+    fn toggle_out_of_bounds() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let err = uni.toggle(256, 3, 1).unwrap_err();
+        assert_eq!(
+            err,
+            OutOfBounds {
+                col:    256,
+                row:    3,
+                width:  256,
+                height: 128,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "ConwayError->OutOfBounds->col=256, row=3 is outside of 256x128 cells"
+        );
+    }
+
+    #[test]
+    fn universe_diff_crazy_numbers_is_err() {
         let uni = generate_test_universe_with_default_params(UniType::Server);
-        assert!(uni.diff(123, 456, None).is_none());
+        assert_eq!(
+            uni.diff(123, 456, None),
+            Err(GenerationMismatch {
+                gen0:   123,
+                gen1:   456,
+                reason: "the latest generation is 1".to_owned(),
+            })
+        );
     }
 
     #[test]
     #[should_panic]
     fn universe_diff_crazier_numbers_panics() {
         let uni = generate_test_universe_with_default_params(UniType::Server);
-        assert!(uni.diff(456, 456, None).is_none());
+        assert!(uni.diff(456, 456, None).is_err());
     }
 
     #[test]
@@ -456,6 +623,14 @@ mod universe_tests {
         let player1 = 1;
         assert!(!uni.writable(100, 70, player1).unwrap());
     }
+
+    #[test]
+    fn universe_writable_region_returns_player_region() {
+        let uni = generate_test_universe_with_default_params(UniType::Client);
+        assert_eq!(uni.writable_region(0), Some(Region::new(100, 70, 34, 16)));
+        assert_eq!(uni.writable_region(1), Some(Region::new(0, 0, 80, 80)));
+        assert_eq!(uni.writable_region(2), None);
+    }
 }
 
 mod genstate_tests {
@@ -1015,7 +1190,7 @@ mod grid_tests {
 }
 
 mod rle_tests {
-    use crate::error::ConwayError;
+    use crate::error::{ConwayError, RleError};
     use crate::grids::BitGrid;
     use crate::rle::*;
     use std::str::FromStr;
@@ -1258,6 +1433,13 @@ mod rle_tests {
         assert_eq!(size, (1, 1));
     }
 
+    #[test]
+    fn calc_size_long_runs() {
+        let pat = Pattern("50000o$50000b2o50000$o!".to_owned());
+        let size = pat.calc_size().unwrap();
+        assert_eq!(size, (50002, 50002));
+    }
+
     #[test]
     fn calc_size_fail1() {
         let pat = Pattern("invalidpatternlol".to_owned());
@@ -1265,9 +1447,2062 @@ mod rle_tests {
         let err = size_result.unwrap_err();
         assert_eq!(
             err,
-            ConwayError::InvalidData {
-                reason: "Premature termination at Some(16)".to_owned(),
+            ConwayError::RleSyntax {
+                line:    1,
+                column:  18,
+                snippet: "tternlol".to_owned(),
+                source:  RleError::MissingEnd,
+            }
+        );
+    }
+
+    #[test]
+    fn syntax_errors_have_locations() {
+        let err = Pattern("3o$\n2b70000o!".to_owned()).calc_size().unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    2,
+                column:  3,
+                snippet: "2b70000o!".to_owned(),
+                source:  RleError::NumberTooLarge { max: 50000 },
+            }
+        );
+        let err = Pattern("bo$2bo$3o$bbbbbbbbbbbbbbbbbbbXbbbbbbbbbbbbbbbbbbbb!".to_owned())
+            .to_new_bit_grid(40, 4)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    1,
+                column:  30,
+                snippet: "bbbbbbbbXbbbbbbb".to_owned(),
+                source:  RleError::UnexpectedChar { ch: 'X' },
+            }
+        );
+        let err = Pattern("3o12!".to_owned()).calc_size().unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    1,
+                column:  3,
+                snippet: "3o12!".to_owned(),
+                source:  RleError::NumberBeforeEnd,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "ConwayError->RleSyntax->line 1, column 3, at \"3o12!\": number right before `!`"
+        );
+    }
+
+    #[test]
+    fn pattern_file_errors_have_file_locations() {
+        let err = PatternFile::from_str("#N Glider\nx = 3, y = 3\nbo$2bo$\n3o$\n2b3o$99999o!\n").unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    5,
+                column:  6,
+                snippet: "2b3o$99999o!".to_owned(),
+                source:  RleError::NumberTooLarge { max: 50000 },
+            }
+        );
+        let err = PatternFile::from_str("x = 3, y = 3\nbo$2bo$\n3o$").unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    3,
+                column:  4,
+                snippet: "3o$".to_owned(),
+                source:  RleError::MissingEnd,
+            }
+        );
+        let err = PatternFile::from_str("x = 3, y = 3\n#C late\n3o!").unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    2,
+                column:  1,
+                snippet: "#C late".to_owned(),
+                source:  RleError::MisplacedComment,
+            }
+        );
+        let err = PatternFile::from_str("#C no header").unwrap_err();
+        assert_eq!(
+            err,
+            ConwayError::RleSyntax {
+                line:    2,
+                column:  1,
+                snippet: "".to_owned(),
+                source:  RleError::MissingHeader,
             }
         );
+        match PatternFile::from_str("x = 3, y = three\n3o!") {
+            Err(ConwayError::RleSyntax {
+                line: 1,
+                column: 8,
+                source: RleError::InvalidNumber { .. },
+                ..
+            }) => {}
+            result => panic!("expected InvalidNumber at 1:8, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn syntax_errors_have_sources() {
+        use std::error::Error;
+        let err = HeaderLine::from_str("x = 3, y = -1").unwrap_err();
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), "invalid number: invalid digit found in string");
+        assert!(source.source().unwrap().is::<std::num::ParseIntError>());
+        assert!(HeaderLine::from_str("x = 3")
+            .unwrap_err()
+            .source()
+            .unwrap()
+            .source()
+            .is_none());
+    }
+
+    #[test]
+    fn plaintext_round_trips() {
+        let file = PatternFile::from_plaintext("!Name: Glider\n.O.\n..*\nOOO\n").unwrap();
+        assert_eq!((file.width(), file.height()), (3, 3));
+        assert_eq!(file.pattern.0, "bo$2bo$3o!");
+        assert_eq!(file.to_plaintext().unwrap(), ".O.\n..O\nOOO\n");
+        assert_eq!(file.to_string(), "x = 3, y = 3\nbo$2bo$3o!\n");
+
+        let empty = PatternFile::from_plaintext("!Name: Nothing\n").unwrap();
+        assert_eq!((empty.width(), empty.height()), (0, 0));
+        assert_eq!(empty.to_plaintext().unwrap(), "");
+        let dead = PatternFile::from_plaintext("..\n").unwrap();
+        assert_eq!(dead.to_plaintext().unwrap(), "..\n");
+    }
+
+    #[test]
+    fn plaintext_rejects_unknown_and_unrepresentable_cells() {
+        match PatternFile::from_plaintext(".O.\n.x.\n") {
+            Err(ConwayError::InvalidData { reason }) => assert_eq!(
+                reason,
+                "Unexpected character 'x' at line 2, column 2 of plaintext pattern"
+            ),
+            result => panic!("expected InvalidData, got {:?}", result),
+        }
+        let walls = PatternFile::from_str("x = 2, y = 1\nbW!").unwrap();
+        assert!(walls.to_plaintext().is_err());
+    }
+}
+
+mod rule_tests {
+    use crate::grids::{BitGrid, BitOperation, CharGrid};
+    use crate::rle::*;
+    use crate::rule::*;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn neighborhood_sizes() {
+        assert_eq!(Neighborhood::Moore(1).size(), 8);
+        assert_eq!(Neighborhood::VonNeumann(1).size(), 4);
+        assert_eq!(Neighborhood::Hexagonal.size(), 6);
+        assert_eq!(Neighborhood::Moore(5).size(), 120);
+        assert_eq!(Neighborhood::VonNeumann(2).size(), 12);
+    }
+
+    #[test]
+    fn hexagonal_neighborhood_excludes_ne_and_sw() {
+        let offsets = Neighborhood::Hexagonal.offsets();
+        assert!(!offsets.contains(&(1, -1)));
+        assert!(!offsets.contains(&(-1, 1)));
+        assert!(offsets.contains(&(-1, -1)));
+        assert!(offsets.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn rule_strings_round_trip() {
+        for rule_str in &[
+            "B3/S23",
+            "B36/S23",
+            "B2/S34H",
+            "B1/S012V",
+            "B2/S",
+            "R5,C0,M1,S34..58,B34..45,NM",
+        ] {
+            let rule = Rule::from_str(rule_str).unwrap();
+            assert_eq!(rule.to_string(), *rule_str);
+            assert_eq!(Rule::from_str(&rule.to_string()).unwrap(), rule);
+        }
+    }
+
+    #[test]
+    fn rule_string_variants_are_accepted() {
+        assert_eq!(Rule::from_str("b3/s23").unwrap(), Rule::conway());
+        assert_eq!(Rule::from_str("S23/B3").unwrap(), Rule::conway());
+        assert_eq!(Rule::from_str("23/3").unwrap(), Rule::conway());
+        assert_eq!(Rule::from_str("R1,C0,M0,S2..3,B3,NM").unwrap(), Rule::conway());
+        assert_eq!(
+            Rule::from_str("R2,C2,M0,S3..5,B4..6,NN").unwrap(),
+            Rule::larger_than_life(Neighborhood::VonNeumann(2), false, 4..=6, 3..=5).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_rule_strings_are_rejected() {
+        assert!(Rule::from_str("B9/S23").is_err());
+        assert!(Rule::from_str("B3/S5V").is_err());
+        assert!(Rule::from_str("B03/S23").is_err());
+        assert!(Rule::from_str("B3S23").is_err());
+        assert!(Rule::from_str("B3/S2x").is_err());
+        assert!(Rule::from_str("R40,C0,M0,S3..5,B3..4,NM").is_err());
+        assert!(Rule::from_str("R2,C0,M0,S3..5,NM").is_err());
+        assert!(Rule::from_str("R2,C13,M0,S3..5,B3..4,NM").is_err());
+        assert!(Rule::larger_than_life(Neighborhood::Hexagonal, false, 2..=2, 3..=4).is_err());
+        assert!(Rule::new(vec![3, 5], vec![2, 3], Neighborhood::Moore(2)).is_err());
+    }
+
+    #[test]
+    fn generic_next_word_matches_conway_fast_path() {
+        // Same data as next_single_gen_test_data1_with_wrapping, laid out as a 3x3 word grid
+        let grid = BitGrid(vec![
+            vec![0x0000000000000000, 0x0000000400000002, 0x8000000000000000],
+            vec![0x0000000000000001, 0xC000000400000001, 0x8000000000000000],
+            vec![0x0000000000000000, 0x8000000400000001, 0x0000000000000000],
+        ]);
+        let rule = Rule::conway();
+        let offsets = rule.neighborhood().offsets();
+        assert_eq!(rule.next_word(&grid, 1, 1, &offsets), 0xC000000E00000002);
+    }
+
+    #[test]
+    fn header_line_with_larger_than_life_rule_round_trips() {
+        let header = "x = 3, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM";
+        let pat = PatternFile::from_str(&format!("{}\n3o!", header)).unwrap();
+        assert_eq!(pat.header_line.x, 3);
+        assert_eq!(pat.header_line.rule, Some("R5,C0,M1,S34..58,B34..45,NM".to_owned()));
+        assert_eq!(pat.header_line.to_string(), header);
+        assert_eq!(
+            pat.header_line.to_rule().unwrap().to_string(),
+            "R5,C0,M1,S34..58,B34..45,NM"
+        );
+    }
+
+    #[test]
+    fn header_line_without_rule_is_conway() {
+        let pat = PatternFile::from_str("x = 3, y = 1\n3o!").unwrap();
+        assert_eq!(pat.header_line.to_string(), "x = 3, y = 1");
+        assert_eq!(pat.header_line.to_rule().unwrap(), Rule::conway());
+    }
+
+    #[test]
+    fn birth_fails_if_neighborhood_is_larger_than_universe() {
+        let rule = Rule::larger_than_life(Neighborhood::Moore(32), false, 40..=60, 40..=80).unwrap();
+        assert!(BigBang::new().width(64).height(64).rule(rule.clone()).birth().is_err());
+        assert!(BigBang::new().width(128).height(65).rule(rule).birth().is_ok());
+    }
+
+    #[test]
+    fn von_neumann_rule_grows_player_owned_diamond() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_rule(Rule::from_str("B1/SV").unwrap()).unwrap();
+        let player1 = 1;
+        uni.toggle(10, 10, player1).unwrap();
+        uni.set_unchecked(11, 10, CellState::Wall);
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dead);
+        assert_eq!(
+            uni.get_cell_state(10, 9, Some(player1)),
+            CellState::Alive(Some(player1))
+        );
+        assert_eq!(
+            uni.get_cell_state(9, 10, Some(player1)),
+            CellState::Alive(Some(player1))
+        );
+        assert_eq!(
+            uni.get_cell_state(10, 11, Some(player1)),
+            CellState::Alive(Some(player1))
+        );
+        assert_eq!(uni.get_cell_state(11, 10, None), CellState::Dead); // wall stays a wall
+        assert_eq!(uni.get_cell_state(9, 9, None), CellState::Dead); // diagonals are not neighbors
+    }
+
+    #[test]
+    fn hexagonal_rule_ignores_ne_and_sw_neighbors() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_rule(Rule::from_str("B2/SH").unwrap()).unwrap();
+        uni.set_unchecked(10, 9, CellState::Alive(None));
+        uni.set_unchecked(11, 9, CellState::Alive(None));
+        uni.next();
+
+        // (10, 10) has (10, 9) to the north and (11, 9) to the north-east, which doesn't count
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dead);
+        // (11, 10) has (11, 9) to the north and (10, 9) to the north-west
+        assert_eq!(uni.get_cell_state(11, 10, None), CellState::Alive(None));
+    }
+
+    #[test]
+    fn larger_than_life_rule_wraps_around_universe_edges() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_rule(Rule::from_str("R2,C0,M0,S1..24,B3..3,NM").unwrap())
+            .unwrap();
+        uni.set_unchecked(255, 127, CellState::Alive(None));
+        uni.set_unchecked(0, 0, CellState::Alive(None));
+        uni.set_unchecked(1, 1, CellState::Alive(None));
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(0, 0, None), CellState::Alive(None));
+        assert_eq!(uni.get_cell_state(255, 0, None), CellState::Alive(None)); // born from 3 within range 2
+        assert_eq!(uni.get_cell_state(1, 1, None), CellState::Alive(None)); // (255,127) is within range 2
+        assert_eq!(uni.get_cell_state(0, 1, None), CellState::Alive(None));
+        assert_eq!(uni.get_cell_state(2, 2, None), CellState::Dead); // (255,127) is too far away
+    }
+
+    #[test]
+    fn generations_rule_strings_round_trip() {
+        for rule_str in &["B2/S/C3", "B2/S345/C4", "B2/S/C12H", "R2,C5,M1,S1..3,B2..4,NN"] {
+            let rule = Rule::from_str(rule_str).unwrap();
+            assert_eq!(rule.to_string(), *rule_str);
+        }
+        assert_eq!(Rule::from_str("/2/3").unwrap().to_string(), "B2/S/C3");
+        assert_eq!(Rule::from_str("345/2/4").unwrap().to_string(), "B2/S345/C4");
+        assert_eq!(Rule::from_str("B2/S/C4").unwrap().dying_stages(), 2);
+        assert_eq!(Rule::conway().dying_stages(), 0);
+        assert!(Rule::from_str("B2/S/C13").is_err());
+        assert!(Rule::from_str("B2/S/C1").is_err());
+    }
+
+    #[test]
+    fn brians_brain_cells_die_after_one_dying_stage() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let player1 = 1;
+        uni.set_rule(Rule::from_str("B2/S/C3").unwrap()).unwrap();
+        uni.set_unchecked(10, 10, CellState::Alive(Some(player1)));
+        uni.set_unchecked(11, 10, CellState::Alive(Some(player1)));
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dying(None, 1));
+        assert_eq!(
+            uni.get_cell_state(11, 10, Some(player1)),
+            CellState::Dying(Some(player1), 1)
+        );
+        assert_eq!(
+            uni.get_cell_state(10, 9, Some(player1)),
+            CellState::Alive(Some(player1))
+        );
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dead);
+        assert_eq!(
+            uni.get_cell_state(10, 9, Some(player1)),
+            CellState::Dying(Some(player1), 1)
+        );
+    }
+
+    #[test]
+    fn dying_cells_cannot_be_born_into() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_rule(Rule::from_str("B2/S/C4").unwrap()).unwrap();
+        uni.set_unchecked(10, 10, CellState::Dying(None, 1));
+        uni.set_unchecked(9, 10, CellState::Alive(None));
+        uni.set_unchecked(11, 10, CellState::Alive(None));
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dying(None, 2));
+        assert_eq!(uni.get_cell_state(9, 10, None), CellState::Dying(None, 1));
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dead);
+        assert_eq!(uni.get_cell_state(9, 10, None), CellState::Dying(None, 2));
+    }
+
+    #[test]
+    fn dying_cells_round_trip_through_rle() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let player1 = 1;
+        uni.set_rule(Rule::from_str("B2/S345/C5").unwrap()).unwrap();
+        uni.set_unchecked(3, 0, CellState::Dying(Some(player1), 3));
+        uni.set_unchecked(4, 0, CellState::Dying(None, 1));
+        uni.set_unchecked(5, 0, CellState::Dying(None, 1));
+        uni.set_unchecked(6, 0, CellState::Alive(Some(player1)));
+        let pattern = uni.to_pattern(None);
+        assert!(pattern.0.starts_with("3brB2poB"), "unexpected pattern {}", pattern.0);
+
+        let mut other_uni = generate_test_universe_with_default_params(UniType::Client);
+        other_uni.set_rule(uni.rule().clone()).unwrap();
+        let diff = GenStateDiff {
+            gen0:    0,
+            gen1:    1,
+            pattern: pattern.clone(),
+        };
+        assert_eq!(other_uni.apply(&diff, None), Ok(Some(1)));
+        for col in 0..8 {
+            assert_eq!(other_uni.get_cell_state(col, 0, None), uni.get_cell_state(col, 0, None));
+        }
+        assert_eq!(
+            other_uni.get_cell_state(3, 0, Some(player1)),
+            CellState::Dying(Some(player1), 3)
+        );
+    }
+
+    #[test]
+    fn invalid_dying_cell_tokens_are_rejected() {
+        for (pattern, is_ok) in &[("pb!", false), ("p", false), ("2po!", true)] {
+            let mut uni = generate_test_universe_with_default_params(UniType::Client);
+            uni.set_rule(Rule::from_str("B2/S/C3").unwrap()).unwrap();
+            let diff = GenStateDiff {
+                gen0:    0,
+                gen1:    1,
+                pattern: Pattern(pattern.to_string()),
+            };
+            assert_eq!(uni.apply(&diff, None).is_ok(), *is_ok, "pattern {}", pattern);
+        }
+    }
+
+    #[test]
+    fn universe_apply_with_dying_cells() {
+        let mut src_uni = generate_test_universe_with_default_params(UniType::Server);
+        let mut dst_uni = generate_test_universe_with_default_params(UniType::Server);
+        let player1 = 1;
+        let rule = Rule::from_str("B2/S/C3").unwrap();
+        src_uni.set_rule(rule.clone()).unwrap();
+        dst_uni.set_rule(rule).unwrap();
+        src_uni.next();
+        src_uni.toggle(16, 15, player1).unwrap();
+        src_uni.toggle(17, 15, player1).unwrap();
+        let diff1 = src_uni.diff(1, 2, None).unwrap();
+        assert_eq!(dst_uni.apply(&diff1, None), Ok(Some(2)));
+        for _ in 0..3 {
+            src_uni.next();
+        }
+        let diff2 = src_uni.diff(2, 5, None).unwrap();
+        assert_eq!(dst_uni.apply(&diff2, None), Ok(Some(5)));
+        assert_eq!(src_uni.to_pattern(None), dst_uni.to_pattern(None));
+        assert_eq!(dst_uni.to_string(), src_uni.to_string());
+    }
+
+    fn volatile_strip_universe() -> Universe {
+        BigBang::new()
+            .width(256)
+            .height(128)
+            .rule(Rule::from_str("B3/S23").unwrap())
+            .rule_zone(Region::new(96, 0, 64, 128), Rule::from_str("B36/S23").unwrap())
+            .birth()
+            .unwrap()
+    }
+
+    fn surround_with_six(uni: &mut Universe, col: usize, row: usize) {
+        for &(dx, dy) in &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1)] {
+            let (neighbor_col, neighbor_row) = ((col as isize + dx) as usize, (row as isize + dy) as usize);
+            uni.set_unchecked(neighbor_col, neighbor_row, CellState::Alive(None));
+        }
+    }
+
+    #[test]
+    fn rule_at_reports_zone_rules() {
+        let uni = BigBang::new()
+            .width(256)
+            .height(128)
+            .rule_zone(Region::new(96, 0, 64, 128), Rule::from_str("B36/S23").unwrap())
+            .rule_zone(Region::new(128, 10, 10, 10), Rule::from_str("B2/S/C3").unwrap())
+            .birth()
+            .unwrap();
+        assert_eq!(uni.rule_at(95, 0).to_string(), "B3/S23");
+        assert_eq!(uni.rule_at(96, 0).to_string(), "B36/S23");
+        assert_eq!(uni.rule_at(159, 127).to_string(), "B36/S23");
+        assert_eq!(uni.rule_at(160, 127).to_string(), "B3/S23");
+        assert_eq!(uni.rule_at(130, 15).to_string(), "B2/S/C3"); // later zones take precedence
+        assert_eq!(uni.rule_at(130, 20).to_string(), "B36/S23");
+    }
+
+    #[test]
+    fn volatile_strip_births_on_six_neighbors() {
+        let mut uni = volatile_strip_universe();
+        surround_with_six(&mut uni, 128, 50);
+        surround_with_six(&mut uni, 20, 50);
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(128, 50, None), CellState::Alive(None));
+        assert_eq!(uni.get_cell_state(20, 50, None), CellState::Dead);
+    }
+
+    #[test]
+    fn zone_border_cells_count_neighbors_outside_their_zone() {
+        let mut uni = volatile_strip_universe();
+        // most of these neighbors are outside the strip, but (96, 50) is inside it
+        surround_with_six(&mut uni, 96, 50);
+        // and these are inside the strip, but (95, 80) is outside it
+        surround_with_six(&mut uni, 95, 80);
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(96, 50, None), CellState::Alive(None));
+        assert_eq!(uni.get_cell_state(95, 80, None), CellState::Dead);
+    }
+
+    #[test]
+    fn rule_zone_mask_applies_to_masked_cells() {
+        let mut mask = BitGrid::new(4, 128);
+        mask.modify_bits_in_word(50, 0, 1 << (63 - 20), BitOperation::Set);
+        let mut uni = BigBang::new()
+            .width(256)
+            .height(128)
+            .rule_zone_mask(mask, Rule::from_str("B36/S23").unwrap())
+            .birth()
+            .unwrap();
+        assert_eq!(uni.rule_at(20, 50).to_string(), "B36/S23");
+        assert_eq!(uni.rule_at(21, 50).to_string(), "B3/S23");
+        surround_with_six(&mut uni, 20, 50);
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(20, 50, None), CellState::Alive(None));
+    }
+
+    #[test]
+    fn generations_rule_zone_decays_only_inside_zone() {
+        let mut uni = BigBang::new()
+            .width(256)
+            .height(128)
+            .rule_zone(Region::new(0, 0, 64, 128), Rule::from_str("B3/S23/C3").unwrap())
+            .birth()
+            .unwrap();
+        uni.set_unchecked(10, 10, CellState::Alive(None));
+        uni.set_unchecked(100, 10, CellState::Alive(None));
+        uni.next();
+
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dying(None, 1));
+        assert_eq!(uni.get_cell_state(100, 10, None), CellState::Dead);
+    }
+
+    #[test]
+    fn birth_fails_for_invalid_rule_zones() {
+        let rule = Rule::from_str("B36/S23").unwrap();
+        assert!(BigBang::new()
+            .rule_zone(Region::new(200, 0, 64, 128), rule.clone())
+            .birth()
+            .is_err());
+        assert!(BigBang::new()
+            .rule_zone_mask(BitGrid::new(2, 128), rule.clone())
+            .birth()
+            .is_err());
+        assert!(BigBang::new()
+            .width(64)
+            .height(64)
+            .rule_zone(
+                Region::new(0, 0, 8, 8),
+                Rule::from_str("R32,C0,M0,S3..5,B3..4,NM").unwrap()
+            )
+            .birth()
+            .is_err());
+    }
+}
+
+mod energy_tests {
+    use crate::error::ConwayError;
+    use crate::grids::{BitGrid, BitOperation};
+    use crate::rle::Pattern;
+    use crate::universe::*;
+
+    const BUDGET: EnergyBudget = EnergyBudget {
+        initial:              30,
+        max:                  100,
+        cell_cost:            10,
+        regen_per_generation: 5,
+        regen_per_live_cell:  1,
+    };
+
+    fn generate_budgeted_universe(server_mode: bool) -> Universe {
+        BigBang::new()
+            .width(256)
+            .height(128)
+            .server_mode(server_mode)
+            .add_player(PlayerBuilder::new(Region::new(0, 0, 80, 80)).energy_budget(BUDGET))
+            .add_player(PlayerBuilder::new(Region::new(100, 0, 80, 80)))
+            .birth()
+            .unwrap()
+    }
+
+    #[test]
+    fn toggle_spends_energy_until_over_budget() {
+        let mut uni = generate_budgeted_universe(true);
+        assert_eq!(uni.energy(0), Some(30));
+        uni.toggle(10, 10, 0).unwrap();
+        uni.toggle(11, 10, 0).unwrap();
+        uni.toggle(12, 10, 0).unwrap();
+        assert_eq!(uni.energy(0), Some(0));
+        assert_eq!(
+            uni.toggle(13, 10, 0),
+            Err(ConwayError::InsufficientEnergy {
+                player_id: 0,
+                cost:      10,
+                energy:    0,
+            })
+        );
+        assert_eq!(uni.get_cell_state(13, 10, Some(0)), CellState::Dead);
+
+        // killing cells is free
+        assert_eq!(uni.toggle(12, 10, 0), Ok(CellState::Dead));
+        assert_eq!(uni.energy(0), Some(0));
+    }
+
+    #[test]
+    fn players_without_budget_have_unlimited_energy() {
+        let mut uni = generate_budgeted_universe(true);
+        assert_eq!(uni.energy(1), None);
+        for col in 100..150 {
+            uni.toggle(col, 10, 1).unwrap();
+        }
+    }
+
+    #[test]
+    fn energy_regenerates_per_generation_and_live_cell_up_to_max() {
+        let mut uni = generate_budgeted_universe(true);
+        // this L-tromino costs all 30 energy, and becomes a block of 4 cells
+        uni.toggle(10, 10, 0).unwrap();
+        uni.toggle(11, 10, 0).unwrap();
+        uni.toggle(10, 11, 0).unwrap();
+        uni.next();
+        assert_eq!(uni.energy(0), Some(5 + 4));
+        uni.next();
+        assert_eq!(uni.energy(0), Some(2 * (5 + 4)));
+        for _ in 0..20 {
+            uni.next();
+        }
+        assert_eq!(uni.energy(0), Some(100));
+    }
+
+    #[test]
+    fn copy_from_bit_grid_over_budget_copies_nothing() {
+        let mut uni = generate_budgeted_universe(true);
+        let mut grid = BitGrid::new(1, 4);
+        grid.modify_region(Region::new(0, 0, 2, 2), BitOperation::Set);
+        assert_eq!(
+            uni.copy_from_bit_grid(&grid, Region::new(10, 10, 4, 4), Some(0)),
+            Err(ConwayError::InsufficientEnergy {
+                player_id: 0,
+                cost:      40,
+                energy:    30,
+            })
+        );
+        assert_eq!(uni.get_cell_state(10, 10, None), CellState::Dead);
+        assert_eq!(uni.energy(0), Some(30));
+
+        grid.modify_region(Region::new(1, 1, 1, 1), BitOperation::Clear);
+        uni.copy_from_bit_grid(&grid, Region::new(10, 10, 4, 4), Some(0))
+            .unwrap();
+        assert_eq!(uni.get_cell_state(10, 10, Some(0)), CellState::Alive(Some(0)));
+        assert_eq!(uni.energy(0), Some(0));
+
+        // the server may place cells on behalf of no one for free
+        grid.modify_region(Region::new(1, 1, 1, 1), BitOperation::Set);
+        uni.copy_from_bit_grid(&grid, Region::new(20, 20, 4, 4), None).unwrap();
+        assert_eq!(uni.get_cell_state(21, 21, None), CellState::Alive(None));
+    }
+
+    #[test]
+    fn set_does_nothing_when_over_budget() {
+        let mut uni = generate_budgeted_universe(true);
+        for col in 10..14 {
+            uni.set(col, 10, CellState::Alive(Some(0)), 0);
+        }
+        assert_eq!(uni.get_cell_state(12, 10, Some(0)), CellState::Alive(Some(0)));
+        assert_eq!(uni.get_cell_state(13, 10, Some(0)), CellState::Dead);
+        assert_eq!(uni.energy(0), Some(0));
+    }
+
+    #[test]
+    fn apply_regenerates_energy_for_generations_skipped() {
+        let mut uni = generate_budgeted_universe(false);
+        let diff = GenStateDiff {
+            gen0:    0,
+            gen1:    3,
+            pattern: Pattern("!".to_owned()),
+        };
+        assert_eq!(uni.apply(&diff, Some(0)), Ok(Some(3)));
+        assert_eq!(uni.energy(0), Some(30 + 2 * 5));
+    }
+}
+
+mod reference_tests {
+    use crate::grids::CharGrid;
+    use crate::reference::*;
+    use crate::rle::Pattern;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn reference_universe_starts_identical() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.toggle(16, 15, 1).unwrap();
+        uni.set_unchecked(20, 20, CellState::Wall);
+        let reference = ReferenceUniverse::from_universe(&uni);
+        assert_eq!(reference.latest_gen(), 1);
+        assert_eq!(reference.to_pattern(None), uni.to_pattern(None));
+        assert_eq!(reference.to_pattern(Some(0)), uni.to_pattern(Some(0)));
+        assert_eq!(first_divergence(&uni, &reference), None);
+    }
+
+    #[test]
+    fn glider_gun_matches_reference() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        // Gosper glider gun; its gliders wrap around the universe and eventually crash into it
+        let gun = Pattern(
+            "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"
+                .to_owned(),
+        )
+        .to_new_bit_grid(36, 9)
+        .unwrap();
+        uni.copy_from_bit_grid(&gun, Region::new(10, 10, 36, 9), None).unwrap();
+        if let Err(divergence) = run_differential(&mut uni, 300) {
+            panic!("{}", divergence);
+        }
+    }
+
+    #[test]
+    fn divergence_is_reported_with_both_patterns() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let reference = ReferenceUniverse::from_universe(&uni);
+        uni.toggle(3, 2, 1).unwrap();
+        let divergence = first_divergence(&uni, &reference).unwrap();
+        assert_eq!((divergence.visibility, divergence.col, divergence.row), (None, 3, 2));
+        assert_eq!(divergence.actual, uni.to_pattern(None));
+        assert_eq!(divergence.expected, reference.to_pattern(None));
+        assert!(divergence.to_string().contains(&divergence.actual.0));
+    }
+
+    #[test]
+    fn random_universes_match_reference() {
+        for seed in 0..60 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut uni = random_universe(&mut rng);
+            if let Err(divergence) = run_differential(&mut uni, 12) {
+                panic!("seed {}, rule {}: {}", seed, uni.rule(), divergence);
+            }
+        }
+    }
+}
+
+mod changed_since_tests {
+    use crate::grids::BitGrid;
+    use crate::rle::Pattern;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+
+    // (row, word_col) of every changed word
+    fn changed_words(changed: &BitGrid) -> Vec<(usize, usize)> {
+        let mut words = vec![];
+        for (row, changed_row) in changed.0.iter().enumerate() {
+            for (word_col, &word) in changed_row.iter().enumerate() {
+                if word != 0 {
+                    assert_eq!(word, u64::MAX);
+                    words.push((row, word_col));
+                }
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn next_marks_only_words_that_changed() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        // blinker
+        for col in 1..=3 {
+            uni.set_unchecked(col, 5, CellState::Alive(None));
+        }
+        assert_eq!(changed_words(&uni.changed_since(1).unwrap()), vec![(5, 0)]);
+
+        uni.next();
+        assert_eq!(changed_words(&uni.changed_since(2).unwrap()), vec![]);
+        assert_eq!(
+            changed_words(&uni.changed_since(1).unwrap()),
+            vec![(4, 0), (5, 0), (6, 0)]
+        );
+
+        // the blinker is back where it started, but it changed in between
+        uni.next();
+        assert_eq!(
+            changed_words(&uni.changed_since(1).unwrap()),
+            vec![(4, 0), (5, 0), (6, 0)]
+        );
+    }
+
+    #[test]
+    fn edits_to_the_latest_generation_are_marked() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.next();
+        uni.toggle_unchecked(130, 20, None);
+        assert_eq!(changed_words(&uni.changed_since(2).unwrap()), vec![(20, 2)]);
+
+        let grid = Pattern("2o$2o!".to_owned()).to_new_bit_grid(2, 2).unwrap();
+        uni.copy_from_bit_grid(&grid, Region::new(63, 40, 2, 2), None).unwrap();
+        assert_eq!(
+            changed_words(&uni.changed_since(2).unwrap()),
+            vec![(20, 2), (40, 0), (40, 1), (41, 0), (41, 1)]
+        );
+
+        // the lone cell dies but the block is still
+        uni.next();
+        assert_eq!(changed_words(&uni.changed_since(3).unwrap()), vec![]);
+        assert_eq!(
+            changed_words(&uni.changed_since(2).unwrap()),
+            vec![(20, 2), (40, 0), (40, 1), (41, 0), (41, 1)]
+        );
+    }
+
+    #[test]
+    fn unknown_generations_have_no_changes() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        assert_eq!(uni.changed_since(2), None);
+        for _ in 0..GEN_BUFSIZE {
+            uni.next();
+        }
+        assert_eq!(uni.changed_since(1), None);
+        assert!(uni.changed_since(2).is_some());
+    }
+
+    #[test]
+    fn apply_marks_words_that_changed() {
+        let mut s_uni = generate_test_universe_with_default_params(UniType::Server);
+        let mut c_uni = generate_test_universe_with_default_params(UniType::Client);
+        let player1 = 1;
+        // glider
+        s_uni.toggle(16, 15, player1).unwrap();
+        s_uni.toggle(17, 16, player1).unwrap();
+        s_uni.toggle(15, 17, player1).unwrap();
+        s_uni.toggle(16, 17, player1).unwrap();
+        s_uni.toggle(17, 17, player1).unwrap();
+        s_uni.next();
+        c_uni.apply(&s_uni.diff(0, 2, None).unwrap(), None).unwrap();
+        // the client doesn't know what generation 1 looked like
+        assert_eq!(c_uni.changed_since(1), None);
+
+        s_uni.next();
+        c_uni.apply(&s_uni.diff(2, 3, None).unwrap(), None).unwrap();
+        let changed = c_uni.changed_since(2).unwrap();
+        assert_eq!(changed_words(&changed), vec![(16, 0), (17, 0), (18, 0)]);
+        assert_eq!(Some(changed), s_uni.changed_since(2));
+    }
+}
+
+mod soup_tests {
+    use crate::error::ConwayError;
+    use crate::grids::{BitGrid, CharGrid};
+    use crate::soup::*;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+
+    fn is_set(grid: &BitGrid, col: usize, row: usize) -> bool {
+        (grid[row][col / 64] >> (63 - col % 64)) & 1 == 1
+    }
+
+    #[test]
+    fn soups_are_reproducible() {
+        let soup = Soup::new(Region::new(0, 0, 16, 2), 0.5, 42);
+        // clients and servers generate soups separately, so this must never change
+        assert_eq!(
+            soup.to_bit_grid().unwrap().to_pattern(None).0,
+            "2bob3o2bo5bo$b5ob3o5bo!"
+        );
+        assert_eq!(soup.to_bit_grid(), soup.to_bit_grid());
+        assert_ne!(
+            soup.to_bit_grid(),
+            Soup::new(Region::new(0, 0, 16, 2), 0.5, 43).to_bit_grid()
+        );
+    }
+
+    #[test]
+    fn soup_density_is_respected() {
+        let region = Region::new(0, 0, 100, 100);
+        assert_eq!(Soup::new(region, 0.0, 1).to_bit_grid().unwrap().count_ones(), 0);
+        assert_eq!(Soup::new(region, 1.0, 1).to_bit_grid().unwrap().count_ones(), 100 * 100);
+        let count = Soup::new(region, 0.3, 1).to_bit_grid().unwrap().count_ones();
+        assert!(2500 < count && count < 3500, "{} cells", count);
+    }
+
+    #[test]
+    fn soup_symmetries() {
+        let region = Region::new(0, 0, 70, 70);
+        let n = 69;
+        let c2 = Soup::new(region, 0.5, 7).symmetry(Symmetry::C2).to_bit_grid().unwrap();
+        let c4 = Soup::new(region, 0.5, 7).symmetry(Symmetry::C4).to_bit_grid().unwrap();
+        let d4 = Soup::new(region, 0.5, 7).symmetry(Symmetry::D4).to_bit_grid().unwrap();
+        let mut c4_is_mirrored = true;
+        for row in 0..=n {
+            for col in 0..=n {
+                assert_eq!(is_set(&c2, col, row), is_set(&c2, n - col, n - row));
+                assert_eq!(is_set(&c4, col, row), is_set(&c4, n - row, col));
+                assert_eq!(is_set(&d4, col, row), is_set(&d4, n - row, col));
+                assert_eq!(is_set(&d4, col, row), is_set(&d4, n - col, row));
+                assert_eq!(is_set(&d4, col, row), is_set(&d4, row, col));
+                c4_is_mirrored &= is_set(&c4, col, row) == is_set(&c4, n - col, row);
+            }
+        }
+        assert!(!c4_is_mirrored);
+    }
+
+    #[test]
+    fn invalid_soups_are_rejected() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        for soup in [
+            Soup::new(Region::new(0, 0, 10, 20), 0.5, 1).symmetry(Symmetry::C4),
+            Soup::new(Region::new(0, 0, 10, 10), 1.5, 1),
+            Soup::new(Region::new(250, 0, 10, 10), 0.5, 1),
+            Soup::new(Region::new(0, 0, 10, 10), 0.5, 1).player(2),
+        ] {
+            match uni.random_soup(&soup) {
+                Err(ConwayError::InvalidData { .. }) => {}
+                result => panic!("expected InvalidData for {:?}, got {:?}", soup, result),
+            }
+        }
+    }
+
+    #[test]
+    fn player_soup_is_clipped_to_writable_region() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let player1 = 1; // writable region is (0, 0, 80, 80)
+        uni.set_unchecked(70, 70, CellState::Wall);
+        uni.random_soup(&Soup::new(Region::new(60, 60, 40, 40), 1.0, 1).player(player1))
+            .unwrap();
+
+        let mut count = 0;
+        uni.each_non_dead_full(None, &mut |col, row, state| {
+            if (col, row) != (70, 70) {
+                assert_eq!(state, CellState::Alive(Some(player1)), "at ({}, {})", col, row);
+                assert!((60..80).contains(&col) && (60..80).contains(&row));
+                count += 1;
+            }
+        });
+        assert_eq!(count, 20 * 20 - 1);
+    }
+
+    #[test]
+    fn bigbang_soups_match_universe_soups() {
+        let soup = Soup::new(Region::new(30, 40, 64, 20), 0.4, 99).symmetry(Symmetry::C2);
+        let budget = EnergyBudget {
+            initial:              10,
+            max:                  10,
+            cell_cost:            1,
+            regen_per_generation: 0,
+            regen_per_live_cell:  0,
+        };
+        let bigbang = BigBang::new()
+            .add_player(PlayerBuilder::new(Region::new(0, 0, 128, 128)).energy_budget(budget))
+            .random_soup(soup.player(0));
+        let uni0 = bigbang.birth().unwrap();
+        let uni1 = bigbang.birth().unwrap();
+        assert_eq!(uni0.energy(0), Some(10)); // soups at birth are free
+        assert_eq!(uni0.to_pattern(None), uni1.to_pattern(None));
+
+        let mut uni2 = BigBang::new().birth().unwrap();
+        uni2.random_soup(&soup).unwrap();
+        let mut expected = BitGrid::new(4, 128);
+        BitGrid::copy(&soup.to_bit_grid().unwrap(), &mut expected, soup.region());
+        assert_eq!(uni2.to_pattern(None), expected.to_pattern(None));
+        assert_eq!(uni0.to_pattern(None).0, uni2.to_pattern(None).0.replace('o', "A"));
+    }
+}
+
+mod scenario_tests {
+    use crate::error::ConwayError;
+    use crate::grids::{BitGrid, CharGrid, Rotation, Transform};
+    use crate::rle::Pattern;
+    use crate::scenario::*;
+    use crate::universe::*;
+    use std::fs;
+
+    const DEMO: &str = include_str!("../scenarios/demo.toml");
+
+    #[test]
+    fn demo_scenario_parses() {
+        let scenario: Scenario = DEMO.parse().unwrap();
+        assert_eq!((scenario.width, scenario.height), (128, 32));
+        assert_eq!(scenario.topology, Topology::Torus);
+        assert_eq!(scenario.fog_radius, Some(16));
+        assert_eq!(scenario.rule, None);
+        assert_eq!(
+            scenario.player_writable,
+            vec![Region::new(40, 6, 16, 8), Region::new(60, 16, 8, 8)]
+        );
+        assert_eq!(scenario.patterns.len(), 3);
+        let wall = &scenario.patterns[2];
+        assert_eq!((wall.width, wall.height, wall.left, wall.top), (5, 12, 74, 13));
+        assert_eq!((wall.owner, wall.transform), (None, Transform::Identity));
+    }
+
+    #[test]
+    fn demo_scenario_matches_cell_by_cell_setup() {
+        let mut expected = Universe::new(
+            128,
+            32,
+            true,
+            16,
+            2,
+            vec![Region::new(40, 6, 16, 8), Region::new(60, 16, 8, 8)],
+            16,
+        )
+        .unwrap();
+        for &(col, row) in [(62, 17), (63, 17), (61, 18), (62, 18), (62, 19)].iter() {
+            expected.toggle(col, row, 1).unwrap();
+        }
+        for &(col, row) in [
+            (48, 6),
+            (47, 7),
+            (47, 8),
+            (52, 8),
+            (47, 9),
+            (48, 9),
+            (49, 9),
+            (50, 9),
+            (51, 9),
+        ]
+        .iter()
+        {
+            expected.toggle(col, row, 0).unwrap();
+        }
+        for col in 74..=78 {
+            expected.set_unchecked(col, 13, CellState::Wall);
+        }
+        for row in 14..=24 {
+            expected.set_unchecked(78, row, CellState::Wall);
+        }
+        for col in 75..=77 {
+            expected.set_unchecked(col, 24, CellState::Wall);
+        }
+
+        let scenario: Scenario = DEMO.parse().unwrap();
+        let mut uni = BigBang::from_scenario(&scenario).birth().unwrap();
+        assert_eq!(uni.to_pattern(None), expected.to_pattern(None));
+        for _ in 0..10 {
+            uni.next();
+            expected.next();
+        }
+        assert_eq!(uni.to_pattern(None), expected.to_pattern(None));
+    }
+
+    #[test]
+    fn transforms_match_bit_grid_rotation() {
+        let (width, height) = (3, 5);
+        let pattern = Pattern("2o$bo$o$2bo$3o!".to_owned());
+        let grid = pattern.to_new_bit_grid(width, height).unwrap();
+        for &(transform, rotation) in [
+            (Transform::RotateCW, Rotation::CW),
+            (Transform::RotateCCW, Rotation::CCW),
+        ]
+        .iter()
+        {
+            let mut rotated = grid.clone();
+            rotated.rotate(width, height, rotation).unwrap();
+
+            let (new_width, new_height) = transform.size(width, height);
+            assert_eq!((new_width, new_height), (5, 3));
+            let mut transformed = BitGrid::new(1, 64);
+            grid.each_set(|col, row| {
+                let (col, row) = transform.apply(col, row, width, height);
+                assert!(col < new_width && row < new_height);
+                transformed[row][0] |= 1 << (63 - col);
+            });
+            assert_eq!(transformed.to_pattern(None), rotated.to_pattern(None));
+        }
+    }
+
+    #[test]
+    fn transforms_are_distinct() {
+        let (width, height) = (3, 2);
+        let images: Vec<Vec<(usize, usize)>> = Transform::ALL
+            .iter()
+            .map(|transform| {
+                (0..height)
+                    .flat_map(|row| (0..width).map(move |col| transform.apply(col, row, width, height)))
+                    .collect()
+            })
+            .collect();
+        for i in 0..images.len() {
+            for j in (i + 1)..images.len() {
+                assert_ne!(
+                    images[i],
+                    images[j],
+                    "{:?} and {:?}",
+                    Transform::ALL[i],
+                    Transform::ALL[j]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn placed_pattern_is_transformed_and_wraps() {
+        let scenario: Scenario = r#"
+            width = 64
+            height = 16
+
+            [[players]]
+            writable = { left = 0, top = 0, width = 32, height = 16 }
+
+            [[patterns]]
+            rle = "3o$o!"
+            left = 63
+            top = 15
+            owner = 0
+            transform = "rotate_cw"
+        "#
+        .parse()
+        .unwrap();
+        let uni = BigBang::from_scenario(&scenario).birth().unwrap();
+        // rotated clockwise, the pattern is "2o$bo$bo!", with its top-left corner at (63, 15)
+        let mut cells = vec![];
+        uni.each_non_dead_full(None, &mut |col, row, state| cells.push((col, row, state)));
+        let alive = CellState::Alive(Some(0));
+        assert_eq!(
+            cells,
+            vec![(0, 0, alive), (0, 1, alive), (0, 15, alive), (63, 15, alive)]
+        );
+    }
+
+    #[test]
+    fn pattern_files_are_relative_to_scenario() {
+        let dir = std::env::temp_dir().join(format!("conway-scenario-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("patterns")).unwrap();
+        fs::write(
+            dir.join("patterns").join("glider.rle"),
+            "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("map.toml"),
+            "width = 64\nheight = 64\nrule = \"B36/S23\"\n\n[[patterns]]\nfile = \"patterns/glider.rle\"\nleft = 10\ntop = 20\ntransform = \"flip_horizontal\"\n",
+        )
+        .unwrap();
+        let result = Scenario::from_file(dir.join("map.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let scenario = result.unwrap();
+        assert_eq!(scenario.rule, Some("B36/S23".parse().unwrap()));
+        let uni = BigBang::from_scenario(&scenario).birth().unwrap();
+        assert_eq!(uni.rule(), &"B36/S23".parse().unwrap());
+        let mut cells = vec![];
+        uni.each_non_dead_full(None, &mut |col, row, _| cells.push((col, row)));
+        assert_eq!(cells, vec![(11, 20), (10, 21), (10, 22), (11, 22), (12, 22)]);
+    }
+
+    #[test]
+    fn invalid_scenarios_are_rejected() {
+        for contents in [
+            "width = 64",
+            "width = 64\nheight = 64\ntopology = \"klein_bottle\"",
+            "width = 64\nheight = 64\ncolor = \"blue\"",
+            "width = 64\nheight = 64\nrule = \"B3/S23/X\"",
+            "width = 64\nheight = 64\n[[players]]\nwritable = { left = 0, top = 0, width = 0, height = 8 }",
+            "width = 64\nheight = 64\n[[patterns]]\nleft = 0\ntop = 0",
+            "width = 64\nheight = 64\n[[patterns]]\nrle = \"o!\"\nfile = \"o.rle\"\nleft = 0\ntop = 0",
+            "width = 64\nheight = 64\n[[patterns]]\nrle = \"o!\"\nleft = 0\ntop = 0\ntransform = \"spin\"",
+            "width = 64\nheight = 64\n[[patterns]]\nfile = \"does/not/exist.rle\"\nleft = 0\ntop = 0",
+        ] {
+            match contents.parse::<Scenario>() {
+                Err(ConwayError::InvalidData { .. }) => {}
+                result => panic!("expected InvalidData for {:?}, got {:?}", contents, result),
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_scenario_patterns_are_syntax_errors() {
+        let contents = "width = 64\nheight = 64\n[[patterns]]\nrle = \"2o\\n3o\"\nleft = 0\ntop = 0";
+        match contents.parse::<Scenario>() {
+            Err(ConwayError::RleSyntax { line: 2, column: 3, .. }) => {}
+            result => panic!("expected RleSyntax at 2:3, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn invalid_placements_are_rejected() {
+        for contents in [
+            // no such player
+            "width = 64\nheight = 64\n[[patterns]]\nrle = \"o!\"\nleft = 0\ntop = 0\nowner = 0",
+            "width = 64\nheight = 64\n[[patterns]]\nrle = \"A!\"\nleft = 0\ntop = 0",
+            // no dying cells in Conway's Game of Life
+            "width = 64\nheight = 64\n[[patterns]]\nrle = \"qo!\"\nleft = 0\ntop = 0",
+        ] {
+            let scenario: Scenario = contents.parse().unwrap();
+            match BigBang::from_scenario(&scenario).birth() {
+                Err(ConwayError::InvalidData { .. }) => {}
+                Err(e) => panic!("expected InvalidData for {:?}, got {:?}", contents, e),
+                Ok(_) => panic!("expected InvalidData for {:?}", contents),
+            }
+        }
+    }
+}
+
+mod find_pattern_tests {
+    use crate::grids::{BitGrid, CharGrid, Transform};
+    use crate::rle::Pattern;
+    use crate::soup::Soup;
+    use crate::universe::*;
+    use std::collections::HashSet;
+
+    fn universe(width: usize, height: usize) -> Universe {
+        BigBang::new()
+            .width(width)
+            .height(height)
+            .add_player(PlayerBuilder::new(Region::new(0, 0, width / 2, height)))
+            .fog_radius(4)
+            .birth()
+            .unwrap()
+    }
+
+    fn grid(pattern: &str) -> BitGrid {
+        let pattern = Pattern(pattern.to_owned());
+        let (width, height) = pattern.calc_size().unwrap();
+        pattern.to_new_bit_grid(width, height).unwrap()
+    }
+
+    fn place(uni: &mut Universe, pattern: &BitGrid, left: usize, top: usize, transform: Transform) {
+        let bbox = pattern.bounding_box().unwrap();
+        pattern.each_set(|col, row| {
+            let (col, row) = transform.apply(col, row, bbox.width(), bbox.height());
+            uni.set_unchecked(
+                (left + col) % uni.width(),
+                (top + row) % uni.height(),
+                CellState::Alive(None),
+            );
+        });
+    }
+
+    /// Cell-by-cell version of `Universe::find_pattern`.
+    fn naive_find(uni: &Universe, pattern: &BitGrid, mode: MatchMode, visibility: Option<usize>) -> Vec<PatternMatch> {
+        let (mut alive, mut hidden) = (HashSet::new(), HashSet::new());
+        uni.each_non_dead_full(visibility, &mut |col, row, state| match state {
+            CellState::Alive(_) => {
+                alive.insert((col, row));
+            }
+            CellState::Fog => {
+                hidden.insert((col, row));
+            }
+            _ => {}
+        });
+        let margin = match mode {
+            MatchMode::Contains => 0,
+            MatchMode::Exact { margin } => margin,
+        };
+        let bbox = pattern.bounding_box().unwrap();
+        let mut shapes: Vec<(Transform, Vec<(usize, usize)>)> = vec![];
+        for &transform in Transform::ALL.iter() {
+            let mut shape = vec![];
+            pattern.each_set(|col, row| shape.push(transform.apply(col, row, bbox.width(), bbox.height())));
+            shape.sort();
+            if !shapes.iter().any(|(_, other)| *other == shape) {
+                shapes.push((transform, shape));
+            }
+        }
+        let mut matches = vec![];
+        for top in 0..uni.height() {
+            for left in 0..uni.width() {
+                for (transform, shape) in shapes.iter() {
+                    let (width, height) = transform.size(bbox.width(), bbox.height());
+                    let is_match = (0..height + 2 * margin).all(|r| {
+                        (0..width + 2 * margin).all(|c| {
+                            let cell = ((left + c) % uni.width(), (top + r) % uni.height());
+                            let want = c >= margin && r >= margin && shape.contains(&(c - margin, r - margin));
+                            let care = want || mode != MatchMode::Contains;
+                            !care || (!hidden.contains(&cell) && alive.contains(&cell) == want)
+                        })
+                    });
+                    if is_match {
+                        let region = Region::new(
+                            ((left + margin) % uni.width()) as isize,
+                            ((top + margin) % uni.height()) as isize,
+                            width,
+                            height,
+                        );
+                        matches.push(PatternMatch {
+                            region,
+                            transform: *transform,
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    #[test]
+    fn asymmetric_pattern_is_found_in_each_orientation() {
+        let pattern = grid("b2o$2o$bo!");
+        for (i, &transform) in Transform::ALL.iter().enumerate() {
+            let mut uni = universe(128, 64);
+            let (left, top) = (10 + 5 * i, 20);
+            place(&mut uni, &pattern, left, top, transform);
+            let matches = uni.find_pattern(&pattern, &Transform::ALL, MatchMode::Exact { margin: 1 }, None);
+            assert_eq!(
+                matches,
+                vec![PatternMatch {
+                    region: Region::new(left as isize, top as isize, 3, 3),
+                    transform,
+                }]
+            );
+            // only the transforms asked for are tried
+            let others: Vec<Transform> = Transform::ALL.iter().cloned().filter(|&t| t != transform).collect();
+            assert_eq!(uni.find_pattern(&pattern, &others, MatchMode::Contains, None), vec![]);
+        }
+    }
+
+    #[test]
+    fn matches_wrap_around_edges() {
+        let mut uni = universe(64, 16);
+        let beehive = grid("b2o$o2bo$b2o!");
+        place(&mut uni, &beehive, 62, 15, Transform::Identity);
+        let matches = uni.find_pattern(&beehive, &Transform::ALL, MatchMode::Exact { margin: 1 }, None);
+        assert_eq!(
+            matches,
+            vec![PatternMatch {
+                region:    Region::new(62, 15, 4, 3),
+                transform: Transform::Identity,
+            }]
+        );
+    }
+
+    #[test]
+    fn exact_mode_requires_dead_margin() {
+        let mut uni = universe(128, 64);
+        let block = grid("2o$2o!");
+        place(&mut uni, &block, 10, 10, Transform::Identity);
+        place(&mut uni, &block, 20, 10, Transform::Identity);
+        uni.set_unchecked(22, 12, CellState::Alive(None)); // touches the second block diagonally
+        uni.set_unchecked(30, 10, CellState::Wall); // walls aren't alive
+        place(&mut uni, &block, 31, 10, Transform::Identity);
+
+        let found = |mode| -> Vec<(isize, isize)> {
+            uni.find_pattern(&block, &[Transform::Identity], mode, None)
+                .iter()
+                .map(|m| (m.region.left(), m.region.top()))
+                .collect()
+        };
+        assert_eq!(found(MatchMode::Contains), vec![(10, 10), (20, 10), (31, 10)]);
+        assert_eq!(
+            found(MatchMode::Exact { margin: 0 }),
+            vec![(10, 10), (20, 10), (31, 10)]
+        );
+        assert_eq!(found(MatchMode::Exact { margin: 1 }), vec![(10, 10), (31, 10)]);
+        assert_eq!(found(MatchMode::Exact { margin: 30 }), vec![]);
+        // the margin must fit in the universe
+        assert_eq!(found(MatchMode::Exact { margin: 40 }), vec![]);
+    }
+
+    #[test]
+    fn symmetric_patterns_are_found_once() {
+        let mut uni = universe(128, 64);
+        let block = grid("2o$2o!");
+        place(&mut uni, &block, 3, 4, Transform::Identity);
+        let matches = uni.find_pattern(&block, &Transform::ALL, MatchMode::Contains, None);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].transform, Transform::Identity);
+        assert_eq!(
+            uni.find_pattern(&BitGrid::new(1, 4), &Transform::ALL, MatchMode::Contains, None),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn fog_hides_matches() {
+        let mut uni = universe(128, 64);
+        let block = grid("2o$2o!");
+        place(&mut uni, &block, 100, 30, Transform::Identity); // outside player 0's region
+        uni.next();
+        let matches = uni.find_pattern(&block, &[Transform::Identity], MatchMode::Contains, None);
+        assert_eq!(matches.len(), 1);
+        let matches = uni.find_pattern(&block, &[Transform::Identity], MatchMode::Contains, Some(0));
+        assert_eq!(matches, vec![]);
+    }
+
+    #[test]
+    fn find_pattern_matches_naive_search() {
+        let patterns = ["2o!", "bo$o!", "3o!", "2o$o!", "b2o$o2bo$b2o!"];
+        for seed in 0..6 {
+            let mut uni = universe(128, 24);
+            uni.random_soup(&Soup::new(uni.region(), 0.3, seed)).unwrap();
+            for _ in 0..seed {
+                uni.next();
+            }
+            for pattern in patterns.iter() {
+                let pattern = grid(pattern);
+                for &mode in [
+                    MatchMode::Contains,
+                    MatchMode::Exact { margin: 0 },
+                    MatchMode::Exact { margin: 1 },
+                ]
+                .iter()
+                {
+                    for &visibility in [None, Some(0)].iter() {
+                        assert_eq!(
+                            uni.find_pattern(&pattern, &Transform::ALL, mode, visibility),
+                            naive_find(&uni, &pattern, mode, visibility),
+                            "seed {}, {:?}, {:?}",
+                            seed,
+                            mode,
+                            visibility
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+mod clipboard_tests {
+    use crate::error::ConwayError;
+    use crate::grids::CharGrid;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+
+    fn non_dead_cells(uni: &Universe) -> Vec<(usize, usize, CellState)> {
+        let mut cells = vec![];
+        uni.each_non_dead_full(None, &mut |col, row, state| cells.push((col, row, state)));
+        cells
+    }
+
+    #[test]
+    fn to_pattern_file_wraps_and_keeps_owners() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(255, 10, CellState::Alive(Some(1)));
+        uni.set_unchecked(0, 10, CellState::Wall);
+        uni.set_unchecked(1, 11, CellState::Alive(None));
+
+        let file = uni.to_pattern_file(Region::new(-1, 10, 3, 2), None);
+        assert_eq!(file.to_string(), "x = 3, y = 2, rule = B3/S23\nBW$2bo!\n");
+    }
+
+    #[test]
+    fn extract_then_paste_round_trips() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.toggle(10, 11, 1).unwrap();
+        uni.toggle(11, 12, 1).unwrap();
+        uni.set_unchecked(12, 13, CellState::Alive(None));
+        uni.set_unchecked(13, 13, CellState::Wall);
+        uni.set_unchecked(120, 75, CellState::Alive(Some(0)));
+
+        let region = Region::new(10, 11, 3, 3);
+        let (cells, owners) = uni.extract(region, None);
+        assert_eq!(cells.to_pattern(None).0, "o$bo$2bo!");
+        assert_eq!(owners.len(), 2);
+        assert_eq!(owners[0].count_ones(), 0);
+        assert_eq!(owners[1].to_pattern(None).0, "o$bo!");
+
+        let mut other = generate_test_universe_with_default_params(UniType::Server);
+        other.copy_from_bit_grid(&owners[1], region, Some(1)).unwrap();
+        other
+            .copy_from_bit_grid(&cells, Region::new(30, 11, 3, 3), None)
+            .unwrap();
+        assert_eq!(
+            non_dead_cells(&other),
+            vec![
+                (10, 11, CellState::Alive(Some(1))),
+                (30, 11, CellState::Alive(None)),
+                (11, 12, CellState::Alive(Some(1))),
+                (31, 12, CellState::Alive(None)),
+                (32, 13, CellState::Alive(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_wraps_around_edges() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(255, 127, CellState::Alive(None));
+        uni.set_unchecked(0, 0, CellState::Alive(Some(1)));
+        uni.set_unchecked(1, 127, CellState::Alive(None));
+        let (cells, owners) = uni.extract(Region::new(-1, -1, 3, 2), None);
+        assert_eq!(cells.to_pattern(None).0, "obo$bo!");
+        assert_eq!(owners[1].to_pattern(None).0, "$bo!");
+        // the same region, with its top-left corner inside the universe
+        assert_eq!(uni.extract(Region::new(255, 127, 3, 2), None), (cells, owners));
+        // larger than the universe
+        let (cells, _) = uni.extract(Region::new(0, 0, 1000, 1000), None);
+        assert_eq!(
+            (cells.width_in_words(), cells.height(), cells.count_ones()),
+            (4, 128, 3)
+        );
+    }
+
+    #[test]
+    fn extract_hides_fog() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(200, 100, CellState::Alive(None)); // far from both players
+        uni.next();
+        uni.set_unchecked(200, 100, CellState::Alive(None));
+        let region = Region::new(190, 90, 20, 20);
+        assert_eq!(uni.extract(region, None).0.count_ones(), 1);
+        assert_eq!(uni.extract(region, Some(0)).0.count_ones(), 0);
+    }
+
+    #[test]
+    fn player_cut_removes_only_writable_cells_not_owned_by_others() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.toggle(78, 10, 1).unwrap();
+        uni.set_unchecked(79, 10, CellState::Alive(Some(0))); // inside player 1's region
+        uni.set_unchecked(78, 11, CellState::Alive(None));
+        uni.set_unchecked(79, 11, CellState::Wall);
+        uni.set_unchecked(80, 10, CellState::Alive(Some(1))); // outside player 1's region
+        let (cells, owners) = uni.cut(Region::new(78, 10, 3, 2), Some(1)).unwrap();
+        assert_eq!(cells.to_pattern(None).0, "o$o!");
+        assert_eq!(owners[1].to_pattern(None).0, "o!");
+        assert_eq!(owners[0].count_ones(), 0);
+        assert_eq!(
+            non_dead_cells(&uni),
+            vec![
+                (79, 10, CellState::Alive(Some(0))),
+                (80, 10, CellState::Alive(Some(1))),
+                (79, 11, CellState::Wall),
+            ]
+        );
+        assert_eq!(uni.changed_since(1).unwrap()[10][1], u64::MAX);
+
+        match uni.cut(Region::new(78, 10, 3, 2), Some(2)) {
+            Err(ConwayError::InvalidData { .. }) => {}
+            result => panic!("expected InvalidData, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn unchecked_cut_removes_everything_but_walls() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(5, 5, CellState::Alive(Some(0)));
+        uni.set_unchecked(6, 5, CellState::Alive(Some(1)));
+        uni.set_unchecked(7, 5, CellState::Alive(None));
+        uni.set_unchecked(5, 6, CellState::Wall);
+        let (cells, owners) = uni.cut(Region::new(5, 5, 3, 2), None).unwrap();
+        assert_eq!(cells.to_pattern(None).0, "3o!");
+        assert_eq!(owners[0].to_pattern(None).0, "o!");
+        assert_eq!(owners[1].to_pattern(None).0, "bo!");
+        assert_eq!(non_dead_cells(&uni), vec![(5, 6, CellState::Wall)]);
+    }
+
+    #[test]
+    fn unchecked_move_keeps_owners_and_wraps() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(10, 10, CellState::Alive(Some(1)));
+        uni.set_unchecked(11, 10, CellState::Alive(None));
+        uni.set_unchecked(11, 11, CellState::Wall);
+        uni.set_unchecked(0, 127, CellState::Wall); // where the unowned cell lands below
+                                                    // overlapping move
+        uni.move_region(Region::new(10, 10, 2, 2), 11, 10, None).unwrap();
+        assert_eq!(
+            non_dead_cells(&uni),
+            vec![
+                (11, 10, CellState::Alive(Some(1))),
+                (12, 10, CellState::Alive(None)),
+                (11, 11, CellState::Wall),
+                (0, 127, CellState::Wall),
+            ]
+        );
+        // wrapping move
+        uni.move_region(Region::new(11, 10, 2, 1), -1, 127, None).unwrap();
+        assert_eq!(
+            non_dead_cells(&uni),
+            vec![
+                (11, 11, CellState::Wall),
+                (0, 127, CellState::Wall),
+                (255, 127, CellState::Alive(Some(1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn player_move_stays_within_writable_region() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.toggle(10, 10, 1).unwrap();
+        uni.toggle(11, 10, 1).unwrap();
+        uni.set_unchecked(12, 10, CellState::Alive(None)); // unowned, so moved too
+        uni.set_unchecked(13, 10, CellState::Alive(Some(0))); // player 0's, so not moved
+        let before = non_dead_cells(&uni);
+
+        for &(dst_left, dst_top) in [(79, 10), (20, 20)].iter() {
+            let result = uni.move_region(Region::new(10, 10, 4, 1), dst_left, dst_top, Some(1));
+            if (dst_left, dst_top) == (79, 10) {
+                match result {
+                    Err(ConwayError::AccessDenied { .. }) => {}
+                    result => panic!("expected AccessDenied, got {:?}", result),
+                }
+                assert_eq!(non_dead_cells(&uni), before);
+            } else {
+                result.unwrap();
+            }
+        }
+        assert_eq!(
+            non_dead_cells(&uni),
+            vec![
+                (13, 10, CellState::Alive(Some(0))),
+                (20, 20, CellState::Alive(Some(1))),
+                (21, 20, CellState::Alive(Some(1))),
+                (22, 20, CellState::Alive(None)),
+            ]
+        );
+
+        // can't land on someone else's cell
+        uni.set_unchecked(31, 20, CellState::Alive(Some(0)));
+        match uni.move_region(Region::new(20, 20, 2, 1), 30, 20, Some(1)) {
+            Err(ConwayError::AccessDenied { .. }) => {}
+            result => panic!("expected AccessDenied, got {:?}", result),
+        }
+    }
+}
+
+mod resize_tests {
+    use crate::error::ConwayError;
+    use crate::grids::CharGrid;
+    use crate::reference::*;
+    use crate::rule::Rule;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn non_dead_cells(uni: &Universe) -> Vec<(usize, usize, CellState)> {
+        let mut cells = vec![];
+        uni.each_non_dead_full(None, &mut |col, row, state| cells.push((col, row, state)));
+        cells
+    }
+
+    #[test]
+    fn resize_keeps_anchor_in_place() {
+        for &(anchor, col, row) in [
+            (Anchor::TopLeft, 10, 20),
+            (Anchor::Top, 74, 20),
+            (Anchor::TopRight, 138, 20),
+            (Anchor::Left, 10, 24),
+            (Anchor::Center, 74, 24),
+            (Anchor::Right, 138, 24),
+            (Anchor::BottomLeft, 10, 28),
+            (Anchor::Bottom, 74, 28),
+            (Anchor::BottomRight, 138, 28),
+        ]
+        .iter()
+        {
+            let mut uni = generate_test_universe_with_default_params(UniType::Server);
+            uni.set_unchecked(10, 20, CellState::Wall);
+            uni.resize(384, 136, anchor).unwrap();
+            assert_eq!((uni.width(), uni.height()), (384, 136));
+            assert_eq!(non_dead_cells(&uni), vec![(col, row, CellState::Wall)], "{:?}", anchor);
+        }
+    }
+
+    #[test]
+    fn shrinking_discards_cells() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(10, 20, CellState::Wall);
+        uni.set_unchecked(150, 90, CellState::Wall);
+        uni.resize(128, 64, Anchor::Center).unwrap();
+        assert_eq!(non_dead_cells(&uni), vec![(86, 58, CellState::Wall)]);
+    }
+
+    #[test]
+    fn crop_preserves_history_and_state() {
+        let rule: Rule = "B2/S/3".parse().unwrap(); // Brian's Brain, for dying cells
+        let mut uni = BigBang::new()
+            .width(256)
+            .height(128)
+            .rule(rule)
+            .add_player(PlayerBuilder::new(Region::new(60, 10, 40, 40)))
+            .add_player(PlayerBuilder::new(Region::new(180, 60, 20, 20)))
+            .birth()
+            .unwrap();
+        uni.toggle(70, 20, 0).unwrap();
+        uni.toggle(71, 20, 0).unwrap();
+        uni.set_unchecked(80, 30, CellState::Wall);
+        uni.next();
+        let gen = uni.next();
+        let before = non_dead_cells(&uni);
+        let diff_before = uni.diff(1, gen, Some(0)).unwrap();
+
+        uni.crop(Region::new(64, 8, 128, 64)).unwrap();
+        assert_eq!(uni.latest_gen(), gen);
+        let moved: Vec<(usize, usize, CellState)> = before
+            .iter()
+            .map(|&(col, row, state)| (col - 64, row - 8, state))
+            .collect();
+        assert_eq!(non_dead_cells(&uni), moved);
+        assert!(moved
+            .iter()
+            .any(|&(_, _, state)| matches!(state, CellState::Dying(Some(0), _))));
+        // writable regions move with the cells, and player 1's is clipped to the universe
+        assert!(uni.writable(0, 2, 0).unwrap());
+        assert!(!uni.writable(36, 2, 0).unwrap());
+        assert!(uni.writable(116, 52, 1).unwrap());
+        assert!(uni.writable(127, 63, 1).unwrap());
+
+        // the older generations were cropped too
+        let diff_after = uni.diff(1, gen, Some(0)).unwrap();
+        assert_eq!((diff_after.gen0, diff_after.gen1), (diff_before.gen0, diff_before.gen1));
+        let changed = uni.changed_since(gen).unwrap();
+        assert!(changed.0.iter().all(|row| row.iter().all(|&word| word == u64::MAX)));
+        uni.next();
+    }
+
+    #[test]
+    fn added_space_is_fogged_outside_writable_regions() {
+        let mut uni = BigBang::new()
+            .width(64)
+            .height(16)
+            .add_player(PlayerBuilder::new(Region::new(0, 0, 64, 16)))
+            .birth()
+            .unwrap();
+        uni.resize(128, 16, Anchor::Left).unwrap();
+        let mut fog = vec![];
+        uni.each_non_dead_full(Some(0), &mut |col, row, state| {
+            assert_eq!(state, CellState::Fog);
+            fog.push((col, row));
+        });
+        assert_eq!(fog.len(), 64 * 16);
+        assert!(fog.iter().all(|&(col, _)| col >= 64));
+        assert!(!uni.writable(64, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn invalid_crops_change_nothing() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        uni.set_unchecked(10, 20, CellState::Wall);
+        let err = |result: Result<(), ConwayError>| match result {
+            Err(ConwayError::InvalidData { .. }) => {}
+            result => panic!("expected InvalidData, got {:?}", result),
+        };
+        err(uni.crop(Region::new(0, 0, 100, 100)));
+        err(uni.resize(0, 100, Anchor::Center));
+        // player 0's writable region is (100, 70, 34, 16)
+        err(uni.crop(Region::new(0, 0, 64, 64)));
+        uni.set_rule("R32,C0,M0,S1..1,B1..1,NM".parse().unwrap()).unwrap();
+        err(uni.resize(256, 64, Anchor::Bottom));
+        assert_eq!((uni.width(), uni.height()), (256, 128));
+        assert_eq!(non_dead_cells(&uni), vec![(10, 20, CellState::Wall)]);
+
+        // a fog circle of radius 20 is 39 cells across
+        let mut uni = BigBang::new().width(128).height(64).fog_radius(20).birth().unwrap();
+        err(uni.crop(Region::new(0, 0, 64, 16)));
+        err(uni.resize(128, 38, Anchor::Center));
+        assert_eq!((uni.width(), uni.height()), (128, 64));
+        uni.resize(64, 39, Anchor::Center).unwrap();
+        assert_eq!((uni.width(), uni.height()), (64, 39));
+    }
+
+    #[test]
+    fn resized_universes_match_reference() {
+        let mut skipped = 0;
+        for seed in 0..30 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut uni = random_universe(&mut rng);
+            uni.next();
+            let (width, height) = (uni.width(), uni.height());
+            let new_width = 64 * rng.gen_range(width / 64..=3);
+            let region = Region::new(
+                rng.gen_range(-16..16),
+                rng.gen_range(-8..8),
+                new_width,
+                height + rng.gen_range(0..8),
+            );
+            if uni.crop(region).is_err() {
+                skipped += 1;
+                continue;
+            }
+            assert_eq!((uni.width(), uni.height()), (new_width, region.height()), "{}", width);
+            if let Err(divergence) = run_differential(&mut uni, 8) {
+                panic!("seed {}, {:?}: {}", seed, region, divergence);
+            }
+        }
+        assert!(skipped < 10, "{} universes had writable regions cropped out", skipped);
+    }
+}
+
+mod activity_tests {
+    use crate::error::ConwayError;
+    use crate::universe::test_helpers::*;
+    use crate::universe::*;
+
+    fn blinker_universe(window: usize) -> Universe {
+        let mut uni = BigBang::new()
+            .width(64)
+            .height(16)
+            .add_player(PlayerBuilder::new(Region::new(0, 0, 32, 16)))
+            .activity_window(window)
+            .birth()
+            .unwrap();
+        for col in 4..7 {
+            uni.toggle(col, 5, 0).unwrap(); // horizontal blinker centered on (5, 5)
+        }
+        uni.set_unchecked(20, 5, CellState::Alive(Some(0))); // block
+        uni.set_unchecked(21, 5, CellState::Alive(Some(0)));
+        uni.set_unchecked(20, 6, CellState::Alive(Some(0)));
+        uni.set_unchecked(21, 6, CellState::Alive(Some(0)));
+        uni
+    }
+
+    #[test]
+    fn tracking_is_off_by_default() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        assert_eq!(uni.ages(uni.region(), None), None);
+        assert_eq!(uni.activity(uni.region(), None), None);
+        match uni.set_activity_window(Some(0)) {
+            Err(ConwayError::InvalidData { .. }) => {}
+            result => panic!("expected InvalidData, got {:?}", result),
+        }
+        uni.set_activity_window(Some(3)).unwrap();
+        assert!(uni.ages(uni.region(), None).is_some());
+        uni.set_activity_window(None).unwrap();
+        assert_eq!(uni.ages(uni.region(), None), None);
+    }
+
+    #[test]
+    fn ages_count_generations_alive() {
+        let mut uni = blinker_universe(4);
+        let region = Region::new(4, 4, 3, 3);
+        // placed cells are one generation old
+        let ages = uni.ages(region, None).unwrap();
+        assert_eq!(
+            (ages.get(0, 1), ages.get(1, 1), ages.get(2, 1), ages.get(1, 0)),
+            (1, 1, 1, 0)
+        );
+        for gen in 2..=5 {
+            uni.next();
+            let ages = uni.ages(region, None).unwrap();
+            assert_eq!(ages.get(1, 1), gen as u32, "center of the blinker");
+            let (end0, end1) = if gen % 2 == 0 {
+                ((1, 0), (1, 2))
+            } else {
+                ((0, 1), (2, 1))
+            };
+            assert_eq!((ages.get(end0.0, end0.1), ages.get(end1.0, end1.1)), (1, 1));
+            assert_eq!(uni.ages(Region::new(20, 5, 2, 2), None).unwrap().max(), gen as u32);
+        }
+    }
+
+    #[test]
+    fn activity_counts_births_and_deaths_within_window() {
+        let mut uni = blinker_universe(3);
+        let region = Region::new(4, 4, 3, 3);
+        assert_eq!(uni.activity(region, None).unwrap().max(), 0); // edits aren't activity
+        uni.next();
+        let activity = uni.activity(region, None).unwrap();
+        assert_eq!((activity.get(0, 1), activity.get(1, 0), activity.get(1, 1)), (1, 1, 0));
+        for _ in 0..10 {
+            uni.next();
+        }
+        // every end cell changes every generation, but only the last 3 count
+        let activity = uni.activity(region, None).unwrap();
+        assert_eq!(
+            (
+                activity.get(0, 1),
+                activity.get(1, 0),
+                activity.get(1, 1),
+                activity.get(0, 0)
+            ),
+            (3, 3, 0, 0)
+        );
+        assert_eq!(uni.activity(Region::new(20, 5, 2, 2), None).unwrap().max(), 0);
+    }
+
+    #[test]
+    fn counts_export_as_grayscale() {
+        let mut uni = blinker_universe(4);
+        uni.next();
+        uni.next();
+        let ages = uni.ages(Region::new(4, 4, 3, 3), None).unwrap();
+        assert_eq!((ages.width(), ages.height()), (3, 3));
+        assert_eq!(ages.to_grayscale(None), vec![0, 0, 0, 85, 255, 85, 0, 0, 0]);
+        assert_eq!(ages.to_grayscale(Some(2)), vec![0, 0, 0, 127, 255, 127, 0, 0, 0]);
+        let pgm = ages.to_pgm(Some(1));
+        assert_eq!(&pgm[..11], b"P5\n3 3\n255\n");
+        assert_eq!(&pgm[11..], &[0, 0, 0, 255, 255, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn fog_hides_counts() {
+        let mut uni = blinker_universe(4);
+        uni.set_unchecked(50, 10, CellState::Alive(None));
+        uni.next();
+        let region = Region::new(49, 9, 3, 3);
+        assert_eq!(uni.ages(region, None).unwrap().max(), 0); // it died
+        uni.set_unchecked(50, 10, CellState::Alive(None));
+        assert_eq!(uni.ages(region, None).unwrap().get(1, 1), 1);
+        assert_eq!(uni.ages(region, Some(0)).unwrap().max(), 0);
+    }
+
+    #[test]
+    fn crop_keeps_counts() {
+        let mut uni = blinker_universe(4);
+        uni.next();
+        uni.next();
+        let before = uni.ages(Region::new(0, 0, 32, 16), None).unwrap();
+        uni.resize(128, 32, Anchor::BottomRight).unwrap();
+        assert_eq!(uni.ages(Region::new(64, 16, 32, 16), None).unwrap(), before);
+        uni.next();
+        assert_eq!(uni.ages(Region::new(64 + 5, 16 + 5, 1, 1), None).unwrap().get(0, 0), 4);
+    }
+
+    #[test]
+    fn client_ages_follow_server() {
+        let mut server = blinker_universe(4);
+        let mut client = BigBang::new()
+            .width(64)
+            .height(16)
+            .server_mode(false)
+            .add_player(PlayerBuilder::new(Region::new(0, 0, 32, 16)))
+            .activity_window(4)
+            .birth()
+            .unwrap();
+        let mut gen0 = 0;
+        for _ in 0..5 {
+            let gen1 = server.next();
+            let diff = server.diff(gen0, gen1, Some(0)).unwrap();
+            client.apply(&diff, Some(0)).unwrap();
+            gen0 = gen1;
+        }
+        let region = Region::new(0, 0, 32, 16);
+        // the first diff counts as the first generation the cells were seen
+        let server_ages = server.ages(region, Some(0)).unwrap();
+        let client_ages = client.ages(region, Some(0)).unwrap();
+        assert_eq!(client_ages.get(20, 5) + 1, server_ages.get(20, 5));
+        assert_eq!(client_ages.get(5, 5) + 1, server_ages.get(5, 5));
+        assert_eq!(client.activity(region, Some(0)), server.activity(region, Some(0)));
+    }
+}
+
+#[cfg(test)]
+mod preview_tests {
+    use crate::error::ConwayError;
+    use crate::grids::{BitGrid, CharGrid};
+    use crate::universe::*;
+
+    fn two_player_universe() -> Universe {
+        BigBang::new()
+            .width(128)
+            .height(32)
+            .fog_radius(4)
+            .add_player(PlayerBuilder::new(Region::new(0, 0, 32, 16)))
+            .add_player(PlayerBuilder::new(Region::new(36, 16, 32, 16)))
+            .birth()
+            .unwrap()
+    }
+
+    /// A glider belonging to player 1, heading up and to the left into player 0's region.
+    fn add_hidden_glider(uni: &mut Universe) {
+        for &(col, row) in &[(40, 20), (41, 20), (42, 20), (40, 21), (41, 22)] {
+            uni.toggle(col, row, 1).unwrap();
+        }
+    }
+
+    fn live_cells(uni: &Universe, region: Region, visibility: Option<usize>) -> Vec<(usize, usize)> {
+        uni.cells_in(region, visibility)
+            .filter(|&(_, _, state)| matches!(state, CellState::Alive(_)))
+            .map(|(col, row, _)| (col, row))
+            .collect()
+    }
+
+    #[test]
+    fn preview_matches_making_the_edits() {
+        let mut uni = two_player_universe();
+        add_hidden_glider(&mut uni);
+        uni.next();
+        let before = uni.to_pattern(None);
+        let generation = uni.latest_gen();
+
+        let mut block = BitGrid::new(1, 2);
+        block[0][0] = 0xC000000000000000;
+        block[1][0] = 0xC000000000000000;
+        let edits = vec![
+            Edit::Toggle { col: 10, row: 5 },
+            Edit::Toggle { col: 11, row: 5 },
+            Edit::Toggle { col: 12, row: 5 },
+            Edit::Set {
+                col:   20,
+                row:   8,
+                state: CellState::Alive(Some(0)),
+            },
+            Edit::Paste {
+                src:    block,
+                region: Region::new(25, 3, 2, 2),
+            },
+        ];
+        let preview = uni.preview(&edits, 7, Some(0)).unwrap();
+        assert_eq!(preview.latest_gen(), generation + 7);
+
+        // the original is untouched
+        assert_eq!(uni.latest_gen(), generation);
+        assert_eq!(uni.to_pattern(None), before);
+
+        let mut expected = two_player_universe();
+        add_hidden_glider(&mut expected);
+        expected.next();
+        for &(col, row) in &[(10, 5), (11, 5), (12, 5)] {
+            expected.toggle(col, row, 0).unwrap();
+        }
+        expected.set(20, 8, CellState::Alive(Some(0)), 0);
+        for &(col, row) in &[(25, 3), (26, 3), (25, 4), (26, 4)] {
+            expected.toggle(col, row, 0).unwrap();
+        }
+        for _ in 0..7 {
+            expected.next();
+        }
+        assert_eq!(preview.to_pattern(Some(0)), expected.to_pattern(Some(0)));
+
+        // without visibility, nothing is hidden, but toggled cells are unowned
+        let preview = uni.preview(&edits, 7, None).unwrap();
+        assert_eq!(
+            live_cells(&preview, preview.region(), None),
+            live_cells(&expected, expected.region(), None)
+        );
+    }
+
+    #[test]
+    fn preview_does_not_reveal_fogged_cells() {
+        let mut uni = two_player_universe();
+        add_hidden_glider(&mut uni);
+        let player0_region = Region::new(0, 0, 32, 16);
+        assert_eq!(uni.cells_in(Region::new(40, 20, 3, 3), Some(0)).count(), 9); // all fog
+
+        // the glider reaches player 0's region, but player 0 can't know that yet
+        let exact = uni.preview(&[], 60, None).unwrap();
+        assert!(!live_cells(&exact, player0_region, None).is_empty());
+        let preview = uni.preview(&[], 60, Some(0)).unwrap();
+        assert_eq!(live_cells(&preview, preview.region(), Some(0)), vec![]);
+        assert_eq!(live_cells(&preview, preview.region(), None), vec![]);
+
+        // player 1 can see their own glider
+        let preview = uni.preview(&[], 60, Some(1)).unwrap();
+        assert_eq!(
+            live_cells(&preview, player0_region, Some(1)),
+            live_cells(&exact, player0_region, Some(1))
+        );
+    }
+
+    #[test]
+    fn clear_region_keeps_cells_of_other_players() {
+        let mut uni = two_player_universe();
+        uni.set_unchecked(5, 5, CellState::Alive(Some(1)));
+        uni.set_unchecked(10, 5, CellState::Alive(None));
+        uni.set_unchecked(15, 5, CellState::Alive(Some(0)));
+        uni.set_unchecked(40, 5, CellState::Alive(None)); // outside player 0's writable region
+        let region = Region::new(0, 0, 50, 10);
+
+        let preview = uni.preview(&[Edit::Clear { region }], 0, Some(0)).unwrap();
+        assert_eq!(live_cells(&preview, region, None), vec![(5, 5)]);
+        uni.clear_region(region, Some(0)).unwrap();
+        assert_eq!(live_cells(&uni, region, None), vec![(5, 5), (40, 5)]);
+
+        match uni.clear_region(region, Some(2)) {
+            Err(ConwayError::InvalidData { .. }) => {}
+            result => panic!("expected InvalidData, got {:?}", result),
+        }
+
+        uni.clear_region(region, None).unwrap();
+        assert_eq!(live_cells(&uni, region, None), vec![]);
+    }
+
+    #[test]
+    fn preview_edits_are_checked() {
+        let uni = two_player_universe();
+        match uni.preview(&[Edit::Toggle { col: 50, row: 20 }], 1, Some(0)) {
+            Err(ConwayError::AccessDenied { .. }) => {}
+            result => panic!("expected AccessDenied, got {:?}", result.map(|_| ())),
+        }
+        // nothing is checked without visibility
+        let preview = uni.preview(&[Edit::Toggle { col: 50, row: 20 }], 0, None).unwrap();
+        assert_eq!(live_cells(&preview, preview.region(), None), vec![(50, 20)]);
     }
 }
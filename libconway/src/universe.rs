@@ -17,9 +17,13 @@
 
 use std::{char, cmp, fmt};
 
+use crate::activity::{ActivityTracker, CellCounts};
 use crate::error::{ConwayError, ConwayResult};
-use crate::grids::{BitGrid, BitOperation, CharGrid};
-use crate::rle::{Pattern, NO_OP_CHAR};
+use crate::grids::{BitGrid, BitOperation, CharGrid, Transform};
+use crate::rle::{HeaderLine, Pattern, PatternFile, NO_OP_CHAR};
+use crate::rule::{self, Rule, MAX_DECAY_PLANES, MAX_STATES};
+use crate::scenario::{PlacedPattern, Scenario};
+use crate::soup::Soup;
 
 /// Builder paradigm to create `Universe` structs with default values.
 pub struct BigBang {
@@ -29,12 +33,25 @@ pub struct BigBang {
     history:         usize,
     num_players:     usize,
     player_writable: Vec<Region>,
+    player_budgets:  Vec<Option<EnergyBudget>>,
     fog_radius:      usize,
+    rule:            Rule,
+    rule_zones:      Vec<(ZoneArea, Rule)>,
+    soups:           Vec<Soup>,
+    patterns:        Vec<PlacedPattern>,
+    activity_window: Option<usize>,
+}
+
+/// Where a rule zone added with `BigBang` applies.
+enum ZoneArea {
+    Region(Region),
+    Mask(BitGrid),
 }
 
 /// Player builder
 pub struct PlayerBuilder {
     writable_region: Region,
+    energy_budget:   Option<EnergyBudget>,
 }
 
 impl PlayerBuilder {
@@ -42,8 +59,30 @@ impl PlayerBuilder {
     pub fn new(region: Region) -> PlayerBuilder {
         PlayerBuilder {
             writable_region: region,
+            energy_budget:   None,
         }
     }
+
+    /// Limits how many cells the player can place. By default, there is no limit.
+    pub fn energy_budget(mut self, budget: EnergyBudget) -> PlayerBuilder {
+        self.energy_budget = Some(budget);
+        self
+    }
+}
+
+/// Energy economy limiting how many cells a player can place with `Universe::toggle`,
+/// `Universe::set`, and `Universe::copy_from_bit_grid`.
+///
+/// Every cell placed costs `cell_cost` energy. After each generation, the player regains
+/// `regen_per_generation` energy plus `regen_per_live_cell` energy for each live cell they own,
+/// up to `max` energy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnergyBudget {
+    pub initial:              u32,
+    pub max:                  u32,
+    pub cell_cost:            u32,
+    pub regen_per_generation: u32,
+    pub regen_per_live_cell:  u32,
 }
 
 /// This is a builder for `Universe` structs.
@@ -68,10 +107,35 @@ impl BigBang {
             history:         16,
             num_players:     0,
             player_writable: vec![],
+            player_budgets:  vec![],
             fog_radius:      6,
+            rule:            Rule::conway(),
+            rule_zones:      vec![],
+            soups:           vec![],
+            patterns:        vec![],
+            activity_window: None,
         }
     }
 
+    /// Creates a builder for the map described by `scenario`. Settings that the scenario leaves
+    /// out keep their defaults, and any setting can still be overridden.
+    pub fn from_scenario(scenario: &Scenario) -> BigBang {
+        let mut bigbang = BigBang::new().width(scenario.width).height(scenario.height);
+        if let Some(fog_radius) = scenario.fog_radius {
+            bigbang = bigbang.fog_radius(fog_radius);
+        }
+        if let Some(ref rule) = scenario.rule {
+            bigbang = bigbang.rule(rule.clone());
+        }
+        for region in scenario.player_writable.iter() {
+            bigbang = bigbang.add_player(PlayerBuilder::new(*region));
+        }
+        for pattern in scenario.patterns.iter() {
+            bigbang = bigbang.place_pattern(pattern.clone());
+        }
+        bigbang
+    }
+
     /// Update the total number of columns for this Universe
     pub fn width(mut self, new_width: usize) -> BigBang {
         self.width = new_width;
@@ -108,6 +172,7 @@ impl BigBang {
     pub fn add_player(mut self, new_player: PlayerBuilder) -> BigBang {
         self.num_players += 1;
         self.player_writable.push(new_player.writable_region);
+        self.player_budgets.push(new_player.energy_budget);
         assert_eq!(self.num_players, self.player_writable.len()); // These should always match up!
         self
     }
@@ -134,16 +199,65 @@ impl BigBang {
         self
     }
 
+    /// Sets the rule used to compute generations. Defaults to Conway's Game of Life.
+    pub fn rule(mut self, rule: Rule) -> BigBang {
+        self.rule = rule;
+        self
+    }
+
+    /// Adds a zone where `rule` is used instead of the universe's rule. Zones added later take
+    /// precedence where zones overlap. See `Universe::add_rule_zone`.
+    pub fn rule_zone(mut self, region: Region, rule: Rule) -> BigBang {
+        self.rule_zones.push((ZoneArea::Region(region), rule));
+        self
+    }
+
+    /// Like `rule_zone`, but the zone is every cell whose bit is set in `mask`, which must be as
+    /// large as the universe.
+    pub fn rule_zone_mask(mut self, mask: BitGrid, rule: Rule) -> BigBang {
+        self.rule_zones.push((ZoneArea::Mask(mask), rule));
+        self
+    }
+
+    /// Places a pattern once the universe is created. Patterns are placed in order, before any
+    /// soups, and don't cost any energy. See `PlacedPattern::place`.
+    pub fn place_pattern(mut self, pattern: PlacedPattern) -> BigBang {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Fills a region with a random soup once the universe is created. Soups are added in order,
+    /// and don't cost any energy. See `Universe::random_soup`.
+    pub fn random_soup(mut self, soup: Soup) -> BigBang {
+        self.soups.push(soup);
+        self
+    }
+
+    /// Tracks the age of each live cell, and how often each cell was born or died within the last
+    /// `window` generations. See `Universe::set_activity_window`.
+    pub fn activity_window(mut self, window: usize) -> BigBang {
+        self.activity_window = Some(window);
+        self
+    }
+
     /// "Gives life to the universe and the first moment of time."
     /// Creates a Universe which can then CGoL process generations.
     ///
     /// # Errors
     ///
     /// - if `width` or `height` are not positive, or if `width` is not a multiple of 64.
-    /// - if `fog_radius` is not positive.
+    /// - if `fog_radius` is not positive, or if the fog circle (`2 * fog_radius - 1` cells across)
+    ///   does not fit within `width` or `height`.
     /// - if `history` is not positive.
+    /// - if the neighborhood of `rule`, or of the rule of any rule zone, does not fit within
+    ///   `width` or `height`.
+    /// - if a rule zone region is not entirely within the universe, or a rule zone mask is not the
+    ///   same size as the universe.
+    /// - if a placed pattern is invalid (see `PlacedPattern::place`).
+    /// - if a soup is invalid (see `Universe::random_soup`).
+    /// - if the activity window is not positive.
     pub fn birth(&self) -> ConwayResult<Universe> {
-        let universe = Universe::new(
+        let mut universe = Universe::new(
             self.width,
             self.height,
             self.is_server, // if false, allow receiving generation 1 as GenStateDiff
@@ -151,8 +265,38 @@ impl BigBang {
             self.num_players,             // number of players in the game (player numbers are 0-based)
             self.player_writable.clone(), // writable region (indexed by player_id)
             self.fog_radius,              // fog radius provides visiblity outside of writable regions
-        );
-        universe
+        )?;
+        universe.set_rule(self.rule.clone())?;
+        // before setting budgets, so that patterns and soups are free
+        for pattern in self.patterns.iter() {
+            pattern.place(&mut universe)?;
+        }
+        for soup in self.soups.iter() {
+            universe.random_soup(soup)?;
+        }
+        for (player_id, opt_budget) in self.player_budgets.iter().enumerate() {
+            universe.set_energy_budget(player_id, *opt_budget)?;
+        }
+        for (area, rule) in self.rule_zones.iter() {
+            let mask = match area {
+                ZoneArea::Region(region) => {
+                    if region.intersection(universe.region()) != Some(*region) {
+                        return Err(ConwayError::InvalidData {
+                            reason: format!("Rule zone {:?} is not within the universe", region),
+                        });
+                    }
+                    let mut mask = BitGrid::new(universe.width_in_words, universe.height);
+                    mask.modify_region(*region, BitOperation::Set);
+                    mask
+                }
+                ZoneArea::Mask(mask) => mask.clone(),
+            };
+            universe.add_rule_zone(mask, rule.clone())?;
+        }
+        if self.activity_window.is_some() {
+            universe.set_activity_window(self.activity_window)?;
+        }
+        Ok(universe)
     }
 }
 
@@ -168,6 +312,12 @@ pub struct Universe {
     player_writable: Vec<Region>,   // writable region (indexed by player_id)
     fog_radius:      usize,
     fog_circle:      BitGrid,
+    rule:            Rule,
+    rule_zones:      Vec<(BitGrid, Rule)>, // (mask, rule) overriding `rule`; later zones take precedence
+    energy_budgets:  Vec<Option<EnergyBudget>>, // indexed by player_id; None means unlimited
+    energy:          Vec<u32>,             // current energy (indexed by player_id)
+    changes:         Vec<GenChanges>,      // changed words for each GenState (indexed like gen_states)
+    activity:        Option<ActivityTracker>, // cell ages and activity counts, if tracked
 }
 
 // Describes the state of the universe for a particular generation
@@ -180,6 +330,16 @@ pub struct GenState {
     wall_cells:    BitGrid,       // 1 = is a wall cell (should this just be fixed for the universe?)
     known:         BitGrid,       // 1 = cell is known (always 1 if this is server)
     player_states: Vec<PlayerGenState>, // player-specific info (indexed by player_id)
+    decay:         Vec<BitGrid>,  // bit-sliced dying stage, least significant first (Generations rules only)
+}
+
+// Describes which words of the universe changed in a generation, for `Universe::changed_since`.
+// Each word of these grids is either all ones (changed) or zero.
+#[derive(Debug, Clone)]
+struct GenChanges {
+    since:   usize,   // generation that `stepped` is relative to (0 if unknown)
+    stepped: BitGrid, // words that differ from generation `since`
+    edited:  BitGrid, // words changed by edits while this was the latest generation
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -191,8 +351,9 @@ pub struct GenStateDiff {
 
 #[derive(Debug, Clone, PartialEq)]
 struct PlayerGenState {
-    cells: BitGrid, // cells belonging to this player (if 1 here, must be 1 in GenState cells)
-    fog:   BitGrid, // cells that are currently invisible to the player
+    cells: BitGrid,         // cells belonging to this player (if 1 here, must be 1 in GenState cells)
+    fog:   BitGrid,         // cells that are currently invisible to the player
+    dying: Option<BitGrid>, // dying cells belonging to this player (Generations rules only)
 }
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
@@ -201,8 +362,17 @@ pub enum CellState {
     Alive(Option<usize>), // Some(player_number) or alive but not belonging to any player
     Wall,
     Fog,
+    Dying(Option<usize>, usize), // owner as in Alive, and the 1-based dying stage (Generations rules only)
 }
 
+/// Dying cells are represented internally by chars in a private use area, so that `CharGrid`
+/// implementations can treat them like any other cell. In RLE patterns they are written as two
+/// characters; see `rle::DYING_STAGE_PREFIXES`.
+const DYING_CHAR_BASE: u32 = 0xE000;
+
+/// Number of owner codes per dying stage in the private use area: 23 players plus unowned.
+const DYING_CHAR_OWNERS: u32 = 24;
+
 impl CellState {
     /// Convert this `CellState` to a `char`. When the state is `Alive(None)` or `Dead`, this will
     /// match what would be found in a .rle file. `Wall`, `Alive(Some(player_id))`, and `Fog` are
@@ -213,8 +383,21 @@ impl CellState {
     ///
     /// Panics if `player_id` is not less than 23, since we map IDs 0 through 22 to uppercase
     /// letters A through V. W is not usable since it represents a wall cell.
+    ///
+    /// `Dying` states are converted to a char in Unicode's private use area, which is only meant
+    /// for use with `CharGrid`. Panics if the dying stage is 0 or greater than `MAX_STATES - 2`.
     pub fn to_char(self) -> char {
         match self {
+            CellState::Dying(opt_player_id, stage) => {
+                if stage == 0 || stage > MAX_STATES - 2 {
+                    panic!("Dying stage must be between 1 and {}", MAX_STATES - 2);
+                }
+                let owner = match opt_player_id {
+                    Some(player_id) => CellState::Alive(Some(player_id)).to_char() as u32 - 65,
+                    None => DYING_CHAR_OWNERS - 1,
+                };
+                char::from_u32(DYING_CHAR_BASE + (stage as u32 - 1) * DYING_CHAR_OWNERS + owner).unwrap()
+            }
             CellState::Alive(Some(player_id)) => {
                 if player_id >= 23 {
                     panic!("Player IDs must be less than 23 to be converted to chars");
@@ -236,11 +419,28 @@ impl CellState {
             'W' => Some(CellState::Wall),
             '?' => Some(CellState::Fog),
             'A'..='V' => Some(CellState::Alive(Some(u32::from(ch) as usize - 65))),
+            _ if is_dying_char(ch) => {
+                let offset = u32::from(ch) - DYING_CHAR_BASE;
+                let stage = (offset / DYING_CHAR_OWNERS) as usize + 1;
+                let owner = offset % DYING_CHAR_OWNERS;
+                let opt_player_id = if owner == DYING_CHAR_OWNERS - 1 {
+                    None
+                } else {
+                    Some(owner as usize)
+                };
+                Some(CellState::Dying(opt_player_id, stage))
+            }
             _ => None,
         }
     }
 }
 
+/// Returns true if `ch` represents a `CellState::Dying` state.
+pub(crate) fn is_dying_char(ch: char) -> bool {
+    let code = u32::from(ch);
+    code >= DYING_CHAR_BASE && code < DYING_CHAR_BASE + (MAX_STATES as u32 - 2) * DYING_CHAR_OWNERS
+}
+
 impl GenState {
     /// Sets the state of a cell, with minimal checking.  It doesn't support setting
     /// `CellState::Fog`.
@@ -267,6 +467,7 @@ impl GenState {
                 grid.modify_bits_in_word(row, word_col, mask, BitOperation::Clear);
             }
         }
+        self.clear_dying(row, word_col, mask);
 
         let cells = &mut self.cells;
         let walls = &mut self.wall_cells;
@@ -289,10 +490,131 @@ impl GenState {
                 cells.modify_bits_in_word(row, word_col, mask, BitOperation::Clear);
                 walls.modify_bits_in_word(row, word_col, mask, BitOperation::Set);
             }
+            CellState::Dying(opt_player_id, stage) => {
+                cells.modify_bits_in_word(row, word_col, mask, BitOperation::Clear);
+                walls.modify_bits_in_word(row, word_col, mask, BitOperation::Clear);
+                self.set_dying(row, word_col, mask, stage, opt_player_id);
+            }
             _ => unimplemented!(),
         }
     }
 
+    /// Returns the dying stage of the cell at bit `shift` of the word at (`row`, `word_col`), or 0
+    /// if it is not a dying cell.
+    fn dying_stage(&self, row: usize, word_col: usize, shift: usize) -> usize {
+        self.decay.iter().enumerate().fold(0, |stage, (i, plane)| {
+            stage | (((plane[row][word_col] >> shift) & 1) as usize) << i
+        })
+    }
+
+    /// Returns the player owning the dying cell at bit `shift` of the word at (`row`, `word_col`),
+    /// if any.
+    fn dying_owner(&self, row: usize, word_col: usize, shift: usize) -> Option<usize> {
+        self.player_states.iter().position(|player| {
+            player
+                .dying
+                .as_ref()
+                .is_some_and(|dying| (dying[row][word_col] >> shift) & 1 == 1)
+        })
+    }
+
+    /// Returns the word at (`row`, `word_col`) with a 1 bit for every dying cell.
+    fn dying_word(&self, row: usize, word_col: usize) -> u64 {
+        self.decay.iter().fold(0, |word, plane| word | plane[row][word_col])
+    }
+
+    /// Clears the dying stage and owner of the cells in `mask`.
+    fn clear_dying(&mut self, row: usize, word_col: usize, mask: u64) {
+        for plane in self.decay.iter_mut() {
+            plane.modify_bits_in_word(row, word_col, mask, BitOperation::Clear);
+        }
+        for player in self.player_states.iter_mut() {
+            if let Some(ref mut dying) = player.dying {
+                dying.modify_bits_in_word(row, word_col, mask, BitOperation::Clear);
+            }
+        }
+    }
+
+    /// Makes every cell set in `mask` dead and unowned, including walls.
+    fn clear_masked(&mut self, mask: &BitGrid) {
+        for row in 0..mask.height() {
+            for word_col in 0..mask.width_in_words() {
+                let word = mask[row][word_col];
+                if word == 0 {
+                    continue;
+                }
+                self.cells[row][word_col] &= !word;
+                self.wall_cells[row][word_col] &= !word;
+                for player in self.player_states.iter_mut() {
+                    player.cells[row][word_col] &= !word;
+                }
+                self.clear_dying(row, word_col, word);
+            }
+        }
+    }
+
+    /// Makes the cells in `mask` dying cells at `stage`, belonging to `opt_player_id`. The caller
+    /// must clear these cells in `self.cells`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stage` is 0 or doesn't fit in the dying stages allocated with
+    /// `set_dying_stages`.
+    fn set_dying(&mut self, row: usize, word_col: usize, mask: u64, stage: usize, opt_player_id: Option<usize>) {
+        if stage == 0 || stage >= 1 << self.decay.len() {
+            panic!("Dying stage {} is out of range at row {}", stage, row);
+        }
+        self.clear_dying(row, word_col, mask);
+        for (i, plane) in self.decay.iter_mut().enumerate() {
+            if (stage >> i) & 1 == 1 {
+                plane.modify_bits_in_word(row, word_col, mask, BitOperation::Set);
+            }
+        }
+        if let Some(player_id) = opt_player_id {
+            if let Some(ref mut dying) = self.player_states[player_id].dying {
+                dying.modify_bits_in_word(row, word_col, mask, BitOperation::Set);
+            }
+        }
+    }
+
+    /// Allocates the bit grids needed for `dying_stages` dying stages, or frees them if
+    /// `dying_stages` is 0. Any existing dying cells are removed.
+    fn set_dying_stages(&mut self, dying_stages: usize) {
+        let num_planes = (0..=MAX_DECAY_PLANES).find(|&i| dying_stages < 1 << i).unwrap();
+        let (width_in_words, height) = (self.cells.width_in_words(), self.cells.height());
+        self.decay = (0..num_planes).map(|_| BitGrid::new(width_in_words, height)).collect();
+        for player in self.player_states.iter_mut() {
+            player.dying = if num_planes > 0 {
+                Some(BitGrid::new(width_in_words, height))
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Sets each word of `changed` to all ones if any cell in that word differs between this
+    /// GenState and `other` (including ownership, fog and dying stages), or to zero otherwise.
+    fn changed_words(&self, other: &GenState, changed: &mut BitGrid) {
+        for (row, changed_row) in changed.0.iter_mut().enumerate() {
+            for (word_col, changed_word) in changed_row.iter_mut().enumerate() {
+                let mut diff = (self.cells[row][word_col] ^ other.cells[row][word_col])
+                    | (self.wall_cells[row][word_col] ^ other.wall_cells[row][word_col])
+                    | (self.known[row][word_col] ^ other.known[row][word_col]);
+                for (plane, other_plane) in self.decay.iter().zip(other.decay.iter()) {
+                    diff |= plane[row][word_col] ^ other_plane[row][word_col];
+                }
+                for (player, other_player) in self.player_states.iter().zip(other.player_states.iter()) {
+                    diff |= (player.cells[row][word_col] ^ other_player.cells[row][word_col])
+                        | (player.fog[row][word_col] ^ other_player.fog[row][word_col]);
+                    if let (Some(dying), Some(other_dying)) = (&player.dying, &other_player.dying) {
+                        diff |= dying[row][word_col] ^ other_dying[row][word_col];
+                    }
+                }
+                *changed_word = if diff != 0 { u64::MAX } else { 0 };
+            }
+        }
+    }
+
     /// Copies from `src` BitGrid to this GenState as the player specified by `opt_player_id`,
     /// unless `opt_player_id` is `None`. This is an "or" operation, so any existing alive cells
     /// are retained, though they may change ownership.  Walls, however, are preserved. Fog is
@@ -326,7 +648,8 @@ impl GenState {
             }
         }
 
-        // on the rows in dst_region, for each wall bit that's 1, clear it in dst.cells
+        // on the rows in dst_region, for each wall bit that's 1, clear it in dst.cells, and for each
+        // cell bit that's 1, make sure it's not also a dying cell
         for row in dst_region.top()..=dst_region.bottom() {
             let row = row as usize;
 
@@ -334,6 +657,8 @@ impl GenState {
                 let word_col = word_col as usize;
 
                 self.cells[row][word_col] &= !self.wall_cells[row][word_col];
+                let alive = self.cells[row][word_col];
+                self.clear_dying(row, word_col, alive);
             }
         }
     }
@@ -404,6 +729,12 @@ impl GenState {
             let p = &mut self.player_states[player_id];
             p.cells.modify_region(region, BitOperation::Clear);
             p.fog.modify_region(region, BitOperation::Clear);
+            if let Some(ref mut dying) = p.dying {
+                dying.modify_region(region, BitOperation::Clear);
+            }
+        }
+        for plane in self.decay.iter_mut() {
+            plane.modify_region(region, BitOperation::Clear);
         }
     }
 
@@ -424,6 +755,15 @@ impl GenState {
                 &mut dest.player_states[player_id].fog,
                 region,
             );
+            if let (Some(ref dying), Some(ref mut dest_dying)) = (
+                &self.player_states[player_id].dying,
+                &mut dest.player_states[player_id].dying,
+            ) {
+                BitGrid::copy(dying, dest_dying, region);
+            }
+        }
+        for (plane, dest_plane) in self.decay.iter().zip(dest.decay.iter_mut()) {
+            BitGrid::copy(plane, dest_plane, region);
         }
     }
 }
@@ -446,18 +786,27 @@ impl CharGrid for GenState {
         }
         let word_col = col / 64;
         let shift = 63 - (col & (64 - 1));
+        let is_dying = is_dying_char(ch);
         // cells
         match ch {
             'b' | 'W' | '?' => self.cells[row][word_col] &= !(1 << shift),
             'o' | 'A'..='V' => self.cells[row][word_col] |= 1 << shift,
+            _ if is_dying => self.cells[row][word_col] &= !(1 << shift),
             _ => unreachable!(),
         }
         // wall cells
         match ch {
             'W' => self.wall_cells[row][word_col] |= 1 << shift,
             'b' | 'o' | 'A'..='V' | '?' => self.wall_cells[row][word_col] &= !(1 << shift),
+            _ if is_dying => self.wall_cells[row][word_col] &= !(1 << shift),
             _ => unreachable!(),
         }
+        // dying cells
+        if let Some(CellState::Dying(opt_player_id, stage)) = CellState::from_char(ch) {
+            self.set_dying(row, word_col, 1 << shift, stage, opt_player_id);
+        } else {
+            self.clear_dying(row, word_col, 1 << shift);
+        }
         // player_states
         if ch == '?' {
             if visibility.is_none() {
@@ -496,7 +845,7 @@ impl CharGrid for GenState {
         match ch {
             'o' | 'b' | 'A'..='W' | '?' => true,
             NO_OP_CHAR => true,
-            _ => false,
+            _ => is_dying_char(ch),
         }
     }
 
@@ -558,6 +907,30 @@ impl CharGrid for GenState {
             }
             return (min_run, CellState::Alive(None).to_char());
         }
+        let mut stage = 0;
+        for (i, plane) in self.decay.iter().enumerate() {
+            let (stage_run, stage_ch) = plane.get_run(col, row, None);
+            if stage_run < min_run {
+                min_run = stage_run;
+            }
+            if stage_ch == 'o' {
+                stage |= 1 << i;
+            }
+        }
+        if stage > 0 {
+            for player_id in 0..self.player_states.len() {
+                if let Some(ref dying) = self.player_states[player_id].dying {
+                    let (player_dying_run, player_dying_ch) = dying.get_run(col, row, None);
+                    if player_dying_run < min_run {
+                        min_run = player_dying_run;
+                    }
+                    if player_dying_ch == 'o' {
+                        return (min_run, CellState::Dying(Some(player_id), stage).to_char());
+                    }
+                }
+            }
+            return (min_run, CellState::Dying(None, stage).to_char());
+        }
         if wall_ch == 'o' {
             return (min_run, CellState::Wall.to_char());
         } else {
@@ -669,6 +1042,11 @@ impl fmt::Display for Universe {
                         }
                     } else if (wall_cen >> shift) & 1 == 1 {
                         s.push('W');
+                    } else if self.gen_states[self.state_index].dying_stage(row_idx, col_idx, shift) > 0 {
+                        match self.gen_states[self.state_index].dying_owner(row_idx, col_idx, shift) {
+                            Some(player_id) => s.push(char::from_u32(player_id as u32 + 97).unwrap()),
+                            None => s.push('.'),
+                        }
                     } else {
                         s.push(' ');
                     }
@@ -693,10 +1071,13 @@ impl Universe {
         let shift = 63 - (col & (64 - 1)); // translate literal col (ex: 134) to bit index in word_col
         let mask = 1 << shift; // cell to set
 
+        let stage = gen_state.dying_stage(row, word_col, shift);
         if let Some(player_id) = opt_player_id {
             let cell = (gen_state.player_states[player_id].cells[row][word_col] & mask) >> shift;
             if cell == 1 {
                 CellState::Alive(opt_player_id)
+            } else if stage > 0 && gen_state.dying_owner(row, word_col, shift) == opt_player_id {
+                CellState::Dying(opt_player_id, stage)
             } else {
                 CellState::Dead
             }
@@ -704,6 +1085,8 @@ impl Universe {
             let cell = (gen_state.cells[row][word_col] & mask) >> shift;
             if cell == 1 {
                 CellState::Alive(None)
+            } else if stage > 0 {
+                CellState::Dying(None, stage)
             } else {
                 CellState::Dead
             }
@@ -717,6 +1100,7 @@ impl Universe {
     ///
     /// Panics if an attempt is made to set an unknown cell.
     pub fn set_unchecked(&mut self, col: usize, row: usize, new_state: CellState) {
+        self.mark_edited(Region::new(col as isize, row as isize, 1, 1));
         self.gen_states[self.state_index].set_unchecked(col, row, new_state)
     }
 
@@ -725,6 +1109,7 @@ impl Universe {
     /// * current cell state (can't change wall)
     /// * fog
     /// * if current cell is alive, player_id matches player_id argument
+    /// * if the cell would come alive, the player has enough energy (see `EnergyBudget`)
     ///
     /// If any of the above checks fail, do nothing.
     ///
@@ -761,10 +1146,26 @@ impl Universe {
                 return;
             }
 
-            if let CellState::Alive(Some(new_state_player_id)) = new_state {
-                if new_state_player_id != player_id {
+            // Likewise if the current cell is dying but not owned by this player
+            if gen_state.dying_stage(row, word_col, shift) > 0
+                && gen_state.dying_owner(row, word_col, shift) != Some(player_id)
+            {
+                return;
+            }
+
+            match new_state {
+                CellState::Alive(Some(new_state_player_id)) | CellState::Dying(Some(new_state_player_id), _)
+                    if new_state_player_id != player_id =>
+                {
                     panic!("A player cannot set the cell state of another player");
                 }
+                _ => {}
+            }
+
+            if let CellState::Alive(_) = new_state {
+                if cells_word & mask == 0 && self.spend_energy(player_id, 1).is_err() {
+                    return;
+                }
             }
         }
 
@@ -785,6 +1186,13 @@ impl Universe {
         let word_col = col / 64;
         let shift = 63 - (col & (64 - 1));
         let mask = 1 << shift;
+        self.mark_edited(Region::new(col as isize, row as isize, 1, 1));
+
+        // Cell transitioned Dying -> Dead
+        if self.gen_states[self.state_index].dying_word(row, word_col) & mask > 0 {
+            self.gen_states[self.state_index].clear_dying(row, word_col, mask);
+            return CellState::Dead;
+        }
 
         let word = {
             let cells = &mut self.gen_states[self.state_index].cells;
@@ -819,9 +1227,12 @@ impl Universe {
     ///
     /// # Errors
     ///
+    /// * It is a `ConwayError::OutOfBounds` error if col or row is outside of the universe.
     /// * It is a `ConwayError::AccessDenied` error to toggle outside player's writable area, or to
     /// toggle a wall or an unknown cell.
     /// * It is a `ConwayError::InvalidData` error to pass in an invalid player_id.
+    /// * It is a `ConwayError::InsufficientEnergy` error to toggle a dead cell to alive if the
+    ///   player can't afford it.
     pub fn toggle(&mut self, col: usize, row: usize, player_id: usize) -> ConwayResult<CellState> {
         use ConwayError::*;
         if !self.writable(col, row, player_id)? {
@@ -846,6 +1257,14 @@ impl Universe {
                 });
             }
         }
+        {
+            let gen_state = &self.gen_states[self.state_index];
+            let is_alive = (gen_state.cells[row][word_col] >> shift) & 1 == 1;
+            let is_dying = (gen_state.dying_word(row, word_col) >> shift) & 1 == 1;
+            if !is_alive && !is_dying {
+                self.spend_energy(player_id, 1)?;
+            }
+        }
         Ok(self.toggle_unchecked(col, row, Some(player_id)))
     }
 
@@ -854,12 +1273,14 @@ impl Universe {
     /// # Errors
     ///
     /// * It is a `ConwayError::InvalidData` error to pass in an invalid player_id.
+    /// * It is a `ConwayError::OutOfBounds` error if col or row is outside of the universe.
     pub fn writable(&self, col: usize, row: usize, player_id: usize) -> ConwayResult<bool> {
         if player_id >= self.player_writable.len() {
             return Err(ConwayError::InvalidData {
                 reason: format!("Unexpected player_id {}", player_id),
             });
         }
+        self.check_bounds(col, row)?;
         let in_writable_region = self.player_writable[player_id].contains(col as isize, row as isize);
         if !in_writable_region {
             return Ok(false);
@@ -873,6 +1294,19 @@ impl Universe {
         Ok(!on_wall_cell)
     }
 
+    /// Returns an error unless (`col`, `row`) is within the universe.
+    fn check_bounds(&self, col: usize, row: usize) -> ConwayResult<()> {
+        if col >= self.width || row >= self.height {
+            return Err(ConwayError::OutOfBounds {
+                col,
+                row,
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok(())
+    }
+
     /// Instantiate a new blank universe with the given width and height, in cells.
     /// The universe is at generation 1.
     ///
@@ -916,15 +1350,18 @@ impl Universe {
                 reason: "Fog radius must be positive".to_owned(),
             });
         }
+        Universe::check_fog_fits(fog_radius, width, height)?;
 
         // Initialize all generational states with the default appropriate bitgrids
         let mut gen_states = Vec::new();
+        let mut changes = Vec::new();
         for i in 0..history {
             let mut player_states = Vec::new();
             for player_id in 0..num_players {
                 let mut pgs = PlayerGenState {
                     cells: BitGrid::new(width_in_words, height),
                     fog:   BitGrid::new(width_in_words, height),
+                    dying: None,
                 };
 
                 // unless writable region, the whole grid is player fog
@@ -957,6 +1394,12 @@ impl Universe {
                 wall_cells:    BitGrid::new(width_in_words, height),
                 known:         known,
                 player_states: player_states,
+                decay:         vec![],
+            });
+            changes.push(GenChanges {
+                since:   0,
+                stepped: BitGrid::new(width_in_words, height),
+                edited:  BitGrid::new(width_in_words, height),
             });
         }
 
@@ -972,6 +1415,12 @@ impl Universe {
             // TODO: it's not very rusty to have uninitialized stuff (use Option<FogInfo> instead)
             fog_radius:      fog_radius,      // uninitialized
             fog_circle:      BitGrid(vec![]), // uninitialized
+            rule:            Rule::conway(),
+            rule_zones:      vec![],
+            energy_budgets:  vec![None; num_players],
+            energy:          vec![0; num_players],
+            changes,
+            activity:        None,
         };
         uni.generate_fog_circle_bitmap();
         Ok(uni)
@@ -1022,6 +1471,139 @@ impl Universe {
         }
     }
 
+    /// Returns the number of players.
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    /// Returns the region the specified player may write to, or `None` if there is no such player.
+    pub fn writable_region(&self, player_id: usize) -> Option<Region> {
+        self.player_writable.get(player_id).copied()
+    }
+
+    /// Returns the radius of the fog cleared around each cell a player acquires.
+    pub fn fog_radius(&self) -> usize {
+        self.fog_radius
+    }
+
+    /// Returns the rule used to compute generations.
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    /// Changes the rule used to compute subsequent generations, outside of any rule zones.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if the neighborhood of `rule` is wider or taller
+    /// than the universe, since cells would then be counted as their own neighbors.
+    ///
+    /// Any dying cells are cleared, since their stages may not be meaningful under `rule`.
+    pub fn set_rule(&mut self, rule: Rule) -> ConwayResult<()> {
+        self.check_rule(&rule)?;
+        self.rule = rule;
+        self.reset_dying_stages();
+        Ok(())
+    }
+
+    /// Adds a rule zone, so that `rule` is used instead of the universe's rule for every cell whose
+    /// bit is set in `mask`. Zones added later take precedence where zones overlap.
+    ///
+    /// Cells near a zone border count their neighbors regardless of which zone the neighbors are
+    /// in; only the rule applied to the counts depends on the zone of the cell itself. Ownership
+    /// and fog spread according to the neighborhood of the cell's rule.
+    ///
+    /// # Errors
+    ///
+    /// * It is a `ConwayError::InvalidData` error if `mask` is not the same size as the universe.
+    /// * It is a `ConwayError::InvalidData` error if the neighborhood of `rule` is wider or taller
+    ///   than the universe.
+    ///
+    /// Any dying cells are cleared, as with `set_rule`.
+    pub fn add_rule_zone(&mut self, mask: BitGrid, rule: Rule) -> ConwayResult<()> {
+        if mask.width_in_words() != self.width_in_words || mask.height() != self.height {
+            return Err(ConwayError::InvalidData {
+                reason: format!(
+                    "Rule zone mask must be {}x{} cells, but is {}x{}",
+                    self.width,
+                    self.height,
+                    mask.width_in_words() * 64,
+                    mask.height()
+                ),
+            });
+        }
+        self.check_rule(&rule)?;
+        self.rule_zones.push((mask, rule));
+        self.reset_dying_stages();
+        Ok(())
+    }
+
+    /// Returns the rule used to compute the next state of the cell at (`col`, `row`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` or `col` are out of range.
+    pub fn rule_at(&self, col: usize, row: usize) -> &Rule {
+        let word_col = col / 64;
+        let shift = 63 - (col & (64 - 1));
+        for (mask, rule) in self.rule_zones.iter().rev() {
+            if (mask[row][word_col] >> shift) & 1 == 1 {
+                return rule;
+            }
+        }
+        &self.rule
+    }
+
+    fn check_rule(&self, rule: &Rule) -> ConwayResult<()> {
+        Universe::check_rule_fits(rule, self.width, self.height)
+    }
+
+    /// Returns an error if the neighborhood of `rule` doesn't fit in a universe of the given size.
+    fn check_rule_fits(rule: &Rule, width: usize, height: usize) -> ConwayResult<()> {
+        let diameter = 2 * rule.neighborhood().range() + 1;
+        if diameter > width || diameter > height {
+            return Err(ConwayError::InvalidData {
+                reason: format!(
+                    "Rule {} needs a universe of at least {}x{} cells",
+                    rule, diameter, diameter
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the fog circle of `fog_radius` doesn't fit in a universe of the given
+    /// size. The circle is cleared in one piece, so it must not wrap onto itself.
+    fn check_fog_fits(fog_radius: usize, width: usize, height: usize) -> ConwayResult<()> {
+        let diameter = 2 * fog_radius - 1;
+        if diameter > width || diameter > height {
+            return Err(ConwayError::InvalidData {
+                reason: format!(
+                    "Fog radius {} needs a universe of at least {}x{} cells",
+                    fog_radius, diameter, diameter
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Allocates enough dying stages for every rule in use, clearing any dying cells.
+    fn reset_dying_stages(&mut self) {
+        let dying_stages = self
+            .rule_zones
+            .iter()
+            .map(|(_, rule)| rule.dying_stages())
+            .fold(self.rule.dying_stages(), cmp::max);
+        let mut had_dying_cells = false;
+        for gen_state in self.gen_states.iter_mut() {
+            had_dying_cells |= gen_state.decay.iter().any(|plane| plane.count_ones() > 0);
+            gen_state.set_dying_stages(dying_stages);
+        }
+        if had_dying_cells {
+            self.mark_edited(self.region());
+        }
+    }
+
     /// Get the latest generation number (1-based).
     pub fn latest_gen(&self) -> usize {
         assert!(self.generation != 0);
@@ -1113,6 +1695,10 @@ impl Universe {
             (&p1[history - 2], &mut p0[0])
         };
 
+        // Words that change are collected as we go, for `changed_since`
+        let stepped = &mut self.changes[next_state_index].stepped;
+        stepped.clear();
+
         {
             let cells = &gen_state.cells;
             let wall = &gen_state.wall_cells;
@@ -1121,6 +1707,20 @@ impl Universe {
             let wall_next = &mut gen_state_next.wall_cells;
             let known_next = &mut gen_state_next.known;
 
+            // Rules other than Conway's Game of Life use the generic (and slower) implementation
+            let opt_offsets = if self.rule.is_conway() {
+                None
+            } else {
+                Some(self.rule.neighborhood().offsets())
+            };
+
+            // Each rule zone overrides the results above for the cells in its mask
+            let zone_offsets: Vec<Vec<(isize, isize)>> = self
+                .rule_zones
+                .iter()
+                .map(|(_, rule)| rule.neighborhood().offsets())
+                .collect();
+
             // Copy fog over to next generation
             for row_idx in 0..self.height {
                 for player_id in 0..self.num_players {
@@ -1182,22 +1782,78 @@ impl Universe {
                     known_se = known_row_s[(col_idx + 1) % self.width_in_words];
 
                     // apply BitGrid changes
-                    let mut cells_cen_next = Universe::next_single_gen(
-                        cells_nw, cells_n, cells_ne, cells_w, cells_cen, cells_e, cells_sw, cells_s, cells_se,
-                    );
+                    let mut cells_cen_next = if let Some(ref offsets) = opt_offsets {
+                        self.rule.next_word(cells, row_idx, col_idx, offsets)
+                    } else {
+                        Universe::next_single_gen(
+                            cells_nw, cells_n, cells_ne, cells_w, cells_cen, cells_e, cells_sw, cells_s, cells_se,
+                        )
+                    };
 
                     // any known cells with at least one unknown neighbor will become unknown in
                     // the next generation
-                    known_next[row_idx][col_idx] = Universe::contagious_zero(
-                        known_nw, known_n, known_ne, known_w, known_cen, known_e, known_sw, known_s, known_se,
-                    );
+                    known_next[row_idx][col_idx] = if let Some(ref offsets) = opt_offsets {
+                        rule::contagious_zero(known, row_idx, col_idx, offsets)
+                    } else {
+                        Universe::contagious_zero(
+                            known_nw, known_n, known_ne, known_w, known_cen, known_e, known_sw, known_s, known_se,
+                        )
+                    };
+
+                    for ((mask, zone_rule), offsets) in self.rule_zones.iter().zip(zone_offsets.iter()) {
+                        let mask = mask[row_idx][col_idx];
+                        if mask != 0 {
+                            cells_cen_next = (cells_cen_next & !mask)
+                                | (zone_rule.next_word(cells, row_idx, col_idx, offsets) & mask);
+                            known_next[row_idx][col_idx] = (known_next[row_idx][col_idx] & !mask)
+                                | (rule::contagious_zero(known, row_idx, col_idx, offsets) & mask);
+                        }
+                    }
 
                     cells_cen_next &= known_next[row_idx][col_idx];
                     cells_cen_next &= !wall_row_c[col_idx];
 
+                    // Generations rules: dying cells can't be born into, and live cells that die
+                    // pass through the dying stages before becoming dead
+                    let mut newly_dying = 0;
+                    let mut dying_next = 0;
+                    if !gen_state.decay.is_empty() {
+                        let mut stage_bits = [0u64; MAX_DECAY_PLANES];
+                        let stage_bits = &mut stage_bits[..gen_state.decay.len()];
+                        for (i, plane) in gen_state.decay.iter().enumerate() {
+                            stage_bits[i] = plane[row_idx][col_idx];
+                        }
+                        let dying_cur = stage_bits.iter().fold(0, |dying, plane| dying | plane);
+                        cells_cen_next &= !dying_cur;
+                        newly_dying = cells_cen & !cells_cen_next & known_next[row_idx][col_idx];
+                        let mut stage_bits_cur = [0u64; MAX_DECAY_PLANES];
+                        stage_bits_cur[..stage_bits.len()].copy_from_slice(stage_bits);
+                        self.rule.next_decay(stage_bits, newly_dying);
+                        for (mask, zone_rule) in self.rule_zones.iter() {
+                            let mask = mask[row_idx][col_idx];
+                            if mask != 0 {
+                                let mut zone_stage_bits = stage_bits_cur;
+                                let zone_stage_bits = &mut zone_stage_bits[..stage_bits.len()];
+                                zone_rule.next_decay(zone_stage_bits, newly_dying);
+                                for (plane, zone_plane) in stage_bits.iter_mut().zip(zone_stage_bits.iter()) {
+                                    *plane = (*plane & !mask) | (zone_plane & mask);
+                                }
+                            }
+                        }
+                        for (i, plane) in gen_state_next.decay.iter_mut().enumerate() {
+                            plane[row_idx][col_idx] = stage_bits[i] & known_next[row_idx][col_idx];
+                            dying_next |= plane[row_idx][col_idx];
+                        }
+                    }
+
                     // assign to the u64 element in the next generation
                     cells_next[row_idx][col_idx] = cells_cen_next;
 
+                    let mut changed_bits = (cells_cen ^ cells_cen_next) | (known_cen ^ known_next[row_idx][col_idx]);
+                    for (plane, plane_next) in gen_state.decay.iter().zip(gen_state_next.decay.iter()) {
+                        changed_bits |= plane[row_idx][col_idx] ^ plane_next[row_idx][col_idx];
+                    }
+
                     let mut in_multiple: u64 = 0;
                     let mut seen_before: u64 = 0;
                     for player_id in 0..self.num_players {
@@ -1211,20 +1867,30 @@ impl Universe {
                         // Any unowned cells are influenced by their neighbors, and if players,
                         // can be acquired by the player, just as long as no two players are
                         // fighting over those cells
-                        let player_cell_next = Universe::contagious_one(
-                            gen_state.player_states[player_id].cells[n_row_idx]
-                                [(col_idx + self.width_in_words - 1) % self.width_in_words],
-                            gen_state.player_states[player_id].cells[n_row_idx][col_idx],
-                            gen_state.player_states[player_id].cells[n_row_idx][(col_idx + 1) % self.width_in_words],
-                            gen_state.player_states[player_id].cells[row_idx]
-                                [(col_idx + self.width_in_words - 1) % self.width_in_words],
-                            gen_state.player_states[player_id].cells[row_idx][col_idx],
-                            gen_state.player_states[player_id].cells[row_idx][(col_idx + 1) % self.width_in_words],
-                            gen_state.player_states[player_id].cells[s_row_idx]
-                                [(col_idx + self.width_in_words - 1) % self.width_in_words],
-                            gen_state.player_states[player_id].cells[s_row_idx][col_idx],
-                            gen_state.player_states[player_id].cells[s_row_idx][(col_idx + 1) % self.width_in_words],
-                        ) & cells_cen_next;
+                        let player_cells = &gen_state.player_states[player_id].cells;
+                        let mut player_cell_next = if let Some(ref offsets) = opt_offsets {
+                            rule::contagious_one(player_cells, row_idx, col_idx, offsets)
+                        } else {
+                            Universe::contagious_one(
+                                player_cells[n_row_idx][(col_idx + self.width_in_words - 1) % self.width_in_words],
+                                player_cells[n_row_idx][col_idx],
+                                player_cells[n_row_idx][(col_idx + 1) % self.width_in_words],
+                                player_cells[row_idx][(col_idx + self.width_in_words - 1) % self.width_in_words],
+                                player_cells[row_idx][col_idx],
+                                player_cells[row_idx][(col_idx + 1) % self.width_in_words],
+                                player_cells[s_row_idx][(col_idx + self.width_in_words - 1) % self.width_in_words],
+                                player_cells[s_row_idx][col_idx],
+                                player_cells[s_row_idx][(col_idx + 1) % self.width_in_words],
+                            )
+                        };
+                        for ((mask, _), offsets) in self.rule_zones.iter().zip(zone_offsets.iter()) {
+                            let mask = mask[row_idx][col_idx];
+                            if mask != 0 {
+                                player_cell_next = (player_cell_next & !mask)
+                                    | (rule::contagious_one(player_cells, row_idx, col_idx, offsets) & mask);
+                            }
+                        }
+                        player_cell_next &= cells_cen_next;
                         in_multiple |= player_cell_next & seen_before;
                         seen_before |= player_cell_next;
                         gen_state_next.player_states[player_id].cells[row_idx][col_idx] = player_cell_next;
@@ -1235,6 +1901,17 @@ impl Universe {
                         cell_next &= !in_multiple; // if a cell would have belonged to multiple players, it belongs to none
                         gen_state_next.player_states[player_id].cells[row_idx][col_idx] = cell_next;
 
+                        // dying cells keep the owner they had when they were alive
+                        if let (Some(dying), Some(player_dying_next)) = (
+                            &gen_state.player_states[player_id].dying,
+                            &mut gen_state_next.player_states[player_id].dying,
+                        ) {
+                            player_dying_next[row_idx][col_idx] =
+                                ((cell_cur & newly_dying) | dying[row_idx][col_idx]) & dying_next;
+                            changed_bits |= dying[row_idx][col_idx] ^ player_dying_next[row_idx][col_idx];
+                        }
+                        changed_bits |= cell_cur ^ cell_next;
+
                         // clear fog for all cells that turned on in this generation
                         Universe::clear_fog(
                             &mut gen_state_next.player_states[player_id].fog,
@@ -1245,8 +1922,12 @@ impl Universe {
                             row_idx,
                             col_idx,
                             cell_next & !cell_cur,
+                            stepped,
                         );
                     }
+                    if changed_bits != 0 {
+                        stepped[row_idx][col_idx] = u64::MAX;
+                    }
                 }
 
                 // copy wall to wall_next
@@ -1255,15 +1936,21 @@ impl Universe {
         }
 
         // increment generation in appropriate places
+        let prev_state_index = self.state_index;
         self.generation += 1;
         self.state_index = next_state_index;
         gen_state_next.gen_or_none = Some(self.generation);
+        let changes = &mut self.changes[self.state_index];
+        changes.since = self.gen_states[prev_state_index].gen_or_none.unwrap();
+        changes.edited.clear();
+        self.record_activity(Some(prev_state_index));
+        self.regenerate_energy(1);
         self.generation
     }
 
     /// Clears the fog for the specified bits in the 64-bit word at `center_row_idx` and
     /// `center_col_idx` using the fog circle (see `generate_fog_circle_bitmap` documentation for
-    /// more on this).
+    /// more on this). Words of `player_fog` that change are set to all ones in `changed`.
     //TODO: unit test with fog_radiuses above and below 64
     fn clear_fog(
         player_fog: &mut BitGrid,
@@ -1274,6 +1961,7 @@ impl Universe {
         center_row_idx: usize,
         center_col_idx: usize,
         bits_to_clear: u64,
+        changed: &mut BitGrid,
     ) {
         if bits_to_clear == 0 {
             return; // nothing to do
@@ -1315,16 +2003,18 @@ impl Universe {
         debug!("row_(top,bottom) range is [{}, {}]", row_top, row_bottom);
         debug!("col_(left,right) range is [{}, {}]", col_left, col_right);
 
-        // Convert cols to col_idxes
+        // Convert cols to col_idxes. The column range can wrap all the way around to the word it
+        // started in, so count the words to visit rather than stopping at `col_right`'s word.
         let col_idx_left = col_left / 64;
-        let col_idx_right = col_right / 64;
+        let uni_word_width = uni_width / 64;
+        let col_span = col_of_lowest_to_clear - col_of_highest_to_clear + 2 * (fog_radius - 1) + 1;
+        let num_col_idxes = cmp::min(uni_word_width, (col_left % 64 + col_span - 1) / 64 + 1);
 
         let mut row_idx = row_top;
-        let uni_word_width = uni_width / 64;
         loop {
             //debug!("row_idx is {} (out of height {})", row_idx, uni_height);
             let mut col_idx = col_idx_left;
-            loop {
+            for _ in 0..num_col_idxes {
                 debug!("row {}, col range [{}, {}]", row_idx, col_idx * 64, col_idx * 64 + 63);
                 //debug!("col_idx is {} (out of word_width {}); stopping after {}", col_idx, uni_word_width, col_idx_right);
                 let mut mask = u64::max_value();
@@ -1349,37 +2039,41 @@ impl Universe {
                                     "  mask is now {:016x}, cleared by fog circle R{}, Ci{}, no shift",
                                     mask, fog_row_idx, fog_col_idx
                                 );
-                            } else if current_highest_col <= fog_lowest_col && fog_lowest_col < current_lowest_col {
-                                // we need to double negate so that shifting results in 1s, not 0s
-                                mask &=
-                                    !(!fog_circle[fog_row_idx][fog_col_idx] << (current_lowest_col - fog_lowest_col));
-                                debug!("  fog word is {:016x}", fog_circle[fog_row_idx][fog_col_idx]);
-                                debug!(
-                                    "  mask is now {:016x}, cleared by fog circle R{}, Ci{}, fog circle << {}",
-                                    mask,
-                                    fog_row_idx,
-                                    fog_col_idx,
-                                    current_lowest_col - fog_lowest_col
-                                );
-                            } else if current_highest_col < fog_highest_col && fog_highest_col <= current_lowest_col {
-                                mask &=
-                                    !(!fog_circle[fog_row_idx][fog_col_idx] >> (fog_highest_col - current_highest_col));
-                                debug!("  fog word is {:016x}", fog_circle[fog_row_idx][fog_col_idx]);
-                                debug!(
-                                    "  mask is now {:016x}, cleared by fog circle R{}, Ci{}, fog circle >> {}",
-                                    mask,
-                                    fog_row_idx,
-                                    fog_col_idx,
-                                    fog_highest_col - current_highest_col
-                                );
+                            } else {
+                                // In a universe only one word wide, the fog circle can wrap around
+                                // into the same word, so both of these can apply.
+                                if current_highest_col <= fog_lowest_col && fog_lowest_col < current_lowest_col {
+                                    // we need to double negate so that shifting results in 1s, not 0s
+                                    mask &= !(!fog_circle[fog_row_idx][fog_col_idx]
+                                        << (current_lowest_col - fog_lowest_col));
+                                    debug!("  fog word is {:016x}", fog_circle[fog_row_idx][fog_col_idx]);
+                                    debug!(
+                                        "  mask is now {:016x}, cleared by fog circle R{}, Ci{}, fog circle << {}",
+                                        mask,
+                                        fog_row_idx,
+                                        fog_col_idx,
+                                        current_lowest_col - fog_lowest_col
+                                    );
+                                }
+                                if current_highest_col < fog_highest_col && fog_highest_col <= current_lowest_col {
+                                    mask &= !(!fog_circle[fog_row_idx][fog_col_idx]
+                                        >> (fog_highest_col - current_highest_col));
+                                    debug!("  fog word is {:016x}", fog_circle[fog_row_idx][fog_col_idx]);
+                                    debug!(
+                                        "  mask is now {:016x}, cleared by fog circle R{}, Ci{}, fog circle >> {}",
+                                        mask,
+                                        fog_row_idx,
+                                        fog_col_idx,
+                                        fog_highest_col - current_highest_col
+                                    );
+                                }
                             }
                         }
                     }
                 }
-                player_fog[row_idx][col_idx] &= mask;
-
-                if col_idx == col_idx_right {
-                    break;
+                if player_fog[row_idx][col_idx] & !mask != 0 {
+                    player_fog[row_idx][col_idx] &= mask;
+                    changed[row_idx][col_idx] = u64::MAX;
                 }
                 col_idx = (col_idx + 1) % uni_word_width;
             }
@@ -1396,120 +2090,137 @@ impl Universe {
     /// if specified, causes cells not visible to the player to be passed as `CellState::Fog` to the
     /// callback.
     ///
-    /// Callback receives (`col`, `row`, `cell_state`).
+    /// Callback receives (`col`, `row`, `cell_state`). See `cells_in` for how `region` is handled.
     ///
     /// # Panics
     ///
     /// Does numerous consistency checks on the bitmaps, and panics if inconsistencies are found.
-    //XXX non_dead_cells_in_region
     pub fn each_non_dead(
         &self,
         region: Region,
         visibility: Option<usize>,
         callback: &mut dyn FnMut(usize, usize, CellState),
     ) {
-        let cells = &self.gen_states[self.state_index].cells;
-        let wall = &self.gen_states[self.state_index].wall_cells;
-        let known = &self.gen_states[self.state_index].known;
-        let opt_player_state = if let Some(player_id) = visibility {
-            Some(&self.gen_states[self.state_index].player_states[player_id])
-        } else {
-            None
-        };
-        let mut col;
-        for row in 0..self.height {
-            let cells_row = &cells[row];
-            let wall_row = &wall[row];
-            let known_row = &known[row];
-            if (row as isize) >= region.top() && (row as isize) < (region.top() + region.height() as isize) {
-                col = 0;
-                for col_idx in 0..self.width_in_words {
-                    let cells_word = cells_row[col_idx];
-                    let wall_word = wall_row[col_idx];
-                    let known_word = known_row[col_idx];
-                    let opt_player_words;
-                    if let Some(player_state) = opt_player_state {
-                        let player_cells_word = player_state.cells[row][col_idx];
-                        let player_fog_word = player_state.fog[row][col_idx];
-                        opt_player_words = Some((player_cells_word, player_fog_word));
-                    } else {
-                        opt_player_words = None;
-                    }
-                    for shift in (0..64).rev() {
-                        if (col as isize) >= region.left() && (col as isize) < (region.left() + region.width() as isize)
-                        {
-                            let mut state = CellState::Wall;
-                            let c = (cells_word >> shift) & 1 == 1;
-                            let w = (wall_word >> shift) & 1 == 1;
-                            let k = (known_word >> shift) & 1 == 1;
-                            if c && w {
-                                panic!("Cannot be both cell and wall at ({}, {})", col, row);
-                            }
-                            if !k && ((c && !w) || (!c && w)) {
-                                panic!("Unspecified invalid state at ({}, {})", col, row);
-                            }
-                            if c && !w && k {
-                                // It's known and it's a cell; check cells + fog for every player
-                                // (expensive step since this is per-bit).
-
-                                let mut opt_player_id = None;
-                                for player_id in 0..self.num_players {
-                                    let player_state = &self.gen_states[self.state_index].player_states[player_id];
-                                    let pc = (player_state.cells[row][col_idx] >> shift) & 1 == 1;
-                                    let pf = (player_state.fog[row][col_idx] >> shift) & 1 == 1;
-                                    if pc && pf {
-                                        panic!(
-                                            "Player cell and player fog at ({}, {}) for player {}",
-                                            col, row, player_id
-                                        );
-                                    }
-                                    if pc {
-                                        if let Some(other_player_id) = opt_player_id {
-                                            panic!(
-                                                "Cell ({}, {}) belongs to player {} and player {}!",
-                                                col, row, other_player_id, player_id
-                                            );
-                                        }
-                                        opt_player_id = Some(player_id);
-                                    }
-                                }
-                                state = CellState::Alive(opt_player_id);
-                            } else {
-                                // (B) other states
-                                if !c && !w {
-                                    state = if k { CellState::Dead } else { CellState::Fog };
-                                } else if !c && w {
-                                    state = CellState::Wall;
-                                }
-                            }
-                            if let Some((player_cells_word, player_fog_word)) = opt_player_words {
-                                let pc = (player_cells_word >> shift) & 1 == 1;
-                                let pf = (player_fog_word >> shift) & 1 == 1;
-                                if !k && pc {
-                                    panic!("Player can't have cells where unknown, at ({}, {})", col, row);
-                                }
-                                if w && pc {
-                                    panic!("Player can't have cells where wall, at ({}, {})", col, row);
-                                }
-                                if pf {
-                                    state = CellState::Fog;
-                                }
-                            }
-                            if state != CellState::Dead {
-                                callback(col, row, state);
-                            }
-                        }
-                        col += 1;
-                    }
-                }
-            }
+        for (col, row, state) in self.cells_in(region, visibility) {
+            callback(col, row, state);
         }
     }
 
-    /// Iterate over every non-dead cell in the universe for the current generation.
-    /// `visibility` is an optional player_id, allowing filtering based on fog.
-    /// Callback receives (col, row, cell_state).
-    pub fn each_non_dead_full(&self, visibility: Option<usize>, callback: &mut dyn FnMut(usize, usize, CellState)) {
+    /// Returns an iterator over every non-dead cell within `region` for the current generation,
+    /// as (`col`, `row`, `cell_state`). `visibility` is an optional player_id; if specified, cells
+    /// not visible to the player are returned as `CellState::Fog`.
+    ///
+    /// `region` may extend past the edges of the universe, in which case it wraps around, and
+    /// the cells are returned in row-major order starting from the region's top-left corner.
+    /// Coordinates returned are always within the universe. A region wider or taller than the
+    /// universe is truncated, so that each cell is returned at most once.
+    ///
+    /// Only the words intersecting `region` are examined.
+    ///
+    /// # Panics
+    ///
+    /// The iterator does numerous consistency checks on the bitmaps, and panics if
+    /// inconsistencies are found.
+    pub fn cells_in(&self, region: Region, visibility: Option<usize>) -> CellsIn<'_> {
+        let region = Region::new(
+            region.left(),
+            region.top(),
+            cmp::min(region.width(), self.width),
+            cmp::min(region.height(), self.height),
+        );
+        CellsIn {
+            uni: self,
+            region,
+            visibility,
+            row_offset: 0,
+            col_offset: 0,
+            row: 0,
+            word_col: 0,
+            pending: 0,
+        }
+    }
+
+    /// Returns the state of a cell in the current generation as seen by `visibility`, checking
+    /// the bitmaps for consistency along the way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if inconsistencies are found.
+    fn checked_cell_state(&self, row: usize, word_col: usize, shift: usize, visibility: Option<usize>) -> CellState {
+        let gen_state = &self.gen_states[self.state_index];
+        let col = word_col * 64 + 63 - shift;
+        let c = (gen_state.cells[row][word_col] >> shift) & 1 == 1;
+        let w = (gen_state.wall_cells[row][word_col] >> shift) & 1 == 1;
+        let k = (gen_state.known[row][word_col] >> shift) & 1 == 1;
+        if c && w {
+            panic!("Cannot be both cell and wall at ({}, {})", col, row);
+        }
+        if !k && ((c && !w) || (!c && w)) {
+            panic!("Unspecified invalid state at ({}, {})", col, row);
+        }
+        let mut state = CellState::Wall;
+        if c && !w && k {
+            // It's known and it's a cell; check cells + fog for every player
+            // (expensive step since this is per-bit).
+
+            let mut opt_player_id = None;
+            for player_id in 0..self.num_players {
+                let player_state = &gen_state.player_states[player_id];
+                let pc = (player_state.cells[row][word_col] >> shift) & 1 == 1;
+                let pf = (player_state.fog[row][word_col] >> shift) & 1 == 1;
+                if pc && pf {
+                    panic!(
+                        "Player cell and player fog at ({}, {}) for player {}",
+                        col, row, player_id
+                    );
+                }
+                if pc {
+                    if let Some(other_player_id) = opt_player_id {
+                        panic!(
+                            "Cell ({}, {}) belongs to player {} and player {}!",
+                            col, row, other_player_id, player_id
+                        );
+                    }
+                    opt_player_id = Some(player_id);
+                }
+            }
+            state = CellState::Alive(opt_player_id);
+        } else {
+            // (B) other states
+            if !c && !w {
+                let stage = gen_state.dying_stage(row, word_col, shift);
+                state = if !k {
+                    CellState::Fog
+                } else if stage > 0 {
+                    CellState::Dying(gen_state.dying_owner(row, word_col, shift), stage)
+                } else {
+                    CellState::Dead
+                };
+            } else if !c && w {
+                state = CellState::Wall;
+            }
+        }
+        if let Some(player_id) = visibility {
+            let player_state = &gen_state.player_states[player_id];
+            let pc = (player_state.cells[row][word_col] >> shift) & 1 == 1;
+            let pf = (player_state.fog[row][word_col] >> shift) & 1 == 1;
+            if !k && pc {
+                panic!("Player can't have cells where unknown, at ({}, {})", col, row);
+            }
+            if w && pc {
+                panic!("Player can't have cells where wall, at ({}, {})", col, row);
+            }
+            if pf {
+                state = CellState::Fog;
+            }
+        }
+        state
+    }
+
+    /// Iterate over every non-dead cell in the universe for the current generation.
+    /// `visibility` is an optional player_id, allowing filtering based on fog.
+    /// Callback receives (col, row, cell_state).
+    pub fn each_non_dead_full(&self, visibility: Option<usize>, callback: &mut dyn FnMut(usize, usize, CellState)) {
         self.each_non_dead(self.region(), visibility, callback);
     }
 
@@ -1518,6 +2229,130 @@ impl Universe {
         Region::new(0, 0, self.width, self.height)
     }
 
+    /// Finds every place in the latest generation where the pattern formed by the 1 bits of
+    /// `pattern` occurs, after applying any of `transforms`. `mode` determines what counts as a
+    /// match, and only live cells count, whoever owns them. Matches may wrap around the edges of the
+    /// universe.
+    ///
+    /// When `visibility` is `Some(player_id)`, every cell that `mode` examines must be visible to
+    /// that player.
+    ///
+    /// Matches are returned in row-major order of their top-left corners. Where several
+    /// transforms produce the same pattern, only a match for the first of them is returned. If
+    /// `pattern` has no 1 bits, or the cells examined would not fit within the universe, nothing
+    /// is found.
+    ///
+    /// The comparisons are done a word at a time rather than cell by cell.
+    pub fn find_pattern(
+        &self,
+        pattern: &BitGrid,
+        transforms: &[Transform],
+        mode: MatchMode,
+        visibility: Option<usize>,
+    ) -> Vec<PatternMatch> {
+        let mut matches = vec![];
+        let bbox = match pattern.bounding_box() {
+            Some(bbox) => bbox,
+            None => return matches,
+        };
+        let margin = match mode {
+            MatchMode::Contains => 0,
+            MatchMode::Exact { margin } => margin,
+        };
+
+        // The window is the transformed pattern plus its margin. Each row of the window is split
+        // into words, and `care` has the bits that must equal the corresponding bits of `want`.
+        struct Window {
+            transform: Transform,
+            width:     usize,
+            height:    usize,
+            want:      Vec<Vec<u64>>,
+            care:      Vec<Vec<u64>>,
+        }
+        let mut windows: Vec<Window> = vec![];
+        for &transform in transforms {
+            let (pattern_width, pattern_height) = transform.size(bbox.width(), bbox.height());
+            let (width, height) = (pattern_width + 2 * margin, pattern_height + 2 * margin);
+            if width > self.width || height > self.height {
+                continue;
+            }
+            let width_in_words = (width - 1) / 64 + 1;
+            let mut want = vec![vec![0u64; width_in_words]; height];
+            pattern.each_set(|col, row| {
+                let (col, row) = (col - bbox.left() as usize, row - bbox.top() as usize);
+                let (col, row) = transform.apply(col, row, bbox.width(), bbox.height());
+                let (col, row) = (col + margin, row + margin);
+                want[row][col / 64] |= 1 << (63 - col % 64);
+            });
+            if windows.iter().any(|window| window.want == want) {
+                continue;
+            }
+            let care = match mode {
+                MatchMode::Contains => want.clone(),
+                MatchMode::Exact { .. } => {
+                    let care_row: Vec<u64> = (0..width_in_words)
+                        .map(|word_idx| u64::MAX << (64 - cmp::min(64, width - word_idx * 64)))
+                        .collect();
+                    vec![care_row; height]
+                }
+            };
+            windows.push(Window {
+                transform,
+                width,
+                height,
+                want,
+                care,
+            });
+        }
+
+        // live cells, and cells that can't be examined
+        let gen_state = &self.gen_states[self.state_index];
+        let mut alive = gen_state.cells.clone();
+        let mut hidden = gen_state.known.clone();
+        for row in 0..self.height {
+            for word_col in 0..self.width_in_words {
+                let mut hidden_word = !gen_state.known[row][word_col];
+                if let Some(player_id) = visibility {
+                    hidden_word |= gen_state.player_states[player_id].fog[row][word_col];
+                }
+                alive[row][word_col] &= !hidden_word;
+                hidden[row][word_col] = hidden_word;
+            }
+        }
+
+        for top in 0..self.height {
+            for left in 0..self.width {
+                'windows: for window in windows.iter() {
+                    for window_row in 0..window.height {
+                        let row = (top + window_row) % self.height;
+                        for (word_idx, (&want, &care)) in window.want[window_row]
+                            .iter()
+                            .zip(window.care[window_row].iter())
+                            .enumerate()
+                        {
+                            let col = (left + word_idx * 64) % self.width;
+                            if Universe::wrapped_word(&alive, col, row) & care != want
+                                || Universe::wrapped_word(&hidden, col, row) & care != 0
+                            {
+                                continue 'windows;
+                            }
+                        }
+                    }
+                    matches.push(PatternMatch {
+                        region:    Region::new(
+                            ((left + margin) % self.width) as isize,
+                            ((top + margin) % self.height) as isize,
+                            window.width - 2 * margin,
+                            window.height - 2 * margin,
+                        ),
+                        transform: window.transform,
+                    });
+                }
+            }
+        }
+        matches
+    }
+
     /// Copies from `src` BitGrid to this GenState as the player specified by `opt_player_id`,
     /// unless `opt_player_id` is `None`.
     ///
@@ -1525,21 +2360,662 @@ impl Universe {
     /// is specified, the specified player's writable region is used, and 2) the latest generation
     /// is written to.
     ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InsufficientEnergy` error if the player can't afford every cell that
+    /// would be placed, in which case nothing is copied.
+    ///
+    /// # Panics
+    ///
     /// Panics if `opt_player_id` is `Some(player_id)` and `player_id` is out of range.
-    pub fn copy_from_bit_grid(&mut self, src: &BitGrid, dst_region: Region, opt_player_id: Option<usize>) {
+    pub fn copy_from_bit_grid(
+        &mut self,
+        src: &BitGrid,
+        dst_region: Region,
+        opt_player_id: Option<usize>,
+    ) -> ConwayResult<()> {
         let region;
         if let Some(player_id) = opt_player_id {
             if let Some(_region) = dst_region.intersection(self.player_writable[player_id]) {
                 region = _region;
             } else {
                 // nothing to do because `dst_region` completely outside of player's writable region
-                return;
+                return Ok(());
             }
         } else {
             region = dst_region;
         }
+        let opt_budgeted_player_id = opt_player_id.filter(|&player_id| self.energy_budgets[player_id].is_some());
         let latest_gen = &mut self.gen_states[self.state_index];
-        latest_gen.copy_from_bit_grid(src, region, opt_player_id);
+        if let Some(player_id) = opt_budgeted_player_id {
+            // the cost is only known after copying, so back up the GenState in case it's too much
+            let backup = latest_gen.clone();
+            let cells_before = latest_gen.player_states[player_id].cells.count_ones();
+            latest_gen.copy_from_bit_grid(src, region, opt_player_id);
+            let cells_placed = latest_gen.player_states[player_id].cells.count_ones() - cells_before;
+            if let Err(e) = self.spend_energy(player_id, cells_placed) {
+                self.gen_states[self.state_index] = backup;
+                return Err(e);
+            }
+        } else {
+            latest_gen.copy_from_bit_grid(src, region, opt_player_id);
+        }
+        self.mark_edited(region);
+        Ok(())
+    }
+
+    /// Kills the live and dying cells in `region` of the latest generation, as the player specified
+    /// by `opt_player_id`, unless `opt_player_id` is `None`. These are the cells that `cut` would
+    /// remove, so a player only kills the cells they can see within their writable region that no
+    /// other player owns. Walls are left alone. `region` wraps and is truncated like for `extract`.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if `opt_player_id` is `Some(player_id)` and
+    /// `player_id` is out of range.
+    pub fn clear_region(&mut self, region: Region, opt_player_id: Option<usize>) -> ConwayResult<()> {
+        let region = self.wrapped_region(region);
+        for (col, row, _) in self.removable_cells(region, opt_player_id)? {
+            self.set_unchecked(col, row, CellState::Dead);
+        }
+        Ok(())
+    }
+
+    /// Copies the live cells in `region` of the latest generation. The result is a `BitGrid` of the
+    /// live cells whose top-left cell is the top-left cell of `region`, like the `src` of
+    /// `copy_from_bit_grid`, along with a `BitGrid` of the cells owned by each player, indexed by
+    /// player_id.
+    ///
+    /// `region` may extend past the edges of the universe, in which case it wraps around. A region
+    /// wider or taller than the universe is truncated. When `visibility` is `Some(player_id)`,
+    /// cells in that player's fog are dead in the result.
+    pub fn extract(&self, region: Region, visibility: Option<usize>) -> (BitGrid, Vec<BitGrid>) {
+        let region = self.wrapped_region(region);
+        let width_in_words = (region.width() - 1) / 64 + 1;
+        let mut cells = BitGrid::new(width_in_words, region.height());
+        let mut owners = vec![BitGrid::new(width_in_words, region.height()); self.num_players];
+        let gen_state = &self.gen_states[self.state_index];
+        for row_offset in 0..region.height() {
+            let row = (region.top() as usize + row_offset) % self.height;
+            for word_idx in 0..width_in_words {
+                let col = (region.left() as usize + word_idx * 64) % self.width;
+                let num_cells = cmp::min(64, region.width() - word_idx * 64);
+                let mut visible = u64::MAX << (64 - num_cells);
+                visible &= Universe::wrapped_word(&gen_state.known, col, row);
+                if let Some(player_id) = visibility {
+                    visible &= !Universe::wrapped_word(&gen_state.player_states[player_id].fog, col, row);
+                }
+                cells[row_offset][word_idx] = Universe::wrapped_word(&gen_state.cells, col, row) & visible;
+                for (player_id, player_cells) in owners.iter_mut().enumerate() {
+                    player_cells[row_offset][word_idx] =
+                        Universe::wrapped_word(&gen_state.player_states[player_id].cells, col, row) & visible;
+                }
+            }
+        }
+        (cells, owners)
+    }
+
+    /// Returns the cells in `region` of the latest generation as the contents of an RLE file, with
+    /// a header line giving the size of `region` and the rule. Unlike `extract`, this keeps walls
+    /// and dying cells. `region` wraps and is truncated like for `extract`. When `visibility` is
+    /// `Some(player_id)`, cells in that player's fog are written as fog.
+    pub fn to_pattern_file(&self, region: Region, visibility: Option<usize>) -> PatternFile {
+        let region = self.wrapped_region(region);
+        let view = RegionView {
+            gen_state:  &self.gen_states[self.state_index],
+            region,
+            uni_width:  self.width,
+            uni_height: self.height,
+        };
+        PatternFile {
+            comment_lines: vec![],
+            header_line:   HeaderLine {
+                x:    region.width(),
+                y:    region.height(),
+                rule: Some(self.rule.to_string()),
+            },
+            pattern:       view.to_pattern(visibility),
+        }
+    }
+
+    /// Like `extract`, but also removes the extracted cells from the latest generation. If
+    /// `opt_player_id` is `None`, every live and dying cell in `region` is removed. Otherwise only
+    /// the cells that player may write are extracted and removed: those they can see within their
+    /// writable region that no other player owns. Walls are never removed. Dying cells are removed
+    /// too, but are not in the result, which only has live cells.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if `opt_player_id` is `Some(player_id)` and
+    /// `player_id` is out of range.
+    pub fn cut(&mut self, region: Region, opt_player_id: Option<usize>) -> ConwayResult<(BitGrid, Vec<BitGrid>)> {
+        let region = self.wrapped_region(region);
+        let removed = self.removable_cells(region, opt_player_id)?;
+        let width_in_words = (region.width() - 1) / 64 + 1;
+        let mut cells = BitGrid::new(width_in_words, region.height());
+        let mut owners = vec![BitGrid::new(width_in_words, region.height()); self.num_players];
+        for (col, row, state) in removed {
+            if let CellState::Alive(opt_owner) = state {
+                let col_offset = (col as isize - region.left()).rem_euclid(self.width as isize) as usize;
+                let row_offset = (row as isize - region.top()).rem_euclid(self.height as isize) as usize;
+                let (word_idx, mask) = (col_offset / 64, 1 << (63 - col_offset % 64));
+                cells.modify_bits_in_word(row_offset, word_idx, mask, BitOperation::Set);
+                if let Some(owner) = opt_owner {
+                    owners[owner].modify_bits_in_word(row_offset, word_idx, mask, BitOperation::Set);
+                }
+            }
+            self.set_unchecked(col, row, CellState::Dead);
+        }
+        Ok((cells, owners))
+    }
+
+    /// Moves the cells that `cut` would remove from `region` so that its top-left corner is at
+    /// (`dst_left`, `dst_top`), keeping their owners and dying stages. The destination may
+    /// overlap `region`, and wraps around the edges of the universe. Cells already at the
+    /// destination are kept unless a moved cell lands on them.
+    ///
+    /// If `opt_player_id` is `None`, moved cells landing on walls or unknown cells disappear.
+    /// Moving cells never costs energy, since no cells are added.
+    ///
+    /// # Errors
+    ///
+    /// * It is a `ConwayError::InvalidData` error if `opt_player_id` is `Some(player_id)` and
+    ///   `player_id` is out of range.
+    /// * It is a `ConwayError::AccessDenied` error if `opt_player_id` is `Some(player_id)` and a
+    ///   moved cell would land outside that player's writable region, or on a wall, on fog, or on
+    ///   a cell owned by someone else. Nothing is moved in that case.
+    pub fn move_region(
+        &mut self,
+        region: Region,
+        dst_left: isize,
+        dst_top: isize,
+        opt_player_id: Option<usize>,
+    ) -> ConwayResult<()> {
+        let region = self.wrapped_region(region);
+        let moved = self.removable_cells(region, opt_player_id)?;
+        let (width, height) = (self.width as isize, self.height as isize);
+        let destination = |col: usize, row: usize| {
+            (
+                (col as isize + dst_left - region.left()).rem_euclid(width) as usize,
+                (row as isize + dst_top - region.top()).rem_euclid(height) as usize,
+            )
+        };
+        if let Some(player_id) = opt_player_id {
+            for &(col, row, _) in moved.iter() {
+                let (dst_col, dst_row) = destination(col, row);
+                let state = self
+                    .cells_in(Region::new(dst_col as isize, dst_row as isize, 1, 1), Some(player_id))
+                    .next()
+                    .map(|(_, _, state)| state)
+                    .unwrap_or(CellState::Dead);
+                let is_blocked = match state {
+                    CellState::Alive(opt_owner) | CellState::Dying(opt_owner, _) => {
+                        matches!(opt_owner, Some(owner) if owner != player_id)
+                    }
+                    CellState::Dead => false,
+                    CellState::Wall | CellState::Fog => true,
+                };
+                if is_blocked || !self.writable(dst_col, dst_row, player_id)? {
+                    return Err(ConwayError::AccessDenied {
+                        reason: format!(
+                            "player {} cannot move a cell to col={}, row={}",
+                            player_id, dst_col, dst_row
+                        ),
+                    });
+                }
+            }
+        }
+        for &(col, row, _) in moved.iter() {
+            self.set_unchecked(col, row, CellState::Dead);
+        }
+        for (col, row, state) in moved {
+            let (dst_col, dst_row) = destination(col, row);
+            let word_col = dst_col / 64;
+            let mask = 1 << (63 - dst_col % 64);
+            let gen_state = &self.gen_states[self.state_index];
+            if gen_state.wall_cells[dst_row][word_col] & mask == 0 && gen_state.known[dst_row][word_col] & mask != 0 {
+                self.set_unchecked(dst_col, dst_row, state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the size of the universe to `new_width` by `new_height` cells, keeping the part of
+    /// the universe given by `anchor` in place. Space added is filled with dead cells, and cells
+    /// that no longer fit are discarded. See `crop` for what is preserved.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if `new_width` or `new_height` are zero. See `crop`
+    /// for other errors.
+    pub fn resize(&mut self, new_width: usize, new_height: usize, anchor: Anchor) -> ConwayResult<()> {
+        use Anchor::*;
+        if new_width == 0 || new_height == 0 {
+            return Err(ConwayError::InvalidData {
+                reason: "Width and height must be positive".to_owned(),
+            });
+        }
+        let offset = |old: usize, new: usize, start: bool, end: bool| -> isize {
+            let grown = new as isize - old as isize;
+            if start {
+                0
+            } else if end {
+                -grown
+            } else {
+                -grown / 2
+            }
+        };
+        let left = offset(
+            self.width,
+            new_width,
+            matches!(anchor, TopLeft | Left | BottomLeft),
+            matches!(anchor, TopRight | Right | BottomRight),
+        );
+        let top = offset(
+            self.height,
+            new_height,
+            matches!(anchor, TopLeft | Top | TopRight),
+            matches!(anchor, BottomLeft | Bottom | BottomRight),
+        );
+        self.crop(Region::new(left, top, new_width, new_height))
+    }
+
+    /// Makes `region` the whole universe, with its top-left corner becoming (0, 0). `region` may
+    /// extend past the edges of the universe, in which case the space added is filled with dead
+    /// cells rather than wrapping around.
+    ///
+    /// Every generation in the history is cropped, so the generation number and history are
+    /// preserved, along with walls, ownership, dying cells, fog, rule zones, energy, and cell ages
+    /// and activity counts. Each player's writable region is moved along with the cells, and
+    /// shrunk to fit within the universe. Space added is fogged for every player outside their
+    /// writable region. Because coordinates change, `changed_since` reports every word as changed.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error, and nothing is changed, if:
+    /// * `region`'s width is not a multiple of 64.
+    /// * the neighborhood of the rule, or of the rule of any rule zone, would not fit.
+    /// * the fog circle (`2 * fog_radius - 1` cells across) would not fit.
+    /// * any player's writable region would be entirely outside the universe.
+    pub fn crop(&mut self, region: Region) -> ConwayResult<()> {
+        use ConwayError::*;
+        let (new_width, new_height) = (region.width(), region.height());
+        if new_width % 64 != 0 {
+            return Err(InvalidData {
+                reason: "Width must be a multiple of 64".to_owned(),
+            });
+        }
+        Universe::check_rule_fits(&self.rule, new_width, new_height)?;
+        for (_, rule) in self.rule_zones.iter() {
+            Universe::check_rule_fits(rule, new_width, new_height)?;
+        }
+        Universe::check_fog_fits(self.fog_radius, new_width, new_height)?;
+        let new_region = Region::new(0, 0, new_width, new_height);
+        let mut new_player_writable = vec![];
+        for (player_id, writable) in self.player_writable.iter().enumerate() {
+            let moved = Region::new(
+                writable.left() - region.left(),
+                writable.top() - region.top(),
+                writable.width(),
+                writable.height(),
+            );
+            match moved.intersection(new_region) {
+                Some(moved) => new_player_writable.push(moved),
+                None => {
+                    return Err(InvalidData {
+                        reason: format!("Player {}'s writable region would be outside the universe", player_id),
+                    });
+                }
+            }
+        }
+
+        // 1 bits where there were cells before
+        let mut existing = BitGrid::new(self.width_in_words, self.height);
+        existing.modify_region(self.region(), BitOperation::Set);
+        let existing = Universe::crop_grid(&existing, region);
+
+        for gen_state in self.gen_states.iter_mut() {
+            gen_state.cells = Universe::crop_grid(&gen_state.cells, region);
+            gen_state.wall_cells = Universe::crop_grid(&gen_state.wall_cells, region);
+            gen_state.known = Universe::crop_grid(&gen_state.known, region);
+            if gen_state.gen_or_none.is_some() {
+                // the space added is known to be dead
+                for (known_row, existing_row) in gen_state.known.0.iter_mut().zip(existing.0.iter()) {
+                    for (known_word, existing_word) in known_row.iter_mut().zip(existing_row.iter()) {
+                        *known_word |= !existing_word;
+                    }
+                }
+            }
+            for plane in gen_state.decay.iter_mut() {
+                *plane = Universe::crop_grid(plane, region);
+            }
+            for (player_id, player) in gen_state.player_states.iter_mut().enumerate() {
+                player.cells = Universe::crop_grid(&player.cells, region);
+                if let Some(ref mut dying) = player.dying {
+                    *dying = Universe::crop_grid(dying, region);
+                }
+                let mut fog = Universe::crop_grid(&player.fog, region);
+                for (fog_row, existing_row) in fog.0.iter_mut().zip(existing.0.iter()) {
+                    for (fog_word, existing_word) in fog_row.iter_mut().zip(existing_row.iter()) {
+                        *fog_word |= !existing_word;
+                    }
+                }
+                let mut added_writable = BitGrid::new(new_width / 64, new_height);
+                added_writable.modify_region(new_player_writable[player_id], BitOperation::Set);
+                for (fog_row, (added_row, existing_row)) in
+                    fog.0.iter_mut().zip(added_writable.0.iter().zip(existing.0.iter()))
+                {
+                    for (fog_word, (added_word, existing_word)) in
+                        fog_row.iter_mut().zip(added_row.iter().zip(existing_row.iter()))
+                    {
+                        *fog_word &= !(added_word & !existing_word);
+                    }
+                }
+                player.fog = fog;
+            }
+        }
+        for (mask, _) in self.rule_zones.iter_mut() {
+            *mask = Universe::crop_grid(mask, region);
+        }
+        if let Some(ref mut tracker) = self.activity {
+            tracker.crop(region);
+        }
+        for changes in self.changes.iter_mut() {
+            changes.stepped = BitGrid::new(new_width / 64, new_height);
+            changes.stepped.modify_region(new_region, BitOperation::Set);
+            changes.edited = changes.stepped.clone();
+        }
+        self.width = new_width;
+        self.height = new_height;
+        self.width_in_words = new_width / 64;
+        self.player_writable = new_player_writable;
+        Ok(())
+    }
+
+    /// Returns the part of `grid`, which must be the size of the universe, within `region`, with
+    /// the top-left corner of `region` at (0, 0). Cells outside of `grid` are 0. The width of
+    /// `region` must be a multiple of 64.
+    fn crop_grid(grid: &BitGrid, region: Region) -> BitGrid {
+        let mut cropped = BitGrid::new(region.width() / 64, region.height());
+        let word_at = |row: isize, word_col: isize| -> u64 {
+            if row < 0 || row >= grid.height() as isize || word_col < 0 || word_col >= grid.width_in_words() as isize {
+                0
+            } else {
+                grid[row as usize][word_col as usize]
+            }
+        };
+        let (word_offset, shift) = (region.left().div_euclid(64), region.left().rem_euclid(64));
+        for (row_offset, cropped_row) in cropped.0.iter_mut().enumerate() {
+            let row = region.top() + row_offset as isize;
+            for (word_idx, cropped_word) in cropped_row.iter_mut().enumerate() {
+                let word_col = word_offset + word_idx as isize;
+                *cropped_word = word_at(row, word_col) << shift;
+                if shift != 0 {
+                    *cropped_word |= word_at(row, word_col + 1) >> (64 - shift);
+                }
+            }
+        }
+        cropped
+    }
+
+    /// Adds the live cells of a random soup to the latest generation, in the soup's region. If the
+    /// soup belongs to a player, only the part within that player's writable region is added,
+    /// and it costs energy like `copy_from_bit_grid`. Walls are preserved.
+    ///
+    /// # Errors
+    ///
+    /// * It is a `ConwayError::InvalidData` error if the soup's region is not within the
+    ///   universe, its player is out of range, or its density or symmetry is invalid (see
+    ///   `Soup::to_bit_grid`).
+    /// * It is a `ConwayError::InsufficientEnergy` error if the player can't afford the soup, in
+    ///   which case nothing is added.
+    pub fn random_soup(&mut self, soup: &Soup) -> ConwayResult<()> {
+        use ConwayError::*;
+        let region = soup.region();
+        if region.intersection(self.region()) != Some(region) {
+            return Err(InvalidData {
+                reason: format!("Soup region {:?} is not within the universe", region),
+            });
+        }
+        if let Some(player_id) = soup.player_id() {
+            if player_id >= self.num_players {
+                return Err(InvalidData {
+                    reason: format!("Soup player ID {} is out of range", player_id),
+                });
+            }
+        }
+        let grid = soup.to_bit_grid()?;
+
+        // `copy_from_bit_grid` clips to the player's writable region, but expects the grid to
+        // start at the top-left of the clipped region, so do the clipping here.
+        let dst_region = match soup.player_id() {
+            Some(player_id) => match region.intersection(self.player_writable[player_id]) {
+                Some(dst_region) => dst_region,
+                None => return Ok(()),
+            },
+            None => region,
+        };
+        let (skip_cols, skip_rows) = (
+            (dst_region.left() - region.left()) as usize,
+            (dst_region.top() - region.top()) as usize,
+        );
+        let mut clipped = BitGrid::new((dst_region.width() - 1) / 64 + 1, dst_region.height());
+        grid.each_set(|col, row| {
+            if dst_region.contains(region.left() + col as isize, region.top() + row as isize) {
+                let (col, row) = (col - skip_cols, row - skip_rows);
+                clipped.modify_bits_in_word(row, col / 64, 1 << (63 - col % 64), BitOperation::Set);
+            }
+        });
+        self.copy_from_bit_grid(&clipped, dst_region, soup.player_id())
+    }
+
+    /// Starts tracking the age of each live cell, and how many times each cell was born or died
+    /// within the last `window` generations, or stops tracking if `opt_window` is `None`. See
+    /// `ages` and `activity`. Tracking starts over each time this is called, with every live cell
+    /// being one generation old.
+    ///
+    /// The ages and counts are updated whenever the latest generation advances, with `next` or
+    /// `apply`. When `apply` skips generations, it counts as one generation. Cells placed or
+    /// removed by edits are not counted as activity.
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error if `opt_window` is `Some(0)`.
+    pub fn set_activity_window(&mut self, opt_window: Option<usize>) -> ConwayResult<()> {
+        self.activity = match opt_window {
+            Some(0) => {
+                return Err(ConwayError::InvalidData {
+                    reason: "Activity window must be positive".to_owned(),
+                });
+            }
+            Some(window) => Some(ActivityTracker::new(&self.gen_states[self.state_index].cells, window)),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Returns how many generations each cell in `region` has been alive, counting the latest
+    /// generation, or `None` if activity isn't being tracked (see `set_activity_window`). Dead
+    /// cells are 0. `region` wraps and is truncated like for `cells_in`. When `visibility` is
+    /// `Some(player_id)`, cells in that player's fog are 0.
+    pub fn ages(&self, region: Region, visibility: Option<usize>) -> Option<CellCounts> {
+        let tracker = self.activity.as_ref()?;
+        Some(self.cell_counts(region, visibility, |col, row, alive| tracker.age(col, row, alive)))
+    }
+
+    /// Returns how many times each cell in `region` was born or died within the activity window,
+    /// or `None` if activity isn't being tracked (see `set_activity_window`). `region` wraps and
+    /// is truncated like for `cells_in`. When `visibility` is `Some(player_id)`, cells in that
+    /// player's fog are 0.
+    pub fn activity(&self, region: Region, visibility: Option<usize>) -> Option<CellCounts> {
+        let tracker = self.activity.as_ref()?;
+        Some(self.cell_counts(region, visibility, |col, row, _| tracker.count(col, row)))
+    }
+
+    /// Calls `count(col, row, is_alive)` for each visible cell in `region`, and returns the
+    /// results.
+    fn cell_counts<F: Fn(usize, usize, bool) -> u32>(
+        &self,
+        region: Region,
+        visibility: Option<usize>,
+        count: F,
+    ) -> CellCounts {
+        let region = self.wrapped_region(region);
+        let gen_state = &self.gen_states[self.state_index];
+        let mut counts = Vec::with_capacity(region.width() * region.height());
+        for row_offset in 0..region.height() {
+            let row = (region.top() as usize + row_offset) % self.height;
+            for col_offset in 0..region.width() {
+                let col = (region.left() as usize + col_offset) % self.width;
+                let (word_col, mask) = (col / 64, 1 << (63 - col % 64));
+                let mut visible = gen_state.known[row][word_col] & mask != 0;
+                if let Some(player_id) = visibility {
+                    visible &= gen_state.player_states[player_id].fog[row][word_col] & mask == 0;
+                }
+                counts.push(if visible {
+                    count(col, row, gen_state.cells[row][word_col] & mask != 0)
+                } else {
+                    0
+                });
+            }
+        }
+        CellCounts::new(region.width(), region.height(), counts)
+    }
+
+    /// Simulates what would happen if `edits` were made to the latest generation, without changing
+    /// this universe. The edits are made in order to a copy of the latest generation, which is
+    /// then run for `generations` generations. The copy is returned as a new universe, whose
+    /// latest generation is the result; it has no earlier generations and doesn't track activity.
+    ///
+    /// When `visibility` is `Some(player_id)`, the edits are made as that player -- with `toggle`,
+    /// `set`, `copy_from_bit_grid` and `clear_region` -- and the cells in the player's fog, including walls, are
+    /// treated as dead. The result therefore only depends on what the player can see, and should
+    /// only be looked at with the same visibility, since the fog moves as the copy runs. When
+    /// `visibility` is `None`, the edits are unchecked, and the result is exact.
+    ///
+    /// # Errors
+    ///
+    /// Any error from an edit is returned, as described for `toggle` and `copy_from_bit_grid`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `visibility` is `Some(player_id)` and `player_id` is out of range, or under the
+    /// same conditions as the edit methods.
+    pub fn preview(&self, edits: &[Edit], generations: usize, visibility: Option<usize>) -> ConwayResult<Universe> {
+        let mut fork = self.fork(visibility);
+        for edit in edits {
+            match (edit, visibility) {
+                (&Edit::Toggle { col, row }, Some(player_id)) => {
+                    fork.toggle(col, row, player_id)?;
+                }
+                (&Edit::Toggle { col, row }, None) => {
+                    fork.toggle_unchecked(col, row, None);
+                }
+                (&Edit::Set { col, row, state }, Some(player_id)) => fork.set(col, row, state, player_id),
+                (&Edit::Set { col, row, state }, None) => fork.set_unchecked(col, row, state),
+                (Edit::Paste { src, region }, opt_player_id) => fork.copy_from_bit_grid(src, *region, opt_player_id)?,
+                (&Edit::Clear { region }, opt_player_id) => fork.clear_region(region, opt_player_id)?,
+            }
+        }
+        for _ in 0..generations {
+            fork.next();
+        }
+        Ok(fork)
+    }
+
+    /// Copies the latest generation into a new universe with room for one more generation. When
+    /// `visibility` is `Some(player_id)`, the cells in that player's fog are cleared in the copy.
+    fn fork(&self, visibility: Option<usize>) -> Universe {
+        let mut latest = self.gen_states[self.state_index].clone();
+        if let Some(player_id) = visibility {
+            let fog = latest.player_states[player_id].fog.clone();
+            latest.clear_masked(&fog);
+        }
+        let mut spare = latest.clone();
+        spare.gen_or_none = None;
+        let changes = GenChanges {
+            since:   self.generation,
+            stepped: BitGrid::new(self.width_in_words, self.height),
+            edited:  BitGrid::new(self.width_in_words, self.height),
+        };
+        Universe {
+            width:           self.width,
+            height:          self.height,
+            width_in_words:  self.width_in_words,
+            generation:      self.generation,
+            num_players:     self.num_players,
+            state_index:     0,
+            gen_states:      vec![latest, spare],
+            player_writable: self.player_writable.clone(),
+            fog_radius:      self.fog_radius,
+            fog_circle:      self.fog_circle.clone(),
+            rule:            self.rule.clone(),
+            rule_zones:      self.rule_zones.clone(),
+            energy_budgets:  self.energy_budgets.clone(),
+            energy:          self.energy.clone(),
+            changes:         vec![changes.clone(), changes],
+            activity:        None,
+        }
+    }
+
+    /// Sets or removes the energy budget for a player, and resets their energy to
+    /// `budget.initial` (but no more than `budget.max`).
+    ///
+    /// # Errors
+    ///
+    /// It is a `ConwayError::InvalidData` error to pass in an invalid player_id.
+    pub fn set_energy_budget(&mut self, player_id: usize, opt_budget: Option<EnergyBudget>) -> ConwayResult<()> {
+        if player_id >= self.num_players {
+            return Err(ConwayError::InvalidData {
+                reason: format!("Unexpected player_id {}", player_id),
+            });
+        }
+        self.energy_budgets[player_id] = opt_budget;
+        self.energy[player_id] = opt_budget.map_or(0, |budget| cmp::min(budget.initial, budget.max));
+        Ok(())
+    }
+
+    /// Returns the current energy of a player, or `None` if the player has no energy budget.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `player_id` is out of range.
+    pub fn energy(&self, player_id: usize) -> Option<u32> {
+        self.energy_budgets[player_id].map(|_| self.energy[player_id])
+    }
+
+    /// Deducts the cost of placing `num_cells` cells from a player's energy, if the player has an
+    /// energy budget.
+    fn spend_energy(&mut self, player_id: usize, num_cells: usize) -> ConwayResult<()> {
+        if let Some(budget) = self.energy_budgets[player_id] {
+            let cost = (num_cells as u32).saturating_mul(budget.cell_cost);
+            if cost > self.energy[player_id] {
+                return Err(ConwayError::InsufficientEnergy {
+                    player_id,
+                    cost,
+                    energy: self.energy[player_id],
+                });
+            }
+            self.energy[player_id] -= cost;
+        }
+        Ok(())
+    }
+
+    /// Regenerates every player's energy for `generations` generations, based on the cells they
+    /// own in the latest generation.
+    fn regenerate_energy(&mut self, generations: usize) {
+        let gen_state = &self.gen_states[self.state_index];
+        for player_id in 0..self.num_players {
+            if let Some(budget) = self.energy_budgets[player_id] {
+                let live_cells = gen_state.player_states[player_id].cells.count_ones() as u32;
+                let regen = budget
+                    .regen_per_live_cell
+                    .saturating_mul(live_cells)
+                    .saturating_add(budget.regen_per_generation)
+                    .saturating_mul(generations as u32);
+                self.energy[player_id] = cmp::min(self.energy[player_id].saturating_add(regen), budget.max);
+            }
+        }
     }
 
     /// Utility function to mutably borrow two separate GenStates from self.gen_states, specified
@@ -1584,14 +3060,16 @@ impl Universe {
     ///     - there is already a greater generation present, or
     ///     - the base generation of this diff (that is, `diff.gen0`) could not be found.
     ///       A base generation of 0 is a special case -- it is always found.
-    /// * `Err(InvalidData{reason: msg})` if the update is invalid, either because:
-    ///     - the difference between `diff.gen0` and `diff.gen1` is too large. Since the server
-    ///     knows the client's buffer size, this should not happen. In this case, no updates are
-    ///     made to the `Universe`. A base generation of 0 is a special case -- the difference is
-    ///     never too large.
-    ///     - the RLE pattern is invalid. NOTE: in this case, the pattern is only partially written
-    ///     and all other updates (e.g., increasing the generation count) are made as if it were
-    ///     valid.
+    /// * `Err(GenerationMismatch{..})` if the difference between `diff.gen0` and `diff.gen1` is
+    ///   too large. Since the server knows the client's buffer size, this should not happen. A
+    ///   base generation of 0 is a special case -- the difference is never too large.
+    /// * `Err(RleSyntax{..})` if the RLE pattern is malformed or has characters that aren't cell
+    ///   states. NOTE: in the latter case, the pattern is only partially written and all other
+    ///   updates (e.g., increasing the generation count) are made as if it were valid.
+    /// * `Err(OutOfBounds{..})` if the RLE pattern doesn't fit in the universe. The position is
+    ///   that of the bottom-right corner of the pattern.
+    ///
+    /// Except as noted, no updates are made to the `Universe` when there is an error.
     ///
     /// # Panics
     ///
@@ -1609,7 +3087,9 @@ impl Universe {
         // if diff too large, return Err(...)
         let gen_state_len = self.gen_states.len();
         if diff.gen0 > 0 && diff.gen1 - diff.gen0 >= gen_state_len {
-            return Err(InvalidData {
+            return Err(GenerationMismatch {
+                gen0:   diff.gen0,
+                gen1:   diff.gen1,
                 reason: format!(
                     "diff is across too many generations to be applied: {} >= {}",
                     diff.gen1 - diff.gen0,
@@ -1617,6 +3097,15 @@ impl Universe {
                 ),
             });
         }
+        let (pattern_width, pattern_height) = diff.pattern.calc_size()?;
+        if pattern_width > self.width || pattern_height > self.height {
+            return Err(OutOfBounds {
+                col:    pattern_width - 1,
+                row:    pattern_height - 1,
+                width:  self.width,
+                height: self.height,
+            });
+        }
 
         // 1) If incremental update, find the gen0 in our gen_states; if not found, return
         //    Ok(None).
@@ -1649,6 +3138,10 @@ impl Universe {
             return Ok(None);
         }
 
+        // remember the latest generation, so that we can record what changed relative to it
+        let prev_state_index = self.state_index;
+        let prev_gen_known = self.gen_states[prev_state_index].gen_or_none.is_some();
+
         // 3) make room for the new gen_state (make room in the circular buffer)
         for i in 0..self.gen_states.len() {
             if let Some(gen) = self.gen_states[i].gen_or_none {
@@ -1677,48 +3170,201 @@ impl Universe {
 
         // 5) update self.generation, self.state_index, and self.gen_states[gen1_idx].gen_or_none
         let new_gen = diff.gen1;
+        let generations_advanced = new_gen.saturating_sub(self.generation);
         self.generation = new_gen;
         self.state_index = gen1_idx;
         self.gen_states[gen1_idx].gen_or_none = Some(new_gen);
 
         // 6) apply the diff!
         // TODO: wrap the error message rather than just passing it through
-        diff.pattern.to_grid(&mut self.gen_states[gen1_idx], visibility)?;
+        let result = diff.pattern.to_grid(&mut self.gen_states[gen1_idx], visibility);
+        let opt_prev_state_index = Some(prev_state_index).filter(|&i| i != gen1_idx && prev_gen_known);
+        self.record_changes(opt_prev_state_index);
+        self.record_activity(opt_prev_state_index);
+        result?;
+
+        // 7) energy regenerates as if every generation in between had the cells of the new one
+        self.regenerate_energy(generations_advanced);
 
         Ok(Some(new_gen))
     }
 
-    /// If it's possible to generate a diff between the GenStates specified by `gen0` and `gen1`, do
-    /// so. Otherwise, return `None`.
+    /// Generates a diff between the GenStates specified by `gen0` and `gen1`. A `gen0` of 0 means
+    /// the diff is from the beginning of time, so it contains all of `gen1`.
     ///
     /// The optional `visibility` specifies the player this is viewed as.
     ///
+    /// # Errors
+    ///
+    /// * It is a `ConwayError::GenerationMismatch` error if `gen1` is newer than the latest
+    ///   generation.
+    /// * It is a `ConwayError::HistoryExhausted` error if `gen0` (unless it is 0) or `gen1` is no
+    ///   longer in the history, in which case a diff from generation 0 can be sent instead.
+    ///
     /// # Panics
     ///
     /// * Panics if `gen0` >= `gen1`.
     /// * Panics if `visibility` is out of range.
-    pub fn diff(&self, gen0: usize, gen1: usize, visibility: Option<usize>) -> Option<GenStateDiff> {
+    pub fn diff(&self, gen0: usize, gen1: usize, visibility: Option<usize>) -> ConwayResult<GenStateDiff> {
         assert!(gen0 < gen1, "expected gen0 < gen1, but {} >= {}", gen0, gen1);
-        let mut opt_genstate0 = None;
-        let mut opt_genstate1 = None;
-        for gen_idx in 0..self.gen_states.len() {
-            let gs = &self.gen_states[gen_idx];
-            if let Some(gen) = gs.gen_or_none {
-                if gen == gen0 {
-                    opt_genstate0 = Some(gs);
-                } else if gen == gen1 {
-                    opt_genstate1 = Some(gs);
+        if gen1 > self.generation {
+            return Err(ConwayError::GenerationMismatch {
+                gen0,
+                gen1,
+                reason: format!("the latest generation is {}", self.generation),
+            });
+        }
+        let find = |gen: usize| -> ConwayResult<&GenState> {
+            let mut oldest = self.generation;
+            for gs in self.gen_states.iter() {
+                match gs.gen_or_none {
+                    Some(gs_gen) if gs_gen == gen => return Ok(gs),
+                    Some(gs_gen) => oldest = cmp::min(oldest, gs_gen),
+                    None => {}
                 }
             }
+            Err(ConwayError::HistoryExhausted {
+                generation: gen,
+                oldest,
+            })
+        };
+        let genstate1 = find(gen1)?;
+        if gen0 == 0 {
+            let pattern = genstate1.to_pattern(visibility);
+            Ok(GenStateDiff { gen0, gen1, pattern })
+        } else {
+            Ok(find(gen0)?.diff(genstate1, visibility))
+        }
+    }
+
+    /// Returns the words of the universe that may have changed since `gen` was the latest
+    /// generation, as a `BitGrid` the size of the universe in which each 64-cell word is either
+    /// all ones (changed) or zero. This covers `next`, `apply`, and edits such as `toggle`, `set`
+    /// and `copy_from_bit_grid`. Edits to generation `gen` itself are always included, even if
+    /// they were made before the caller last looked at it.
+    ///
+    /// Returns `None` if `gen` is newer than the latest generation or is no longer in the
+    /// history, in which case anything may have changed.
+    pub fn changed_since(&self, gen: usize) -> Option<BitGrid> {
+        if gen > self.generation {
+            return None;
         }
-        if gen0 == 0 && opt_genstate1.is_some() {
-            let pattern = opt_genstate1.unwrap().to_pattern(visibility);
-            Some(GenStateDiff { gen0, gen1, pattern })
+        let mut changed = BitGrid::new(self.width_in_words, self.height);
+        let mut state_index = self.state_index;
+        loop {
+            let changes = &self.changes[state_index];
+            Universe::or_words(&mut changed, &changes.edited);
+            if self.gen_states[state_index].gen_or_none? == gen {
+                return Some(changed);
+            }
+            Universe::or_words(&mut changed, &changes.stepped);
+            if changes.since < gen {
+                return None; // `gen` was skipped over, so we can't tell what changed since then
+            }
+            state_index = self
+                .gen_states
+                .iter()
+                .position(|gen_state| gen_state.gen_or_none == Some(changes.since))?;
+        }
+    }
+
+    /// Records which words of the latest generation changed relative to the GenState at
+    /// `opt_prev_state_index`, for `changed_since`. If `None`, everything may have changed.
+    fn record_changes(&mut self, opt_prev_state_index: Option<usize>) {
+        let region = self.region();
+        let changes = &mut self.changes[self.state_index];
+        changes.edited.clear();
+        if let Some(prev_state_index) = opt_prev_state_index {
+            let prev_gen_state = &self.gen_states[prev_state_index];
+            prev_gen_state.changed_words(&self.gen_states[self.state_index], &mut changes.stepped);
+            changes.since = prev_gen_state.gen_or_none.unwrap_or(0);
         } else {
-            if opt_genstate0.is_none() || opt_genstate1.is_none() {
-                None
-            } else {
-                Some(opt_genstate0.unwrap().diff(opt_genstate1.unwrap(), visibility))
+            changes.stepped.modify_region(region, BitOperation::Set);
+            changes.since = 0;
+        }
+    }
+
+    /// Updates the cell ages and activity counts, if tracked, for the latest generation having
+    /// followed the GenState at `opt_prev_state_index`. If `None`, the previous state is unknown.
+    fn record_activity(&mut self, opt_prev_state_index: Option<usize>) {
+        let gen_states = &self.gen_states;
+        if let Some(ref mut tracker) = self.activity {
+            let opt_old = opt_prev_state_index.map(|i| &gen_states[i].cells);
+            tracker.update(opt_old, &gen_states[self.state_index].cells);
+        }
+    }
+
+    /// Marks the words containing `region` as edited in the latest generation. `region` must be
+    /// within the universe.
+    fn mark_edited(&mut self, region: Region) {
+        let edited = &mut self.changes[self.state_index].edited;
+        for row in region.top()..=region.bottom() {
+            for word_col in (region.left() / 64)..=(region.right() / 64) {
+                edited[row as usize][word_col as usize] = u64::MAX;
+            }
+        }
+    }
+
+    /// Returns the 64 cells of `grid` starting at (`col`, `row`), wrapping around to the start of
+    /// the row. `grid` must be the size of the universe.
+    fn wrapped_word(grid: &BitGrid, col: usize, row: usize) -> u64 {
+        let (word_col, shift) = (col / 64, col % 64);
+        let word = grid[row][word_col] << shift;
+        if shift == 0 {
+            word
+        } else {
+            word | grid[row][(word_col + 1) % grid.width_in_words()] >> (64 - shift)
+        }
+    }
+
+    /// Returns `region` with its top-left corner moved within the universe, and its size truncated
+    /// to the size of the universe.
+    fn wrapped_region(&self, region: Region) -> Region {
+        Region::new(
+            region.left().rem_euclid(self.width as isize),
+            region.top().rem_euclid(self.height as isize),
+            cmp::min(region.width(), self.width),
+            cmp::min(region.height(), self.height),
+        )
+    }
+
+    /// Returns the non-dead cells in `region` that `cut`, `clear_region` and `move_region` remove:
+    /// every live or dying cell if `opt_player_id` is `None`, or else those the player may write
+    /// that are unowned or their own.
+    fn removable_cells(
+        &self,
+        region: Region,
+        opt_player_id: Option<usize>,
+    ) -> ConwayResult<Vec<(usize, usize, CellState)>> {
+        if let Some(player_id) = opt_player_id {
+            if player_id >= self.num_players {
+                return Err(ConwayError::InvalidData {
+                    reason: format!("Unexpected player_id {}", player_id),
+                });
+            }
+        }
+        let mut cells = vec![];
+        for (col, row, state) in self.cells_in(region, opt_player_id) {
+            let opt_owner = match state {
+                CellState::Alive(opt_owner) | CellState::Dying(opt_owner, _) => opt_owner,
+                _ => continue,
+            };
+            if let Some(player_id) = opt_player_id {
+                let owned_by_other = matches!(opt_owner, Some(owner) if owner != player_id);
+                if owned_by_other || !self.writable(col, row, player_id)? {
+                    continue;
+                }
+            }
+            cells.push((col, row, state));
+        }
+        Ok(cells)
+    }
+
+    /// ORs each word of `src` into `dst`.
+    fn or_words(dst: &mut BitGrid, src: &BitGrid) {
+        for (dst_row, src_row) in dst.0.iter_mut().zip(src.0.iter()) {
+            for (dst_word, src_word) in dst_row.iter_mut().zip(src_row.iter()) {
+                *dst_word |= src_word;
             }
         }
     }
@@ -1749,6 +3395,165 @@ impl CharGrid for Universe {
     }
 }
 
+/// Read-only view of a region of a GenState, which may wrap around the edges of the universe, so
+/// that it can be written as a pattern with `CharGrid::to_pattern`.
+struct RegionView<'a> {
+    gen_state:  &'a GenState,
+    region:     Region, // left and top within the universe
+    uni_width:  usize,
+    uni_height: usize,
+}
+
+impl<'a> CharGrid for RegionView<'a> {
+    fn is_valid(ch: char) -> bool {
+        GenState::is_valid(ch)
+    }
+
+    fn write_at_position(&mut self, _col: usize, _row: usize, _ch: char, _visibility: Option<usize>) {
+        unimplemented!("This is a read-only struct!");
+    }
+
+    fn width(&self) -> usize {
+        self.region.width()
+    }
+
+    fn height(&self) -> usize {
+        self.region.height()
+    }
+
+    /// Like `GenState::get_run`, but `col` and `row` are within the region, and runs stop at the
+    /// right edge of the region.
+    fn get_run(&self, col: usize, row: usize, visibility: Option<usize>) -> (usize, char) {
+        let uni_row = (self.region.top() as usize + row) % self.uni_height;
+        let width = self.region.width() - col;
+        let uni_col = (self.region.left() as usize + col) % self.uni_width;
+        let (mut run, ch) = self.gen_state.get_run(uni_col, uni_row, visibility);
+        // a run that reaches the right edge of the universe may continue from its left edge
+        while run < width {
+            let uni_col = (self.region.left() as usize + col + run) % self.uni_width;
+            let (next_run, next_ch) = self.gen_state.get_run(uni_col, uni_row, visibility);
+            if next_ch != ch {
+                break;
+            }
+            run += next_run;
+        }
+        (cmp::min(run, width), ch)
+    }
+}
+
+/// Iterator over the non-dead cells within a region of a `Universe`, returned by
+/// `Universe::cells_in`.
+pub struct CellsIn<'a> {
+    uni:        &'a Universe,
+    region:     Region,
+    visibility: Option<usize>,
+    row_offset: usize, // row within `region` of the current word
+    col_offset: usize, // column within `region` just past the current word
+    row:        usize, // row of the current word in the universe
+    word_col:   usize, // column of the current word in the universe, in words
+    pending:    u64,   // cells in the current word that have yet to be checked
+}
+
+impl<'a> Iterator for CellsIn<'a> {
+    type Item = (usize, usize, CellState);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // return the next non-dead cell in the current word, if any
+            while self.pending != 0 {
+                let shift = 63 - self.pending.leading_zeros() as usize;
+                self.pending &= !(1 << shift);
+                let state = self
+                    .uni
+                    .checked_cell_state(self.row, self.word_col, shift, self.visibility);
+                if state != CellState::Dead {
+                    return Some((self.word_col * 64 + 63 - shift, self.row, state));
+                }
+            }
+
+            // move on to the part of the next word that is within the region
+            if self.col_offset >= self.region.width() {
+                self.row_offset += 1;
+                self.col_offset = 0;
+            }
+            if self.row_offset >= self.region.height() {
+                return None;
+            }
+            let uni = self.uni;
+            let col = (self.region.left() + self.col_offset as isize).rem_euclid(uni.width as isize) as usize;
+            self.row = (self.region.top() + self.row_offset as isize).rem_euclid(uni.height as isize) as usize;
+            self.word_col = col / 64;
+            let first_bit = col % 64;
+            let num_bits = cmp::min(64 - first_bit, self.region.width() - self.col_offset);
+            self.col_offset += num_bits;
+            let mask = (u64::MAX >> first_bit) & !u64::MAX.checked_shr((first_bit + num_bits) as u32).unwrap_or(0);
+
+            // only cells that are set in one of these could be non-dead
+            let gen_state = &uni.gen_states[uni.state_index];
+            let (row, word_col) = (self.row, self.word_col);
+            let mut candidates = gen_state.cells[row][word_col]
+                | gen_state.wall_cells[row][word_col]
+                | !gen_state.known[row][word_col]
+                | gen_state.dying_word(row, word_col);
+            if let Some(player_id) = self.visibility {
+                candidates |= gen_state.player_states[player_id].fog[row][word_col];
+            }
+            self.pending = candidates & mask;
+        }
+    }
+}
+
+/// Which part of a universe stays in place when it is resized with `Universe::resize`.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// A proposed change to the latest generation, for `Universe::preview`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// Toggles a cell, as with `Universe::toggle`.
+    Toggle { col: usize, row: usize },
+    /// Sets the state of a cell, as with `Universe::set`.
+    Set {
+        col:   usize,
+        row:   usize,
+        state: CellState,
+    },
+    /// Copies live cells into a region, as with `Universe::copy_from_bit_grid`.
+    Paste { src: BitGrid, region: Region },
+    /// Kills the cells in a region, as with `Universe::clear_region`.
+    Clear { region: Region },
+}
+
+/// What `Universe::find_pattern` counts as a match.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum MatchMode {
+    /// Every live cell of the pattern is alive. Other cells may be in any state.
+    Contains,
+    /// Every cell in the pattern's bounding box is alive exactly where the pattern has live
+    /// cells, and no cell within `margin` cells of the bounding box is alive. A margin of 1 finds
+    /// the pattern only where it is isolated from other live cells.
+    Exact { margin: usize },
+}
+
+/// A match found by `Universe::find_pattern`.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct PatternMatch {
+    /// Where the bounding box of the transformed pattern is. Its left and top are always within
+    /// the universe, but it may extend past the right and bottom edges, wrapping around.
+    pub region:    Region,
+    pub transform: Transform,
+}
+
 /// Rectangular area within a `Universe`.
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
 pub struct Region {
@@ -2245,6 +4050,7 @@ mod universe_tests {
             row_index_outside_of_p0_region,
             col_index_outside_of_p0_region,
             one_bit_to_clear,
+            &mut BitGrid::new(uni.width_in_words, uni.height),
         );
 
         for x in 0..4 {
@@ -2257,6 +4063,78 @@ mod universe_tests {
         }
     }
 
+    /// Clears fog around `bits_to_clear` of word `col_idx` in an entirely fogged, one-player universe
+    /// `width` cells wide, and checks the result against a cell-by-cell fog circle wrapped around the
+    /// universe's edges.
+    fn check_clear_fog_wraps(width: usize, fog_radius: usize, row: usize, col_idx: usize, bits_to_clear: u64) {
+        let height = 32;
+        let uni = BigBang::new()
+            .width(width)
+            .height(height)
+            .server_mode(true)
+            .fog_radius(fog_radius)
+            .add_players(vec![PlayerBuilder::new(Region::new(0, 0, 8, 8))])
+            .birth()
+            .unwrap();
+
+        let mut fog = BitGrid::new(uni.width_in_words, height);
+        for row_idx in 0..height {
+            for word_idx in 0..uni.width_in_words {
+                fog[row_idx][word_idx] = u64::MAX;
+            }
+        }
+        Universe::clear_fog(
+            &mut fog,
+            &uni.fog_circle,
+            fog_radius,
+            width,
+            height,
+            row,
+            col_idx,
+            bits_to_clear,
+            &mut BitGrid::new(uni.width_in_words, height),
+        );
+
+        let wrapped_delta = |a: usize, b: usize, size: usize| {
+            let delta = a.abs_diff(b);
+            cmp::min(delta, size - delta)
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let cleared = (0..64).filter(|shift| bits_to_clear & (1 << shift) != 0).any(|shift| {
+                    let center_col = col_idx * 64 + 63 - shift;
+                    let x_delta = wrapped_delta(x, center_col, width);
+                    let y_delta = wrapped_delta(y, row, height);
+                    x_delta * x_delta + y_delta * y_delta < fog_radius * fog_radius
+                });
+                let fogged = fog[y][x / 64] & (1 << (63 - x % 64)) != 0;
+                assert_eq!(
+                    fogged, !cleared,
+                    "width {}, bits {:016x}: fog at row {}, col {}",
+                    width, bits_to_clear, y, x
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn clear_fog_wraps_around_one_word_wide_universe() {
+        // circles centered on the first and last columns wrap onto the other side of the same word
+        check_clear_fog_wraps(64, 4, 0, 0, 1 << 63);
+        check_clear_fog_wraps(64, 4, 31, 0, 1);
+        check_clear_fog_wraps(64, 8, 5, 0, (1 << 63) | 1);
+        // the circle spans the whole universe width
+        check_clear_fog_wraps(64, 16, 16, 0, 0x0000_ffff_0000_0000);
+    }
+
+    #[test]
+    fn clear_fog_wraps_around_two_word_wide_universe() {
+        check_clear_fog_wraps(128, 4, 0, 0, 1 << 63);
+        check_clear_fog_wraps(128, 4, 31, 1, 1);
+        check_clear_fog_wraps(128, 8, 5, 1, (1 << 63) | 1);
+        check_clear_fog_wraps(128, 16, 16, 0, u64::MAX);
+    }
+
     #[test]
     fn universe_copy_from_bit_grid_as_player() {
         let mut uni = generate_test_universe_with_default_params(UniType::Server);
@@ -2265,7 +4143,7 @@ mod universe_tests {
         let write_pattern_as = Some(1); // player 1
         let dst_region = Region::new(0, 0, 32, 3);
 
-        uni.copy_from_bit_grid(&grid, dst_region, write_pattern_as);
+        uni.copy_from_bit_grid(&grid, dst_region, write_pattern_as).unwrap();
 
         {
             let genstate = &uni.gen_states[uni.state_index];
@@ -2281,6 +4159,22 @@ mod universe_tests {
         }
     }
 
+    #[test]
+    fn next_records_the_same_changes_as_comparing_gen_states() {
+        let mut uni = generate_test_universe_with_default_params(UniType::Server);
+        let grid = Pattern("bo$2bo$3o!".to_owned()).to_new_bit_grid(3, 3).unwrap();
+        uni.copy_from_bit_grid(&grid, Region::new(126, 82, 3, 3), Some(0)).unwrap();
+
+        for _ in 0..8 {
+            let prev_state_index = uni.state_index;
+            uni.next();
+            let mut expected = BitGrid::new(uni.width_in_words, uni.height);
+            uni.gen_states[prev_state_index].changed_words(&uni.gen_states[uni.state_index], &mut expected);
+            assert_ne!(expected, BitGrid::new(uni.width_in_words, uni.height));
+            assert_eq!(uni.changes[uni.state_index].stepped, expected);
+        }
+    }
+
     #[test]
     fn universe_copy_from_bit_grid_as_player_out_of_range() {
         let mut uni = generate_test_universe_with_default_params(UniType::Server);
@@ -2289,7 +4183,7 @@ mod universe_tests {
         let write_pattern_as = Some(0); // player 0
         let dst_region = Region::new(0, 0, 32, 3); // out of range for player 0
 
-        uni.copy_from_bit_grid(&grid, dst_region, write_pattern_as);
+        uni.copy_from_bit_grid(&grid, dst_region, write_pattern_as).unwrap();
 
         {
             let genstate = &uni.gen_states[uni.state_index];
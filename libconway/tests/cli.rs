@@ -0,0 +1,193 @@
+/*  Copyright 2026 the Conwayste Developers.
+ *
+ *  This file is part of libconway.
+ *
+ *  libconway is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  libconway is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with libconway.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Tests of the `conway` command-line tool, which needs the default `cli` feature.
+
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const GLIDER: &str = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+
+fn conway(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_conway")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+/// Writes `contents` to a file named `name` in a scratch directory, returning its path.
+fn write_file(name: &str, contents: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn run_glider() {
+    let glider = write_file("run_glider.rle", GLIDER);
+    let output = conway(&["run", &glider, "-g", "1"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "x = 3, y = 3, rule = B3/S23\nobo$b2o$bo!\n");
+
+    // every four generations, the glider is back in its original phase
+    let output = conway(&["run", &glider, "--generations", "8"]);
+    assert_eq!(stdout(&output), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+}
+
+#[test]
+fn run_writes_output_file() {
+    let glider = write_file("run_writes_output_file.rle", GLIDER);
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("run_writes_output_file.cells");
+    let output = conway(&["run", &glider, "-g", "1", "-o", out.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(fs::read_to_string(&out).unwrap(), "O.O\n.OO\n.O.\n");
+}
+
+#[test]
+fn run_with_rule_override() {
+    // a blinker under Life without Death (B3/S012345678) grows instead of oscillating
+    let blinker = write_file("run_with_rule_override.rle", "x = 3, y = 1\n3o!\n");
+    let output = conway(&["run", &blinker, "-g", "1", "--rule", "B3/S012345678"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "x = 3, y = 3, rule = B3/S012345678\nbo$3o$bo!\n");
+}
+
+#[test]
+fn convert_round_trip() {
+    let glider = write_file("convert_round_trip.rle", GLIDER);
+    let output = conway(&["convert", &glider, "--format", "cells"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), ".O.\n..O\nOOO\n");
+
+    let cells = write_file("convert_round_trip.cells", "!Name: Glider\n.O.\n..O\nOOO\n");
+    let output = conway(&["convert", &cells]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+}
+
+#[test]
+fn convert_keeps_empty_patterns_empty() {
+    let empty = write_file("convert_keeps_empty_patterns_empty.rle", "x = 0, y = 0\n!\n");
+    let output = conway(&["convert", &empty]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "x = 0, y = 0, rule = B3/S23\n!\n");
+
+    let output = conway(&["convert", &empty, "--format", "cells"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+
+    // a pattern of dead cells is not empty
+    let dead = write_file("convert_keeps_empty_patterns_empty_dead.rle", "x = 2, y = 1\n!\n");
+    let output = conway(&["diff", &empty, &dead]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "size: 0x0 -> 2x1\n");
+}
+
+#[test]
+fn convert_keeps_owners_and_walls() {
+    let pattern = write_file("convert_keeps_owners_and_walls.rle", "x = 4, y = 2\nAB$2bWo!\n");
+    let output = conway(&["convert", &pattern]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "x = 4, y = 2, rule = B3/S23\nAB$2bWo!\n");
+
+    // plaintext has no walls
+    let output = conway(&["convert", &pattern, "-f", "cells"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn census_json() {
+    let pattern = write_file("census_json.rle", "x = 5, y = 3\n3A$2bW$2o!\n");
+    let output = conway(&["census", &pattern, "--margin", "4"]);
+    assert!(output.status.success());
+    let census: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(census["generations"], 0);
+    assert_eq!(census["width"], 64);
+    assert_eq!(census["height"], 11);
+    assert_eq!(census["rule"], "B3/S23");
+    assert_eq!(census["population"], 5);
+    assert_eq!(census["players"], serde_json::json!([3]));
+    assert_eq!(census["unowned"], 2);
+    assert_eq!(census["walls"], 1);
+    assert_eq!(census["dying"], 0);
+    assert_eq!(
+        census["bounding_box"],
+        serde_json::json!({"left": 4, "top": 4, "width": 3, "height": 3})
+    );
+
+    // stats is another name for census, and an empty pattern has no bounding box
+    let empty = write_file("census_json_empty.rle", "x = 3, y = 3\n!\n");
+    let output = conway(&["stats", &empty]);
+    assert!(output.status.success());
+    let census: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(census["population"], 0);
+    assert_eq!(census["bounding_box"], serde_json::Value::Null);
+}
+
+#[test]
+fn census_of_demo_scenario() {
+    let demo = concat!(env!("CARGO_MANIFEST_DIR"), "/scenarios/demo.toml");
+    let output = conway(&["census", demo, "-g", "10"]);
+    assert!(output.status.success());
+    let census: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(census["generations"], 10);
+    assert_eq!(census["width"], 128);
+    assert_eq!(census["players"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn diff_exit_codes() {
+    let glider = write_file("diff_exit_codes.rle", GLIDER);
+    let same = write_file("diff_exit_codes.cells", ".O.\n..O\nOOO\n");
+    let output = conway(&["diff", &glider, &same]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let other = write_file("diff_exit_codes_other.rle", "x = 3, y = 3\nbo$2bo$2o!\n");
+    let output = conway(&["diff", &glider, &other]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "(2, 2): Alive(None) -> Dead\n");
+}
+
+#[test]
+fn errors_exit_with_2() {
+    let output = conway(&["run", "no_such_file.rle"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with("conway: error: cannot read no_such_file.rle"),
+        "{}",
+        stderr
+    );
+
+    let bad = write_file("errors_exit_with_2.rle", "#C no header\n");
+    let output = conway(&["convert", &bad]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let bad = write_file("errors_exit_with_2.cells", ".O.\n.X.\n");
+    let output = conway(&["census", &bad]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let glider = write_file("errors_exit_with_2_glider.rle", GLIDER);
+    let output = conway(&["run", &glider, "-g", "many"]);
+    assert_eq!(output.status.code(), Some(2));
+}
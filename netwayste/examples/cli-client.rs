@@ -35,7 +35,7 @@ use futures as Fut;
 use log::LevelFilter;
 use netwayste::{
     client::{ClientNetState, CLIENT_VERSION},
-    net::NetwaysteEvent,
+    net::{GameOptions, NetwaysteEvent},
     utils::PingPong,
 };
 use Fut::{channel::mpsc, StreamExt};
@@ -102,8 +102,14 @@ fn print_help() {
     info!("/list                  - list rooms when in lobby, or players when in game");
     info!("/new <room_name>       - create a new room (when not in game)");
     info!("/join <room_name>      - join a room (when not in game)");
+    info!("/spectate <room_name> [player_index] - watch a room, as seen by a player if given (when not in game)");
     info!("/leave                 - leave a room (when in game)");
     info!("/part                  - alias of leave");
+    info!("/clear <x> <y> <w> <h> - clear an area of your cells and unowned cells (when in game)");
+    info!("/automatch <on|off>    - be matched with other players while in the lobby");
+    info!("/ready <on|off>        - say whether you are ready for the game to start (when in game)");
+    info!("/name <new_name>       - change your name");
+    info!("/options <w> <h> <n>   - set up a game for n players in a w x h universe (in a room you created)");
     info!("/quit                  - exit the program");
     info!("...or just type text to chat!");
 }
@@ -154,6 +160,21 @@ fn build_command_request_action(cmd: String, args: Vec<String>) -> NetwaysteEven
                 debug!("Command failed: Expected room name only (no spaces allowed)");
             }
         }
+        "spectate" => {
+            let watch_index = match args.get(1).map(|arg| arg.parse()) {
+                None => Ok(None),
+                Some(Ok(idx)) => Ok(Some(idx)),
+                Some(Err(_)) => Err(()),
+            };
+            match watch_index {
+                Ok(watch_index) if args.len() == 1 || args.len() == 2 => {
+                    new_event = NetwaysteEvent::SpectateRoom(args[0].clone(), watch_index);
+                }
+                _ => {
+                    debug!("Command failed: Expected room name and optionally a non-negative player index");
+                }
+            }
+        }
         "part" | "leave" => {
             if args.len() == 0 {
                 new_event = NetwaysteEvent::LeaveRoom;
@@ -161,6 +182,59 @@ fn build_command_request_action(cmd: String, args: Vec<String>) -> NetwaysteEven
                 debug!("Command failed: Expected no arguments to leave");
             }
         }
+        "clear" => {
+            let numbers = if args.len() == 4 {
+                (args[0].parse(), args[1].parse(), args[2].parse(), args[3].parse())
+            } else {
+                debug!("Command failed: Expected x, y, width and height");
+                return new_event;
+            };
+            match numbers {
+                (Ok(x), Ok(y), Ok(w), Ok(h)) => {
+                    new_event = NetwaysteEvent::ClearArea(x, y, w, h);
+                }
+                _ => {
+                    debug!("Command failed: x and y must be integers, and width and height non-negative integers");
+                }
+            }
+        }
+        "automatch" => {
+            match args.first().map(|arg| arg.as_str()) {
+                Some("on") if args.len() == 1 => new_event = NetwaysteEvent::SetAutoMatch(true),
+                Some("off") if args.len() == 1 => new_event = NetwaysteEvent::SetAutoMatch(false),
+                _ => debug!("Command failed: Expected on or off"),
+            }
+        }
+        "ready" => {
+            match args.first().map(|arg| arg.as_str()) {
+                Some("on") if args.len() == 1 => new_event = NetwaysteEvent::SetReady(true),
+                Some("off") if args.len() == 1 => new_event = NetwaysteEvent::SetReady(false),
+                _ => debug!("Command failed: Expected on or off"),
+            }
+        }
+        "name" => {
+            if args.len() == 1 {
+                new_event = NetwaysteEvent::SetName(args[0].clone());
+            } else {
+                debug!("Command failed: Expected new name only (no spaces allowed)");
+            }
+        }
+        "options" => {
+            let numbers = if args.len() == 3 {
+                (args[0].parse(), args[1].parse(), args[2].parse())
+            } else {
+                debug!("Command failed: Expected width, height and number of players");
+                return new_event;
+            };
+            match numbers {
+                (Ok(w), Ok(h), Ok(n)) => {
+                    new_event = NetwaysteEvent::SetGameOptions(GameOptions::new(w, h, n));
+                }
+                _ => {
+                    debug!("Command failed: width, height and number of players must be non-negative integers");
+                }
+            }
+        }
         "quit" | "q" | "exit" => {
             trace!("Peace out!");
             new_event = NetwaysteEvent::Disconnect;
@@ -33,8 +33,9 @@ use Fut::prelude::*;
 use Fut::select;
 
 use crate::net::{
-    bind, has_connection_timed_out, BroadcastChatMessage, NetwaysteEvent, NetwaystePacketCodec, NetworkManager,
-    NetworkQueue, Packet, RequestAction, ResponseCode, RoomList, DEFAULT_PORT, VERSION,
+    bind, has_connection_timed_out, BroadcastChatMessage, GameUpdate, GenPartInfo, GenStateDiffPart, NetwaysteEvent,
+    NetwaystePacketCodec, NetworkManager, NetworkQueue, Packet, RequestAction, ResponseCode, RoomList, UniUpdate,
+    DEFAULT_PORT, MAX_GEN_STATE_DIFF_PARTS, VERSION,
 };
 
 use crate::utils::{LatencyFilter, PingPong};
@@ -52,6 +53,9 @@ pub struct ClientNetState {
     pub room:                 Option<String>,
     pub cookie:               Option<String>,
     pub chat_msg_seq_num:     u64,
+    pub game_update_seq_num:  u64, // Sequence number of the latest game update received in this room or the lobby
+    pub last_full_gen:        Option<u64>, // Latest generation received in full from the server
+    pub diff_parts:           Vec<GenStateDiffPart>, // Parts received so far of the diff after last_full_gen
     pub tick:                 usize,
     pub network:              NetworkManager,
     pub last_received:        Option<Instant>,
@@ -70,6 +74,9 @@ impl ClientNetState {
             room:                 None,
             cookie:               None,
             chat_msg_seq_num:     0,
+            game_update_seq_num:  0,
+            last_full_gen:        None,
+            diff_parts:           vec![],
             tick:                 0,
             network:              NetworkManager::new().with_message_buffering(),
             last_received:        None,
@@ -92,6 +99,9 @@ impl ClientNetState {
             ref mut room,
             ref mut cookie,
             ref mut chat_msg_seq_num,
+            ref mut game_update_seq_num,
+            ref mut last_full_gen,
+            ref mut diff_parts,
             ref mut tick,
             ref mut network,
             ref mut last_received,
@@ -105,6 +115,9 @@ impl ClientNetState {
         *room = None;
         *cookie = None;
         *chat_msg_seq_num = 0;
+        *game_update_seq_num = 0;
+        *last_full_gen = None;
+        diff_parts.clear();
         *tick = 0;
         *last_received = None;
         *disconnect_initiated = false;
@@ -233,25 +246,40 @@ impl ClientNetState {
                 }
                 return vec![];
             }
-            // TODO game_updates, game_update_seq, universe_update,
             Packet::Update {
+                room,
                 chats,
-                game_updates: _,
-                game_update_seq: _,
-                universe_update: _,
+                game_updates,
+                game_update_seq,
+                universe_update,
                 ping,
             } => {
-                if chats.len() != 0 {
-                    self.handle_incoming_chats(chats).await;
+                // The updates of a room we have not heard we joined, or have heard we left, are
+                // numbered apart from ours. The server resends them until we acknowledge them.
+                if room == self.room {
+                    if !chats.is_empty() {
+                        self.handle_incoming_chats(chats).await;
+                    }
+
+                    if !game_updates.is_empty() {
+                        self.handle_game_updates(game_update_seq, game_updates).await;
+                    }
+
+                    if let UniUpdate::Diff { diff } = universe_update {
+                        self.handle_universe_diff_part(diff).await;
+                    }
+                } else {
+                    debug!("Ignoring update for room {:?} while in room {:?}", room, self.room);
                 }
 
                 // Reply to the update
                 let update_reply_packet = Packet::UpdateReply {
                     cookie:               self.cookie.clone().unwrap(),
+                    room:                 self.room.clone(),
                     last_chat_seq:        Some(self.chat_msg_seq_num),
-                    last_game_update_seq: None,
-                    last_full_gen:        None,
-                    partial_gen:          None,
+                    last_game_update_seq: Some(self.game_update_seq_num),
+                    last_full_gen:        self.last_full_gen,
+                    partial_gen:          GenPartInfo::from_parts(&self.diff_parts),
                     pong:                 PingPong::pong(ping.nonce),
                 };
 
@@ -343,6 +371,7 @@ impl ClientNetState {
 
     pub fn handle_joined_room(&mut self, room_name: &String) {
         self.room = Some(room_name.clone());
+        self.game_update_seq_num = 0; // The room's game updates are numbered apart from the lobby's
         info!("Joined room: {}", room_name);
     }
 
@@ -352,6 +381,9 @@ impl ClientNetState {
         }
         self.room = None;
         self.chat_msg_seq_num = 0;
+        self.game_update_seq_num = 0;
+        self.last_full_gen = None;
+        self.diff_parts.clear();
     }
 
     pub fn handle_player_list(&mut self, player_names: Vec<String>) {
@@ -366,8 +398,8 @@ impl ClientNetState {
         info!("---BEGIN GAME ROOM LIST---");
         for room in rooms {
             info!(
-                "#name: {},\trunning? {:?},\tplayers: {:?}",
-                room.room_name, room.in_progress, room.player_count
+                "#name: {},\trunning? {:?},\tplayers: {:?},\tspectators: {:?}",
+                room.room_name, room.in_progress, room.player_count, room.spectator_count
             );
         }
         info!("---END GAME ROOM LIST---");
@@ -406,6 +438,129 @@ impl ClientNetState {
         }
     }
 
+    /// Passes game updates on to conwayste in order, and goes back to the lobby when the server
+    /// deletes the room. `first_seq` is the sequence number of the first update if they are about
+    /// the game in this room, in which case the ones already received are skipped. The server
+    /// always sends the oldest updates we have not acknowledged, so a gap in the sequence numbers
+    /// means it skipped some, for instance those from before we joined.
+    pub async fn handle_game_updates(&mut self, first_seq: Option<u64>, game_updates: Vec<GameUpdate>) {
+        for (i, game_update) in game_updates.into_iter().enumerate() {
+            if let Some(first_seq) = first_seq {
+                let seq = first_seq + i as u64;
+                if seq <= self.game_update_seq_num {
+                    continue; // already received
+                }
+                if seq > self.game_update_seq_num + 1 {
+                    debug!(
+                        "Server skipped game updates {} to {}",
+                        self.game_update_seq_num + 1,
+                        seq - 1
+                    );
+                }
+                self.game_update_seq_num = seq;
+            }
+            let nw_response = match game_update {
+                GameUpdate::GameNotification { msg } => {
+                    info!("{}", msg);
+                    NetwaysteEvent::GameNotification(msg)
+                }
+                GameUpdate::GameStart { options } => {
+                    info!("Game started in a {}x{} universe.", options.width, options.height);
+                    // The generations we have are from a previous game
+                    self.last_full_gen = None;
+                    self.diff_parts.clear();
+                    NetwaysteEvent::GameStart(options)
+                }
+                GameUpdate::GameFinish { outcome } => {
+                    match outcome.winner {
+                        Some(ref winner) => info!("Game finished. {} won!", winner),
+                        None => info!("Game finished without a winner."),
+                    }
+                    NetwaysteEvent::GameFinish(outcome)
+                }
+                GameUpdate::PlayerList { players } => {
+                    let names: Vec<&str> = players.iter().map(|p| p.name.as_str()).collect();
+                    info!("Players in room: {}", names.join(", "));
+                    NetwaysteEvent::PlayerRoster(players)
+                }
+                GameUpdate::PlayerJoin { player } => {
+                    info!("{} joined the room.", player.name);
+                    NetwaysteEvent::PlayerJoin(player)
+                }
+                GameUpdate::PlayerLeave { name } => {
+                    info!("{} left the room.", name);
+                    NetwaysteEvent::PlayerLeave(name)
+                }
+                GameUpdate::PlayerChange { player, old_name } => {
+                    if let Some(ref old_name) = old_name {
+                        info!("{} is now known as {}.", old_name, player.name);
+                        if self.name.as_ref() == Some(old_name) {
+                            self.name = Some(player.name.clone());
+                        }
+                    }
+                    NetwaysteEvent::PlayerChange(player, old_name)
+                }
+                GameUpdate::Match { room, expire_secs } => {
+                    info!("Matched in room {}; join within {} seconds to play.", room, expire_secs);
+                    NetwaysteEvent::Match(room, expire_secs)
+                }
+                GameUpdate::RoomDeleted => {
+                    if !self.in_game() {
+                        continue;
+                    }
+                    self.handle_left_room();
+                    NetwaysteEvent::LeftRoom
+                }
+            };
+            if let Err(e) = self.channel_to_conwayste.send(nw_response).await {
+                error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e);
+            }
+        }
+    }
+
+    /// Collects the parts of the universe diff following `last_full_gen`. Once all parts of a diff
+    /// have arrived, it is sent to conwayste and becomes the latest full generation. Parts of diffs
+    /// that are older, or are based on a generation other than `last_full_gen` (or 0), are ignored.
+    pub async fn handle_universe_diff_part(&mut self, part: GenStateDiffPart) {
+        let (gen0, gen1) = (part.gen0 as u64, part.gen1 as u64);
+        if gen0 >= gen1
+            || part.total_parts == 0
+            || part.total_parts as usize > MAX_GEN_STATE_DIFF_PARTS
+            || part.part_number >= part.total_parts
+        {
+            warn!("Ignoring malformed universe diff part: {:?}", part);
+            return;
+        }
+        if self.last_full_gen.is_some_and(|last_full_gen| gen1 <= last_full_gen) {
+            return; // already have it
+        }
+        if gen0 != 0 && Some(gen0) != self.last_full_gen {
+            return; // not based on a generation we have
+        }
+
+        // Only one diff is collected at a time; a newer one replaces an incomplete older one.
+        if let Some(first) = self.diff_parts.first() {
+            if (first.gen0, first.gen1, first.total_parts) != (part.gen0, part.gen1, part.total_parts) {
+                if part.gen1 < first.gen1 {
+                    return;
+                }
+                self.diff_parts.clear();
+            }
+        }
+        if self.diff_parts.iter().any(|p| p.part_number == part.part_number) {
+            return;
+        }
+        self.diff_parts.push(part);
+
+        if let Some(diff) = GenStateDiffPart::join(&self.diff_parts) {
+            self.diff_parts.clear();
+            self.last_full_gen = Some(gen1);
+            if let Err(e) = self.channel_to_conwayste.send(NetwaysteEvent::UniverseUpdate(diff)).await {
+                error!("Could not send a netwayste response via channel_to_conwayste: {:?}", e);
+            }
+        }
+    }
+
     /// Prepare a request action to the connected server
     fn action_to_packet(&mut self, action: RequestAction) -> Packet {
         // Sequence number can increment once we're talking to a server
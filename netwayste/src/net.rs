@@ -17,7 +17,7 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::cmp::{Ordering, PartialEq, PartialOrd};
+use std::cmp::{self, Ordering, PartialEq, PartialOrd};
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::net::{self, SocketAddr};
@@ -30,6 +30,8 @@ use crate::utils::PingPong;
 
 use bincode::{deserialize, serialize};
 use bytes::{Buf, BytesMut};
+use conway::rle::Pattern;
+use conway::universe::{GenStateDiff, Region};
 use semver::{Error as SemVerError, Version};
 use serde::{Deserialize, Serialize};
 use tokio::net::UdpSocket;
@@ -51,6 +53,28 @@ const RETRANSMISSION_COUNT: usize = 32; // Testing some ideas out:. Resend lengt
 // (110 is the avg weight of an amino acid in daltons :] Much larger than our current queue size)
 const MATCH_FOUND_SENTINEL: usize = 110;
 
+/// A `GenStateDiff` is split into parts with patterns of at most this many bytes where it can be,
+/// so that most `Update` packets fit in a datagram without fragmentation.
+#[allow(unused)]
+pub const MAX_PATTERN_PART_LEN: usize = 1024;
+/// A `GenStateDiff` is split into at most this many parts. Patterns too long to fit in this many
+/// parts of `MAX_PATTERN_PART_LEN` bytes get longer parts, which go over the MTU: in the worst
+/// case, a 512x512 universe in which every cell differs from the one before it, they are up to
+/// 8.5 KiB long. An `Update` packet holding such a part still fits in a UDP datagram, but is
+/// fragmented by IP, and is lost if any of its fragments is.
+#[allow(unused)]
+pub const MAX_GEN_STATE_DIFF_PARTS: usize = 32;
+/// An `Update` packet holds at most this many `GameUpdate`s. The rest are sent once these are
+/// acknowledged.
+#[allow(unused)]
+pub const MAX_GAME_UPDATES_PER_PACKET: usize = 16;
+/// Number of generations a universe keeps in its history, unless the `GameOptions` say otherwise.
+#[allow(unused)]
+pub const DEFAULT_GAME_HISTORY: u16 = 16;
+/// How far players can see beyond their writable regions, unless the `GameOptions` say otherwise.
+#[allow(unused)]
+pub const DEFAULT_FOG_RADIUS: u32 = 6;
+
 //////////////// Public Macros /////////////////
 
 #[macro_export]
@@ -116,28 +140,49 @@ pub enum RequestAction {
     JoinRoom {
         room_name: String,
     },
+    // Join a room as a spectator, who can chat but not play. The universe is seen as the player
+    // with universe index watch_index sees it (see PlayerInfo.index), or in full if it is None.
+    // To watch another player, leave the room and join it again.
+    SpectateRoom {
+        room_name:   String,
+        watch_index: Option<u64>,
+    },
     LeaveRoom,
-    // TODO: add support ("auto_match" bool key, see issue #101)
+    // Set an option for this client, or reset it to its default if value is None. The options are:
+    //  * "auto_match" (Bool): while it is on and the player is in the lobby, the server matches the
+    //    player with others and sends a Match game update. Letting a match expire, or leaving its
+    //    room, declines the match and turns "auto_match" off.
+    //  * "ready" (Bool): whether the player is ready for the game in their room to start. It is
+    //    only shown to the others in the room; games start once enough players join, ready or not.
+    //    It is turned off whenever the player joins a room.
+    //  * "name" (Str): renames the player. It cannot be reset.
+    // Changing "ready" or "name" in a room sends everyone there a PlayerChange game update.
     SetClientOptions {
         key:   String,
         value: Option<ClientOptionValue>,
     },
-    // TODO: add support
-    // Draw the specified RLE Pattern with upper-left cell at position x, y.
+    // Draw the specified RLE Pattern with upper-left cell at position x, y. It must be within the
+    // player's writable region, and is drawn in the next generation the server computes.
     DropPattern {
         x:       i32,
         y:       i32,
         pattern: String,
     },
-    // TODO: add support (also need it in the ggez client)
     // Clear all cells in the specified region not belonging to other players. No part of this
-    // region may be outside the player's writable region.
+    // region may be outside the player's writable region. It is cleared in the next generation the
+    // server computes.
     ClearArea {
         x: i32,
         y: i32,
         w: u32,
         h: u32,
     },
+    // Set the options for the game in the player's room. Only the player who created the room may
+    // do this, and only before the game starts. The game starts once as many players as there are
+    // writable regions have joined.
+    SetGameOptions {
+        options: GameOptions,
+    },
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -252,7 +297,6 @@ impl BroadcastChatMessage {
     }
 }
 
-// TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct GameOutcome {
     pub winner: Option<String>, // Some(<name>) if winner, or None, meaning it was a tie/forfeit
@@ -261,38 +305,95 @@ pub struct GameOutcome {
 /// All options needed to initialize a Universe. Notably, num_players is absent, because it can be
 /// inferred from the index values of the latest list of PlayerInfos received from the server.
 /// Also, is_server is absent.
-// TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct GameOptions {
-    width:           u32,
-    height:          u32,
-    history:         u16,
-    player_writable: Vec<NetRegion>,
-    fog_radius:      u32,
+    pub width:           u32,
+    pub height:          u32,
+    pub history:         u16,
+    pub player_writable: Vec<NetRegion>, // one per player, in order of universe index
+    pub fog_radius:      u32,
+}
+
+impl GameOptions {
+    /// Options for a `width`x`height` universe in which each of `num_players` players can write to
+    /// an equal vertical strip, in order from left to right.
+    #[allow(unused)]
+    pub fn new(width: u32, height: u32, num_players: u32) -> Self {
+        let player_writable = (0..num_players)
+            .map(|i| {
+                let left = i * width / num_players;
+                let right = (i + 1) * width / num_players;
+                NetRegion {
+                    left:   left as i32,
+                    top:    0,
+                    width:  right - left,
+                    height,
+                }
+            })
+            .collect();
+        GameOptions {
+            width,
+            height,
+            history: DEFAULT_GAME_HISTORY,
+            player_writable,
+            fog_radius: DEFAULT_FOG_RADIUS,
+        }
+    }
 }
 
 /// Net-safe version of a libconway Region
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct NetRegion {
-    left:   i32,
-    top:    i32,
-    width:  u32,
-    height: u32,
+    pub left:   i32,
+    pub top:    i32,
+    pub width:  u32,
+    pub height: u32,
+}
+
+impl From<Region> for NetRegion {
+    fn from(region: Region) -> Self {
+        NetRegion {
+            left:   region.left() as i32,
+            top:    region.top() as i32,
+            width:  region.width() as u32,
+            height: region.height() as u32,
+        }
+    }
+}
+
+impl NetRegion {
+    /// Converts this to a libconway Region, unless it is empty.
+    #[allow(unused)]
+    pub fn to_region(&self) -> Option<Region> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        Some(Region::new(
+            self.left as isize,
+            self.top as isize,
+            self.width as usize,
+            self.height as usize,
+        ))
+    }
 }
 
-// TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct PlayerInfo {
     /// Name of the player.
-    name:  String,
+    pub name:  String,
     /// Index of player in Universe; None means this player is a lurker (non-participant)
-    index: Option<u64>,
+    pub index: Option<u64>,
+    /// Average latency between the player and the server in milliseconds; None if not yet measured.
+    pub ping:  Option<u64>,
+    /// Whether the player has said they are ready for the game to start. This is informational;
+    /// the game starts without it.
+    pub ready: bool,
 }
 
-// TODO: add support
 // The server doesn't have to send all GameUpdates to all clients because that would entail keeping
 // them all for the lifetime of the room, and sending that arbitrarily large list to clients upon
-// joining.
+// joining. Instead, a client joining a room is sent the updates from the start of the room's current
+// game on, and each client gets the rest in order, resent until acknowledged.
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum GameUpdate {
     GameNotification {
@@ -301,10 +402,14 @@ pub enum GameUpdate {
     GameStart {
         options: GameOptions,
     },
+    /// Everyone in the room. Sent to someone who joins in place of the `PlayerJoin` the others get,
+    /// to everyone when the game starts or is reset, since those change the players' indices, and
+    /// every few seconds at most while pings are changing.
     PlayerList {
         /// List of names and other info of all users including current user.
         players: Vec<PlayerInfo>,
     },
+    /// A player was renamed, or became ready or not.
     PlayerChange {
         /// Most up to date player information.
         player:   PlayerInfo,
@@ -331,14 +436,12 @@ pub enum GameUpdate {
     },
 }
 
-// TODO: add support
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum UniUpdate {
     Diff { diff: GenStateDiffPart },
     NoChange,
 }
 
-// TODO: add support
 /// One or more of these can be recombined into a GenStateDiff from the conway crate.
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct GenStateDiffPart {
@@ -349,7 +452,70 @@ pub struct GenStateDiffPart {
     pub pattern_part: String, // concatenated together to form a Pattern
 }
 
-// TODO: add support
+impl GenStateDiffPart {
+    /// Splits `diff` into parts whose patterns are at most `MAX_PATTERN_PART_LEN` bytes long, or
+    /// longer if that would take more than `MAX_GEN_STATE_DIFF_PARTS` parts; see there for how
+    /// long they can get.
+    #[allow(unused)]
+    pub fn split(diff: &GenStateDiff) -> Vec<GenStateDiffPart> {
+        let pattern = diff.pattern.0.as_str();
+        let part_len = cmp::max(MAX_PATTERN_PART_LEN, pattern.len().div_ceil(MAX_GEN_STATE_DIFF_PARTS));
+
+        let mut pattern_parts = vec![];
+        let mut rest = pattern;
+        loop {
+            let mut end = cmp::min(part_len, rest.len());
+            while !rest.is_char_boundary(end) {
+                end += 1;
+            }
+            let (pattern_part, tail) = rest.split_at(end);
+            pattern_parts.push(pattern_part.to_owned());
+            rest = tail;
+            if rest.is_empty() {
+                break;
+            }
+        }
+
+        let total_parts = pattern_parts.len() as u8;
+        pattern_parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, pattern_part)| GenStateDiffPart {
+                part_number:  i as u8,
+                total_parts,
+                gen0:         diff.gen0 as u32,
+                gen1:         diff.gen1 as u32,
+                pattern_part,
+            })
+            .collect()
+    }
+
+    /// Recombines the parts of a diff, which may be in any order. Returns `None` if a part is
+    /// missing, or if the parts are not all from the same diff.
+    #[allow(unused)]
+    pub fn join(parts: &[GenStateDiffPart]) -> Option<GenStateDiff> {
+        let first = parts.first()?;
+        let total_parts = first.total_parts as usize;
+        let mut pattern_parts: Vec<Option<&str>> = vec![None; total_parts];
+        for part in parts {
+            if (part.gen0, part.gen1, part.total_parts) != (first.gen0, first.gen1, first.total_parts) {
+                return None;
+            }
+            *pattern_parts.get_mut(part.part_number as usize)? = Some(&part.pattern_part);
+        }
+
+        let mut pattern = String::new();
+        for pattern_part in pattern_parts {
+            pattern.push_str(pattern_part?);
+        }
+        Some(GenStateDiff {
+            gen0:    first.gen0 as usize,
+            gen1:    first.gen1 as usize,
+            pattern: Pattern(pattern),
+        })
+    }
+}
+
 /// GenPartInfo is sent in the UpdateReply to indicate which GenStateDiffParts are needed.
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct GenPartInfo {
@@ -358,12 +524,33 @@ pub struct GenPartInfo {
     pub have_bitmask: u32, // bitmask indicating which parts for the specified diff are present; must be less than 1<<total_parts
 }
 
+impl GenPartInfo {
+    /// Describes which parts of a diff are present, given some of its parts. Returns `None` if
+    /// there are no parts.
+    #[allow(unused)]
+    pub fn from_parts(parts: &[GenStateDiffPart]) -> Option<GenPartInfo> {
+        let first = parts.first()?;
+        let have_bitmask = parts.iter().fold(0, |mask, part| mask | (1 << part.part_number));
+        Some(GenPartInfo {
+            gen0:         first.gen0,
+            gen1:         first.gen1,
+            have_bitmask,
+        })
+    }
+
+    /// Returns true if the part numbered `part_number` is present.
+    #[allow(unused)]
+    pub fn has_part(&self, part_number: u8) -> bool {
+        part_number < 32 && self.have_bitmask & (1 << part_number) != 0
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct RoomList {
-    pub room_name:    String,
-    pub player_count: u8,
-    // TODO: add support
-    pub in_progress:  bool,
+    pub room_name:       String,
+    pub player_count:    u8, // not including spectators
+    pub spectator_count: u8,
+    pub in_progress:     bool, // the game is counting down or running
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -385,18 +572,22 @@ pub enum Packet {
     Update {
         // Usually in-game: sent by server.
         // All of these except ping are reset to new values upon joining a room and cleared upon
-        // leaving. Also note that the server may not send all GameUpdates or BroadcastChatMessages
-        // in a single packet, since it could exceed the MTU.
-        // TODO: limit chats and game_updates based on MTU!
+        // leaving. The lobby and each room have their own sequence numbers, so a client ignores
+        // the updates of a room it has not heard it joined yet, or has heard it left, and vice versa.
+        // Also note that the server may not send all GameUpdates or BroadcastChatMessages in a
+        // single packet, since it could exceed the MTU.
+        // TODO: limit chats based on MTU! (game_updates are limited to MAX_GAME_UPDATES_PER_PACKET)
+        room:            Option<String>, // Room these updates are from, or None for the lobby
         chats:           Vec<BroadcastChatMessage>, // All non-acknowledged chats are sent each update
-        game_update_seq: Option<u64>,
+        game_update_seq: Option<u64>,     // Sequence number of the first of game_updates, if any
         game_updates:    Vec<GameUpdate>, // Information pertaining to a game tick update.
-        universe_update: UniUpdate,       // TODO: add support
+        universe_update: UniUpdate,       // One part of a diff of the universe, if it changed
         ping:            PingPong,        // Used for server-to-client latency measurement (no room needed)
     },
     UpdateReply {
         // in-game: sent by client in reply to server
         cookie:               String,
+        room:                 Option<String>, // Room the client is in, or None; acks for another are ignored
         last_chat_seq:        Option<u64>, // sequence number of latest chat msg. received from server
         last_game_update_seq: Option<u64>, // seq. number of latest game update from server
         last_full_gen:        Option<u64>, // generation number client is currently at
@@ -434,6 +625,7 @@ impl Packet {
         {
             *sequence
         } else if let Packet::Update {
+            room: _,
             chats: _,
             game_updates: _,
             game_update_seq: _,
@@ -516,6 +708,7 @@ impl fmt::Debug for Packet {
                 sequence, request_ack, code
             ),
             Packet::Update {
+                room,
                 chats: _,
                 game_updates,
                 game_update_seq,
@@ -523,11 +716,12 @@ impl fmt::Debug for Packet {
                 ping: _,
             } => write!(
                 f,
-                "[Update] game_updates: {:?} universe_update: {:?}, game_update_seq: {:?}",
-                game_updates, universe_update, game_update_seq
+                "[Update] room: {:?} game_updates: {:?} universe_update: {:?}, game_update_seq: {:?}",
+                room, game_updates, universe_update, game_update_seq
             ),
             Packet::UpdateReply {
                 cookie,
+                room,
                 last_chat_seq,
                 last_game_update_seq,
                 last_full_gen,
@@ -535,8 +729,8 @@ impl fmt::Debug for Packet {
                 pong: _,
             } => write!(
                 f,
-                "[UpdateReply] cookie: {:?} last_chat_seq: {:?} last_game_update_seq: {:?} last_full_gen: {:?} partial_gen: {:?}",
-                cookie, last_chat_seq, last_game_update_seq, last_full_gen, partial_gen
+                "[UpdateReply] cookie: {:?} room: {:?} last_chat_seq: {:?} last_game_update_seq: {:?} last_full_gen: {:?} partial_gen: {:?}",
+                cookie, room, last_chat_seq, last_game_update_seq, last_full_gen, partial_gen
             ),
             Packet::GetStatus { ping } => write!(f, "[GetStatus] nonce: {}", ping.nonce),
             Packet::Status {
@@ -1213,23 +1407,37 @@ pub enum NetwaysteEvent {
     Connect(String, String), // Player name, version
     Disconnect,
     List,
-    ChatMessage(String), // chat message
-    NewRoom(String),     // room name
-    JoinRoom(String),    // room name
+    ChatMessage(String),               // chat message
+    NewRoom(String),                   // room name
+    JoinRoom(String),                  // room name
+    SpectateRoom(String, Option<u64>), // room name, universe index of the player to watch (None for all)
     LeaveRoom,
+    ClearArea(i32, i32, u32, u32), // x, y, width, height
+    SetAutoMatch(bool),            // whether to be matched with other players while in the lobby
+    SetReady(bool),                // whether this player is ready for the game in this room to start
+    SetName(String),               // new name for this player
+    SetGameOptions(GameOptions),   // for the game in the room this player created
 
     // Responses
     LoggedIn(String),        // player is logged in -- (version)
     JoinedRoom(String),      // player has joined the room
     PlayerList(Vec<String>), // list of players in room or lobby with ping (ms)
-    RoomList(Vec<RoomList>), // (room name, # players, game has started?)
+    RoomList(Vec<RoomList>), // (room name, # players, # spectators, game has started?)
     LeftRoom,
     BadRequest(String),
     ServerError(String),
 
     // Updates
-    ChatMessages(Vec<(String, String)>), // (player name, message)
-    UniverseUpdate,                      // TODO add libconway stuff for current universe gen
+    ChatMessages(Vec<(String, String)>),      // (player name, message)
+    UniverseUpdate(GenStateDiff),             // a complete diff, based on the previous one (or on generation 0)
+    Match(String, u32),                       // (room name, seconds to join it before the match expires)
+    GameNotification(String),                 // message about the game in this room, such as a countdown
+    GameStart(GameOptions),                   // the game in this room has started
+    GameFinish(GameOutcome),                  // the game in this room has finished
+    PlayerRoster(Vec<PlayerInfo>),            // everyone in this room
+    PlayerJoin(PlayerInfo),                   // someone joined this room
+    PlayerLeave(String),                      // name of someone who left this room
+    PlayerChange(PlayerInfo, Option<String>), // someone in this room changed; old name if renamed
 
     // Server Status
     GetStatus(PingPong),
@@ -1272,6 +1480,17 @@ impl NetwaysteEvent {
                     RequestAction::None
                 }
             }
+            NetwaysteEvent::SpectateRoom(name, watch_index) => {
+                if !is_in_game {
+                    RequestAction::SpectateRoom {
+                        room_name: name,
+                        watch_index,
+                    }
+                } else {
+                    debug!("Command failed: You are already in a game");
+                    RequestAction::None
+                }
+            }
             NetwaysteEvent::LeaveRoom => {
                 if is_in_game {
                     RequestAction::LeaveRoom
@@ -1280,6 +1499,41 @@ impl NetwaysteEvent {
                     RequestAction::None
                 }
             }
+            NetwaysteEvent::SetAutoMatch(auto_match) => RequestAction::SetClientOptions {
+                key:   "auto_match".to_owned(),
+                value: Some(ClientOptionValue::Bool { value: auto_match }),
+            },
+            NetwaysteEvent::SetReady(ready) => {
+                if is_in_game {
+                    RequestAction::SetClientOptions {
+                        key:   "ready".to_owned(),
+                        value: Some(ClientOptionValue::Bool { value: ready }),
+                    }
+                } else {
+                    debug!("Command failed: You are not in a game");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::SetName(name) => RequestAction::SetClientOptions {
+                key:   "name".to_owned(),
+                value: Some(ClientOptionValue::Str { value: name }),
+            },
+            NetwaysteEvent::SetGameOptions(options) => {
+                if is_in_game {
+                    RequestAction::SetGameOptions { options }
+                } else {
+                    debug!("Command failed: You are not in a game");
+                    RequestAction::None
+                }
+            }
+            NetwaysteEvent::ClearArea(x, y, w, h) => {
+                if is_in_game {
+                    RequestAction::ClearArea { x, y, w, h }
+                } else {
+                    debug!("Command failed: You are not in a game");
+                    RequestAction::None
+                }
+            }
             _ => {
                 panic!(
                     "Unexpected netwayste event during request action construction! {:?}",
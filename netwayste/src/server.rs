@@ -29,8 +29,9 @@ mod utils;
 extern crate proptest;
 
 use netwayste::net::{
-    bind, get_version, has_connection_timed_out, BroadcastChatMessage, NetwaystePacketCodec, NetworkManager,
-    NetworkQueue, Packet, RequestAction, ResponseCode, RoomList, UniUpdate, DEFAULT_HOST, DEFAULT_PORT, VERSION,
+    bind, get_version, has_connection_timed_out, BroadcastChatMessage, ClientOptionValue, GameOptions, GameOutcome,
+    GameUpdate, GenPartInfo, GenStateDiffPart, NetwaystePacketCodec, NetworkManager, NetworkQueue, Packet, PlayerInfo,
+    RequestAction, ResponseCode, RoomList, UniUpdate, DEFAULT_HOST, DEFAULT_PORT, MAX_GAME_UPDATES_PER_PACKET, VERSION,
 };
 use netwayste::utils::{LatencyFilter, PingPong};
 
@@ -45,6 +46,10 @@ use std::time::{self, Duration, Instant};
 use base64::{engine::general_purpose, Engine as _};
 use chrono::Local;
 use clap::{App, Arg};
+use conway::grids::BitGrid;
+use conway::rle::Pattern;
+use conway::universe::{BigBang, CellState, Edit, PlayerBuilder, Region, Universe};
+use conway::{ConwayError, ConwayResult};
 use futures as Fut;
 use log::LevelFilter;
 use rand::RngCore;
@@ -60,6 +65,8 @@ use Fut::select;
 pub const TICK_INTERVAL_IN_MS: u64 = 10;
 pub const NETWORK_INTERVAL_IN_MS: u64 = 100; // Arbitrarily chosen
 pub const HEARTBEAT_INTERVAL_IN_MS: u64 = 1000; // Arbitrarily chosen
+pub const GENERATION_INTERVAL_IN_MS: u64 = 100; // Each room's universe advances this often
+pub const UNIVERSE_RESEND_MIN_MS: u64 = 200; // Unconfirmed universe updates are resent no sooner than this
 pub const REGISTER_INTERVAL_IN_MS: u64 = 10_000_000;
 pub const REGISTER_RETRIES: usize = 3;
 pub const REGISTER_RETRY_SLEEP: Duration = Duration::from_millis(5000);
@@ -69,6 +76,18 @@ pub const MAX_NUM_CHAT_MESSAGES: usize = 128;
 pub const MAX_AGE_CHAT_MESSAGES: usize = 60 * 5; // seconds
 pub const SERVER_ID: PlayerID = PlayerID(u64::max_value()); // 0xFFFF....FFFF
 pub const DEFAULT_NAME: &str = "Leto II";
+pub const PLAYERS_PER_GAME: usize = 2; // Unless the room's owner sets other GameOptions
+pub const MAX_PLAYERS_PER_GAME: usize = 8;
+pub const UNIVERSE_WIDTH: usize = 256;
+pub const UNIVERSE_HEIGHT: usize = 128;
+pub const MAX_UNIVERSE_SIZE: u32 = 512; // Maximum width and height of a universe in GameOptions
+pub const MAX_GAME_HISTORY: u16 = 32;
+pub const GAME_COUNTDOWN_SECS: u64 = 3; // How long a room counts down before its game starts
+pub const GAME_RESET_SECS: u64 = 10; // How long a room without an owner shows a finished game's outcome
+pub const MAX_GAME_GENERATIONS: usize = 3000; // A game finishes after this many generations
+pub const MATCH_EXPIRE_SECS: u32 = 30; // How long matched players have to join their room
+pub const PING_CHANGE_THRESHOLD_MS: u64 = 20; // Smaller changes to a player's ping are not sent to their room
+pub const PING_REPORT_INTERVAL_SECS: u64 = 5; // A room is sent changed pings at most this often
 
 #[derive(PartialEq, Debug, Clone, Copy, Eq, Hash)]
 pub struct PlayerID(pub u64);
@@ -90,23 +109,83 @@ impl fmt::Display for RoomID {
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Player {
-    pub player_id:      PlayerID,
-    pub cookie:         String,
-    pub addr:           SocketAddr,
-    pub name:           String,
-    pub request_ack:    Option<u64>, // The next number we expect is request_ack + 1
-    pub next_resp_seq:  u64, // This is the sequence number for the Response packet the Server sends to the Client
-    pub game_info:      Option<PlayerInGameInfo>, // none means in lobby
-    pub last_received:  time::Instant, // Time of last message received from player
-    pub latency_filter: LatencyFilter, // Latency information
+    pub player_id:        PlayerID,
+    pub cookie:           String,
+    pub addr:             SocketAddr,
+    pub name:             String,
+    pub request_ack:      Option<u64>, // The next number we expect is request_ack + 1
+    pub next_resp_seq:    u64, // This is the sequence number for the Response packet the Server sends to the Client
+    pub game_info:        Option<PlayerInGameInfo>, // none means in lobby
+    pub last_received:    time::Instant, // Time of last message received from player
+    pub latency_filter:   LatencyFilter, // Latency information
+    pub options:          ClientOptions, // Set by the client with `RequestAction::SetClientOptions`
+    pub lobby_update_seq: u64, // Sequence number of the latest update pushed to the player in the lobby
+    pub lobby_updates:    VecDeque<(u64, GameUpdate)>, // Lobby updates not yet acknowledged; Front == Oldest
+}
+
+// options a client can set for its player
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ClientOptions {
+    pub auto_match: bool, // Whether the player wants to be matched with other players while in the lobby
+    pub ready:      bool, // Whether the player says they are ready; it is shown to others but starts nothing
+}
+
+impl ClientOptions {
+    /// Sets the option named `key` to `value`, or back to its default if `value` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message saying what's wrong if there is no option named `key`, or if `value` is
+    /// the wrong type for it.
+    pub fn set(&mut self, key: &str, value: Option<ClientOptionValue>) -> Result<(), String> {
+        match key {
+            "auto_match" => match value {
+                Some(ClientOptionValue::Bool { value }) => self.auto_match = value,
+                None => self.auto_match = false,
+                Some(value) => return Err(format!("client option \"auto_match\" must be a Bool, not {:?}", value)),
+            },
+            "ready" => match value {
+                Some(ClientOptionValue::Bool { value }) => self.ready = value,
+                None => self.ready = false,
+                Some(value) => return Err(format!("client option \"ready\" must be a Bool, not {:?}", value)),
+            },
+            _ => return Err(format!("unknown client option {:?}", key)),
+        }
+        Ok(())
+    }
 }
 
 // info for a player as it relates to a game/room
 #[derive(PartialEq, Debug, Clone)]
 pub struct PlayerInGameInfo {
-    room_id:          RoomID,
-    chat_msg_seq_num: Option<u64>, // Server has confirmed the client has received messages up to this value.
-                                   // TODO: add support
+    room_id:             RoomID,
+    chat_msg_seq_num:    Option<u64>, // Server has confirmed the client has received messages up to this value.
+                                      // TODO: add support
+    game_update_seq_num: Option<u64>, // Likewise for game updates
+    spectator:           bool, // Spectators watch the game but cannot play in it
+    watched_index:       Option<usize>, // Whose view of the Universe a spectator sees; None means everything
+    universe_index:      Option<usize>, // Index of player in the room's Universe; None means not playing
+    last_full_gen:       Option<u64>, // Latest generation the client has confirmed it has in full
+    partial_gen:         Option<GenPartInfo>, // Parts the client has of the diff after last_full_gen
+    universe_sent:       Option<(u64, Instant)>, // Latest generation sent to the client, and when
+    reported_ping:       Option<u64>, // Ping last sent to the room in a PlayerInfo
+}
+
+impl PlayerInGameInfo {
+    fn new(room_id: RoomID) -> Self {
+        PlayerInGameInfo {
+            room_id,
+            chat_msg_seq_num:    None,
+            game_update_seq_num: None,
+            spectator:           false,
+            watched_index:       None,
+            universe_index:      None,
+            last_full_gen:       None,
+            partial_gen:         None,
+            universe_sent:       None,
+            reported_ping:       None,
+        }
+    }
 }
 
 impl Player {
@@ -145,6 +224,46 @@ impl Player {
         return None;
     }
 
+    // Update the Server's record of what game updates the player has obtained. Like chat messages,
+    // older sequence numbers are ignored since replies can arrive out of order.
+    pub fn update_game_update_seq_num(&mut self, opt_game_update_seq_num: Option<u64>) {
+        if let Some(ref mut game_info) = self.game_info {
+            if game_info.game_update_seq_num < opt_game_update_seq_num {
+                game_info.game_update_seq_num = opt_game_update_seq_num;
+            }
+        }
+    }
+
+    // Update the Server's record of which generation of the room's universe the player has. Older
+    // generations are ignored since replies can arrive out of order.
+    pub fn update_universe_gen(&mut self, opt_last_full_gen: Option<u64>, opt_partial_gen: Option<GenPartInfo>) {
+        if let Some(ref mut game_info) = self.game_info {
+            if game_info.last_full_gen < opt_last_full_gen {
+                game_info.last_full_gen = opt_last_full_gen;
+            }
+            game_info.partial_gen = opt_partial_gen;
+        }
+    }
+
+    /// Adds a `Match` or `RoomDeleted` update for the player to receive in the lobby. It is resent
+    /// until the player acknowledges it, or joins a room.
+    pub fn push_lobby_update(&mut self, game_update: GameUpdate) {
+        self.lobby_update_seq += 1;
+        self.lobby_updates.push_back((self.lobby_update_seq, game_update));
+    }
+
+    /// Forgets the lobby updates that the player has confirmed receiving, up to
+    /// `opt_game_update_seq_num`.
+    pub fn acknowledge_lobby_updates(&mut self, opt_game_update_seq_num: Option<u64>) {
+        if let Some(game_update_seq_num) = opt_game_update_seq_num {
+            self.lobby_updates.retain(|&(seq, _)| seq > game_update_seq_num);
+        }
+    }
+
+    pub fn is_spectator(&self) -> bool {
+        self.game_info.as_ref().is_some_and(|game_info| game_info.spectator)
+    }
+
     // Allow dead_code for unit testing
     #[cfg(test)]
     pub fn has_chatted(&self) -> bool {
@@ -168,26 +287,73 @@ pub struct ServerChatMessage {
     pub timestamp:   Instant,
 }
 
-#[derive(Clone, PartialEq)]
 pub struct Room {
-    pub room_id:        RoomID,
-    pub name:           String,
-    pub player_ids:     Vec<PlayerID>,
-    pub game_running:   bool,
-    pub universe:       u64, // Temp until we integrate
-    pub latest_seq_num: u64,
-    pub messages:       VecDeque<ServerChatMessage>, // Front == Oldest, Back == Newest
+    pub room_id:                RoomID,
+    pub name:                   String,
+    pub player_ids:             Vec<PlayerID>,
+    pub owner:                  Option<PlayerID>, // Player who may set its options: its creator, or their successor
+    pub options:                GameOptions,
+    pub state:                  RoomState,
+    pub universe:               Option<Universe>, // Some once the game has started
+    pub pending_edits:          Vec<RoomEdit>,    // Made when the universe's next generation is computed
+    pub latest_seq_num:         u64,
+    pub messages:               VecDeque<ServerChatMessage>, // Front == Oldest, Back == Newest
+    pub latest_game_update_seq: u64,
+    pub game_updates:           VecDeque<(u64, GameUpdate)>, // (sequence number, update); Front == Oldest
+    pub game_update_overrides:  HashMap<u64, (PlayerID, GameUpdate)>, // What one player gets in place of an update
+    pub pings_reported:         Option<Instant>, // When changed pings were last sent to the room
+    pub from_matchmaker:        bool,            // Removed once its last player leaves
+}
+
+/// Where a room's game is in its lifecycle.
+#[derive(PartialEq, Debug, Clone)]
+pub enum RoomState {
+    /// Not enough players have joined yet.
+    Waiting,
+    /// Enough players have joined, and the game starts at `starts` unless some of them leave.
+    Countdown { starts: Instant },
+    Running,
+    /// The players can stay, and the game's universe is kept until the owner sets new options. A
+    /// room without an owner waits for players again `GAME_RESET_SECS` after `since`.
+    Finished { outcome: GameOutcome, since: Instant },
+}
+
+/// A change to a room's universe that a player asked for. It is made right after the next
+/// generation is computed, so it first appears in that generation and no client has seen the
+/// generation without it.
+#[derive(Debug)]
+pub enum RoomEdit {
+    DropPattern {
+        universe_index: usize,
+        src:            BitGrid,
+        region:         Region,
+    },
+    ClearArea {
+        universe_index: usize,
+        region:         Region,
+    },
+}
+
+/// Players the matchmaker has put in a new room, who have until `expires` to join it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PendingMatch {
+    pub room_id:    RoomID,
+    pub player_ids: Vec<PlayerID>,
+    pub expires:    Instant,
 }
 
 pub struct ServerState {
-    pub tick:        usize,
-    pub name:        String,
-    pub reg_params:  Option<RegistryParams>,
-    pub players:     HashMap<PlayerID, Player>,
-    pub player_map:  HashMap<String, PlayerID>, // map cookie to player ID
-    pub rooms:       HashMap<RoomID, Room>,
-    pub room_map:    HashMap<String, RoomID>, // map room name to room ID
-    pub network_map: HashMap<PlayerID, NetworkManager>, // map Player ID to Player's network data
+    pub tick:              usize,
+    pub name:              String,
+    pub reg_params:        Option<RegistryParams>,
+    pub players:           HashMap<PlayerID, Player>,
+    pub player_map:        HashMap<String, PlayerID>, // map cookie to player ID
+    pub rooms:             HashMap<RoomID, Room>,
+    pub room_map:          HashMap<String, RoomID>, // map room name to room ID
+    pub network_map:       HashMap<PlayerID, NetworkManager>, // map Player ID to Player's network data
+    pub match_queue:       VecDeque<PlayerID>, // Players in the lobby waiting to be matched; Front == Next
+    pub matches:           Vec<PendingMatch>, // Matches that not all players have joined yet
+    pub next_match_number: u64,               // Used to name match rooms
 }
 
 #[derive(Debug, Clone)]
@@ -269,13 +435,21 @@ impl Room {
     /// the players (via `player_ids`) immediately to it.
     pub fn new(name: String, player_ids: Vec<PlayerID>) -> Self {
         Room {
-            room_id:        RoomID(new_uuid()),
-            name:           name,
-            player_ids:     player_ids,
-            game_running:   false,
-            universe:       0,
-            messages:       VecDeque::<ServerChatMessage>::with_capacity(MAX_NUM_CHAT_MESSAGES),
-            latest_seq_num: 0,
+            room_id:                RoomID(new_uuid()),
+            name,
+            player_ids,
+            owner:                  None,
+            options:                default_game_options(),
+            state:                  RoomState::Waiting,
+            universe:               None,
+            pending_edits:          vec![],
+            latest_seq_num:         0,
+            messages:               VecDeque::<ServerChatMessage>::with_capacity(MAX_NUM_CHAT_MESSAGES),
+            latest_game_update_seq: 0,
+            game_updates:           VecDeque::new(),
+            game_update_overrides:  HashMap::new(),
+            pings_reported:         None,
+            from_matchmaker:        false,
         }
     }
 
@@ -350,6 +524,241 @@ impl Room {
         let seq_num = self.increment_seq_num();
         self.add_message(ServerChatMessage::new(SERVER_ID, "Server".to_owned(), event, seq_num));
     }
+
+    /// Adds an update about the room's game for every player in the room to receive, in order. It
+    /// is kept until everyone in the room has acknowledged it; see
+    /// `ServerState::discard_acknowledged_game_updates`.
+    pub fn push_game_update(&mut self, game_update: GameUpdate) {
+        self.latest_game_update_seq += 1;
+        self.game_updates.push_back((self.latest_game_update_seq, game_update));
+    }
+
+    /// Adds an update like `push_game_update`, except that the player receives `player_update` in
+    /// its place.
+    pub fn push_game_update_with_override(
+        &mut self,
+        game_update: GameUpdate,
+        player_id: PlayerID,
+        player_update: GameUpdate,
+    ) {
+        self.push_game_update(game_update);
+        self.game_update_overrides
+            .insert(self.latest_game_update_seq, (player_id, player_update));
+    }
+
+    /// Returns the game update with sequence number `seq` as the player receives it.
+    pub fn game_update_for<'a>(&'a self, seq: u64, game_update: &'a GameUpdate, player_id: PlayerID) -> &'a GameUpdate {
+        match self.game_update_overrides.get(&seq) {
+            Some((p_id, player_update)) if *p_id == player_id => player_update,
+            _ => game_update,
+        }
+    }
+
+    /// Returns the sequence number of the last game update that a player joining the room does not
+    /// need. They are sent the updates from the start of the room's current game on, or just the
+    /// new ones if no game has started.
+    pub fn skipped_game_update_seq(&self) -> u64 {
+        match (self.universe.as_ref(), self.latest_game_start_seq()) {
+            (Some(_), Some(start_seq)) => start_seq - 1,
+            _ => self.latest_game_update_seq,
+        }
+    }
+
+    /// Returns the sequence number of the `GameStart` update for the room's latest game, if any.
+    pub fn latest_game_start_seq(&self) -> Option<u64> {
+        self.game_updates
+            .iter()
+            .rev()
+            .find(|(_, game_update)| matches!(game_update, GameUpdate::GameStart { .. }))
+            .map(|&(seq, _)| seq)
+    }
+
+    /// Creates the universe for a game from the room's options, and tells the players it started.
+    pub fn start_game(&mut self) -> ConwayResult<()> {
+        self.universe = Some(build_universe(&self.options)?);
+        self.pending_edits.clear();
+        self.state = RoomState::Running;
+        self.push_game_update(GameUpdate::GameStart {
+            options: self.options.clone(),
+        });
+        Ok(())
+    }
+
+    /// Stops the room's game at `now`, keeping its universe, and tells the players the outcome.
+    pub fn finish_game(&mut self, outcome: GameOutcome, now: Instant) {
+        self.pending_edits.clear();
+        self.state = RoomState::Finished {
+            outcome: outcome.clone(),
+            since:   now,
+        };
+        self.push_game_update(GameUpdate::GameFinish { outcome });
+    }
+
+    /// Computes the next generation of the room's universe, if its game is running, and makes the
+    /// pending edits to it. Returns the edits that could not be made, with the reasons why.
+    pub fn advance_universe(&mut self) -> Vec<(RoomEdit, ConwayError)> {
+        let mut failed = vec![];
+        if self.state != RoomState::Running {
+            return failed;
+        }
+        let uni = match self.universe.as_mut() {
+            Some(uni) => uni,
+            None => return failed,
+        };
+        uni.next();
+        for edit in self.pending_edits.drain(..) {
+            let result = match edit {
+                RoomEdit::DropPattern {
+                    universe_index,
+                    ref src,
+                    region,
+                } => uni.copy_from_bit_grid(src, region, Some(universe_index)),
+                RoomEdit::ClearArea { universe_index, region } => uni.clear_region(region, Some(universe_index)),
+            };
+            // Edits were checked when requested, so this would only happen if the universe changed
+            // in a way that the player could not have known about.
+            if let Err(e) = result {
+                warn!("Could not make {:?} in universe of room {:?}: {}", edit, self.name, e);
+                failed.push((edit, e));
+            }
+        }
+        failed
+    }
+
+    /// The edits that the player with index `universe_index` is waiting on, in the order they will
+    /// be made, so that a new edit can be previewed after them.
+    fn pending_edits_of(&self, universe_index: usize) -> Vec<Edit> {
+        let mut edits = vec![];
+        for edit in &self.pending_edits {
+            match *edit {
+                RoomEdit::DropPattern {
+                    universe_index: idx,
+                    ref src,
+                    region,
+                } if idx == universe_index => {
+                    edits.push(Edit::Paste {
+                        src: src.clone(),
+                        region,
+                    });
+                }
+                RoomEdit::ClearArea { universe_index: idx, region } if idx == universe_index => {
+                    edits.push(Edit::Clear { region });
+                }
+                _ => {}
+            }
+        }
+        edits
+    }
+
+    /// Drops the room's universe, and waits for players to start a new game.
+    pub fn end_game(&mut self) {
+        self.universe = None;
+        self.pending_edits.clear();
+        self.state = RoomState::Waiting;
+    }
+}
+
+/// Options for a game between `PLAYERS_PER_GAME` players, used until a room's owner sets others.
+pub fn default_game_options() -> GameOptions {
+    GameOptions::new(UNIVERSE_WIDTH as u32, UNIVERSE_HEIGHT as u32, PLAYERS_PER_GAME as u32)
+}
+
+/// Creates the universe described by `options`, in which each writable region belongs to the
+/// player with the same index.
+fn build_universe(options: &GameOptions) -> ConwayResult<Universe> {
+    let mut players = vec![];
+    for (i, net_region) in options.player_writable.iter().enumerate() {
+        let region = net_region.to_region().ok_or_else(|| ConwayError::InvalidData {
+            reason: format!("writable region of player {} is empty", i),
+        })?;
+        players.push(PlayerBuilder::new(region));
+    }
+    BigBang::new()
+        .width(options.width as usize)
+        .height(options.height as usize)
+        .history(options.history as usize)
+        .fog_radius(options.fog_radius as usize)
+        .add_players(players)
+        .birth()
+}
+
+/// Returns an error message unless a game can be played with `options`.
+fn validate_game_options(options: &GameOptions) -> Result<(), String> {
+    let num_players = options.player_writable.len();
+    if !(2..=MAX_PLAYERS_PER_GAME).contains(&num_players) {
+        return Err(format!(
+            "game must have from 2 to {} players, not {}",
+            MAX_PLAYERS_PER_GAME, num_players
+        ));
+    }
+    if options.width > MAX_UNIVERSE_SIZE || options.height > MAX_UNIVERSE_SIZE {
+        return Err(format!(
+            "universe of {}x{} cells is larger than the maximum of {}x{} cells",
+            options.width, options.height, MAX_UNIVERSE_SIZE, MAX_UNIVERSE_SIZE
+        ));
+    }
+    if options.history > MAX_GAME_HISTORY {
+        return Err(format!(
+            "history of {} generations is longer than the maximum of {}",
+            options.history, MAX_GAME_HISTORY
+        ));
+    }
+    let universe_region = Region::new(0, 0, options.width.max(1) as usize, options.height.max(1) as usize);
+    for (i, net_region) in options.player_writable.iter().enumerate() {
+        match net_region.to_region() {
+            Some(region) if universe_region.intersection(region) != Some(region) => {
+                return Err(format!("writable region of player {} is not within the universe", i));
+            }
+            _ => {}
+        }
+    }
+    build_universe(options).map(|_| ()).map_err(|e| format!("invalid game options: {}", e))
+}
+
+/// How long to wait for a player to confirm a universe update before sending the unconfirmed
+/// parts again: twice the player's round-trip time, but at least `UNIVERSE_RESEND_MIN_MS`.
+fn universe_resend_delay(player: &Player) -> Duration {
+    // The latency is half of the round-trip time
+    let round_trip_ms = player.latency_filter.average_latency_ms.unwrap_or(0) * 2;
+    Duration::from_millis(UNIVERSE_RESEND_MIN_MS.max(2 * round_trip_ms))
+}
+
+/// Returns true if the player has confirmed the latest generation sent to them, or if it is time
+/// to resend it.
+fn is_universe_update_due(player: &Player, now: Instant) -> bool {
+    let game_info = match player.game_info {
+        Some(ref game_info) => game_info,
+        None => return false,
+    };
+    match game_info.universe_sent {
+        None => true,
+        Some((sent_gen, sent_at)) => {
+            game_info.last_full_gen >= Some(sent_gen) || now >= sent_at + universe_resend_delay(player)
+        }
+    }
+}
+
+/// Returns a `BadRequest` unless `region` is entirely within the writable region of the player
+/// with index `universe_index` in `uni`. `what` describes the region in the error message.
+fn check_writable(uni: &Universe, universe_index: usize, region: Region, what: &str) -> Result<(), ResponseCode> {
+    let writable = uni.writable_region(universe_index).unwrap(); // unwrap ok because player is in this game
+    if writable.intersection(region) == Some(region) {
+        return Ok(());
+    }
+    Err(ResponseCode::BadRequest {
+        error_msg: format!(
+            "{} of {}x{} cells at ({}, {}) is not within writable region of {}x{} cells at ({}, {})",
+            what,
+            region.width(),
+            region.height(),
+            region.left(),
+            region.top(),
+            writable.width(),
+            writable.height(),
+            writable.left(),
+            writable.top()
+        ),
+    })
 }
 
 impl ServerState {
@@ -452,13 +861,145 @@ impl ServerState {
         return ResponseCode::OK;
     }
 
+    /// Checks that the RLE `pattern` can be drawn by the player with its upper-left cell at (`x`,
+    /// `y`), and if so, queues it to be drawn in the next generation of the room's universe. The
+    /// pattern must lie entirely within the player's writable region, and must not cover any
+    /// fogged or unknown cells.
+    pub fn handle_drop_pattern(&mut self, player_id: PlayerID, x: i32, y: i32, pattern: String) -> ResponseCode {
+        let (room, universe_index) = match self.get_game_mut(player_id) {
+            Some(game) => game,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot drop pattern because not playing in a game".to_owned(),
+                };
+            }
+        };
+        let uni = room.universe.as_ref().unwrap(); // unwrap ok because get_game_mut checks it
+
+        let pattern = Pattern(pattern);
+        let (width, height) = match pattern.calc_size() {
+            Ok(size) => size,
+            Err(e) => {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("invalid pattern: {}", e),
+                };
+            }
+        };
+
+        // Checked before making a region, which can't be wider than the universe
+        let writable = uni.writable_region(universe_index).unwrap(); // unwrap ok because player is in this game
+        if width > writable.width() || height > writable.height() {
+            return ResponseCode::BadRequest {
+                error_msg: format!(
+                    "pattern of {}x{} cells is larger than writable region of {}x{} cells",
+                    width,
+                    height,
+                    writable.width(),
+                    writable.height()
+                ),
+            };
+        }
+
+        let region = Region::new(x as isize, y as isize, width, height);
+        if let Err(response) = check_writable(uni, universe_index, region, "pattern") {
+            return response;
+        }
+
+        let opt_fogged = uni
+            .cells_in(region, Some(universe_index))
+            .find(|&(_, _, state)| state == CellState::Fog);
+        if let Some((col, row, _)) = opt_fogged {
+            return ResponseCode::BadRequest {
+                error_msg: format!("pattern covers fogged or unknown cell at ({}, {})", col, row),
+            };
+        }
+
+        let src = match pattern.to_new_bit_grid(width, height) {
+            Ok(src) => src,
+            Err(e) => {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("invalid pattern: {}", e),
+                };
+            }
+        };
+
+        // Catch anything else, such as the player not having enough energy for this pattern along
+        // with the edits they are already waiting on
+        let mut edits = room.pending_edits_of(universe_index);
+        edits.push(Edit::Paste {
+            src: src.clone(),
+            region,
+        });
+        if let Err(e) = uni.preview(&edits, 0, Some(universe_index)) {
+            return ResponseCode::BadRequest {
+                error_msg: format!("cannot drop pattern: {}", e),
+            };
+        }
+
+        room.pending_edits.push(RoomEdit::DropPattern {
+            universe_index,
+            src,
+            region,
+        });
+        ResponseCode::OK
+    }
+
+    /// Checks that the player may clear the area of `w`x`h` cells with its upper-left cell at (`x`,
+    /// `y`), and if so, queues it to be cleared in the next generation of the room's universe. The
+    /// area must lie entirely within the player's writable region. Cells owned by other players
+    /// are left alone.
+    pub fn handle_clear_area(&mut self, player_id: PlayerID, x: i32, y: i32, w: u32, h: u32) -> ResponseCode {
+        let (room, universe_index) = match self.get_game_mut(player_id) {
+            Some(game) => game,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot clear area because not playing in a game".to_owned(),
+                };
+            }
+        };
+        let uni = room.universe.as_ref().unwrap(); // unwrap ok because get_game_mut checks it
+
+        if w == 0 || h == 0 {
+            return ResponseCode::BadRequest {
+                error_msg: format!("area of {}x{} cells is empty", w, h),
+            };
+        }
+
+        let region = Region::new(x as isize, y as isize, w as usize, h as usize);
+        if let Err(response) = check_writable(uni, universe_index, region, "area") {
+            return response;
+        }
+
+        room.pending_edits.push(RoomEdit::ClearArea { universe_index, region });
+        ResponseCode::OK
+    }
+
+    /// Returns the player's room and their index in its universe, if they are playing in a game.
+    fn get_game_mut(&mut self, player_id: PlayerID) -> Option<(&mut Room, usize)> {
+        let universe_index = self
+            .get_player(player_id)
+            .game_info
+            .as_ref()
+            .and_then(|game_info| game_info.universe_index)?;
+        let room = self.get_room_mut(player_id)?;
+        if room.state != RoomState::Running {
+            return None;
+        }
+        Some((room, universe_index))
+    }
+
     pub fn list_rooms(&mut self) -> ResponseCode {
         let mut rooms = vec![];
         self.rooms.values().for_each(|gs| {
+            let (num_players, num_spectators) = self.count_players(gs.room_id);
             let room_details = RoomList {
-                room_name:    gs.name.clone(),
-                player_count: gs.player_ids.len() as u8,
-                in_progress:  gs.game_running,
+                room_name:       gs.name.clone(),
+                player_count:    num_players as u8,
+                spectator_count: num_spectators as u8,
+                in_progress:     match gs.state {
+                    RoomState::Countdown { .. } | RoomState::Running => true,
+                    RoomState::Waiting | RoomState::Finished { .. } => false,
+                },
             };
             rooms.push(room_details);
         });
@@ -493,7 +1034,8 @@ impl ServerState {
 
         // Create room if the room name is not already taken
         if !self.room_map.get(&room_name).is_some() {
-            self.new_room(room_name);
+            let room_id = self.new_room(room_name);
+            self.rooms.get_mut(&room_id).unwrap().owner = opt_player_id;
 
             return ResponseCode::OK;
         } else {
@@ -504,6 +1046,31 @@ impl ServerState {
     }
 
     pub fn join_room(&mut self, player_id: PlayerID, room_name: &str) -> ResponseCode {
+        self.enter_room(player_id, room_name, false)
+    }
+
+    /// Adds the player to the room as a spectator, who sees the universe as the player with index
+    /// `watch_index` in it does, or in full if it is `None`.
+    pub fn spectate_room(&mut self, player_id: PlayerID, room_name: &str, watch_index: Option<u64>) -> ResponseCode {
+        if let (Some(idx), Some(room_id)) = (watch_index, self.room_map.get(room_name)) {
+            let num_players = self.rooms.get(room_id).unwrap().options.player_writable.len();
+            if idx >= num_players as u64 {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("no player with index {} in room {:?}", idx, room_name),
+                };
+            }
+        }
+
+        let response = self.enter_room(player_id, room_name, true);
+        if let ResponseCode::JoinedRoom { .. } = response {
+            let game_info = self.get_player_mut(player_id).game_info.as_mut().unwrap(); // unwrap ok because joined
+            game_info.watched_index = watch_index.map(|idx| idx as usize);
+        }
+        response
+    }
+
+    /// Adds the player to the room, either as someone who can play in its game or as a spectator.
+    fn enter_room(&mut self, player_id: PlayerID, room_name: &str, spectator: bool) -> ResponseCode {
         let already_playing = self.is_player_in_game(player_id);
         if already_playing {
             return ResponseCode::BadRequest {
@@ -511,16 +1078,45 @@ impl ServerState {
             };
         }
 
+        if let Some(&room_id) = self.room_map.get(room_name) {
+            let opt_match = self.matches.iter().find(|m| m.room_id == room_id);
+            if !spectator && opt_match.is_some_and(|m| !m.player_ids.contains(&player_id)) {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("room {:?} is reserved for a match", room_name),
+                };
+            }
+            if spectator || opt_match.is_none() {
+                // Spectating, or joining some other room, declines the player's match, if any
+                self.decline_match(player_id);
+            }
+        }
+        self.match_queue.retain(|&p_id| p_id != player_id);
+
         let player: &mut Player = self.players.get_mut(&player_id).unwrap();
 
         // TODO replace loop with `get_key_value` once it reaches stable. Same thing with `leave_room` algorithm
         for ref mut gs in self.rooms.values_mut() {
             if gs.name == room_name {
                 gs.player_ids.push(player_id);
-                player.game_info = Some(PlayerInGameInfo {
-                    room_id:          gs.room_id.clone(),
-                    chat_msg_seq_num: None,
-                });
+                let mut game_info = PlayerInGameInfo::new(gs.room_id);
+                game_info.game_update_seq_num = Some(gs.skipped_game_update_seq());
+                game_info.spectator = spectator;
+                player.game_info = Some(game_info);
+                player.options.ready = false;
+                player.lobby_updates.clear(); // Joining a room makes any match or deleted room moot
+
+                let room_id = gs.room_id;
+                self.complete_match(room_id);
+                // The others only need to hear about the newcomer, but the newcomer needs the full list
+                if let Some(player) = self.report_player_info(player_id) {
+                    let players = self.player_list(room_id);
+                    let room: &mut Room = self.rooms.get_mut(&room_id).unwrap();
+                    room.push_game_update_with_override(
+                        GameUpdate::PlayerJoin { player },
+                        player_id,
+                        GameUpdate::PlayerList { players },
+                    );
+                }
                 return ResponseCode::JoinedRoom {
                     room_name: room_name.to_owned(),
                 };
@@ -531,95 +1127,635 @@ impl ServerState {
         }
     }
 
-    pub fn leave_room(&mut self, player_id: PlayerID) -> ResponseCode {
-        let already_playing = self.is_player_in_game(player_id);
-        if !already_playing {
+    /// Sets the options for the game in the player's room, which the player must own. Once the game
+    /// has finished, this makes the room wait for players to start a new one.
+    pub fn set_game_options(&mut self, player_id: PlayerID, options: GameOptions) -> ResponseCode {
+        let room: &mut Room = match self.get_room_mut(player_id) {
+            Some(room) => room,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot set game options because in lobby".to_owned(),
+                };
+            }
+        };
+        if room.owner != Some(player_id) {
             return ResponseCode::BadRequest {
-                error_msg: "cannot leave game because in lobby".to_owned(),
+                error_msg: "only the player who created the room can set its game options".to_owned(),
             };
         }
+        match room.state {
+            RoomState::Waiting | RoomState::Finished { .. } => {}
+            RoomState::Countdown { .. } | RoomState::Running => {
+                return ResponseCode::BadRequest {
+                    error_msg: "cannot set game options once the game has started".to_owned(),
+                };
+            }
+        }
+        if let Err(error_msg) = validate_game_options(&options) {
+            return ResponseCode::BadRequest { error_msg };
+        }
 
-        let player: &mut Player = self.players.get_mut(&player_id).unwrap();
-        {
-            let room_id = &player.game_info.as_ref().unwrap().room_id; // unwrap ok because of test above
-            for ref mut gs in self.rooms.values_mut() {
-                if gs.room_id == *room_id {
-                    // remove player_id from room's player_ids
-                    gs.player_ids.retain(|&p_id| p_id != player.player_id);
-                    break;
+        room.options = options;
+        let room_id = room.room_id;
+        self.reset_game(room_id);
+        ResponseCode::OK
+    }
+
+    /// Drops the room's universe so that it waits for players to start a new game, and tells them
+    /// that none of them has a place in a universe any more.
+    fn reset_game(&mut self, room_id: RoomID) {
+        let room: &mut Room = self.rooms.get_mut(&room_id).unwrap();
+        room.end_game();
+        let player_ids = room.player_ids.clone();
+        for p_id in player_ids {
+            if let Some(ref mut game_info) = self.players.get_mut(&p_id).and_then(|p| p.game_info.as_mut()) {
+                game_info.universe_index = None;
+            }
+        }
+        self.push_player_list(room_id);
+    }
+
+    /// Moves the game in each room along its lifecycle. A waiting room counts down once as many
+    /// players as the game needs have joined, the game starts when the countdown ends, and a
+    /// running game finishes once its outcome is decided. A finished game in a room without an
+    /// owner, who could set new options, is reset after `GAME_RESET_SECS`.
+    pub fn update_room_states(&mut self, now: Instant) {
+        let room_ids: Vec<RoomID> = self.rooms.keys().cloned().collect();
+        for room_id in room_ids {
+            let num_players = self.count_players(room_id).0;
+            let room: &mut Room = self.rooms.get_mut(&room_id).unwrap();
+            let has_enough_players = num_players >= room.options.player_writable.len();
+            match room.state {
+                RoomState::Waiting if has_enough_players => {
+                    room.state = RoomState::Countdown {
+                        starts: now + Duration::from_secs(GAME_COUNTDOWN_SECS),
+                    };
+                    room.push_game_update(GameUpdate::GameNotification {
+                        msg: format!("Game starts in {} seconds.", GAME_COUNTDOWN_SECS),
+                    });
+                }
+                RoomState::Countdown { .. } if !has_enough_players => {
+                    room.state = RoomState::Waiting;
+                    room.push_game_update(GameUpdate::GameNotification {
+                        msg: "Countdown canceled; waiting for more players.".to_owned(),
+                    });
+                }
+                RoomState::Countdown { starts } if starts <= now => {
+                    self.start_game(room_id, now);
+                }
+                RoomState::Running => {
+                    if let Some(outcome) = self.decide_outcome(room_id) {
+                        self.rooms.get_mut(&room_id).unwrap().finish_game(outcome, now);
+                    }
                 }
+                RoomState::Finished { since, .. }
+                    if room.owner.is_none() && since + Duration::from_secs(GAME_RESET_SECS) <= now =>
+                {
+                    room.push_game_update(GameUpdate::GameNotification {
+                        msg: "Waiting for players to start a new game.".to_owned(),
+                    });
+                    self.reset_game(room_id);
+                }
+                _ => {}
             }
         }
-        player.game_info = None;
+    }
 
-        return ResponseCode::LeaveRoom;
+    /// Returns the number of players in the room who are not spectators, and the number who are.
+    pub fn count_players(&self, room_id: RoomID) -> (usize, usize) {
+        let room: &Room = match self.rooms.get(&room_id) {
+            Some(room) => room,
+            None => return (0, 0),
+        };
+        let num_spectators = room
+            .player_ids
+            .iter()
+            .filter(|p_id| self.players.get(p_id).is_some_and(|p| p.is_spectator()))
+            .count();
+        (room.player_ids.len() - num_spectators, num_spectators)
     }
 
-    pub fn remove_player(&mut self, player_id: PlayerID, player_cookie: &str) {
-        if self.is_player_in_game(player_id) {
-            let player = self.get_player(player_id);
-            let broadcast_msg = format!("Player {} has left.", player.name);
-            let room: &mut Room = self.get_room_mut(player_id).unwrap(); // safe because in game check verifies room's existence
-            room.broadcast(broadcast_msg);
-            let _left = self.leave_room(player_id); // Ignore return since we don't care
+    /// Starts a game between the first players to join the room. Spectators and players who join
+    /// later do not play.
+    pub fn start_game(&mut self, room_id: RoomID, now: Instant) {
+        let room: &mut Room = self.rooms.get_mut(&room_id).unwrap();
+        if let Err(e) = room.start_game() {
+            error!("Could not create universe for room {:?}: {}", room.name, e);
+            room.finish_game(GameOutcome { winner: None }, now);
+            return;
         }
-        self.player_map.remove(player_cookie);
-        self.players.remove(&player_id);
+
+        let num_players = room.options.player_writable.len();
+        let mut next_index = 0;
+        for player_id in room.player_ids.iter() {
+            if let Some(ref mut game_info) = self.players.get_mut(player_id).and_then(|p| p.game_info.as_mut()) {
+                game_info.universe_index = None;
+                if !game_info.spectator && next_index < num_players {
+                    game_info.universe_index = Some(next_index);
+                    next_index += 1;
+                }
+                // Any generations the player has are from a previous game
+                game_info.last_full_gen = None;
+                game_info.partial_gen = None;
+                game_info.universe_sent = None;
+            }
+        }
+        self.push_player_list(room_id);
     }
 
-    pub fn handle_disconnect(&mut self, player_id: PlayerID) -> ResponseCode {
-        let player = self.get_player(player_id);
-        let player_cookie = player.cookie.clone();
-        self.remove_player(player_id, &player_cookie);
+    /// Returns what everyone in the player's room is told about the player, or `None` if the
+    /// player is in the lobby. The ping in it is remembered so that only noticeable changes to it
+    /// are sent later.
+    fn report_player_info(&mut self, player_id: PlayerID) -> Option<PlayerInfo> {
+        let player: &mut Player = self.players.get_mut(&player_id)?;
+        let ping = player.latency_filter.average_latency_ms;
+        let game_info = player.game_info.as_mut()?;
+        game_info.reported_ping = ping;
+        Some(PlayerInfo {
+            name:  player.name.clone(),
+            index: game_info.universe_index.map(|idx| idx as u64),
+            ping,
+            ready: player.options.ready,
+        })
+    }
 
-        ResponseCode::OK
+    /// Returns what everyone in the room is told about each player in it.
+    fn player_list(&mut self, room_id: RoomID) -> Vec<PlayerInfo> {
+        let player_ids = match self.rooms.get(&room_id) {
+            Some(room) => room.player_ids.clone(),
+            None => return vec![],
+        };
+        player_ids
+            .into_iter()
+            .filter_map(|p_id| self.report_player_info(p_id))
+            .collect()
     }
 
-    // not used for connect
-    pub fn process_request_action(&mut self, player_id: PlayerID, action: RequestAction) -> ResponseCode {
-        match action {
-            RequestAction::Disconnect => {
-                return self.handle_disconnect(player_id);
+    /// Tells everyone in the room who is in it.
+    fn push_player_list(&mut self, room_id: RoomID) {
+        let players = self.player_list(room_id);
+        if let Some(room) = self.rooms.get_mut(&room_id) {
+            room.push_game_update(GameUpdate::PlayerList { players });
+        }
+    }
+
+    /// Tells everyone in the player's room what changed about the player, which includes their old
+    /// name if they were renamed. Does nothing if the player is in the lobby.
+    fn push_player_change(&mut self, player_id: PlayerID, old_name: Option<String>) {
+        let player = match self.report_player_info(player_id) {
+            Some(player) => player,
+            None => return,
+        };
+        let room: &mut Room = self.get_room_mut(player_id).unwrap(); // unwrap ok because in a room
+        room.push_game_update(GameUpdate::PlayerChange { player, old_name });
+    }
+
+    /// Sends a `PlayerList` to each room with a player whose ping has changed by at least
+    /// `PING_CHANGE_THRESHOLD_MS` since it was last reported. Since pings change all the time, a
+    /// room is sent them at most once every `PING_REPORT_INTERVAL_SECS`.
+    pub fn push_ping_changes(&mut self, now: Instant) {
+        let interval = Duration::from_secs(PING_REPORT_INTERVAL_SECS);
+        let players = &self.players;
+        let changed: Vec<RoomID> = self
+            .rooms
+            .values()
+            .filter(|room| room.pings_reported.is_none_or(|reported| now >= reported + interval))
+            .filter(|room| {
+                room.player_ids.iter().filter_map(|p_id| players.get(p_id)).any(|p| {
+                    let reported_ping = match p.game_info.as_ref() {
+                        Some(game_info) => game_info.reported_ping,
+                        None => return false,
+                    };
+                    match (reported_ping, p.latency_filter.average_latency_ms) {
+                        (Some(old), Some(new)) => old.max(new) - old.min(new) >= PING_CHANGE_THRESHOLD_MS,
+                        (None, Some(_)) => true,
+                        (_, None) => false,
+                    }
+                })
+            })
+            .map(|room| room.room_id)
+            .collect();
+        for room_id in changed {
+            self.push_player_list(room_id);
+            self.rooms.get_mut(&room_id).unwrap().pings_reported = Some(now);
+        }
+    }
+
+    /// Returns the outcome of the room's running game, if it is over. The game is forfeited to the
+    /// last player left playing, and otherwise ends after `MAX_GAME_GENERATIONS`, when whoever owns
+    /// the most live cells wins. A tie has no winner.
+    fn decide_outcome(&self, room_id: RoomID) -> Option<GameOutcome> {
+        let room: &Room = self.rooms.get(&room_id)?;
+        let playing: Vec<(usize, &Player)> = room
+            .player_ids
+            .iter()
+            .filter_map(|p_id| self.players.get(p_id))
+            .filter_map(|p| p.game_info.as_ref().and_then(|gi| gi.universe_index).map(|idx| (idx, p)))
+            .collect();
+        if playing.len() <= 1 {
+            return Some(GameOutcome {
+                winner: playing.first().map(|&(_, p)| p.name.clone()),
+            });
+        }
+
+        let uni = room.universe.as_ref()?;
+        if uni.latest_gen() < MAX_GAME_GENERATIONS {
+            return None;
+        }
+        let mut live_cells = vec![0; uni.num_players()];
+        for (_, _, state) in uni.cells_in(uni.region(), None) {
+            if let CellState::Alive(Some(owner)) = state {
+                live_cells[owner] += 1;
+            }
+        }
+        let most = playing.iter().map(|&(idx, _)| live_cells[idx]).max().unwrap(); // unwrap ok because not empty
+        let leaders: Vec<&Player> = playing
+            .iter()
+            .filter(|&&(idx, _)| live_cells[idx] == most)
+            .map(|&(_, p)| p)
+            .collect();
+        let winner = if leaders.len() == 1 {
+            Some(leaders[0].name.clone())
+        } else {
+            None
+        };
+        Some(GameOutcome { winner })
+    }
+
+    pub fn leave_room(&mut self, player_id: PlayerID) -> ResponseCode {
+        let already_playing = self.is_player_in_game(player_id);
+        if !already_playing {
+            return ResponseCode::BadRequest {
+                error_msg: "cannot leave game because in lobby".to_owned(),
+            };
+        }
+
+        let player: &mut Player = self.players.get_mut(&player_id).unwrap();
+        let room_id = player.game_info.as_ref().unwrap().room_id; // unwrap ok because of test above
+        let mut is_abandoned = false;
+        for ref mut gs in self.rooms.values_mut() {
+            if gs.room_id == room_id {
+                // remove player_id from room's player_ids
+                gs.player_ids.retain(|&p_id| p_id != player.player_id);
+                gs.push_game_update(GameUpdate::PlayerLeave {
+                    name: player.name.clone(),
+                });
+                if !gs.has_players() {
+                    gs.end_game();
+                    is_abandoned = gs.from_matchmaker;
+                }
+                break;
+            }
+        }
+        player.game_info = None;
+
+        if self.matches.iter().any(|m| m.room_id == room_id) {
+            self.decline_match(player_id);
+        } else if player.options.auto_match {
+            self.enqueue_for_match(player_id, false);
+        }
+        self.pass_on_ownership(room_id, player_id);
+        if is_abandoned {
+            // Nobody can be matched into it again, so nobody would ever join it
+            self.remove_room(room_id);
+        }
+
+        return ResponseCode::LeaveRoom;
+    }
+
+    /// Makes the first player left in the room who is not a spectator its owner, if its owner was
+    /// the player who left. Without such a player, the room has no owner.
+    fn pass_on_ownership(&mut self, room_id: RoomID, leaver_id: PlayerID) {
+        let room: &Room = match self.rooms.get(&room_id) {
+            Some(room) if room.owner == Some(leaver_id) => room,
+            _ => return,
+        };
+        let opt_owner = room
+            .player_ids
+            .iter()
+            .cloned()
+            .find(|p_id| self.players.get(p_id).is_some_and(|p| !p.is_spectator()));
+        let opt_owner_name = opt_owner.map(|p_id| self.get_player(p_id).name.clone());
+        let room: &mut Room = self.rooms.get_mut(&room_id).unwrap();
+        room.owner = opt_owner;
+        if let Some(name) = opt_owner_name {
+            room.push_game_update(GameUpdate::GameNotification {
+                msg: format!("{} now owns the room, and may set its game options.", name),
+            });
+        }
+    }
+
+    /// Removes a room, without checking whether any players are in it.
+    pub fn remove_room(&mut self, room_id: RoomID) {
+        self.rooms.remove(&room_id);
+        self.room_map.retain(|_, &mut r_id| r_id != room_id);
+    }
+
+    /// Sets one of the player's `ClientOptions`. Turning on `auto_match` in the lobby puts the player
+    /// in the matchmaking queue, and turning it off takes them out, declining any pending match.
+    pub fn set_client_options(
+        &mut self,
+        player_id: PlayerID,
+        key: String,
+        value: Option<ClientOptionValue>,
+    ) -> ResponseCode {
+        if key == "name" {
+            return self.rename_player(player_id, value);
+        }
+
+        let player: &mut Player = self.get_player_mut(player_id);
+        let was_ready = player.options.ready;
+        if let Err(error_msg) = player.options.set(&key, value) {
+            return ResponseCode::BadRequest { error_msg };
+        }
+        if player.options.ready != was_ready {
+            self.push_player_change(player_id, None);
+        }
+
+        let player: &Player = self.get_player(player_id);
+        if !player.options.auto_match {
+            self.match_queue.retain(|&p_id| p_id != player_id);
+            self.decline_match(player_id);
+        } else if player.game_info.is_none() && !self.matches.iter().any(|m| m.player_ids.contains(&player_id)) {
+            self.enqueue_for_match(player_id, false);
+        }
+        ResponseCode::OK
+    }
+
+    /// Renames the player, which is how the "name" client option is set. Everyone in the player's
+    /// room is told the player's old and new names.
+    fn rename_player(&mut self, player_id: PlayerID, value: Option<ClientOptionValue>) -> ResponseCode {
+        let name = match value {
+            Some(ClientOptionValue::Str { value }) => value,
+            None => {
+                return ResponseCode::BadRequest {
+                    error_msg: "client option \"name\" cannot be reset".to_owned(),
+                };
+            }
+            Some(value) => {
+                return ResponseCode::BadRequest {
+                    error_msg: format!("client option \"name\" must be a Str, not {:?}", value),
+                };
+            }
+        };
+        if name == self.get_player(player_id).name {
+            return ResponseCode::OK;
+        }
+        if name.is_empty() {
+            return ResponseCode::BadRequest {
+                error_msg: "player name cannot be empty".to_owned(),
+            };
+        }
+        if !self.is_unique_player_name(&name) {
+            return ResponseCode::BadRequest {
+                error_msg: format!("player name {:?} is already in use", name),
+            };
+        }
+
+        let old_name = std::mem::replace(&mut self.get_player_mut(player_id).name, name);
+        self.push_player_change(player_id, Some(old_name));
+        ResponseCode::OK
+    }
+
+    /// Adds the player to the back of the matchmaking queue, or the front if they were already
+    /// matched but the match fell through.
+    fn enqueue_for_match(&mut self, player_id: PlayerID, at_front: bool) {
+        self.match_queue.retain(|&p_id| p_id != player_id);
+        if at_front {
+            self.match_queue.push_front(player_id);
+        } else {
+            self.match_queue.push_back(player_id);
+        }
+    }
+
+    /// Declines the player's pending match, if any, which turns off their `auto_match` option and
+    /// cancels the match for the other players.
+    pub fn decline_match(&mut self, player_id: PlayerID) {
+        let idx = match self.matches.iter().position(|m| m.player_ids.contains(&player_id)) {
+            Some(idx) => idx,
+            None => return,
+        };
+        if let Some(player) = self.players.get_mut(&player_id) {
+            player.options.auto_match = false;
+        }
+        self.match_queue.retain(|&p_id| p_id != player_id);
+        self.cancel_match(idx, &[player_id]);
+    }
+
+    /// Cancels a pending match. Anyone who has joined its room is sent back to the lobby and told
+    /// the room was deleted, and the players other than `decliners` return to the front of the
+    /// matchmaking queue if they still have `auto_match` on.
+    fn cancel_match(&mut self, idx: usize, decliners: &[PlayerID]) {
+        let pending = self.matches.remove(idx);
+        let (room_name, room_player_ids) = match self.rooms.get(&pending.room_id) {
+            Some(room) => (room.name.clone(), room.player_ids.clone()),
+            None => (String::new(), vec![]),
+        };
+        for &p_id in &pending.player_ids {
+            if let Some(player) = self.players.get_mut(&p_id) {
+                player
+                    .lobby_updates
+                    .retain(|(_, game_update)| !matches!(game_update, GameUpdate::Match { room, .. } if *room == room_name));
+            }
+        }
+        for p_id in room_player_ids {
+            let _left = self.leave_room(p_id); // Ignore return since we know the player is in the room
+            if let Some(player) = self.players.get_mut(&p_id) {
+                player.push_lobby_update(GameUpdate::RoomDeleted);
+            }
+        }
+        for &p_id in pending.player_ids.iter().rev() {
+            let auto_match = self.players.get(&p_id).is_some_and(|p| p.options.auto_match);
+            if !decliners.contains(&p_id) && auto_match {
+                self.enqueue_for_match(p_id, true);
+            }
+        }
+        self.remove_room(pending.room_id);
+    }
+
+    /// Forgets the pending match for a room once all of its players have joined it.
+    fn complete_match(&mut self, room_id: RoomID) {
+        let is_complete = match self.matches.iter().find(|m| m.room_id == room_id) {
+            Some(pending) => pending
+                .player_ids
+                .iter()
+                .all(|&p_id| self.is_player_in_room(p_id, room_id)),
+            None => return,
+        };
+        if is_complete {
+            self.matches.retain(|m| m.room_id != room_id);
+        }
+    }
+
+    /// Returns true if the player exists and is in the room.
+    fn is_player_in_room(&self, player_id: PlayerID, room_id: RoomID) -> bool {
+        let opt_game_info = self.players.get(&player_id).and_then(|p| p.game_info.as_ref());
+        opt_game_info.is_some_and(|game_info| game_info.room_id == room_id)
+    }
+
+    /// Expires matches that not all players joined in time, pairs up the players waiting in the
+    /// matchmaking queue in new rooms, and returns updates for the players in the lobby with the
+    /// `Match` and `RoomDeleted` updates they have not acknowledged. Players who let a match expire
+    /// have declined it.
+    pub fn run_matchmaking(&mut self, now: Instant) -> Vec<(SocketAddr, Packet)> {
+        while let Some(idx) = self.matches.iter().position(|m| m.expires <= now) {
+            let room_id = self.matches[idx].room_id;
+            let decliners: Vec<PlayerID> = self.matches[idx]
+                .player_ids
+                .iter()
+                .cloned()
+                .filter(|&p_id| !self.is_player_in_room(p_id, room_id))
+                .collect();
+            for p_id in &decliners {
+                if let Some(player) = self.players.get_mut(p_id) {
+                    player.options.auto_match = false;
+                }
+            }
+            self.cancel_match(idx, &decliners);
+        }
+
+        // Only players in the lobby who still want a match are waiting for one
+        let players = &self.players;
+        self.match_queue.retain(|p_id| {
+            players
+                .get(p_id)
+                .is_some_and(|p| p.options.auto_match && p.game_info.is_none())
+        });
+        while self.match_queue.len() >= PLAYERS_PER_GAME {
+            let player_ids: Vec<PlayerID> = self.match_queue.drain(..PLAYERS_PER_GAME).collect();
+            self.create_match(player_ids, now);
+        }
+
+        let mut client_updates = vec![];
+        let mut updated_player_ids = vec![];
+        for player in self.players.values() {
+            let first_seq = match player.lobby_updates.front() {
+                Some(&(seq, _)) if player.game_info.is_none() => seq,
+                _ => continue,
+            };
+            let game_updates = player
+                .lobby_updates
+                .iter()
+                .take(MAX_GAME_UPDATES_PER_PACKET)
+                .map(|(_, game_update)| game_update.clone())
+                .collect();
+            let update_packet = Packet::Update {
+                room:            None,
+                chats:           vec![],
+                game_updates,
+                game_update_seq: Some(first_seq),
+                universe_update: UniUpdate::NoChange,
+                ping:            PingPong::ping(),
+            };
+            client_updates.push((player.addr, update_packet));
+            updated_player_ids.push(player.player_id);
+        }
+        self.start_latency_measurements(&updated_player_ids);
+        client_updates
+    }
+
+    /// Creates a room for the players and asks them to join it.
+    fn create_match(&mut self, player_ids: Vec<PlayerID>, now: Instant) {
+        let room_name = loop {
+            self.next_match_number += 1;
+            let room_name = format!("match-{}", self.next_match_number);
+            if !self.room_map.contains_key(&room_name) {
+                break room_name;
+            }
+        };
+        let room_id = self.new_room(room_name.clone());
+        if let Some(room) = self.rooms.get_mut(&room_id) {
+            room.from_matchmaker = true;
+        }
+        for &p_id in &player_ids {
+            let game_update = GameUpdate::Match {
+                room:        room_name.clone(),
+                expire_secs: MATCH_EXPIRE_SECS,
+            };
+            if let Some(player) = self.players.get_mut(&p_id) {
+                player.push_lobby_update(game_update);
+            }
+        }
+        self.matches.push(PendingMatch {
+            room_id,
+            player_ids,
+            expires: now + Duration::from_secs(MATCH_EXPIRE_SECS as u64),
+        });
+    }
+
+    pub fn remove_player(&mut self, player_id: PlayerID, player_cookie: &str) {
+        self.match_queue.retain(|&p_id| p_id != player_id);
+        self.decline_match(player_id);
+        if self.is_player_in_game(player_id) {
+            let player = self.get_player(player_id);
+            let broadcast_msg = format!("Player {} has left.", player.name);
+            let room: &mut Room = self.get_room_mut(player_id).unwrap(); // safe because in game check verifies room's existence
+            room.broadcast(broadcast_msg);
+            let _left = self.leave_room(player_id); // Ignore return since we don't care
+        }
+        self.player_map.remove(player_cookie);
+        self.players.remove(&player_id);
+    }
+
+    pub fn handle_disconnect(&mut self, player_id: PlayerID) -> ResponseCode {
+        let player = self.get_player(player_id);
+        let player_cookie = player.cookie.clone();
+        self.remove_player(player_id, &player_cookie);
+
+        ResponseCode::OK
+    }
+
+    // not used for connect
+    pub fn process_request_action(&mut self, player_id: PlayerID, action: RequestAction) -> ResponseCode {
+        match action {
+            RequestAction::Disconnect => {
+                self.handle_disconnect(player_id)
             }
             RequestAction::KeepAlive { latest_response_ack: _ } => {
-                return ResponseCode::OK;
+                ResponseCode::OK
             }
             RequestAction::ListPlayers => {
-                return self.list_players(player_id);
+                self.list_players(player_id)
             }
             RequestAction::ChatMessage { message } => {
-                return self.handle_chat_message(player_id, message);
+                self.handle_chat_message(player_id, message)
             }
             RequestAction::ListRooms => {
-                return self.list_rooms();
+                self.list_rooms()
             }
             RequestAction::NewRoom { room_name } => {
-                return self.create_new_room(Some(player_id), room_name);
+                self.create_new_room(Some(player_id), room_name)
             }
             RequestAction::JoinRoom { room_name } => {
-                return self.join_room(player_id, &room_name);
+                self.join_room(player_id, &room_name)
+            }
+            RequestAction::SpectateRoom { room_name, watch_index } => {
+                self.spectate_room(player_id, &room_name, watch_index)
             }
             RequestAction::LeaveRoom => {
-                return self.leave_room(player_id);
+                self.leave_room(player_id)
             }
             RequestAction::Connect { .. } => {
-                return ResponseCode::BadRequest {
+                ResponseCode::BadRequest {
                     error_msg: "Already connected".to_owned(),
-                };
+                }
+            }
+            RequestAction::SetClientOptions { key, value } => {
+                self.set_client_options(player_id, key, value)
             }
-            RequestAction::SetClientOptions { .. } => {
-                unimplemented!(); // TODO: add support ("auto_match" bool key, see issue #101)
+            RequestAction::DropPattern { x, y, pattern } => {
+                self.handle_drop_pattern(player_id, x, y, pattern)
             }
-            RequestAction::DropPattern { .. } => {
-                unimplemented!(); // TODO: add support
+            RequestAction::ClearArea { x, y, w, h } => {
+                self.handle_clear_area(player_id, x, y, w, h)
             }
-            RequestAction::ClearArea { .. } => {
-                unimplemented!(); // TODO: add support
+            RequestAction::SetGameOptions { options } => {
+                self.set_game_options(player_id, options)
             }
             RequestAction::None => {
-                return ResponseCode::BadRequest {
+                ResponseCode::BadRequest {
                     error_msg: format!("Invalid request: {:?}", action),
-                };
+                }
             }
         }
     }
@@ -993,10 +2129,11 @@ impl ServerState {
             }
             Packet::UpdateReply {
                 cookie,
+                room,
                 last_chat_seq,
-                last_game_update_seq: _,
-                last_full_gen: _,
-                partial_gen: _,
+                last_game_update_seq,
+                last_full_gen,
+                partial_gen,
                 pong: _,
             } => {
                 let opt_player_id = self.get_player_id_by_cookie(cookie.as_str());
@@ -1006,6 +2143,9 @@ impl ServerState {
                 }
 
                 let player_id = opt_player_id.unwrap();
+                let opt_room = self.get_room(player_id);
+                let opt_room_name = opt_room.map(|room| room.name.clone());
+                let opt_game_start_seq = opt_room.and_then(|room| room.latest_game_start_seq());
                 let opt_player = self.players.get_mut(&player_id);
 
                 if opt_player.is_none() {
@@ -1014,11 +2154,26 @@ impl ServerState {
 
                 let player: &mut Player = opt_player.unwrap();
 
-                if player.game_info.is_some() {
+                if room != opt_room_name {
+                    // The client has not heard that it joined or left a room yet, so it is
+                    // acknowledging the updates of the lobby or room it was in, which are
+                    // numbered apart from those of where it is now
+                } else if player.game_info.is_none() {
+                    player.acknowledge_lobby_updates(last_game_update_seq);
+                } else {
                     player.update_chat_seq_num(last_chat_seq);
+                    player.update_game_update_seq_num(last_game_update_seq);
+                    // Until the client has the latest GameStart, its generations are from a previous game
+                    let confirmed_seq = player.game_info.as_ref().and_then(|gi| gi.game_update_seq_num);
+                    if opt_game_start_seq.is_none_or(|start_seq| confirmed_seq >= Some(start_seq)) {
+                        player.update_universe_gen(last_full_gen, partial_gen);
+                    }
                 }
 
-                player.latency_filter.update();
+                // Replies to Updates sent before the measurement started are not counted
+                if player.latency_filter.is_in_progress() {
+                    player.latency_filter.update();
+                }
 
                 Ok(None)
             }
@@ -1105,15 +2260,17 @@ impl ServerState {
     // Right now we'll be constructing all client Update packets for _every_ room.
     pub fn construct_client_updates(&mut self) -> Vec<(SocketAddr, Packet)> {
         let mut client_updates: Vec<(SocketAddr, Packet)> = vec![];
+        let mut updated_player_ids = vec![];
 
         if self.rooms.len() == 0 {
             return vec![];
         }
 
-        // For each room, determine if each player has unread messages based on chat_msg_seq_num
+        // For each room, determine if each player has unread messages based on chat_msg_seq_num,
+        // and game updates based on game_update_seq_num
         // TODO: POOR PERFORMANCE BOUNTY
         for room in self.rooms.values() {
-            if room.messages.is_empty() || room.player_ids.len() == 0 {
+            if (room.messages.is_empty() && room.game_updates.is_empty()) || room.player_ids.is_empty() {
                 continue;
             }
 
@@ -1133,28 +2290,182 @@ impl ServerState {
                     unsent_messages = new_messages.to_vec();
                 }
 
-                let messages_available = unsent_messages.len() != 0;
-                // TODO: add support
-                let game_updates_available = false;
+                let mut game_update_seq = None;
+                let mut unsent_game_updates = vec![];
+                if let Some((first_seq, new_game_updates)) = self.collect_unacknowledged_game_updates(room, player) {
+                    game_update_seq = Some(first_seq);
+                    unsent_game_updates = new_game_updates;
+                }
+
+                let messages_available = !unsent_messages.is_empty();
+                let game_updates_available = !unsent_game_updates.is_empty();
+                // Universe updates are sent as they are computed by advance_universes
                 let universe_updates_available = false;
 
                 let update_packet = Packet::Update {
+                    room:            Some(room.name.clone()),
                     chats:           unsent_messages,
-                    game_updates:    vec![],
-                    game_update_seq: None,
+                    game_updates:    unsent_game_updates,
+                    game_update_seq,
                     universe_update: UniUpdate::NoChange,
                     ping:            PingPong::ping(),
                 };
 
                 if messages_available || game_updates_available || universe_updates_available {
                     client_updates.push((player.addr.clone(), update_packet));
+                    updated_player_ids.push(player_id);
                 }
             }
         }
 
+        self.start_latency_measurements(&updated_player_ids);
         return client_updates;
     }
 
+    /// Moves each room's game along its lifecycle, advances the universe of every room with a
+    /// running game by one generation, and returns the resulting universe updates for each player
+    /// in rooms with a universe.
+    pub fn advance_universes(&mut self) -> Vec<(SocketAddr, Packet)> {
+        let now = Instant::now();
+        self.update_room_states(now);
+        let players = &self.players;
+        for room in self.rooms.values_mut() {
+            for (edit, e) in room.advance_universe() {
+                let universe_index = match edit {
+                    RoomEdit::DropPattern { universe_index, .. } | RoomEdit::ClearArea { universe_index, .. } => {
+                        universe_index
+                    }
+                };
+                let opt_player = room.player_ids.iter().filter_map(|p_id| players.get(p_id)).find(|player| {
+                    player
+                        .game_info
+                        .as_ref()
+                        .is_some_and(|game_info| game_info.universe_index == Some(universe_index))
+                });
+                let name = opt_player.map_or("a player who left", |player| &player.name);
+                room.push_game_update(GameUpdate::GameNotification {
+                    msg: format!("Could not make change for {}: {}", name, e),
+                });
+            }
+        }
+        self.construct_universe_updates(now)
+    }
+
+    /// Returns the universe updates due to each player in a room with a universe. A player who has
+    /// not confirmed the generation last sent to them is sent nothing until
+    /// `universe_resend_delay` has passed, so that parts still in flight are not resent.
+    pub fn construct_universe_updates(&mut self, now: Instant) -> Vec<(SocketAddr, Packet)> {
+        let mut client_updates: Vec<(SocketAddr, Packet)> = vec![];
+        let mut updated_players = vec![];
+
+        for room in self.rooms.values() {
+            let latest_gen = match room.universe {
+                Some(ref uni) => uni.latest_gen() as u64,
+                None => continue,
+            };
+
+            for &player_id in &room.player_ids {
+                let player: &Player = match self.players.get(&player_id) {
+                    Some(player) => player,
+                    None => continue,
+                };
+                if !is_universe_update_due(player, now) {
+                    continue;
+                }
+
+                let update_packets = self.collect_universe_updates(room, player);
+                if !update_packets.is_empty() {
+                    updated_players.push((player_id, latest_gen));
+                }
+                for update_packet in update_packets {
+                    client_updates.push((player.addr, update_packet));
+                }
+            }
+        }
+
+        for &(player_id, latest_gen) in &updated_players {
+            if let Some(game_info) = self.players.get_mut(&player_id).and_then(|p| p.game_info.as_mut()) {
+                game_info.universe_sent = Some((latest_gen, now));
+            }
+        }
+        let updated_player_ids: Vec<PlayerID> = updated_players.into_iter().map(|(p_id, _)| p_id).collect();
+        self.start_latency_measurements(&updated_player_ids);
+        client_updates
+    }
+
+    /// Starts measuring the latency of each player who was just sent an `Update`, unless it is
+    /// already being measured. The measurement ends when the player's next `UpdateReply` arrives.
+    fn start_latency_measurements(&mut self, player_ids: &[PlayerID]) {
+        for player_id in player_ids {
+            if let Some(player) = self.players.get_mut(player_id) {
+                if !player.latency_filter.is_in_progress() {
+                    player.latency_filter.start();
+                }
+            }
+        }
+    }
+
+    /// Creates `Update` packets holding the parts of a diff that brings the provided Player from
+    /// the latest generation it has confirmed to the room's latest generation, as seen by that
+    /// player. If the player has confirmed receiving some parts of a diff that is still in the
+    /// universe's history, only the parts of it that are missing are sent.
+    pub fn collect_universe_updates(&self, room: &Room, player: &Player) -> Vec<Packet> {
+        let (uni, game_info) = match (room.universe.as_ref(), player.game_info.as_ref()) {
+            (Some(uni), Some(game_info)) => (uni, game_info),
+            _ => return vec![],
+        };
+        let visibility = if game_info.spectator {
+            // The watched player may not be in this game if the room's options have changed
+            game_info.watched_index.filter(|&idx| idx < uni.num_players())
+        } else {
+            game_info.universe_index
+        };
+        let latest_gen = uni.latest_gen();
+
+        let mut parts = vec![];
+        if let Some(ref partial_gen) = game_info.partial_gen {
+            let (gen0, gen1) = (partial_gen.gen0 as usize, partial_gen.gen1 as usize);
+            let is_newer = game_info.last_full_gen.is_none_or(|last_full_gen| gen1 as u64 > last_full_gen);
+            if is_newer && gen0 < gen1 {
+                if let Ok(diff) = uni.diff(gen0, gen1, visibility) {
+                    parts = GenStateDiffPart::split(&diff);
+                    parts.retain(|part| !partial_gen.has_part(part.part_number));
+                }
+            }
+        }
+
+        if parts.is_empty() {
+            let gen0 = match game_info.last_full_gen {
+                Some(last_full_gen) if last_full_gen >= latest_gen as u64 => return vec![], // caught up
+                Some(last_full_gen) => last_full_gen as usize,
+                None => 0,
+            };
+            // If the player's generation is no longer in the history, send everything
+            let result = uni
+                .diff(gen0, latest_gen, visibility)
+                .or_else(|_| uni.diff(0, latest_gen, visibility));
+            match result {
+                Ok(diff) => parts = GenStateDiffPart::split(&diff),
+                Err(e) => {
+                    error!("Could not diff universe of room {:?}: {}", room.name, e);
+                    return vec![];
+                }
+            }
+        }
+
+        parts
+            .into_iter()
+            .map(|part| Packet::Update {
+                room:            Some(room.name.clone()),
+                chats:           vec![],
+                game_updates:    vec![],
+                game_update_seq: None,
+                universe_update: UniUpdate::Diff { diff: part },
+                ping:            PingPong::ping(),
+            })
+            .collect()
+    }
+
     /// Creates a vector of messages that the provided Player has not yet acknowledged.
     /// Exits early if the player is already caught up.
     pub fn collect_unacknowledged_messages(&self, room: &Room, player: &Player) -> Option<Vec<BroadcastChatMessage>> {
@@ -1203,6 +2514,61 @@ impl ServerState {
         return Some(unsent_messages);
     }
 
+    /// Creates a vector of the oldest game updates in the room that the provided Player has not yet
+    /// acknowledged, and returns it with the sequence number of the first of them. At most
+    /// `MAX_GAME_UPDATES_PER_PACKET` are collected, so that the rest are sent once these have been
+    /// acknowledged. Exits early if the player is already caught up.
+    pub fn collect_unacknowledged_game_updates(&self, room: &Room, player: &Player) -> Option<(u64, Vec<GameUpdate>)> {
+        let game_update_seq_num = player.game_info.as_ref()?.game_update_seq_num.unwrap_or(0);
+        if game_update_seq_num == room.latest_game_update_seq {
+            // Player is caught up
+            return None;
+        } else if game_update_seq_num > room.latest_game_update_seq {
+            error!(
+                "Misbehaving client {:?};\nClient says it has more game updates than we sent!",
+                player
+            );
+            return None;
+        }
+
+        let mut unsent: Vec<(u64, &GameUpdate)> = vec![];
+        for &(seq, ref game_update) in room
+            .game_updates
+            .iter()
+            .skip_while(|&&(seq, _)| seq <= game_update_seq_num)
+            .take(MAX_GAME_UPDATES_PER_PACKET)
+        {
+            // The client numbers the updates in a packet consecutively, so stop at a gap left by
+            // discarded updates
+            if unsent.last().is_some_and(|&(last_seq, _)| last_seq + 1 != seq) {
+                break;
+            }
+            unsent.push((seq, room.game_update_for(seq, game_update, player.player_id)));
+        }
+        let first_seq = unsent.first()?.0;
+        let unsent_game_updates = unsent.into_iter().map(|(_, game_update)| game_update.clone()).collect();
+        Some((first_seq, unsent_game_updates))
+    }
+
+    /// Discards the game updates that everyone in each room has acknowledged, except the
+    /// `GameStart` of the room's latest game, which players who join later still need.
+    pub fn discard_acknowledged_game_updates(&mut self) {
+        let players = &self.players;
+        for room in self.rooms.values_mut() {
+            let acknowledged_seq = room
+                .player_ids
+                .iter()
+                .filter_map(|p_id| players.get(p_id).and_then(|p| p.game_info.as_ref()))
+                .map(|game_info| game_info.game_update_seq_num.unwrap_or(0))
+                .min()
+                .unwrap_or(room.latest_game_update_seq);
+            let opt_game_start_seq = room.latest_game_start_seq();
+            room.game_updates
+                .retain(|&(seq, _)| seq > acknowledged_seq || Some(seq) == opt_game_start_seq);
+            room.game_update_overrides.retain(|&seq, _| seq > acknowledged_seq);
+        }
+    }
+
     pub fn expire_old_messages_in_all_rooms(&mut self, current_timestamp: time::Instant) {
         if self.rooms.len() != 0 {
             for room in self.rooms.values_mut() {
@@ -1219,15 +2585,18 @@ impl ServerState {
         let cookie = new_cookie();
         let player_id = PlayerID(new_uuid());
         let player = Player {
-            player_id:      player_id.clone(),
-            cookie:         cookie.clone(),
-            addr:           addr,
-            name:           name,
-            request_ack:    None,
-            next_resp_seq:  0,
-            game_info:      None,
-            last_received:  Instant::now(),
-            latency_filter: LatencyFilter::new(),
+            player_id,
+            cookie:           cookie.clone(),
+            addr,
+            name,
+            request_ack:      None,
+            next_resp_seq:    0,
+            game_info:        None,
+            last_received:    Instant::now(),
+            latency_filter:   LatencyFilter::new(),
+            options:          ClientOptions::default(),
+            lobby_update_seq: 0,
+            lobby_updates:    VecDeque::new(),
         };
 
         // save player into players hash map, and save player ID into hash map using cookie
@@ -1261,14 +2630,17 @@ impl ServerState {
     /// room -- "general".
     pub fn new() -> Self {
         let mut server_state = ServerState {
-            tick:        0,
-            name:        DEFAULT_NAME.to_owned(),
-            reg_params:  None,
-            players:     HashMap::<PlayerID, Player>::new(),
-            rooms:       HashMap::<RoomID, Room>::new(),
-            player_map:  HashMap::<String, PlayerID>::new(),
-            room_map:    HashMap::<String, RoomID>::new(),
-            network_map: HashMap::<PlayerID, NetworkManager>::new(),
+            tick:              0,
+            name:              DEFAULT_NAME.to_owned(),
+            reg_params:        None,
+            players:           HashMap::<PlayerID, Player>::new(),
+            rooms:             HashMap::<RoomID, Room>::new(),
+            player_map:        HashMap::<String, PlayerID>::new(),
+            room_map:          HashMap::<String, RoomID>::new(),
+            network_map:       HashMap::<PlayerID, NetworkManager>::new(),
+            match_queue:       VecDeque::new(),
+            matches:           vec![],
+            next_match_number: 0,
         };
         server_state.new_room("general".to_owned());
         server_state
@@ -1319,7 +2691,10 @@ impl ServerState {
 
     fn garbage_collection(&mut self) -> Vec<(SocketAddr, Packet)> {
         self.expire_old_messages_in_all_rooms(time::Instant::now());
-        let update_packets_vec = self.construct_client_updates();
+        self.discard_acknowledged_game_updates();
+        self.push_ping_changes(Instant::now());
+        let mut update_packets_vec = self.construct_client_updates();
+        update_packets_vec.extend(self.run_matchmaking(Instant::now()));
 
         self.remove_timed_out_clients();
         self.tick = 1usize.wrapping_add(self.tick);
@@ -1468,6 +2843,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
     let heartbeat_interval = TokioTime::interval(Duration::from_millis(HEARTBEAT_INTERVAL_IN_MS));
     let mut heartbeat_interval_stream = IntervalStream::new(heartbeat_interval).fuse();
 
+    let generation_interval = TokioTime::interval(Duration::from_millis(GENERATION_INTERVAL_IN_MS));
+    let mut generation_interval_stream = IntervalStream::new(generation_interval).fuse();
+
     let register_interval = TokioTime::interval(Duration::from_millis(REGISTER_INTERVAL_IN_MS));
     let mut register_interval_stream = IntervalStream::new(register_interval).fuse();
 
@@ -1491,6 +2869,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
                     udp_sink.send(packet_addr_tuple).await?;
                 }
             },
+            _ = generation_interval_stream.select_next_some() => {
+                let update_packets = server_state.advance_universes();
+                for (addr, packet) in update_packets {
+                    udp_sink.send((packet, addr)).await?;
+                }
+            },
             _ = register_interval_stream.select_next_some() => {
                 if let Some(ref reg_params) = server_state.reg_params {
                     tokio::spawn(try_register(reg_params.clone()));
@@ -1586,6 +2970,7 @@ mod netwayste_server_tests {
                 fake_socket_addr(),
                 Packet::UpdateReply {
                     cookie:               player_cookie.clone(),
+                    room:                 Some(room_name.to_owned()),
                     last_chat_seq:        Some(1),
                     last_game_update_seq: None,
                     last_full_gen:        None,
@@ -1606,6 +2991,7 @@ mod netwayste_server_tests {
                 fake_socket_addr(),
                 Packet::UpdateReply {
                     cookie:               player_cookie.clone(),
+                    room:                 Some(room_name.to_owned()),
                     last_chat_seq:        Some(0),
                     last_game_update_seq: None,
                     last_full_gen:        None,
@@ -1626,6 +3012,7 @@ mod netwayste_server_tests {
                 fake_socket_addr(),
                 Packet::UpdateReply {
                     cookie:               player_cookie,
+                    room:                 Some(room_name.to_owned()),
                     last_chat_seq:        None,
                     last_game_update_seq: None,
                     last_full_gen:        None,
@@ -2542,6 +3929,7 @@ mod netwayste_server_tests {
         // TODO: Move this into a private helper
         let update_reply_packet = Packet::UpdateReply {
             cookie:               cookie,
+            room:                 None,
             last_chat_seq:        Some(0),
             last_game_update_seq: None,
             last_full_gen:        None,
@@ -2566,6 +3954,7 @@ mod netwayste_server_tests {
 
         let update_reply_packet = Packet::UpdateReply {
             cookie:               cookie,
+            room:                 None,
             last_chat_seq:        Some(0),
             last_game_update_seq: None,
             last_full_gen:        None,
@@ -2606,6 +3995,11 @@ mod netwayste_server_tests {
             player.player_id
         };
         server.join_room(player_id, room_name);
+        // Assume that the client has the roster updates sent when it joined
+        let latest_game_update_seq = server.get_room(player_id).unwrap().latest_game_update_seq;
+        server
+            .get_player_mut(player_id)
+            .update_game_update_seq_num(Some(latest_game_update_seq));
         server.handle_chat_message(player_id, message_text.clone());
         server.handle_chat_message(player_id, message_text.clone());
         server.handle_chat_message(player_id, message_text.clone());
@@ -2620,12 +4014,14 @@ mod netwayste_server_tests {
 
         match pkt {
             Packet::Update {
+                room,
                 chats,
                 game_updates,
                 game_update_seq,
                 universe_update,
                 ping: _,
             } => {
+                assert_eq!(room, Some(room_name.to_owned()));
                 assert!(game_updates.is_empty());
                 assert!(game_update_seq.is_none());
                 assert_eq!(universe_update, UniUpdate::NoChange);
@@ -2659,6 +4055,11 @@ mod netwayste_server_tests {
             player.player_id
         };
         server.join_room(player_id, room_name);
+        // Assume that the client has the roster updates sent when it joined
+        let latest_game_update_seq = server.get_room(player_id).unwrap().latest_game_update_seq;
+        server
+            .get_player_mut(player_id)
+            .update_game_update_seq_num(Some(latest_game_update_seq));
         server.handle_chat_message(player_id, message_text.clone());
         server.handle_chat_message(player_id, message_text.clone());
         server.handle_chat_message(player_id, message_text.clone());
@@ -2680,12 +4081,14 @@ mod netwayste_server_tests {
 
         match pkt {
             Packet::Update {
+                room,
                 mut chats,
                 game_updates,
                 game_update_seq,
                 universe_update,
                 ping: _,
             } => {
+                assert_eq!(room, Some(room_name.to_owned()));
                 assert!(game_updates.is_empty());
                 assert!(game_update_seq.is_none());
                 assert_eq!(universe_update, UniUpdate::NoChange);
@@ -2702,6 +4105,1512 @@ mod netwayste_server_tests {
         }
     }
 
+    /// Makes a room named "some room" with two players, which starts a game.
+    fn new_server_with_game() -> (ServerState, PlayerID, PlayerID) {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id, room_name);
+        server.join_room(player_id2, room_name);
+        let now = Instant::now();
+        server.update_room_states(now);
+        server.update_room_states(now + Duration::from_secs(GAME_COUNTDOWN_SECS));
+        (server, player_id, player_id2)
+    }
+
+    /// What a room is told about a player who is not ready and whose ping has not been measured.
+    fn player_info(name: &str, index: Option<u64>) -> PlayerInfo {
+        PlayerInfo {
+            name:  name.to_owned(),
+            index,
+            ping:  None,
+            ready: false,
+        }
+    }
+
+    fn update_parts(packets: Vec<Packet>) -> Vec<GenStateDiffPart> {
+        packets
+            .into_iter()
+            .map(|packet| match packet {
+                Packet::Update {
+                    universe_update: UniUpdate::Diff { diff },
+                    ..
+                } => diff,
+                packet => panic!("Unexpected packet: {:?}", packet),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn join_room_game_starts_when_enough_players_join() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id, room_name);
+        assert_eq!(server.advance_universes().len(), 0);
+        assert_eq!(server.get_room(player_id).unwrap().state, RoomState::Waiting);
+
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id2, room_name);
+        let now = Instant::now();
+        server.update_room_states(now);
+        let starts = now + Duration::from_secs(GAME_COUNTDOWN_SECS);
+        assert_eq!(
+            server.get_room(player_id).unwrap().state,
+            RoomState::Countdown { starts }
+        );
+        server.update_room_states(starts);
+        {
+            let room = server.get_room(player_id).unwrap();
+            assert_eq!(room.state, RoomState::Running);
+            assert_eq!(room.universe.as_ref().unwrap().num_players(), PLAYERS_PER_GAME);
+        }
+        let universe_index = |server: &ServerState, p_id| {
+            let player = server.get_player(p_id);
+            player.game_info.as_ref().unwrap().universe_index
+        };
+        assert_eq!(universe_index(&server, player_id), Some(0));
+        assert_eq!(universe_index(&server, player_id2), Some(1));
+
+        // Latecomers only watch
+        let player_id3 = server.add_new_player("player three".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id3, room_name);
+        assert_eq!(universe_index(&server, player_id3), None);
+    }
+
+    #[test]
+    fn leave_room_game_ends_when_room_is_empty() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        let room_id = server.get_room_id(player_id).unwrap();
+
+        server.leave_room(player_id);
+        assert!(server.rooms.get(&room_id).unwrap().universe.is_some());
+        server.leave_room(player_id2);
+        let room = server.rooms.get(&room_id).unwrap();
+        assert!(room.universe.is_none());
+        assert_eq!(room.state, RoomState::Waiting);
+    }
+
+    #[test]
+    fn leave_room_tells_room_player_left() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        server.leave_room(player_id2);
+
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(
+            room.game_updates.back().unwrap().1,
+            GameUpdate::PlayerLeave {
+                name: "player two".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn spectate_room_spectators_do_not_play() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        server.create_new_room(None, room_name.to_owned());
+
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let spectator_id = server.add_new_player("spectator".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id, room_name);
+        assert_eq!(
+            server.spectate_room(spectator_id, room_name, None),
+            ResponseCode::JoinedRoom {
+                room_name: room_name.to_owned(),
+            }
+        );
+        assert!(server.get_player(spectator_id).is_spectator());
+        match server.list_rooms() {
+            ResponseCode::RoomList { rooms } => {
+                let room = rooms.iter().find(|room| room.room_name == room_name).unwrap();
+                assert_eq!((room.player_count, room.spectator_count), (1, 1));
+            }
+            response => panic!("Unexpected response: {:?}", response),
+        }
+
+        // A spectator does not count towards the players needed for a game
+        server.update_room_states(Instant::now());
+        assert_eq!(server.get_room(player_id).unwrap().state, RoomState::Waiting);
+
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id2, room_name);
+        let now = Instant::now();
+        server.update_room_states(now);
+        server.update_room_states(now + Duration::from_secs(GAME_COUNTDOWN_SECS));
+        assert_eq!(server.get_room(player_id).unwrap().state, RoomState::Running);
+        let universe_index = |server: &ServerState, p_id| {
+            let player = server.get_player(p_id);
+            player.game_info.as_ref().unwrap().universe_index
+        };
+        assert_eq!(universe_index(&server, player_id), Some(0));
+        assert_eq!(universe_index(&server, spectator_id), None);
+        assert_eq!(universe_index(&server, player_id2), Some(1));
+
+        let action = RequestAction::DropPattern {
+            x:       10,
+            y:       10,
+            pattern: "3o!".to_owned(),
+        };
+        assert_eq!(
+            server.process_request_action(spectator_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "cannot drop pattern because not playing in a game".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn spectate_room_watch_index_must_be_a_player_in_the_game() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        server.create_new_room(None, room_name.to_owned());
+        let spectator_id = server.add_new_player("spectator".to_owned(), fake_socket_addr()).player_id;
+
+        let action = RequestAction::SpectateRoom {
+            room_name:   room_name.to_owned(),
+            watch_index: Some(PLAYERS_PER_GAME as u64),
+        };
+        assert_eq!(
+            server.process_request_action(spectator_id, action),
+            ResponseCode::BadRequest {
+                error_msg: format!("no player with index {} in room {:?}", PLAYERS_PER_GAME, room_name),
+            }
+        );
+        assert!(!server.is_player_in_game(spectator_id));
+
+        let action = RequestAction::SpectateRoom {
+            room_name:   room_name.to_owned(),
+            watch_index: Some(1),
+        };
+        assert_eq!(
+            server.process_request_action(spectator_id, action),
+            ResponseCode::JoinedRoom {
+                room_name: room_name.to_owned(),
+            }
+        );
+        assert_eq!(server.get_player(spectator_id).game_info.as_ref().unwrap().watched_index, Some(1));
+    }
+
+    #[test]
+    fn set_game_options_only_room_owner_before_game_starts() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        assert_eq!(server.create_new_room(Some(player_id), room_name.to_owned()), ResponseCode::OK);
+
+        let options = GameOptions::new(128, 64, 3);
+        let action = RequestAction::SetGameOptions {
+            options: options.clone(),
+        };
+        assert_eq!(
+            server.process_request_action(player_id, action.clone()),
+            ResponseCode::BadRequest {
+                error_msg: "cannot set game options because in lobby".to_owned(),
+            }
+        );
+        server.join_room(player_id, room_name);
+        server.join_room(player_id2, room_name);
+        assert_eq!(
+            server.process_request_action(player_id2, action.clone()),
+            ResponseCode::BadRequest {
+                error_msg: "only the player who created the room can set its game options".to_owned(),
+            }
+        );
+        assert_eq!(server.process_request_action(player_id, action.clone()), ResponseCode::OK);
+        assert_eq!(server.get_room(player_id).unwrap().options, options);
+
+        // Two players are no longer enough
+        server.update_room_states(Instant::now());
+        assert_eq!(server.get_room(player_id).unwrap().state, RoomState::Waiting);
+
+        let player_id3 = server.add_new_player("player three".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id3, room_name);
+        server.update_room_states(Instant::now());
+        assert_eq!(
+            server.process_request_action(player_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "cannot set game options once the game has started".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn set_game_options_invalid_options_are_bad_requests() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("some player".to_owned(), fake_socket_addr()).player_id;
+        server.create_new_room(Some(player_id), "some room".to_owned());
+        server.join_room(player_id, "some room");
+
+        let mut set_options = |options: GameOptions| {
+            let action = RequestAction::SetGameOptions { options };
+            match server.process_request_action(player_id, action) {
+                ResponseCode::BadRequest { error_msg } => error_msg,
+                response => panic!("Unexpected response: {:?}", response),
+            }
+        };
+        assert_eq!(
+            set_options(GameOptions::new(128, 64, 1)),
+            "game must have from 2 to 8 players, not 1"
+        );
+        assert_eq!(
+            set_options(GameOptions::new(1024, 64, 2)),
+            "universe of 1024x64 cells is larger than the maximum of 512x512 cells"
+        );
+        let mut options = GameOptions::new(128, 64, 2);
+        options.player_writable[1].top = 32;
+        assert_eq!(
+            set_options(options),
+            "writable region of player 1 is not within the universe"
+        );
+        assert!(set_options(GameOptions::new(100, 64, 2)).starts_with("invalid game options: "));
+    }
+
+    #[test]
+    fn update_room_states_countdown_is_canceled_when_player_leaves() {
+        let mut server = ServerState::new();
+        server.create_new_room(None, "some room".to_owned());
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id, "some room");
+        server.join_room(player_id2, "some room");
+
+        let now = Instant::now();
+        server.update_room_states(now);
+        match server.list_rooms() {
+            ResponseCode::RoomList { rooms } => {
+                for room in rooms {
+                    assert_eq!(room.in_progress, room.room_name == "some room");
+                }
+            }
+            response => panic!("Unexpected response: {:?}", response),
+        }
+
+        server.leave_room(player_id2);
+        server.update_room_states(now + Duration::from_secs(GAME_COUNTDOWN_SECS));
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(room.state, RoomState::Waiting);
+        assert!(room.universe.is_none());
+        assert_eq!(
+            room.game_updates.back().map(|(_, game_update)| game_update),
+            Some(&GameUpdate::GameNotification {
+                msg: "Countdown canceled; waiting for more players.".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn update_room_states_game_is_forfeited_to_last_player() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        server.leave_room(player_id2);
+        let now = Instant::now();
+        server.update_room_states(now);
+
+        let outcome = GameOutcome {
+            winner: Some("player one".to_owned()),
+        };
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(
+            room.state,
+            RoomState::Finished {
+                outcome: outcome.clone(),
+                since:   now,
+            }
+        );
+        assert_eq!(room.game_updates.back().unwrap().1, GameUpdate::GameFinish { outcome });
+
+        // The game no longer accepts edits
+        let action = RequestAction::ClearArea { x: 0, y: 0, w: 1, h: 1 };
+        assert_eq!(
+            server.process_request_action(player_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "cannot clear area because not playing in a game".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn update_room_states_player_with_most_cells_wins_after_max_generations() {
+        use conway::universe::CellState;
+
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.create_new_room(Some(player_id), "some room".to_owned());
+        server.join_room(player_id, "some room");
+        server.join_room(player_id2, "some room");
+        let action = RequestAction::SetGameOptions {
+            options: GameOptions::new(64, 16, 2),
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+        let now = Instant::now();
+        server.update_room_states(now);
+        server.update_room_states(now + Duration::from_secs(GAME_COUNTDOWN_SECS));
+
+        {
+            // A block in the second player's region
+            let room = server.get_room_mut(player_id).unwrap();
+            let uni = room.universe.as_mut().unwrap();
+            for &(col, row) in &[(40, 5), (41, 5), (40, 6), (41, 6)] {
+                uni.set_unchecked(col, row, CellState::Alive(Some(1)));
+            }
+            while uni.latest_gen() < MAX_GAME_GENERATIONS - 1 {
+                uni.next();
+            }
+        }
+        server.update_room_states(now);
+        assert_eq!(server.get_room(player_id).unwrap().state, RoomState::Running);
+
+        server.get_room_mut(player_id).unwrap().advance_universe();
+        server.update_room_states(now);
+        assert_eq!(
+            server.get_room(player_id).unwrap().state,
+            RoomState::Finished {
+                outcome: GameOutcome {
+                    winner: Some("player two".to_owned()),
+                },
+                since:   now,
+            }
+        );
+    }
+
+    #[test]
+    fn update_room_states_room_without_owner_plays_again() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id, "general");
+        server.join_room(player_id2, "general");
+        assert_eq!(server.get_room(player_id).unwrap().owner, None);
+        let now = Instant::now();
+        server.update_room_states(now);
+        let now = now + Duration::from_secs(GAME_COUNTDOWN_SECS);
+        server.update_room_states(now);
+        let first_start_seq = server.get_room(player_id).unwrap().latest_game_start_seq();
+        server
+            .get_room_mut(player_id)
+            .unwrap()
+            .finish_game(GameOutcome { winner: None }, now);
+
+        // The outcome is shown for a while
+        server.update_room_states(now + Duration::from_secs(GAME_RESET_SECS - 1));
+        assert!(matches!(
+            server.get_room(player_id).unwrap().state,
+            RoomState::Finished { .. }
+        ));
+
+        let now = now + Duration::from_secs(GAME_RESET_SECS);
+        server.update_room_states(now);
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(room.state, RoomState::Waiting);
+        assert!(room.universe.is_none());
+        assert_eq!(server.get_player(player_id).game_info.as_ref().unwrap().universe_index, None);
+
+        // Both players are still there, so a second game counts down and starts
+        server.update_room_states(now);
+        server.update_room_states(now + Duration::from_secs(GAME_COUNTDOWN_SECS));
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(room.state, RoomState::Running);
+        assert!(room.latest_game_start_seq() > first_start_seq);
+        assert_eq!(server.get_player(player_id2).game_info.as_ref().unwrap().universe_index, Some(1));
+    }
+
+    #[test]
+    fn update_room_states_room_with_owner_stays_finished() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        server.create_new_room(Some(player_id), "some room".to_owned());
+        server.join_room(player_id, "some room");
+        let now = Instant::now();
+        server
+            .get_room_mut(player_id)
+            .unwrap()
+            .finish_game(GameOutcome { winner: None }, now);
+
+        server.update_room_states(now + Duration::from_secs(GAME_RESET_SECS));
+        assert!(matches!(
+            server.get_room(player_id).unwrap().state,
+            RoomState::Finished { .. }
+        ));
+    }
+
+    #[test]
+    fn leave_room_owner_passes_ownership_to_next_player() {
+        let mut server = ServerState::new();
+        let room_name = "some room";
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let spectator_id = server.add_new_player("spectator".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        server.create_new_room(Some(player_id), room_name.to_owned());
+        server.join_room(player_id, room_name);
+        let action = RequestAction::SpectateRoom {
+            room_name:   room_name.to_owned(),
+            watch_index: None,
+        };
+        assert!(matches!(
+            server.process_request_action(spectator_id, action),
+            ResponseCode::JoinedRoom { .. }
+        ));
+        server.join_room(player_id2, room_name);
+
+        // Someone other than the owner leaving changes nothing
+        let player_id3 = server.add_new_player("player three".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id3, room_name);
+        server.leave_room(player_id3);
+        assert_eq!(server.get_room(player_id).unwrap().owner, Some(player_id));
+
+        server.leave_room(player_id);
+        let room = server.get_room(player_id2).unwrap();
+        assert_eq!(room.owner, Some(player_id2));
+        assert_eq!(
+            room.game_updates.back().unwrap().1,
+            GameUpdate::GameNotification {
+                msg: "player two now owns the room, and may set its game options.".to_owned(),
+            }
+        );
+        let action = RequestAction::SetGameOptions {
+            options: GameOptions::new(128, 64, 3),
+        };
+        assert_eq!(server.process_request_action(player_id2, action), ResponseCode::OK);
+
+        // With only a spectator left, nobody owns the room
+        server.leave_room(player_id2);
+        assert_eq!(server.get_room(spectator_id).unwrap().owner, None);
+    }
+
+    #[test]
+    fn construct_client_updates_game_updates_are_sent_until_acknowledged() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        let cookie = server.get_player(player_id).cookie.clone();
+
+        let updates = server.construct_client_updates();
+        assert_eq!(updates.len(), 2);
+        match updates[0].1 {
+            Packet::Update {
+                game_update_seq,
+                ref game_updates,
+                ..
+            } => {
+                assert_eq!(game_update_seq, Some(1));
+                assert_eq!(
+                    game_updates[..],
+                    [
+                        // A newcomer is sent everyone in the room in place of their own PlayerJoin
+                        GameUpdate::PlayerList {
+                            players: vec![player_info("player one", None)],
+                        },
+                        GameUpdate::PlayerJoin {
+                            player: player_info("player two", None),
+                        },
+                        GameUpdate::GameNotification {
+                            msg: format!("Game starts in {} seconds.", GAME_COUNTDOWN_SECS),
+                        },
+                        GameUpdate::GameStart {
+                            options: default_game_options(),
+                        },
+                        GameUpdate::PlayerList {
+                            players: vec![player_info("player one", Some(0)), player_info("player two", Some(1))],
+                        },
+                    ]
+                );
+            }
+            ref packet => panic!("Unexpected packet: {:?}", packet),
+        }
+
+        server
+            .decode_packet(
+                fake_socket_addr(),
+                Packet::UpdateReply {
+                    cookie,
+                    room:                 Some("some room".to_owned()),
+                    last_chat_seq:        None,
+                    last_game_update_seq: Some(5),
+                    last_full_gen:        None,
+                    partial_gen:          None,
+                    pong:                 PingPong::pong(0),
+                },
+            )
+            .unwrap();
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(
+            server.collect_unacknowledged_game_updates(room, server.get_player(player_id)),
+            None
+        );
+        // Player two joined after the first update
+        let (game_update_seq, game_updates) = server
+            .collect_unacknowledged_game_updates(room, server.get_player(player_id2))
+            .unwrap();
+        assert_eq!((game_update_seq, game_updates.len()), (2, 4));
+        assert_eq!(
+            game_updates[0],
+            GameUpdate::PlayerList {
+                players: vec![player_info("player one", None), player_info("player two", None)],
+            }
+        );
+    }
+
+    #[test]
+    fn decode_packet_update_reply_ignores_acks_from_other_stream() {
+        let (mut server, player_id, _) = new_server_with_game();
+        let cookie = server.get_player(player_id).cookie.clone();
+        let latest_seq = server.get_room(player_id).unwrap().latest_game_update_seq;
+        let update_reply = |room: Option<&str>, last_game_update_seq| Packet::UpdateReply {
+            cookie:               cookie.clone(),
+            room:                 room.map(str::to_owned),
+            last_chat_seq:        None,
+            last_game_update_seq,
+            last_full_gen:        None,
+            partial_gen:          None,
+            pong:                 PingPong::pong(0),
+        };
+
+        // A client that has not heard it joined acknowledges lobby updates, numbered apart
+        server
+            .decode_packet(fake_socket_addr(), update_reply(None, Some(latest_seq)))
+            .unwrap();
+        server.discard_acknowledged_game_updates();
+        let room = server.get_room(player_id).unwrap();
+        let (first_seq, _) = server
+            .collect_unacknowledged_game_updates(room, server.get_player(player_id))
+            .unwrap();
+        assert_eq!(first_seq, 1);
+
+        server
+            .decode_packet(fake_socket_addr(), update_reply(Some("some room"), Some(latest_seq)))
+            .unwrap();
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(
+            server.collect_unacknowledged_game_updates(room, server.get_player(player_id)),
+            None
+        );
+
+        // Nor are a room's acknowledgements taken for the lobby's once the player has left
+        assert_eq!(server.leave_room(player_id), ResponseCode::LeaveRoom);
+        server.get_player_mut(player_id).push_lobby_update(GameUpdate::RoomDeleted);
+        server
+            .decode_packet(fake_socket_addr(), update_reply(Some("some room"), Some(latest_seq)))
+            .unwrap();
+        assert_eq!(server.get_player(player_id).lobby_updates.len(), 1);
+        server
+            .decode_packet(fake_socket_addr(), update_reply(None, Some(1)))
+            .unwrap();
+        assert!(server.get_player(player_id).lobby_updates.is_empty());
+    }
+
+    #[test]
+    fn collect_unacknowledged_game_updates_are_limited_per_packet() {
+        let (mut server, player_id, _) = new_server_with_game();
+        let cookie = server.get_player(player_id).cookie.clone();
+        {
+            let room = server.get_room_mut(player_id).unwrap();
+            for i in 0..MAX_GAME_UPDATES_PER_PACKET {
+                room.push_game_update(GameUpdate::GameNotification {
+                    msg: format!("notification {}", i),
+                });
+            }
+        }
+        let total = server.get_room(player_id).unwrap().latest_game_update_seq;
+
+        let mut acked = 0;
+        while acked < total {
+            let room = server.get_room(player_id).unwrap();
+            let (first_seq, game_updates) = server
+                .collect_unacknowledged_game_updates(room, server.get_player(player_id))
+                .unwrap();
+            assert_eq!(first_seq, acked + 1);
+            assert!(game_updates.len() <= MAX_GAME_UPDATES_PER_PACKET);
+            let (seq, ref game_update) = room.game_updates[acked as usize];
+            assert_eq!(&game_updates[0], room.game_update_for(seq, game_update, player_id));
+
+            // Until acknowledged, the same updates are resent
+            let (resent_seq, resent_game_updates) = server
+                .collect_unacknowledged_game_updates(room, server.get_player(player_id))
+                .unwrap();
+            assert_eq!((resent_seq, &resent_game_updates), (first_seq, &game_updates));
+
+            acked += game_updates.len() as u64;
+            server
+                .decode_packet(
+                    fake_socket_addr(),
+                    Packet::UpdateReply {
+                        cookie:               cookie.clone(),
+                        room:                 Some("some room".to_owned()),
+                        last_chat_seq:        None,
+                        last_game_update_seq: Some(acked),
+                        last_full_gen:        None,
+                        partial_gen:          None,
+                        pong:                 PingPong::pong(0),
+                    },
+                )
+                .unwrap();
+        }
+        assert_eq!(acked, total);
+        let room = server.get_room(player_id).unwrap();
+        assert_eq!(
+            server.collect_unacknowledged_game_updates(room, server.get_player(player_id)),
+            None
+        );
+    }
+
+    #[test]
+    fn join_room_latecomer_gets_game_updates_from_game_start() {
+        let (mut server, player_id, _) = new_server_with_game();
+        let player_id3 = server.add_new_player("player three".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id3, "some room");
+
+        let room = server.get_room(player_id).unwrap();
+        let (first_seq, game_updates) = server
+            .collect_unacknowledged_game_updates(room, server.get_player(player_id3))
+            .unwrap();
+        assert_eq!(Some(first_seq), room.latest_game_start_seq());
+        assert_eq!(
+            game_updates,
+            vec![
+                GameUpdate::GameStart {
+                    options: default_game_options(),
+                },
+                GameUpdate::PlayerList {
+                    players: vec![player_info("player one", Some(0)), player_info("player two", Some(1))],
+                },
+                GameUpdate::PlayerList {
+                    players: vec![
+                        player_info("player one", Some(0)),
+                        player_info("player two", Some(1)),
+                        player_info("player three", None),
+                    ],
+                },
+            ]
+        );
+
+        // The others are only told about the newcomer
+        let (_, game_updates) = server
+            .collect_unacknowledged_game_updates(room, server.get_player(player_id))
+            .unwrap();
+        assert_eq!(
+            game_updates.last(),
+            Some(&GameUpdate::PlayerJoin {
+                player: player_info("player three", None),
+            })
+        );
+    }
+
+    #[test]
+    fn discard_acknowledged_game_updates_keeps_game_start_and_unacknowledged_updates() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        let (start_seq, latest_seq) = {
+            let room = server.get_room_mut(player_id).unwrap();
+            for i in 0..3 {
+                room.push_game_update(GameUpdate::GameNotification {
+                    msg: format!("notification {}", i),
+                });
+            }
+            (room.latest_game_start_seq().unwrap(), room.latest_game_update_seq)
+        };
+        server
+            .get_player_mut(player_id)
+            .update_game_update_seq_num(Some(latest_seq));
+        server
+            .get_player_mut(player_id2)
+            .update_game_update_seq_num(Some(latest_seq - 2));
+
+        server.discard_acknowledged_game_updates();
+        let seqs: Vec<u64> = server
+            .get_room(player_id)
+            .unwrap()
+            .game_updates
+            .iter()
+            .map(|&(seq, _)| seq)
+            .collect();
+        assert_eq!(seqs, vec![start_seq, latest_seq - 1, latest_seq]);
+        // The PlayerLists the players were sent on joining are discarded along with them
+        assert!(server.get_room(player_id).unwrap().game_update_overrides.is_empty());
+
+        // A player who joins gets the GameStart on its own, since the updates after it are gone
+        let player_id3 = server.add_new_player("player three".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id3, "some room");
+        let room = server.get_room(player_id3).unwrap();
+        let (first_seq, game_updates) = server
+            .collect_unacknowledged_game_updates(room, server.get_player(player_id3))
+            .unwrap();
+        assert_eq!(first_seq, start_seq);
+        assert_eq!(
+            game_updates,
+            vec![GameUpdate::GameStart {
+                options: room.options.clone(),
+            }]
+        );
+    }
+
+    #[test]
+    fn construct_universe_updates_waits_before_resending_unconfirmed_updates() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        let universe_sent = |server: &ServerState, p_id| server.get_player(p_id).game_info.as_ref().unwrap().universe_sent;
+        let now = Instant::now();
+
+        assert!(server.construct_universe_updates(now).len() >= 2);
+        assert_eq!(universe_sent(&server, player_id), Some((1, now)));
+        assert_eq!(universe_sent(&server, player_id2), Some((1, now)));
+
+        // Nothing is resent while the parts may still be in flight
+        let soon = now + Duration::from_millis(10);
+        assert_eq!(server.construct_universe_updates(soon), vec![]);
+
+        // A player who confirmed the last update gets the next one right away
+        server.get_player_mut(player_id).update_universe_gen(Some(1), None);
+        server.get_room_mut(player_id).unwrap().advance_universe();
+        assert!(!server.construct_universe_updates(soon).is_empty());
+        assert_eq!(universe_sent(&server, player_id), Some((2, soon)));
+        assert_eq!(universe_sent(&server, player_id2), Some((1, now)));
+
+        let later = now + Duration::from_millis(UNIVERSE_RESEND_MIN_MS);
+        assert!(!server.construct_universe_updates(later).is_empty());
+        assert_eq!(universe_sent(&server, player_id2), Some((2, later)));
+    }
+
+    #[test]
+    fn advance_universes_sends_diffs_from_last_full_gen() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        let cookie = server.get_player(player_id).cookie.clone();
+        let game_update_seq = server.get_room(player_id).unwrap().latest_game_update_seq;
+
+        // Both players get the whole universe at first
+        assert!(server.advance_universes().len() >= 2);
+        for &p_id in &[player_id, player_id2] {
+            let room = server.get_room(p_id).unwrap();
+            let parts = update_parts(server.collect_universe_updates(room, server.get_player(p_id)));
+            let diff = GenStateDiffPart::join(&parts).unwrap();
+            assert_eq!((diff.gen0, diff.gen1), (0, 2));
+        }
+
+        server
+            .decode_packet(
+                fake_socket_addr(),
+                Packet::UpdateReply {
+                    cookie:               cookie.clone(),
+                    room:                 Some("some room".to_owned()),
+                    last_chat_seq:        None,
+                    last_game_update_seq: Some(game_update_seq),
+                    last_full_gen:        Some(2),
+                    partial_gen:          None,
+                    pong:                 PingPong::pong(0),
+                },
+            )
+            .unwrap();
+
+        let room = server.get_room(player_id).unwrap();
+        let player = server.get_player(player_id);
+        assert_eq!(server.collect_universe_updates(room, player), vec![]); // caught up
+
+        server.advance_universes();
+        let room = server.get_room(player_id).unwrap();
+        let parts = update_parts(server.collect_universe_updates(room, server.get_player(player_id)));
+        assert_eq!((parts[0].gen0, parts[0].gen1), (2, 3));
+        let parts = update_parts(server.collect_universe_updates(room, server.get_player(player_id2)));
+        assert_eq!((parts[0].gen0, parts[0].gen1), (0, 3));
+    }
+
+    #[test]
+    fn drop_pattern_not_in_game_is_bad_request() {
+        let mut server = ServerState::new();
+        server.create_new_room(None, "some room".to_owned());
+        let player_id = server.add_new_player("some player".to_owned(), fake_socket_addr()).player_id;
+        server.join_room(player_id, "some room"); // game has not started
+
+        let action = RequestAction::DropPattern {
+            x:       10,
+            y:       10,
+            pattern: "3o!".to_owned(),
+        };
+        assert_eq!(
+            server.process_request_action(player_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "cannot drop pattern because not playing in a game".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn drop_pattern_appears_in_next_generation() {
+        use conway::universe::CellState;
+
+        let (mut server, player_id, _) = new_server_with_game();
+        let action = RequestAction::DropPattern {
+            x:       10,
+            y:       20,
+            pattern: "3o!".to_owned(),
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+        {
+            // Nothing changes until the next generation
+            let uni = server.get_room_mut(player_id).unwrap().universe.as_mut().unwrap();
+            assert_eq!(uni.latest_gen(), 1);
+            assert_eq!(uni.get_cell_state(11, 20, None), CellState::Dead);
+        }
+
+        server.advance_universes();
+        let uni = server.get_room_mut(player_id).unwrap().universe.as_mut().unwrap();
+        assert_eq!(uni.latest_gen(), 2);
+        for col in 10..13 {
+            assert_eq!(uni.get_cell_state(col, 20, Some(0)), CellState::Alive(Some(0)));
+        }
+        assert_eq!(uni.get_cell_state(11, 19, None), CellState::Dead);
+    }
+
+    #[test]
+    fn drop_pattern_outside_writable_region_is_bad_request() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        // Player one may write to columns 0 to 127, and player two to columns 128 to 255
+        for &(p_id, x, y) in &[(player_id, 126, 0), (player_id, -1, 0), (player_id2, 127, 0), (player_id2, 200, 127)] {
+            let action = RequestAction::DropPattern {
+                x,
+                y,
+                pattern: "3o$3o!".to_owned(),
+            };
+            match server.process_request_action(p_id, action) {
+                ResponseCode::BadRequest { error_msg } => {
+                    assert!(error_msg.contains("is not within writable region"), "{}", error_msg);
+                }
+                response => panic!("Unexpected response at ({}, {}): {:?}", x, y, response),
+            }
+        }
+        assert!(server.get_room(player_id).unwrap().pending_edits.is_empty());
+    }
+
+    #[test]
+    fn drop_pattern_invalid_rle_is_bad_request() {
+        let (mut server, player_id, _) = new_server_with_game();
+        for pattern in &["3o", "2o$q!"] {
+            let action = RequestAction::DropPattern {
+                x:       10,
+                y:       10,
+                pattern: pattern.to_string(),
+            };
+            match server.process_request_action(player_id, action) {
+                ResponseCode::BadRequest { error_msg } => {
+                    assert!(error_msg.starts_with("invalid pattern: "), "{}", error_msg);
+                }
+                response => panic!("Unexpected response: {:?}", response),
+            }
+        }
+    }
+
+    #[test]
+    fn drop_pattern_larger_than_writable_region_is_bad_request() {
+        let (mut server, player_id, _) = new_server_with_game();
+        // Player one may write to a region of 128x128 cells
+        let long_runs = format!("{}!", "50000o".repeat(1000));
+        for pattern in &["129o!", "o128$o!", "50000o!", "o50000$o!", &long_runs] {
+            let action = RequestAction::DropPattern {
+                x:       0,
+                y:       0,
+                pattern: pattern.to_string(),
+            };
+            match server.process_request_action(player_id, action) {
+                ResponseCode::BadRequest { error_msg } => {
+                    assert!(error_msg.contains("is larger than writable region of 128x128 cells"), "{}", error_msg);
+                }
+                response => panic!("Unexpected response: {:?}", response),
+            }
+        }
+
+        // Too large to even parse
+        let action = RequestAction::DropPattern {
+            x:       0,
+            y:       0,
+            pattern: "999999999999o!".to_owned(),
+        };
+        match server.process_request_action(player_id, action) {
+            ResponseCode::BadRequest { error_msg } => {
+                assert!(error_msg.starts_with("invalid pattern: "), "{}", error_msg);
+            }
+            response => panic!("Unexpected response: {:?}", response),
+        }
+        assert!(server.get_room(player_id).unwrap().pending_edits.is_empty());
+    }
+
+    #[test]
+    fn drop_pattern_is_previewed_after_pending_edits() {
+        use conway::universe::EnergyBudget;
+
+        let (mut server, player_id, _) = new_server_with_game();
+        let budget = EnergyBudget {
+            initial:              5,
+            max:                  5,
+            cell_cost:            1,
+            regen_per_generation: 0,
+            regen_per_live_cell:  0,
+        };
+        {
+            let uni = server.get_room_mut(player_id).unwrap().universe.as_mut().unwrap();
+            uni.set_energy_budget(0, Some(budget)).unwrap();
+        }
+        let drop_at = |y| RequestAction::DropPattern {
+            x:       10,
+            y,
+            pattern: "3o!".to_owned(),
+        };
+
+        // Either drop is affordable, but not both
+        assert_eq!(server.process_request_action(player_id, drop_at(10)), ResponseCode::OK);
+        match server.process_request_action(player_id, drop_at(20)) {
+            ResponseCode::BadRequest { error_msg } => {
+                assert!(error_msg.starts_with("cannot drop pattern: "), "{}", error_msg);
+            }
+            response => panic!("Unexpected response: {:?}", response),
+        }
+        assert_eq!(server.get_room(player_id).unwrap().pending_edits.len(), 1);
+
+        // Dropping the same cells again is free, unless they are cleared first
+        server.advance_universes();
+        assert_eq!(server.process_request_action(player_id, drop_at(10)), ResponseCode::OK);
+        let action = RequestAction::ClearArea {
+            x: 10,
+            y: 10,
+            w: 3,
+            h: 1,
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+        match server.process_request_action(player_id, drop_at(10)) {
+            ResponseCode::BadRequest { error_msg } => {
+                assert!(error_msg.starts_with("cannot drop pattern: "), "{}", error_msg);
+            }
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    #[test]
+    fn drop_pattern_that_fails_when_made_is_announced() {
+        use conway::universe::EnergyBudget;
+
+        let (mut server, player_id, _) = new_server_with_game();
+        let action = RequestAction::DropPattern {
+            x:       10,
+            y:       10,
+            pattern: "3o!".to_owned(),
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+        {
+            // The player's energy runs out before the pattern is dropped
+            let budget = EnergyBudget {
+                initial:              0,
+                max:                  5,
+                cell_cost:            1,
+                regen_per_generation: 0,
+                regen_per_live_cell:  0,
+            };
+            let uni = server.get_room_mut(player_id).unwrap().universe.as_mut().unwrap();
+            uni.set_energy_budget(0, Some(budget)).unwrap();
+        }
+
+        server.advance_universes();
+        let room = server.get_room_mut(player_id).unwrap();
+        assert_eq!(room.universe.as_mut().unwrap().get_cell_state(11, 10, None), CellState::Dead);
+        match room.game_updates.back() {
+            Some(&(_, GameUpdate::GameNotification { ref msg })) => {
+                assert!(msg.starts_with("Could not make change for player one: "), "{}", msg);
+            }
+            other => panic!("Unexpected game update: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn clear_area_keeps_cells_of_other_players() {
+        use conway::universe::CellState;
+
+        let (mut server, player_id, _) = new_server_with_game();
+        {
+            // A block for each of player two, player one, and no one, in player one's region
+            let uni = server.get_room_mut(player_id).unwrap().universe.as_mut().unwrap();
+            for &(left, state) in &[
+                (20, CellState::Alive(Some(1))),
+                (30, CellState::Alive(Some(0))),
+                (40, CellState::Alive(None)),
+            ] {
+                for &(col, row) in &[(left, 20), (left + 1, 20), (left, 21), (left + 1, 21)] {
+                    uni.set_unchecked(col, row, state);
+                }
+            }
+        }
+
+        let action = RequestAction::ClearArea {
+            x: 10,
+            y: 10,
+            w: 40,
+            h: 20,
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+        server.advance_universes();
+
+        let uni = server.get_room_mut(player_id).unwrap().universe.as_mut().unwrap();
+        assert_eq!(uni.get_cell_state(20, 20, Some(1)), CellState::Alive(Some(1)));
+        assert_eq!(uni.get_cell_state(31, 21, None), CellState::Dead);
+        assert_eq!(uni.get_cell_state(41, 21, None), CellState::Dead);
+    }
+
+    #[test]
+    fn clear_area_outside_writable_region_is_bad_request() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        for &(p_id, x, y, w, h) in &[
+            (player_id, 100, 0, 28, 129),
+            (player_id, 100, 0, 29, 128),
+            (player_id, 0, -1, 10, 10),
+            (player_id2, 127, 0, 10, 10),
+        ] {
+            let action = RequestAction::ClearArea { x, y, w, h };
+            match server.process_request_action(p_id, action) {
+                ResponseCode::BadRequest { error_msg } => {
+                    assert!(error_msg.contains("is not within writable region"), "{}", error_msg);
+                }
+                response => panic!("Unexpected response at ({}, {}): {:?}", x, y, response),
+            }
+        }
+
+        let action = RequestAction::ClearArea {
+            x: 0,
+            y: 0,
+            w: 0,
+            h: 10,
+        };
+        assert_eq!(
+            server.process_request_action(player_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "area of 0x10 cells is empty".to_owned(),
+            }
+        );
+        assert!(server.get_room(player_id).unwrap().pending_edits.is_empty());
+
+        let action = RequestAction::ClearArea {
+            x: 100,
+            y: 0,
+            w: 28,
+            h: 128,
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+    }
+
+    fn set_auto_match(server: &mut ServerState, player_id: PlayerID, auto_match: bool) -> ResponseCode {
+        let action = RequestAction::SetClientOptions {
+            key:   "auto_match".to_owned(),
+            value: Some(ClientOptionValue::Bool { value: auto_match }),
+        };
+        server.process_request_action(player_id, action)
+    }
+
+    fn new_server_with_match() -> (ServerState, PlayerID, PlayerID, String) {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        let player_id2 = server.add_new_player("player two".to_owned(), fake_socket_addr()).player_id;
+        assert_eq!(set_auto_match(&mut server, player_id, true), ResponseCode::OK);
+        assert_eq!(set_auto_match(&mut server, player_id2, true), ResponseCode::OK);
+
+        let updates = server.run_matchmaking(Instant::now());
+        assert_eq!(updates.len(), 2);
+        let room_name = match updates[0].1 {
+            Packet::Update { ref game_updates, .. } => match game_updates[..] {
+                [GameUpdate::Match { ref room, expire_secs }] => {
+                    assert_eq!(expire_secs, MATCH_EXPIRE_SECS);
+                    room.clone()
+                }
+                _ => panic!("Unexpected game updates: {:?}", game_updates),
+            },
+            ref packet => panic!("Unexpected packet: {:?}", packet),
+        };
+        assert_eq!(updates[0].1, updates[1].1);
+        (server, player_id, player_id2, room_name)
+    }
+
+    #[test]
+    fn set_client_options_invalid_options_are_bad_requests() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("some player".to_owned(), fake_socket_addr()).player_id;
+
+        let action = RequestAction::SetClientOptions {
+            key:   "auto_matc".to_owned(),
+            value: Some(ClientOptionValue::Bool { value: true }),
+        };
+        assert_eq!(
+            server.process_request_action(player_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "unknown client option \"auto_matc\"".to_owned(),
+            }
+        );
+
+        let action = RequestAction::SetClientOptions {
+            key:   "auto_match".to_owned(),
+            value: Some(ClientOptionValue::U8 { value: 1 }),
+        };
+        assert_eq!(
+            server.process_request_action(player_id, action),
+            ResponseCode::BadRequest {
+                error_msg: "client option \"auto_match\" must be a Bool, not U8 { value: 1 }".to_owned(),
+            }
+        );
+        assert_eq!(server.get_player(player_id).options, ClientOptions::default());
+        assert!(server.match_queue.is_empty());
+    }
+
+    #[test]
+    fn set_client_options_rename_in_room_tells_room_old_name() {
+        let (mut server, player_id, player_id2) = new_server_with_game();
+
+        for (name, error_msg) in &[
+            ("", "player name cannot be empty"),
+            ("player two", "player name \"player two\" is already in use"),
+        ] {
+            let action = RequestAction::SetClientOptions {
+                key:   "name".to_owned(),
+                value: Some(ClientOptionValue::Str {
+                    value: name.to_string(),
+                }),
+            };
+            assert_eq!(
+                server.process_request_action(player_id, action),
+                ResponseCode::BadRequest {
+                    error_msg: error_msg.to_string(),
+                }
+            );
+        }
+
+        let action = RequestAction::SetClientOptions {
+            key:   "name".to_owned(),
+            value: Some(ClientOptionValue::Str {
+                value: "player uno".to_owned(),
+            }),
+        };
+        assert_eq!(server.process_request_action(player_id, action), ResponseCode::OK);
+        assert_eq!(server.get_player(player_id).name, "player uno");
+        let room = server.get_room(player_id2).unwrap();
+        assert_eq!(
+            room.game_updates.back().unwrap().1,
+            GameUpdate::PlayerChange {
+                player:   player_info("player uno", Some(0)),
+                old_name: Some("player one".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn set_client_options_ready_is_reset_on_joining_room() {
+        let mut server = ServerState::new();
+        server.create_new_room(None, "some room".to_owned());
+        let player_id = server.add_new_player("some player".to_owned(), fake_socket_addr()).player_id;
+        let set_ready = |server: &mut ServerState, ready| {
+            let action = RequestAction::SetClientOptions {
+                key:   "ready".to_owned(),
+                value: Some(ClientOptionValue::Bool { value: ready }),
+            };
+            server.process_request_action(player_id, action)
+        };
+
+        assert_eq!(set_ready(&mut server, true), ResponseCode::OK);
+        server.join_room(player_id, "some room");
+        assert!(!server.get_player(player_id).options.ready);
+
+        assert_eq!(set_ready(&mut server, true), ResponseCode::OK);
+        let mut player = player_info("some player", None);
+        player.ready = true;
+        let room = server.get_room(player_id).unwrap();
+        let num_game_updates = room.game_updates.len();
+        assert_eq!(
+            room.game_updates.back().unwrap().1,
+            GameUpdate::PlayerChange {
+                player,
+                old_name: None,
+            }
+        );
+
+        // Nothing changed, so the room is not told
+        assert_eq!(set_ready(&mut server, true), ResponseCode::OK);
+        assert_eq!(server.get_room(player_id).unwrap().game_updates.len(), num_game_updates);
+    }
+
+    #[test]
+    fn push_ping_changes_sends_noticeable_changes_at_most_every_interval() {
+        let (mut server, player_id, _) = new_server_with_game();
+        let num_game_updates = |server: &ServerState| server.get_room(player_id).unwrap().latest_game_update_seq;
+        let set_ping = |server: &mut ServerState, ping| {
+            server.get_player_mut(player_id).latency_filter.average_latency_ms = Some(ping);
+        };
+        let now = Instant::now();
+        let interval = Duration::from_secs(PING_REPORT_INTERVAL_SECS);
+
+        let before = num_game_updates(&server);
+        server.push_ping_changes(now);
+        assert_eq!(num_game_updates(&server), before);
+
+        set_ping(&mut server, 100);
+        server.push_ping_changes(now);
+        assert_eq!(num_game_updates(&server), before + 1);
+        let mut player = player_info("player one", Some(0));
+        player.ping = Some(100);
+        assert_eq!(
+            server.get_room(player_id).unwrap().game_updates.back().unwrap().1,
+            GameUpdate::PlayerList {
+                players: vec![player, player_info("player two", Some(1))],
+            }
+        );
+
+        // Too soon to send the change
+        set_ping(&mut server, 100 + PING_CHANGE_THRESHOLD_MS);
+        server.push_ping_changes(now + interval / 2);
+        assert_eq!(num_game_updates(&server), before + 1);
+        server.push_ping_changes(now + interval);
+        assert_eq!(num_game_updates(&server), before + 2);
+
+        // Too small a change to send
+        set_ping(&mut server, 100 + 2 * PING_CHANGE_THRESHOLD_MS - 1);
+        server.push_ping_changes(now + 2 * interval);
+        assert_eq!(num_game_updates(&server), before + 2);
+    }
+
+    #[test]
+    fn set_client_options_auto_match_queues_player_in_lobby() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("some player".to_owned(), fake_socket_addr()).player_id;
+
+        assert_eq!(set_auto_match(&mut server, player_id, true), ResponseCode::OK);
+        assert!(server.get_player(player_id).options.auto_match);
+        assert_eq!(server.match_queue, vec![player_id]);
+
+        // One player is not enough for a match
+        assert_eq!(server.run_matchmaking(Instant::now()), vec![]);
+
+        assert_eq!(set_auto_match(&mut server, player_id, false), ResponseCode::OK);
+        assert!(server.match_queue.is_empty());
+    }
+
+    #[test]
+    fn run_matchmaking_match_is_complete_once_players_join() {
+        let (mut server, player_id, player_id2, room_name) = new_server_with_match();
+        assert!(server.match_queue.is_empty());
+
+        // The room is reserved for the matched players
+        let player_id3 = server.add_new_player("player three".to_owned(), fake_socket_addr()).player_id;
+        assert_eq!(
+            server.join_room(player_id3, &room_name),
+            ResponseCode::BadRequest {
+                error_msg: format!("room {:?} is reserved for a match", room_name),
+            }
+        );
+
+        for &p_id in &[player_id, player_id2] {
+            assert_eq!(
+                server.join_room(p_id, &room_name),
+                ResponseCode::JoinedRoom {
+                    room_name: room_name.clone(),
+                }
+            );
+        }
+        assert!(server.matches.is_empty());
+        server.update_room_states(Instant::now());
+        match server.get_room(player_id).unwrap().state {
+            RoomState::Countdown { .. } => {}
+            ref state => panic!("Unexpected room state: {:?}", state),
+        }
+
+        // Nothing expires once the match is complete
+        let later = Instant::now() + Duration::from_secs(MATCH_EXPIRE_SECS as u64 + 1);
+        assert_eq!(server.run_matchmaking(later), vec![]);
+        assert_eq!(server.get_room(player_id2).unwrap().name, room_name);
+    }
+
+    #[test]
+    fn run_matchmaking_expired_match_requeues_player_who_joined() {
+        let (mut server, player_id, player_id2, room_name) = new_server_with_match();
+        server.join_room(player_id, &room_name);
+
+        // Player two's match is withdrawn, and player one hears about it in the lobby, whose
+        // updates are numbered apart from the room's
+        let later = Instant::now() + Duration::from_secs(MATCH_EXPIRE_SECS as u64 + 1);
+        let updates = server.run_matchmaking(later);
+        assert_eq!(updates.len(), 1);
+        match updates[0].1 {
+            Packet::Update {
+                ref room,
+                ref game_updates,
+                game_update_seq,
+                ..
+            } => {
+                assert_eq!(*room, None);
+                assert_eq!(game_updates, &vec![GameUpdate::RoomDeleted]);
+                assert_eq!(game_update_seq, Some(2)); // after the Match
+            }
+            ref packet => panic!("Unexpected packet: {:?}", packet),
+        }
+
+        assert!(!server.is_player_in_game(player_id));
+        assert!(!server.room_map.contains_key(&room_name));
+        assert!(server.matches.is_empty());
+        assert_eq!(server.match_queue, vec![player_id]);
+        // Letting the match expire declined it
+        assert!(!server.get_player(player_id2).options.auto_match);
+    }
+
+    #[test]
+    fn run_matchmaking_resends_match_until_acknowledged() {
+        let (mut server, player_id, player_id2, _) = new_server_with_match();
+        assert_eq!(server.run_matchmaking(Instant::now()).len(), 2);
+
+        let cookie = server.get_player(player_id).cookie.clone();
+        server
+            .decode_packet(
+                fake_socket_addr(),
+                Packet::UpdateReply {
+                    cookie,
+                    room:                 None,
+                    last_chat_seq:        None,
+                    last_game_update_seq: Some(1),
+                    last_full_gen:        None,
+                    partial_gen:          None,
+                    pong:                 PingPong::pong(0),
+                },
+            )
+            .unwrap();
+        assert!(server.get_player(player_id).lobby_updates.is_empty());
+        let updates = server.run_matchmaking(Instant::now());
+        assert_eq!(updates.len(), 1);
+        match updates[0].1 {
+            Packet::Update { game_update_seq, .. } => assert_eq!(game_update_seq, Some(1)),
+            ref packet => panic!("Unexpected packet: {:?}", packet),
+        }
+        assert_eq!(server.get_player(player_id2).lobby_updates.len(), 1);
+    }
+
+    #[test]
+    fn leave_room_in_match_room_declines_match() {
+        let (mut server, player_id, player_id2, room_name) = new_server_with_match();
+        server.join_room(player_id, &room_name);
+        assert_eq!(server.leave_room(player_id), ResponseCode::LeaveRoom);
+
+        assert!(!server.get_player(player_id).options.auto_match);
+        assert!(!server.room_map.contains_key(&room_name));
+        assert_eq!(server.match_queue, vec![player_id2]);
+    }
+
+    #[test]
+    fn leave_room_removes_match_room_once_empty() {
+        let (mut server, player_id, player_id2, room_name) = new_server_with_match();
+        server.join_room(player_id, &room_name);
+        server.join_room(player_id2, &room_name);
+        assert!(server.matches.is_empty());
+        let room_id = server.room_map[&room_name];
+
+        assert_eq!(server.leave_room(player_id), ResponseCode::LeaveRoom);
+        assert!(server.room_map.contains_key(&room_name));
+        assert_eq!(server.leave_room(player_id2), ResponseCode::LeaveRoom);
+        assert!(!server.room_map.contains_key(&room_name));
+        assert!(!server.rooms.contains_key(&room_id));
+    }
+
+    #[test]
+    fn leave_room_keeps_other_rooms_once_empty() {
+        let mut server = ServerState::new();
+        let player_id = server.add_new_player("player one".to_owned(), fake_socket_addr()).player_id;
+        server.create_new_room(None, "some room".to_owned());
+        server.join_room(player_id, "some room");
+        assert_eq!(server.leave_room(player_id), ResponseCode::LeaveRoom);
+        assert!(server.room_map.contains_key("some room"));
+    }
+
+    #[test]
+    fn collect_universe_updates_resends_only_missing_parts() {
+        use conway::universe::CellState;
+
+        let (mut server, player_id, _) = new_server_with_game();
+        {
+            // A checkerboard takes many parts to send
+            let room = server.get_room_mut(player_id).unwrap();
+            let uni = room.universe.as_mut().unwrap();
+            for row in 0..UNIVERSE_HEIGHT {
+                for col in ((row % 2)..UNIVERSE_WIDTH).step_by(2) {
+                    uni.set_unchecked(col, row, CellState::Alive(None));
+                }
+            }
+        }
+
+        let room = server.get_room(player_id).unwrap();
+        let parts = update_parts(server.collect_universe_updates(room, server.get_player(player_id)));
+        let total_parts = parts.len();
+        assert!(total_parts > 2);
+        let have_parts = vec![parts[0].clone(), parts[2].clone()];
+
+        server
+            .get_player_mut(player_id)
+            .update_universe_gen(None, GenPartInfo::from_parts(&have_parts));
+        let room = server.get_room(player_id).unwrap();
+        let missing_parts = update_parts(server.collect_universe_updates(room, server.get_player(player_id)));
+        assert_eq!(missing_parts.len(), total_parts - 2);
+        assert!(missing_parts.iter().all(|part| part.part_number != 0 && part.part_number != 2));
+
+        let mut all_parts = have_parts;
+        all_parts.extend(missing_parts);
+        let diff = GenStateDiffPart::join(&all_parts).unwrap();
+        assert_eq!((diff.gen0, diff.gen1), (0, 1));
+    }
+
+    #[test]
+    fn split_worst_case_diff_fits_in_a_datagram() {
+        use conway::universe::CellState;
+        use netwayste::net::MAX_GEN_STATE_DIFF_PARTS;
+
+        // Every cell differs from the one before it, so each takes a byte of the pattern
+        let num_players = MAX_PLAYERS_PER_GAME;
+        let options = GameOptions::new(MAX_UNIVERSE_SIZE, MAX_UNIVERSE_SIZE, num_players as u32);
+        let mut uni = build_universe(&options).unwrap();
+        let size = MAX_UNIVERSE_SIZE as usize;
+        for row in 0..size {
+            for col in 0..size {
+                uni.set_unchecked(col, row, CellState::Alive(Some((col + row) % num_players)));
+            }
+        }
+        let diff = uni.diff(0, uni.latest_gen(), None).unwrap();
+        let parts = GenStateDiffPart::split(&diff);
+        assert_eq!(parts.len(), MAX_GEN_STATE_DIFF_PARTS);
+        for part in parts {
+            // Longer than MAX_PATTERN_PART_LEN, as documented
+            assert!(part.pattern_part.len() <= 8704);
+            let packet = Packet::Update {
+                room:            Some("x".repeat(MAX_ROOM_NAME)),
+                chats:           vec![],
+                game_updates:    vec![],
+                game_update_seq: None,
+                universe_update: UniUpdate::Diff { diff: part },
+                ping:            PingPong::ping(),
+            };
+            // The largest UDP payload over IPv4
+            assert!(bincode::serialized_size(&packet).unwrap() <= 65_507);
+        }
+    }
+
+    #[test]
+    fn collect_universe_updates_spectator_sees_as_watched_player() {
+        use conway::universe::CellState;
+
+        let (mut server, player_id, player_id2) = new_server_with_game();
+        let watcher_id = server.add_new_player("watcher".to_owned(), fake_socket_addr()).player_id;
+        let spectator_id = server.add_new_player("spectator".to_owned(), fake_socket_addr()).player_id;
+        server.spectate_room(watcher_id, "some room", Some(0));
+        server.spectate_room(spectator_id, "some room", None);
+        {
+            // A cell owned by player two, far from player one's cells, is fogged for player one
+            let room = server.get_room_mut(player_id).unwrap();
+            let uni = room.universe.as_mut().unwrap();
+            uni.set_unchecked(UNIVERSE_WIDTH - 10, UNIVERSE_HEIGHT / 2, CellState::Alive(Some(1)));
+        }
+
+        let room = server.get_room(player_id).unwrap();
+        let parts = |p_id| update_parts(server.collect_universe_updates(room, server.get_player(p_id)));
+        assert_eq!(parts(watcher_id), parts(player_id));
+        assert_ne!(parts(spectator_id), parts(player_id));
+        assert_ne!(parts(spectator_id), parts(player_id2));
+    }
+
     #[test]
     fn broadcast_message_to_two_players_in_room() {
         let mut server = ServerState::new();
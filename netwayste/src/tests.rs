@@ -17,6 +17,8 @@
 extern crate tokio_test;
 
 use crate::net::*;
+use crate::utils::PingPong;
+use bincode::serialize;
 use std::net::SocketAddr;
 use std::{
     thread,
@@ -1139,6 +1141,75 @@ mod netwayste_net_tests {
         }
     }
 
+    fn make_gen_state_diff(pattern: &str) -> conway::universe::GenStateDiff {
+        conway::universe::GenStateDiff {
+            gen0:    3,
+            gen1:    5,
+            pattern: conway::rle::Pattern(pattern.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_gen_state_diff_part_split_small_diff_is_one_part() {
+        let diff = make_gen_state_diff("bo$2bo$3o!");
+        let parts = GenStateDiffPart::split(&diff);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].part_number, 0);
+        assert_eq!(parts[0].total_parts, 1);
+        assert_eq!((parts[0].gen0, parts[0].gen1), (3, 5));
+        assert_eq!(GenStateDiffPart::join(&parts), Some(diff));
+    }
+
+    #[test]
+    fn test_gen_state_diff_part_split_and_join_out_of_order() {
+        // multi-byte chars must not be split
+        let pattern = "ob\u{100}".repeat(MAX_PATTERN_PART_LEN);
+        let diff = make_gen_state_diff(&pattern);
+        let mut parts = GenStateDiffPart::split(&diff);
+        assert!(parts.len() > 1);
+        for part in parts.iter() {
+            assert!(part.pattern_part.len() <= MAX_PATTERN_PART_LEN + 1);
+            assert_eq!(part.total_parts as usize, parts.len());
+        }
+
+        parts.reverse();
+        assert_eq!(GenStateDiffPart::join(&parts), Some(diff.clone()));
+
+        parts.remove(1);
+        assert_eq!(GenStateDiffPart::join(&parts), None);
+    }
+
+    #[test]
+    fn test_gen_state_diff_part_split_huge_diff_has_longer_parts() {
+        let pattern = "o".repeat(MAX_PATTERN_PART_LEN * MAX_GEN_STATE_DIFF_PARTS * 2);
+        let diff = make_gen_state_diff(&pattern);
+        let parts = GenStateDiffPart::split(&diff);
+        assert_eq!(parts.len(), MAX_GEN_STATE_DIFF_PARTS);
+        assert_eq!(GenStateDiffPart::join(&parts), Some(diff));
+    }
+
+    #[test]
+    fn test_gen_state_diff_part_join_parts_of_different_diffs() {
+        let pattern = "o".repeat(MAX_PATTERN_PART_LEN * 2);
+        let mut parts = GenStateDiffPart::split(&make_gen_state_diff(&pattern));
+        parts[1].gen1 = 6;
+        assert_eq!(GenStateDiffPart::join(&parts), None);
+    }
+
+    #[test]
+    fn test_gen_part_info_from_parts() {
+        assert_eq!(GenPartInfo::from_parts(&[]), None);
+
+        let pattern = "o".repeat(MAX_PATTERN_PART_LEN * 4);
+        let parts = GenStateDiffPart::split(&make_gen_state_diff(&pattern));
+        let info = GenPartInfo::from_parts(&[parts[3].clone(), parts[1].clone()]).unwrap();
+        assert_eq!((info.gen0, info.gen1), (3, 5));
+        assert_eq!(info.have_bitmask, 0b1010);
+        assert!(info.has_part(1));
+        assert!(!info.has_part(2));
+        assert!(!info.has_part(40));
+    }
+
     // IMPORTANT: if these two tests break, it's likely the Go registrar is broken as well.
     #[test]
     fn test_serialize_getstatus() {
@@ -1267,6 +1338,60 @@ mod netwayste_client_tests {
         client_state.handle_incoming_chats(incoming_messages).await;
     }
 
+    fn make_diff_parts(gen0: usize, gen1: usize) -> Vec<GenStateDiffPart> {
+        let diff = conway::universe::GenStateDiff {
+            gen0,
+            gen1,
+            pattern: conway::rle::Pattern("o".repeat(MAX_PATTERN_PART_LEN * 3)),
+        };
+        GenStateDiffPart::split(&diff)
+    }
+
+    #[tokio::test]
+    async fn handle_universe_diff_part_complete_diff_is_forwarded() {
+        let (nw_server_response, mut ggez_server_response) = futures::channel::mpsc::channel::<NetwaysteEvent>(5);
+        let mut client_state = ClientNetState::new(nw_server_response);
+
+        let mut parts = make_diff_parts(0, 4);
+        parts.reverse();
+        let last_part = parts.pop().unwrap();
+        for part in parts {
+            client_state.handle_universe_diff_part(part).await;
+        }
+        assert_eq!(client_state.last_full_gen, None);
+        assert_eq!(GenPartInfo::from_parts(&client_state.diff_parts).unwrap().have_bitmask, 0b110);
+
+        client_state.handle_universe_diff_part(last_part).await;
+        assert_eq!(client_state.last_full_gen, Some(4));
+        assert!(client_state.diff_parts.is_empty());
+        match ggez_server_response.try_next() {
+            Ok(Some(NetwaysteEvent::UniverseUpdate(diff))) => assert_eq!((diff.gen0, diff.gen1), (0, 4)),
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn handle_universe_diff_part_ignores_unusable_parts() {
+        let mut client_state = create_client_net_state();
+        client_state.last_full_gen = Some(4);
+
+        // Already have it
+        client_state.handle_universe_diff_part(make_diff_parts(0, 3).remove(0)).await;
+        // Based on a generation we don't have
+        client_state.handle_universe_diff_part(make_diff_parts(3, 6).remove(0)).await;
+        assert!(client_state.diff_parts.is_empty());
+
+        client_state.handle_universe_diff_part(make_diff_parts(4, 6).remove(0)).await;
+        assert_eq!(client_state.diff_parts.len(), 1);
+
+        // A newer diff replaces the incomplete one, but an older one doesn't
+        client_state.handle_universe_diff_part(make_diff_parts(4, 7).remove(1)).await;
+        client_state.handle_universe_diff_part(make_diff_parts(4, 6).remove(2)).await;
+        assert_eq!(client_state.diff_parts.len(), 1);
+        assert_eq!(client_state.diff_parts[0].gen1, 7);
+        assert_eq!(client_state.last_full_gen, Some(4));
+    }
+
     #[tokio::test]
     async fn handle_incoming_chats_new_messages_are_old_and_new() {
         let mut client_state = create_client_net_state();
@@ -1291,4 +1416,105 @@ mod netwayste_client_tests {
             seq_num += 1;
         }
     }
+
+    #[tokio::test]
+    async fn handle_game_updates_skips_received_updates_and_delivers_rest_in_order() {
+        let (nw_server_response, mut ggez_server_response) = futures::channel::mpsc::channel::<NetwaysteEvent>(5);
+        let mut client_state = ClientNetState::new(nw_server_response);
+        client_state.game_update_seq_num = 2;
+
+        let game_updates = (2..5)
+            .map(|i| GameUpdate::GameNotification {
+                msg: format!("notification {}", i),
+            })
+            .collect();
+        client_state.handle_game_updates(Some(2), game_updates).await;
+        assert_eq!(client_state.game_update_seq_num, 4);
+        for i in 3..5 {
+            match ggez_server_response.try_next() {
+                Ok(Some(NetwaysteEvent::GameNotification(msg))) => assert_eq!(msg, format!("notification {}", i)),
+                other => panic!("Unexpected event: {:?}", other),
+            }
+        }
+        assert!(ggez_server_response.try_next().is_err()); // nothing else was sent
+    }
+
+    #[tokio::test]
+    async fn handle_incoming_event_ignores_updates_of_room_until_joined() {
+        let (nw_server_response, mut ggez_server_response) = futures::channel::mpsc::channel::<NetwaysteEvent>(5);
+        let mut client_state = ClientNetState::new(nw_server_response);
+        client_state.cookie = Some("cookie".to_owned());
+        client_state.game_update_seq_num = 2; // from the lobby
+        let room_update = Packet::Update {
+            room:            Some("some room".to_owned()),
+            chats:           vec![],
+            game_updates:    vec![GameUpdate::GameNotification {
+                msg: "hello room".to_owned(),
+            }],
+            game_update_seq: Some(1),
+            universe_update: UniUpdate::NoChange,
+            ping:            PingPong::ping(),
+        };
+        let reply_acks = |replies: Vec<(Packet, SocketAddr)>| match replies[..] {
+            [(
+                Packet::UpdateReply {
+                    ref room,
+                    last_game_update_seq,
+                    ..
+                },
+                _,
+            )] => (room.clone(), last_game_update_seq),
+            _ => panic!("Unexpected replies: {:?}", replies),
+        };
+
+        // The room's update arrives before the response saying we joined it
+        let replies = client_state
+            .handle_incoming_event(room_update.clone(), fake_socket_addr())
+            .await;
+        assert_eq!(reply_acks(replies), (None, Some(2)));
+        assert!(ggez_server_response.try_next().is_err()); // nothing was delivered
+
+        client_state.handle_joined_room(&"some room".to_owned());
+        let replies = client_state
+            .handle_incoming_event(room_update.clone(), fake_socket_addr())
+            .await;
+        assert_eq!(reply_acks(replies), (Some("some room".to_owned()), Some(1)));
+        match ggez_server_response.try_next() {
+            Ok(Some(NetwaysteEvent::GameNotification(msg))) => assert_eq!(msg, "hello room"),
+            other => panic!("Unexpected event: {:?}", other),
+        }
+
+        // Once we have left, the room's updates are ignored again
+        client_state.handle_left_room();
+        let replies = client_state.handle_incoming_event(room_update, fake_socket_addr()).await;
+        assert_eq!(reply_acks(replies), (None, Some(0)));
+        assert!(ggez_server_response.try_next().is_err());
+    }
+
+    #[tokio::test]
+    async fn handle_game_updates_player_change_renames_this_client() {
+        let (nw_server_response, mut ggez_server_response) = futures::channel::mpsc::channel::<NetwaysteEvent>(5);
+        let mut client_state = ClientNetState::new(nw_server_response);
+        client_state.name = Some("old name".to_owned());
+
+        let player = PlayerInfo {
+            name:  "new name".to_owned(),
+            index: Some(0),
+            ping:  Some(30),
+            ready: true,
+        };
+        let game_update = GameUpdate::PlayerChange {
+            player:   player.clone(),
+            old_name: Some("old name".to_owned()),
+        };
+        client_state.handle_game_updates(Some(1), vec![game_update]).await;
+        assert_eq!(client_state.name, Some("new name".to_owned()));
+        match ggez_server_response.try_next() {
+            Ok(Some(NetwaysteEvent::PlayerChange(p, old_name))) => {
+                assert_eq!(p, player);
+                assert_eq!(old_name, Some("old name".to_owned()));
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
 }
@@ -84,6 +84,11 @@ impl LatencyFilter {
         self.in_progress = true;
     }
 
+    /// Returns true if `start()` was called and `update()` has not been called since.
+    pub fn is_in_progress(&self) -> bool {
+        self.in_progress
+    }
+
     pub fn update(&mut self) {
         if !self.in_progress {
             error!("The LatencyFilter's start() was not called so a duration cannot be computed.");